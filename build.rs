@@ -0,0 +1,23 @@
+use std::{env, process::Command};
+
+/// Exposes the commit the indexer is built from as `GIT_COMMIT`. It can be set
+/// explicitly for builds without access to the git repository.
+fn main() {
+    println!("cargo:rerun-if-env-changed=GIT_COMMIT");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+
+    let git_commit = env::var("GIT_COMMIT").ok().or_else(|| {
+        Command::new("git")
+            .args(["rev-parse", "--short", "HEAD"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .map(|commit| commit.trim().to_string())
+    });
+
+    if let Some(git_commit) = git_commit {
+        println!("cargo:rustc-env=GIT_COMMIT={git_commit}");
+    }
+}
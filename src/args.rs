@@ -1,25 +1,154 @@
-use clap::{ArgAction, Parser};
+use std::{net::SocketAddr, path::PathBuf};
 
-use crate::clients::beacon::types::BlockId;
+use alloy::primitives::{Address, B256};
+use clap::{ArgAction, ArgGroup, Parser, Subcommand, ValueEnum};
+
+use crate::{
+    clients::beacon::types::BlockId,
+    context::DEFAULT_BEACON_HEADER_CACHE_SIZE,
+    sinks::SinkType,
+    slots_processor::indexed_slots::IndexedSlotsSource,
+    synchronizer::{backfill::BackfillDirection, job::SlotRange, CheckpointType},
+    utils::banner::ConfigOutput,
+};
 
 /// Blobscan's indexer for the EIP-4844 upgrade.
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// TOML file with the environment and indexing options, overridden by the
+    /// environment variables and the command line flags
+    #[arg(long, value_name = "PATH", global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Print the effective configuration merged from the config file, the
+    /// environment and the command line, and exit
+    #[arg(long, global = true)]
+    pub print_config: bool,
+
+    // Indexing arguments used when no subcommand is given
+    #[command(flatten)]
+    pub index: IndexArgs,
+}
+
+/// Combination of indexing flags that can't be checked by clap on its own.
+#[derive(Debug, thiserror::Error)]
+pub enum ArgsError {
+    #[error("--from-slot and --to-slot are the same block, so there's nothing to index")]
+    EmptyRange,
+    #[error("slot ranges {0} and {1} overlap")]
+    OverlappingRanges(SlotRange, SlotRange),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Index blocks, transactions and blobs (default)
+    Index(Box<IndexArgs>),
+    /// Force the reprocessing of a single block, even if it was already indexed
+    Reindex(Box<ReindexArgs>),
+    /// Check the data stored in Blobscan against the chain
+    Verify(VerifyArgs),
+    /// Check that the beacon and execution nodes agree on the blobs of every
+    /// block, without writing anything
+    Audit(AuditArgs),
+    /// Send the blocks exported with `--export-dir` to Blobscan, e.g. to backfill
+    /// an instance the nodes can't reach
+    Import(ImportArgs),
+    /// Print the current sync state stored in Blobscan, or the status of a
+    /// running indexer through its control server
+    Status(StatusArgs),
+    /// Pause or resume the backfill of a running indexer through its control
+    /// server, without stopping the live indexing
+    Backfill(BackfillArgs),
+    /// Let a running indexer handle the reorg deeper than
+    /// `--reorg-confirmation-depth` it's holding, through its control server
+    ConfirmReorg(ConfirmReorgArgs),
+    /// Create an archive with the logs, config, versions and sync state needed to
+    /// troubleshoot the indexer
+    SupportBundle(SupportBundleArgs),
+    /// Check the connectivity and credentials of every service the indexer
+    /// depends on, printing the result and latency of each check
+    SelfTest(SelfTestArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct IndexArgs {
     /// Slot to start indexing from
     #[arg(short, long)]
     pub from_slot: Option<BlockId>,
 
     /// Slot to stop indexing at
-    #[arg(short, long)]
+    #[arg(short, long, requires = "from_slot")]
     pub to_slot: Option<BlockId>,
 
+    /// Comma-separated `FROM-TO` ranges of slots to index, each synced by its
+    /// own task with `--num-threads` threads at the same time as the others.
+    /// Like `--from-slot` and `--to-slot`, the `TO` slot is excluded and ranges
+    /// where it's lower are synced backwards. The sync state isn't updated
+    #[arg(
+        long,
+        value_name = "RANGES",
+        value_delimiter = ',',
+        conflicts_with_all = ["from_slot", "to_slot"]
+    )]
+    pub ranges: Vec<SlotRange>,
+
+    /// Resume a bounded job interrupted by a termination signal from the
+    /// manifest it left behind
+    #[arg(long, value_name = "MANIFEST", conflicts_with_all = ["from_slot", "to_slot", "ranges"])]
+    pub resume_job: Option<PathBuf>,
+
+    /// Resume a bounded job that failed or was interrupted from the manifest it
+    /// left at the `--job-manifest` path, starting at the slot it stopped at
+    #[arg(long, conflicts_with_all = ["from_slot", "to_slot", "ranges", "resume_job"])]
+    pub resume: bool,
+
+    /// Where the slots left to sync are saved when a bounded job fails or is
+    /// interrupted
+    #[arg(long, value_name = "PATH", default_value = "indexer-job.json")]
+    pub job_manifest: PathBuf,
+
+    /// File where the slot ranges being synced are journaled before being
+    /// indexed. Ranges left unconfirmed by a crash are synced again on startup.
+    /// Disabled when not set
+    #[arg(long, value_name = "PATH")]
+    pub intent_log: Option<PathBuf>,
+
+    /// File every handled reorg is appended to as a JSON line, with the blocks
+    /// rewound and forwarded, for post-incident analysis. Disabled when not set
+    #[arg(long, value_name = "PATH")]
+    pub reorg_log: Option<PathBuf>,
+
+    /// Reorgs deeper than this amount of slots are held until they're confirmed
+    /// with `confirm-reorg` through the control server, while shallower ones
+    /// are handled right away. Every reorg is handled right away when not set
+    #[arg(long, value_name = "SLOTS")]
+    pub reorg_confirmation_depth: Option<u32>,
+
+    /// Skip the slots already indexed when backfilling, looking them up in
+    /// Blobscan or in a local bitmap of the slots processed by previous runs
+    #[arg(long, value_enum, value_name = "SOURCE")]
+    pub skip_indexed_slots: Option<IndexedSlotsSource>,
+
+    /// File the bitmap of processed slots is kept in when skipping indexed
+    /// slots with the `bitmap` source
+    #[arg(long, value_name = "PATH", default_value = "indexed-slots.bitmap")]
+    pub indexed_slots_bitmap: PathBuf,
+
     /// Number of threads used for parallel indexing
-    #[arg(short, long)]
+    #[arg(short, long, value_parser = clap::value_parser!(u32).range(1..))]
     pub num_threads: Option<u32>,
 
+    /// Adjust the number of threads to the providers' latency and error rate,
+    /// using `--num-threads` as the upper bound
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub adaptive_concurrency: bool,
+
     /// Amount of slots to be processed before saving latest slot in the database
-    #[arg(short, long)]
+    #[arg(short, long, value_parser = clap::value_parser!(u32).range(1..))]
     pub slots_per_save: Option<u32>,
 
     /// Disable slot checkpoint saving when syncing
@@ -29,4 +158,324 @@ pub struct Args {
     /// Disable historical synchronization
     #[arg(short = 'd', long, action = ArgAction::SetTrue)]
     pub disable_sync_historical: bool,
+
+    /// Order the historical slots are backfilled in: from the lowest synced
+    /// slot down to the Dencun fork, or from the Dencun fork up, oldest first
+    #[arg(long, value_enum, value_name = "DIRECTION", default_value_t = BackfillDirection::Backward)]
+    pub backfill_direction: BackfillDirection,
+
+    /// File where a forward backfill keeps its progress until it reaches the
+    /// synced slots, as only then it can be saved in Blobscan's sync state
+    #[arg(long, value_name = "PATH", default_value = "backfill-checkpoint.json")]
+    pub backfill_checkpoint: PathBuf,
+
+    /// Index finalized blocks only, following the finalized checkpoints instead
+    /// of the head, so reorged blocks never get indexed
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub finalized_only: bool,
+
+    /// Seconds between checks for missing slots within the already synced range.
+    /// Gap filling is disabled when not set
+    #[arg(long, value_name = "SECONDS")]
+    pub gap_fill_interval: Option<u64>,
+
+    /// Amount of slots behind the head above which live indexing catches up using
+    /// multiple threads [default: 4]
+    #[arg(long)]
+    pub catch_up_threshold: Option<u32>,
+
+    /// Amount of slots without head events, or epochs without finalized
+    /// checkpoints when indexing finalized blocks only, after which the beacon
+    /// events stream is considered stalled and resubscribed to [default: 5]
+    #[arg(long, value_name = "SLOTS")]
+    pub sse_stall_slots: Option<u32>,
+
+    /// Times a syncing task (historical or live) that failed with a recoverable
+    /// error (network failures, server errors) is restarted before the indexer
+    /// exits. Restarted indefinitely when unset. Fatal errors (authentication
+    /// failures, schema mismatches) are never retried
+    #[arg(long, value_name = "RESTARTS")]
+    pub max_task_restarts: Option<u32>,
+
+    /// Seconds to wait before restarting a failed syncing task. Doubled on every
+    /// restart of the same task, up to a minute
+    #[arg(long, value_name = "SECONDS", default_value_t = 1)]
+    pub task_restart_delay: u64,
+
+    /// Seconds a syncing thread can go without making progress before it's
+    /// aborted and its remaining slots are resumed on a new one. Stalled threads
+    /// aren't restarted when not set
+    #[arg(long, value_name = "SECONDS")]
+    pub worker_stall_timeout: Option<u64>,
+
+    /// Seconds a slot can take to be processed, including the retries of its
+    /// requests, before it's considered failed and its syncing task restarted.
+    /// Slots aren't timed out when not set
+    #[arg(long, value_name = "SECONDS")]
+    pub slot_timeout: Option<u64>,
+
+    /// Seconds past which a processed slot is logged as slow, along with the
+    /// time spent fetching it from the nodes and sending it to Blobscan
+    #[arg(long, value_name = "SECONDS", default_value_t = 12)]
+    pub slow_slot_threshold: u64,
+
+    #[command(flatten)]
+    pub processing: ProcessingArgs,
+
+    /// How the effective configuration is printed on startup. It's logged
+    /// either way
+    #[arg(long, value_enum, default_value_t = ConfigOutput::Text)]
+    pub config_output: ConfigOutput,
+}
+
+/// Options for how slots are processed and where they're written to, shared by
+/// the commands indexing blocks.
+#[derive(clap::Args, Debug)]
+pub struct ProcessingArgs {
+    /// Maximum combined size, in bytes, of the index requests sent to Blobscan
+    /// at the same time. Workers wait for in-flight requests to complete when
+    /// exceeded, except for the ones indexing the head
+    #[arg(long, value_name = "BYTES")]
+    pub max_inflight_request_bytes: Option<usize>,
+
+    /// Maximum combined size, in bytes, of the blobs held in memory by all
+    /// workers, from fetching them until their slot is indexed. Workers wait
+    /// before fetching more blobs when exceeded, except for the ones indexing
+    /// the head
+    #[arg(long, value_name = "BYTES")]
+    pub max_inflight_blob_bytes: Option<usize>,
+
+    /// When Blobscan rejects a block because of invalid transactions or blobs,
+    /// resubmit it without them instead of failing the slot
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub drop_invalid_entities: bool,
+
+    /// Send blobs to Blobscan with their metadata only, without their contents.
+    /// Meant for deployments storing blob data elsewhere
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub no_blob_data: bool,
+
+    /// Amount of recently fetched beacon block headers kept in memory so head
+    /// tracking, reorg handling and catch-up don't fetch them again. Set to 0
+    /// to disable the cache
+    #[arg(long, value_name = "HEADERS", default_value_t = DEFAULT_BEACON_HEADER_CACHE_SIZE)]
+    pub header_cache_size: usize,
+
+    /// JSON file mapping rollup labels to the addresses they send blob
+    /// transactions from or to. Indexed transactions are tagged with the
+    /// rollup they belong to
+    #[arg(long, value_name = "PATH")]
+    pub rollup_mapping: Option<PathBuf>,
+
+    /// JSON file mapping transaction categories to the calldata signatures
+    /// identifying them, such as the selectors of rollup batch submission
+    /// functions. Indexed transactions are tagged with their category
+    #[arg(long, value_name = "PATH")]
+    pub tx_category_mapping: Option<PathBuf>,
+
+    /// Only index blob transactions sent from one of these comma-separated
+    /// addresses, or to one of the `--filter-to-addresses` ones
+    #[arg(long, value_name = "ADDRESSES", value_delimiter = ',')]
+    pub filter_from_addresses: Vec<Address>,
+
+    /// Only index blob transactions sent to one of these comma-separated
+    /// addresses, or from one of the `--filter-from-addresses` ones
+    #[arg(long, value_name = "ADDRESSES", value_delimiter = ',')]
+    pub filter_to_addresses: Vec<Address>,
+
+    /// Verify the KZG proofs of the blobs before indexing them
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub verify_blobs: bool,
+
+    /// Maximum number of blobs verified at the same time [default: number of CPUs]
+    #[arg(long, requires = "verify_blobs")]
+    pub blob_verification_threads: Option<usize>,
+
+    /// Where indexed blocks, transactions and blobs are written to
+    #[arg(long, value_enum, default_value_t = SinkType::Blobscan)]
+    pub sink: SinkType,
+
+    /// Also write every indexed block, along with its transactions and blobs,
+    /// to a JSON file per slot in the given directory
+    #[arg(long, value_name = "DIR")]
+    pub export_dir: Option<PathBuf>,
+
+    /// Only write indexed blocks to the export directory, without sending them
+    /// to the sink. Sync checkpoints aren't saved either
+    #[arg(long, action = ArgAction::SetTrue, requires = "export_dir")]
+    pub export_only: bool,
+
+    /// Write the metadata of every indexed blob (slot, rollup, size, fee…) to
+    /// Parquet files partitioned by day in the given directory
+    #[arg(long, value_name = "DIR")]
+    pub analytics_dir: Option<PathBuf>,
+
+    /// Read the blocks of the slots covered by the beacon era files of the
+    /// given directory from them instead of the nodes. Blobs are still fetched
+    /// from the beacon node
+    #[arg(long, value_name = "DIR")]
+    pub era_dir: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(group = ArgGroup::new("block").required(true).multiple(false))]
+pub struct ReindexArgs {
+    /// Slot of the block to reindex
+    #[arg(long, group = "block")]
+    pub slot: Option<u32>,
+
+    /// Beacon block root of the block to reindex
+    #[arg(long, group = "block")]
+    pub block_hash: Option<B256>,
+
+    #[command(flatten)]
+    pub processing: ProcessingArgs,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct VerifyArgs {
+    /// Slot to start verifying from
+    #[arg(short, long)]
+    pub from_slot: u32,
+
+    /// Slot to stop verifying at (exclusive)
+    #[arg(short, long)]
+    pub to_slot: u32,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct AuditArgs {
+    /// Slot to start auditing from
+    #[arg(short, long)]
+    pub from_slot: u32,
+
+    /// Slot to stop auditing at (exclusive)
+    #[arg(short, long)]
+    pub to_slot: u32,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ImportArgs {
+    /// Directory the blocks were exported to
+    #[arg(long, value_name = "DIR")]
+    pub dir: PathBuf,
+
+    /// Slot to start importing from
+    #[arg(short, long)]
+    pub from_slot: Option<u32>,
+
+    /// Slot to stop importing at (exclusive)
+    #[arg(short, long)]
+    pub to_slot: Option<u32>,
+
+    /// Sync state saved as blocks are imported
+    #[arg(long, value_enum, default_value_t = CheckpointType::Disabled)]
+    pub checkpoint: CheckpointType,
+
+    /// Amount of blocks imported before saving the sync state
+    #[arg(short, long, default_value_t = 1000)]
+    pub slots_per_save: u32,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct SelfTestArgs {
+    /// Seconds each check can take before it's considered failed
+    #[arg(long, value_name = "SECONDS", default_value_t = 10)]
+    pub timeout: u64,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct StatusArgs {
+    /// Address of the control server of a running indexer to report the tasks,
+    /// sync positions, lag and errors of
+    #[arg(long, value_name = "ADDRESS")]
+    pub instance: Option<SocketAddr>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct BackfillArgs {
+    pub action: BackfillAction,
+
+    /// Address of the control server of the running indexer
+    #[arg(long, value_name = "ADDRESS")]
+    pub instance: SocketAddr,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ConfirmReorgArgs {
+    /// Address of the control server of the running indexer
+    #[arg(long, value_name = "ADDRESS")]
+    pub instance: SocketAddr,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackfillAction {
+    /// Hold the backfill once its workers finish their current work units
+    Pause,
+    /// Continue the backfill from where it was paused
+    Resume,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct SupportBundleArgs {
+    /// Directory the archive is written to
+    #[arg(short, long, default_value = ".")]
+    pub output_dir: PathBuf,
+
+    /// Log file to include the most recent lines and errors of
+    #[arg(short, long)]
+    pub log_file: Option<PathBuf>,
+
+    /// Amount of log lines to include
+    #[arg(long, default_value_t = 1000)]
+    pub log_lines: usize,
+}
+
+impl Args {
+    /// Returns the indexing arguments, unless another command is run.
+    pub fn index_args(&self) -> Option<&IndexArgs> {
+        match &self.command {
+            Some(Command::Index(index_args)) => Some(index_args),
+            Some(_) => None,
+            None => Some(&self.index),
+        }
+    }
+
+    /// Returns the command to run, falling back to indexing when no subcommand
+    /// is given.
+    pub fn into_command(self) -> Command {
+        self.command
+            .unwrap_or_else(|| Command::Index(Box::new(self.index)))
+    }
+}
+
+impl IndexArgs {
+    /// Rejects the combinations of flags that make no sense together.
+    pub fn validate(&self) -> Result<(), ArgsError> {
+        // Reverse ranges are valid, they're synced backwards
+        if self.from_slot.is_some() && self.from_slot == self.to_slot {
+            return Err(ArgsError::EmptyRange);
+        }
+
+        for (i, range) in self.ranges.iter().enumerate() {
+            if let Some(other) = self.ranges[i + 1..]
+                .iter()
+                .find(|other| range.overlaps(other))
+            {
+                return Err(ArgsError::OverlappingRanges(*range, *other));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ReindexArgs {
+    pub fn block_id(&self) -> BlockId {
+        match (self.slot, self.block_hash) {
+            (Some(slot), _) => BlockId::Slot(slot),
+            (None, Some(hash)) => BlockId::Hash(hash),
+            (None, None) => unreachable!("clap requires either a slot or a block hash"),
+        }
+    }
 }
@@ -0,0 +1,215 @@
+use std::{str::FromStr, time::Duration};
+
+use backoff::{ExponentialBackoff, ExponentialBackoffBuilder};
+use serde::{Deserialize, Deserializer};
+
+/// Kinds of requests with different latency and failure characteristics, each
+/// one retried according to its own [`BackoffProfile`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OperationClass {
+    /// Cheap consensus layer reads such as blocks and headers
+    BeaconRead,
+    /// Blob sidecar reads, which carry multi-megabyte payloads
+    BlobSidecarRead,
+    /// Execution layer reads
+    ExecutionRead,
+    /// Blobscan API reads
+    BlobscanRead,
+    /// Blobscan API writes
+    BlobscanWrite,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BackoffProfile {
+    pub initial_interval: Duration,
+    pub max_interval: Duration,
+    pub multiplier: f64,
+    /// Time after which retrying stops. Retries forever when `None`
+    pub max_elapsed_time: Option<Duration>,
+    /// Timeout of every single attempt
+    pub timeout: Duration,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BackoffProfiles {
+    pub beacon_read: BackoffProfile,
+    pub blob_sidecar_read: BackoffProfile,
+    pub execution_read: BackoffProfile,
+    pub blobscan_read: BackoffProfile,
+    pub blobscan_write: BackoffProfile,
+}
+
+/// Partial [`BackoffProfile`] read from a comma-separated list of settings,
+/// e.g. `initial_interval_ms=500,max_elapsed_time_secs=120,timeout_secs=30`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BackoffProfileOverride {
+    initial_interval: Option<Duration>,
+    max_interval: Option<Duration>,
+    multiplier: Option<f64>,
+    max_elapsed_time: Option<Option<Duration>>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BackoffProfileOverrideError {
+    #[error("invalid setting \"{0}\": expected <key>=<value>")]
+    InvalidSetting(String),
+    #[error("unknown setting \"{0}\"")]
+    UnknownSetting(String),
+    #[error("invalid value \"{value}\" for setting \"{key}\"")]
+    InvalidValue { key: String, value: String },
+}
+
+impl BackoffProfile {
+    pub fn exponential_backoff(&self) -> ExponentialBackoff {
+        ExponentialBackoffBuilder::default()
+            .with_initial_interval(self.initial_interval)
+            .with_max_interval(self.max_interval)
+            .with_multiplier(self.multiplier)
+            .with_max_elapsed_time(self.max_elapsed_time)
+            .build()
+    }
+
+    pub fn apply(&mut self, profile_override: &BackoffProfileOverride) {
+        if let Some(initial_interval) = profile_override.initial_interval {
+            self.initial_interval = initial_interval;
+        }
+
+        if let Some(max_interval) = profile_override.max_interval {
+            self.max_interval = max_interval;
+        }
+
+        if let Some(multiplier) = profile_override.multiplier {
+            self.multiplier = multiplier;
+        }
+
+        if let Some(max_elapsed_time) = profile_override.max_elapsed_time {
+            self.max_elapsed_time = max_elapsed_time;
+        }
+
+        if let Some(timeout) = profile_override.timeout {
+            self.timeout = timeout;
+        }
+    }
+}
+
+impl BackoffProfiles {
+    pub fn get(&self, operation_class: OperationClass) -> &BackoffProfile {
+        match operation_class {
+            OperationClass::BeaconRead => &self.beacon_read,
+            OperationClass::BlobSidecarRead => &self.blob_sidecar_read,
+            OperationClass::ExecutionRead => &self.execution_read,
+            OperationClass::BlobscanRead => &self.blobscan_read,
+            OperationClass::BlobscanWrite => &self.blobscan_write,
+        }
+    }
+
+    pub fn get_mut(&mut self, operation_class: OperationClass) -> &mut BackoffProfile {
+        match operation_class {
+            OperationClass::BeaconRead => &mut self.beacon_read,
+            OperationClass::BlobSidecarRead => &mut self.blob_sidecar_read,
+            OperationClass::ExecutionRead => &mut self.execution_read,
+            OperationClass::BlobscanRead => &mut self.blobscan_read,
+            OperationClass::BlobscanWrite => &mut self.blobscan_write,
+        }
+    }
+}
+
+impl Default for BackoffProfiles {
+    fn default() -> Self {
+        Self {
+            beacon_read: BackoffProfile {
+                initial_interval: Duration::from_millis(500),
+                max_interval: Duration::from_secs(30),
+                multiplier: 1.5,
+                max_elapsed_time: Some(Duration::from_secs(15 * 60)),
+                timeout: Duration::from_secs(8),
+            },
+            blob_sidecar_read: BackoffProfile {
+                initial_interval: Duration::from_secs(1),
+                max_interval: Duration::from_secs(60),
+                multiplier: 2.0,
+                max_elapsed_time: Some(Duration::from_secs(15 * 60)),
+                timeout: Duration::from_secs(30),
+            },
+            execution_read: BackoffProfile {
+                initial_interval: Duration::from_millis(500),
+                max_interval: Duration::from_secs(30),
+                multiplier: 1.5,
+                max_elapsed_time: Some(Duration::from_secs(5 * 60)),
+                timeout: Duration::from_secs(10),
+            },
+            blobscan_read: BackoffProfile {
+                initial_interval: Duration::from_millis(500),
+                max_interval: Duration::from_secs(60),
+                multiplier: 1.5,
+                max_elapsed_time: Some(Duration::from_secs(15 * 60)),
+                timeout: Duration::from_secs(8),
+            },
+            blobscan_write: BackoffProfile {
+                initial_interval: Duration::from_secs(1),
+                max_interval: Duration::from_secs(30),
+                multiplier: 2.0,
+                max_elapsed_time: Some(Duration::from_secs(5 * 60)),
+                timeout: Duration::from_secs(30),
+            },
+        }
+    }
+}
+
+impl FromStr for BackoffProfileOverride {
+    type Err = BackoffProfileOverrideError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut profile_override = BackoffProfileOverride::default();
+
+        for setting in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (key, value) = setting
+                .split_once('=')
+                .ok_or_else(|| BackoffProfileOverrideError::InvalidSetting(setting.to_string()))?;
+            let (key, value) = (key.trim(), value.trim());
+            let invalid_value = || BackoffProfileOverrideError::InvalidValue {
+                key: key.to_string(),
+                value: value.to_string(),
+            };
+            let parse_u64 = || value.parse::<u64>().map_err(|_| invalid_value());
+
+            match key {
+                "initial_interval_ms" => {
+                    profile_override.initial_interval = Some(Duration::from_millis(parse_u64()?))
+                }
+                "max_interval_ms" => {
+                    profile_override.max_interval = Some(Duration::from_millis(parse_u64()?))
+                }
+                "multiplier" => {
+                    profile_override.multiplier =
+                        Some(value.parse::<f64>().map_err(|_| invalid_value())?)
+                }
+                "max_elapsed_time_secs" => {
+                    profile_override.max_elapsed_time = Some(if value == "none" {
+                        None
+                    } else {
+                        Some(Duration::from_secs(parse_u64()?))
+                    })
+                }
+                "timeout_secs" => {
+                    profile_override.timeout = Some(Duration::from_secs(parse_u64()?))
+                }
+                _ => return Err(BackoffProfileOverrideError::UnknownSetting(key.to_string())),
+            }
+        }
+
+        Ok(profile_override)
+    }
+}
+
+impl<'de> Deserialize<'de> for BackoffProfileOverride {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,58 @@
+use std::num::NonZeroUsize;
+
+use alloy::primitives::B256;
+use lru::LruCache;
+
+use super::types::{BlockHeader, BlockId};
+
+/// Recently fetched block headers, looked up by root or by slot.
+///
+/// Headers looked up by root never change, but the header at a slot does when
+/// the slot gets reorged, so slot entries have to be invalidated whenever a
+/// reorg is detected.
+#[derive(Debug)]
+pub struct BlockHeaderCache {
+    headers: LruCache<B256, BlockHeader>,
+    roots_by_slot: LruCache<u32, B256>,
+}
+
+impl BlockHeaderCache {
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            headers: LruCache::new(capacity),
+            roots_by_slot: LruCache::new(capacity),
+        }
+    }
+
+    /// Returns the cached header of the block, if any. Only slots and roots
+    /// are cached, as the block other identifiers point to keeps changing.
+    pub fn get(&mut self, block_id: &BlockId) -> Option<BlockHeader> {
+        let root = match block_id {
+            BlockId::Hash(root) => *root,
+            BlockId::Slot(slot) => *self.roots_by_slot.get(slot)?,
+            BlockId::Head | BlockId::Finalized => return None,
+        };
+
+        self.headers.get(&root).cloned()
+    }
+
+    pub fn insert(&mut self, header: &BlockHeader) {
+        self.roots_by_slot
+            .put(header.header.message.slot, header.root);
+        self.headers.put(header.root, header.clone());
+    }
+
+    /// Forgets which headers are at the given slot and the following ones.
+    pub fn invalidate_slots_from(&mut self, from_slot: u32) {
+        let slots = self
+            .roots_by_slot
+            .iter()
+            .filter(|(&slot, _)| slot >= from_slot)
+            .map(|(&slot, _)| slot)
+            .collect::<Vec<_>>();
+
+        for slot in slots {
+            self.roots_by_slot.pop(&slot);
+        }
+    }
+}
@@ -1,8 +1,12 @@
-use std::fmt::Debug;
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex, MutexGuard},
+};
 
 use anyhow::Context as AnyhowContext;
 use async_trait::async_trait;
-use backoff::ExponentialBackoff;
 
 use reqwest::{Client, Url};
 use reqwest_eventsource::EventSource;
@@ -11,24 +15,51 @@ use reqwest_eventsource::EventSource;
 use mockall::automock;
 
 use crate::{
-    clients::{beacon::types::BlockHeaderResponse, common::ClientResult},
+    clients::{
+        backoff::{BackoffProfile, BackoffProfiles, OperationClass},
+        beacon::types::BlockHeaderResponse,
+        common::ClientResult,
+    },
     json_get,
 };
 
-use self::types::{Blob, BlobsResponse, Block, BlockHeader, BlockId, BlockResponse, Topic};
+use self::{
+    cache::BlockHeaderCache,
+    types::{
+        Blob, BlobsResponse, Block, BlockHeader, BlockId, BlockResponse, Fork,
+        ForkScheduleResponse, Genesis, GenesisResponse, NodeVersionResponse, SpecResponse, Topic,
+    },
+};
 
+mod cache;
 pub mod types;
 
 #[derive(Debug, Clone)]
 pub struct BeaconClient {
     base_url: Url,
+    blob_sidecars_base_url: Url,
+    events_base_url: Url,
     client: Client,
-    exp_backoff: Option<ExponentialBackoff>,
+    events_client: Option<Client>,
+    backoff_profiles: Option<BackoffProfiles>,
+    header_cache: Option<Arc<Mutex<BlockHeaderCache>>>,
 }
 
 pub struct Config {
     pub base_url: String,
-    pub exp_backoff: Option<ExponentialBackoff>,
+    /// Node the blob sidecars are fetched from, such as an archive node
+    /// keeping them past the retention period. The main node is used when not
+    /// set
+    pub blob_sidecars_base_url: Option<String>,
+    /// Node the event stream is subscribed to, such as a low-latency one. The
+    /// main node is used when not set
+    pub events_base_url: Option<String>,
+    pub backoff_profiles: Option<BackoffProfiles>,
+    /// Amount of recently fetched block headers kept in memory. Disabled when 0
+    pub header_cache_size: usize,
+    /// Client the event stream is opened with, which mustn't time out as the
+    /// stream is long-lived. A default client is used when not set
+    pub events_client: Option<Client>,
 }
 
 #[async_trait]
@@ -37,21 +68,60 @@ pub trait CommonBeaconClient: Send + Sync + Debug {
     async fn get_block(&self, block_id: &BlockId) -> ClientResult<Option<Block>>;
     async fn get_block_header(&self, block_id: &BlockId) -> ClientResult<Option<BlockHeader>>;
     async fn get_blobs(&self, block_id: &BlockId) -> ClientResult<Option<Vec<Blob>>>;
+    async fn get_node_version(&self) -> ClientResult<Option<String>>;
+    async fn get_spec(&self) -> ClientResult<Option<HashMap<String, serde_json::Value>>>;
+    async fn get_fork_schedule(&self) -> ClientResult<Option<Vec<Fork>>>;
+    async fn get_genesis(&self) -> ClientResult<Option<Genesis>>;
     fn subscribe_to_events(&self, topics: &[Topic]) -> ClientResult<EventSource>;
+    /// Stops serving the cached headers of the given slot and the following
+    /// ones, as they may have been reorged.
+    fn invalidate_block_headers(&self, from_slot: u32);
 }
 
 impl BeaconClient {
     pub fn try_with_client(client: Client, config: Config) -> ClientResult<Self> {
-        let base_url = Url::parse(&format!("{}/eth/", config.base_url))
-            .with_context(|| "Failed to parse base URL")?;
-        let exp_backoff = config.exp_backoff;
+        let base_url = eth_base_url(&config.base_url)?;
+        let blob_sidecars_base_url = match &config.blob_sidecars_base_url {
+            Some(blob_sidecars_base_url) => eth_base_url(blob_sidecars_base_url)?,
+            None => base_url.clone(),
+        };
+        let events_base_url = match &config.events_base_url {
+            Some(events_base_url) => eth_base_url(events_base_url)?,
+            None => base_url.clone(),
+        };
+        let backoff_profiles = config.backoff_profiles;
+        let header_cache = NonZeroUsize::new(config.header_cache_size)
+            .map(|capacity| Arc::new(Mutex::new(BlockHeaderCache::new(capacity))));
 
         Ok(Self {
             base_url,
+            blob_sidecars_base_url,
+            events_base_url,
             client,
-            exp_backoff,
+            events_client: config.events_client,
+            backoff_profiles,
+            header_cache,
         })
     }
+
+    fn header_cache(&self) -> Option<MutexGuard<'_, BlockHeaderCache>> {
+        self.header_cache
+            .as_ref()
+            .map(|header_cache| match header_cache.lock() {
+                Ok(header_cache) => header_cache,
+                Err(poisoned) => poisoned.into_inner(),
+            })
+    }
+
+    fn backoff_profile(&self, operation_class: OperationClass) -> Option<&BackoffProfile> {
+        self.backoff_profiles
+            .as_ref()
+            .map(|profiles| profiles.get(operation_class))
+    }
+}
+
+fn eth_base_url(url: &str) -> ClientResult<Url> {
+    Ok(Url::parse(&format!("{url}/eth/")).with_context(|| "Failed to parse base URL")?)
 }
 
 #[async_trait]
@@ -60,40 +130,110 @@ impl CommonBeaconClient for BeaconClient {
         let path = format!("v2/beacon/blocks/{}", { block_id.to_detailed_string() });
         let url = self.base_url.join(path.as_str())?;
 
-        json_get!(&self.client, url, BlockResponse, self.exp_backoff.clone()).map(|res| match res {
+        json_get!(
+            &self.client,
+            url,
+            BlockResponse,
+            self.backoff_profile(OperationClass::BeaconRead)
+        )
+        .map(|res| match res {
             Some(r) => Some(r.data),
             None => None,
         })
     }
 
     async fn get_block_header(&self, block_id: &BlockId) -> ClientResult<Option<BlockHeader>> {
+        if let Some(header) = self
+            .header_cache()
+            .and_then(|mut header_cache| header_cache.get(block_id))
+        {
+            return Ok(Some(header));
+        }
+
         let path = format!("v1/beacon/headers/{}", { block_id.to_detailed_string() });
         let url = self.base_url.join(path.as_str())?;
 
-        json_get!(
+        let header = json_get!(
             &self.client,
             url,
             BlockHeaderResponse,
-            self.exp_backoff.clone()
-        )
-        .map(|res| match res {
-            Some(r) => Some(r.data),
-            None => None,
-        })
+            self.backoff_profile(OperationClass::BeaconRead)
+        )?
+        .map(|res| res.data);
+
+        if let (Some(header), Some(mut header_cache)) = (&header, self.header_cache()) {
+            header_cache.insert(header);
+        }
+
+        Ok(header)
     }
 
     async fn get_blobs(&self, block_id: &BlockId) -> ClientResult<Option<Vec<Blob>>> {
         let path = format!("v1/beacon/blob_sidecars/{}", {
             block_id.to_detailed_string()
         });
-        let url = self.base_url.join(path.as_str())?;
+        let url = self.blob_sidecars_base_url.join(path.as_str())?;
 
-        json_get!(&self.client, url, BlobsResponse, self.exp_backoff.clone()).map(|res| match res {
+        json_get!(
+            &self.client,
+            url,
+            BlobsResponse,
+            self.backoff_profile(OperationClass::BlobSidecarRead)
+        )
+        .map(|res| match res {
             Some(r) => Some(r.data),
             None => None,
         })
     }
 
+    async fn get_node_version(&self) -> ClientResult<Option<String>> {
+        let url = self.base_url.join("v1/node/version")?;
+
+        json_get!(
+            &self.client,
+            url,
+            NodeVersionResponse,
+            self.backoff_profile(OperationClass::BeaconRead)
+        )
+        .map(|res| res.map(|r| r.data.version))
+    }
+
+    async fn get_spec(&self) -> ClientResult<Option<HashMap<String, serde_json::Value>>> {
+        let url = self.base_url.join("v1/config/spec")?;
+
+        json_get!(
+            &self.client,
+            url,
+            SpecResponse,
+            self.backoff_profile(OperationClass::BeaconRead)
+        )
+        .map(|res| res.map(|r| r.data))
+    }
+
+    async fn get_fork_schedule(&self) -> ClientResult<Option<Vec<Fork>>> {
+        let url = self.base_url.join("v1/config/fork_schedule")?;
+
+        json_get!(
+            &self.client,
+            url,
+            ForkScheduleResponse,
+            self.backoff_profile(OperationClass::BeaconRead)
+        )
+        .map(|res| res.map(|r| r.data))
+    }
+
+    async fn get_genesis(&self) -> ClientResult<Option<Genesis>> {
+        let url = self.base_url.join("v1/beacon/genesis")?;
+
+        json_get!(
+            &self.client,
+            url,
+            GenesisResponse,
+            self.backoff_profile(OperationClass::BeaconRead)
+        )
+        .map(|res| res.map(|r| r.data))
+    }
+
     fn subscribe_to_events(&self, topics: &[Topic]) -> ClientResult<EventSource> {
         let topics = topics
             .iter()
@@ -101,8 +241,18 @@ impl CommonBeaconClient for BeaconClient {
             .collect::<Vec<String>>()
             .join(",");
         let path = format!("v1/events?topics={topics}");
-        let url = self.base_url.join(&path)?;
+        let url = self.events_base_url.join(&path)?;
+
+        match &self.events_client {
+            Some(events_client) => Ok(EventSource::new(events_client.get(url))
+                .with_context(|| "Failed to build the event stream request")?),
+            None => Ok(EventSource::get(url)),
+        }
+    }
 
-        Ok(EventSource::get(url))
+    fn invalidate_block_headers(&self, from_slot: u32) {
+        if let Some(mut header_cache) = self.header_cache() {
+            header_cache.invalidate_slots_from(from_slot);
+        }
     }
 }
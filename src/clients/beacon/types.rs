@@ -1,4 +1,4 @@
-use std::{fmt, str::FromStr};
+use std::{collections::HashMap, fmt, str::FromStr};
 
 use alloy::primitives::{Bytes, B256};
 use serde::{Deserialize, Serialize};
@@ -16,6 +16,7 @@ pub enum BlockId {
 pub enum Topic {
     Head,
     FinalizedCheckpoint,
+    ChainReorg,
 }
 
 #[derive(Deserialize, Debug)]
@@ -32,6 +33,8 @@ pub struct BlockBody {
 }
 #[derive(Deserialize, Debug)]
 pub struct BlockMessage {
+    #[serde(deserialize_with = "deserialize_number")]
+    pub proposer_index: u32,
     pub body: BlockBody,
 }
 
@@ -45,11 +48,45 @@ pub struct BlockResponse {
     pub data: Block,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct Blob {
+    /// Position of the blob within the block
+    #[serde(deserialize_with = "deserialize_number")]
+    pub index: u32,
     pub kzg_commitment: String,
     pub kzg_proof: String,
     pub blob: Bytes,
+    /// Merkle branch proving the commitment is part of the block body. Not
+    /// served by every beacon node API
+    #[serde(default)]
+    pub kzg_commitment_inclusion_proof: Vec<B256>,
+    /// Header of the block the blob belongs to, as signed by its proposer
+    #[serde(default)]
+    pub signed_block_header: Option<SignedBeaconBlockHeader>,
+}
+
+/// Serialized in camel case to be forwarded to Blobscan as is. Read back in
+/// either case, as exported blocks carry the camel case one
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct SignedBeaconBlockHeader {
+    pub message: BeaconBlockHeader,
+    pub signature: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct BeaconBlockHeader {
+    #[serde(deserialize_with = "deserialize_number")]
+    pub slot: u32,
+    #[serde(deserialize_with = "deserialize_number", alias = "proposerIndex")]
+    pub proposer_index: u32,
+    #[serde(alias = "parentRoot")]
+    pub parent_root: B256,
+    #[serde(alias = "stateRoot")]
+    pub state_root: B256,
+    #[serde(alias = "bodyRoot")]
+    pub body_root: B256,
 }
 
 #[derive(Deserialize, Debug)]
@@ -57,22 +94,64 @@ pub struct BlobsResponse {
     pub data: Vec<Blob>,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct NodeVersion {
+    pub version: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NodeVersionResponse {
+    pub data: NodeVersion,
+}
+
+/// Chain configuration and preset values of the beacon node, keyed by their
+/// spec name (e.g. `DENEB_FORK_EPOCH`). Most values are given as strings.
+#[derive(Deserialize, Debug)]
+pub struct SpecResponse {
+    pub data: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GenesisResponse {
+    pub data: Genesis,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Genesis {
+    /// Unix timestamp, given as a string
+    pub genesis_time: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ForkScheduleResponse {
+    pub data: Vec<Fork>,
+}
+
+/// Fork scheduled by the beacon node, identified by its version.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Fork {
+    pub previous_version: String,
+    pub current_version: String,
+    /// Activation epoch, kept as a string as unscheduled forks use `u64::MAX`
+    pub epoch: String,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct BlockHeaderResponse {
     pub data: BlockHeader,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct BlockHeader {
     pub root: B256,
     pub header: InnerBlockHeader,
 }
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct InnerBlockHeader {
     pub message: BlockHeaderMessage,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct BlockHeaderMessage {
     pub parent_root: B256,
     #[serde(deserialize_with = "deserialize_number")]
@@ -86,18 +165,40 @@ pub struct HeadEventData {
     pub block: B256,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct ChainReorgEventData {
+    /// Slot of the new head
+    #[serde(deserialize_with = "deserialize_number")]
+    pub slot: u32,
+    /// Amount of slots between the new head and the common ancestor
+    #[serde(deserialize_with = "deserialize_number")]
+    pub depth: u32,
+    pub old_head_block: B256,
+    pub new_head_block: B256,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct FinalizedCheckpointEventData {
     pub block: B256,
 }
 
+/// Beacon nodes send numbers as strings, while the ones serialized by the
+/// indexer itself, e.g. in exported blocks, are plain numbers.
 fn deserialize_number<'de, D>(deserializer: D) -> Result<u32, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
-    let value = String::deserialize(deserializer)?;
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Number {
+        String(String),
+        Number(u32),
+    }
 
-    value.parse::<u32>().map_err(serde::de::Error::custom)
+    match Number::deserialize(deserializer)? {
+        Number::String(value) => value.parse::<u32>().map_err(serde::de::Error::custom),
+        Number::Number(value) => Ok(value),
+    }
 }
 
 impl BlockId {
@@ -153,6 +254,37 @@ impl From<&Topic> for String {
         match value {
             Topic::Head => String::from("head"),
             Topic::FinalizedCheckpoint => String::from("finalized_checkpoint"),
+            Topic::ChainReorg => String::from("chain_reorg"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::SignedBeaconBlockHeader;
+
+    #[test]
+    fn signed_block_headers_are_read_back_once_serialized() {
+        let header: SignedBeaconBlockHeader = serde_json::from_value(json!({
+            "message": {
+                "slot": "10",
+                "proposer_index": "42",
+                "parent_root": format!("0x{}", "11".repeat(32)),
+                "state_root": format!("0x{}", "22".repeat(32)),
+                "body_root": format!("0x{}", "33".repeat(32)),
+            },
+            "signature": "0x00",
+        }))
+        .unwrap();
+        let serialized = serde_json::to_value(&header).unwrap();
+
+        assert_eq!(serialized["message"]["proposerIndex"], 42);
+
+        let header: SignedBeaconBlockHeader = serde_json::from_value(serialized).unwrap();
+
+        assert_eq!(header.message.slot, 10);
+        assert_eq!(header.message.proposer_index, 42);
+    }
+}
@@ -0,0 +1,116 @@
+use std::{
+    io,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use serde::Serialize;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tracing::debug;
+
+use crate::{clients::priority::RequestPriority, metrics::set_gauge};
+
+/// Keeps track of the request bodies currently being sent to Blobscan and,
+/// when a cap is set, makes new requests wait until enough bytes are released.
+#[derive(Debug)]
+pub struct InflightRequests {
+    semaphore: Option<Semaphore>,
+    max_bytes: usize,
+    count: AtomicUsize,
+    bytes: AtomicUsize,
+}
+
+pub struct InflightRequestGuard<'a> {
+    requests: &'a InflightRequests,
+    size: usize,
+    _permit: Option<SemaphorePermit<'a>>,
+}
+
+impl InflightRequests {
+    pub fn new(max_bytes: Option<usize>) -> Self {
+        let max_bytes = max_bytes.map(|max_bytes| max_bytes.clamp(1, u32::MAX as usize));
+
+        Self {
+            semaphore: max_bytes.map(Semaphore::new),
+            max_bytes: max_bytes.unwrap_or(usize::MAX),
+            count: AtomicUsize::new(0),
+            bytes: AtomicUsize::new(0),
+        }
+    }
+
+    /// Registers a request body of the given size, waiting first if it would
+    /// exceed the cap. Bodies bigger than the cap itself are sent alone. High
+    /// priority requests don't wait.
+    pub async fn acquire(&self, size: usize) -> InflightRequestGuard<'_> {
+        let permit = match &self.semaphore {
+            Some(_) if RequestPriority::current().is_high() => None,
+            Some(semaphore) => {
+                let permits = size.clamp(1, self.max_bytes);
+
+                if semaphore.available_permits() < permits {
+                    debug!(
+                        size,
+                        inflight_bytes = self.bytes.load(Ordering::Relaxed),
+                        "Waiting for in-flight index requests to complete…"
+                    );
+                }
+
+                // The semaphore is never closed
+                semaphore.acquire_many(permits as u32).await.ok()
+            }
+            None => None,
+        };
+
+        let count = self.count.fetch_add(1, Ordering::Relaxed) + 1;
+        let bytes = self.bytes.fetch_add(size, Ordering::Relaxed) + size;
+
+        report(count, bytes);
+
+        InflightRequestGuard {
+            requests: self,
+            size,
+            _permit: permit,
+        }
+    }
+}
+
+impl Drop for InflightRequestGuard<'_> {
+    fn drop(&mut self) {
+        let count = self.requests.count.fetch_sub(1, Ordering::Relaxed) - 1;
+        let bytes = self.requests.bytes.fetch_sub(self.size, Ordering::Relaxed) - self.size;
+
+        report(count, bytes);
+    }
+}
+
+fn report(count: usize, bytes: usize) {
+    set_gauge("blob_indexer_inflight_index_requests", &[], count as u64);
+    set_gauge(
+        "blob_indexer_inflight_index_request_bytes",
+        &[],
+        bytes as u64,
+    );
+}
+
+/// Returns the length of the JSON representation of the given value without
+/// keeping it in memory.
+pub fn serialized_size<T: Serialize>(value: &T) -> serde_json::Result<usize> {
+    let mut counter = ByteCounter(0);
+
+    serde_json::to_writer(&mut counter, value)?;
+
+    Ok(counter.0)
+}
+
+struct ByteCounter(usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -1,10 +1,16 @@
 use std::sync::{Arc, Mutex};
 
-use chrono::{Duration, TimeDelta, Utc};
+use chrono::{Duration, Utc};
 use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
 use serde::{Deserialize, Serialize};
 use tracing::{debug, error};
 
+/// Default lifetime of the created tokens
+pub const DEFAULT_REFRESH_INTERVAL: Duration = Duration::hours(1);
+
+/// Default time before their expiration tokens are refreshed
+pub const DEFAULT_SAFETY_MARGIN: Duration = Duration::minutes(1);
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     exp: usize,
@@ -19,12 +25,51 @@ pub struct JWTManager {
     secret_key: String,
     refresh_interval: Duration,
     safety_margin: Duration,
+    algorithm: JwtAlgorithm,
 }
 
+#[derive(Debug, Clone)]
 pub struct Config {
     pub secret_key: String,
+    /// Lifetime of the created tokens
     pub refresh_interval: Duration,
-    pub safety_magin: Option<Duration>,
+    /// Time before their expiration tokens are refreshed
+    pub safety_margin: Option<Duration>,
+    pub algorithm: JwtAlgorithm,
+}
+
+/// Algorithm tokens are signed with. Only HMAC ones are supported, as the
+/// secret key is shared with the API.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum JwtAlgorithm {
+    #[serde(rename = "HS256")]
+    Hs256,
+    #[serde(rename = "HS384")]
+    Hs384,
+    #[default]
+    #[serde(rename = "HS512")]
+    Hs512,
+}
+
+impl Config {
+    pub fn new(secret_key: String) -> Self {
+        Self {
+            secret_key,
+            refresh_interval: DEFAULT_REFRESH_INTERVAL,
+            safety_margin: None,
+            algorithm: JwtAlgorithm::default(),
+        }
+    }
+}
+
+impl From<JwtAlgorithm> for Algorithm {
+    fn from(algorithm: JwtAlgorithm) -> Self {
+        match algorithm {
+            JwtAlgorithm::Hs256 => Algorithm::HS256,
+            JwtAlgorithm::Hs384 => Algorithm::HS384,
+            JwtAlgorithm::Hs512 => Algorithm::HS512,
+        }
+    }
 }
 
 impl JWTManager {
@@ -34,10 +79,8 @@ impl JWTManager {
             expiration_date: Arc::new(Mutex::new(None)),
             secret_key: config.secret_key,
             refresh_interval: config.refresh_interval,
-            safety_margin: match config.safety_magin {
-                Some(safety_margin) => safety_margin,
-                None => TimeDelta::try_minutes(1).unwrap(),
-            },
+            safety_margin: config.safety_margin.unwrap_or(DEFAULT_SAFETY_MARGIN),
+            algorithm: config.algorithm,
         }
     }
 
@@ -78,13 +121,26 @@ impl JWTManager {
         }
     }
 
+    /// Replaces the current token with a new one, for when the API rejects it
+    /// before it expires, e.g. after its secret was rotated.
+    pub fn refresh_token(&self) -> Result<String, anyhow::Error> {
+        let mut token_guard = self.token.lock().unwrap();
+        let mut expr_guard = self.expiration_date.lock().unwrap();
+        let (token, expiration_date) = self.create_token()?;
+
+        *token_guard = Some(token.clone());
+        *expr_guard = Some(expiration_date);
+
+        Ok(token)
+    }
+
     fn create_token(&self) -> Result<(String, chrono::DateTime<Utc>), anyhow::Error> {
         let encoding_key = EncodingKey::from_secret(self.secret_key.as_ref());
         let expiration_date = chrono::Utc::now() + self.refresh_interval;
         let claims = Claims {
             exp: expiration_date.timestamp() as usize,
         };
-        let header = Header::new(Algorithm::HS512);
+        let header = Header::new(self.algorithm.into());
 
         match encode(&header, &claims, &encoding_key) {
             Err(error) => {
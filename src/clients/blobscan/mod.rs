@@ -1,27 +1,37 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, future::Future, sync::Arc};
 
 use async_trait::async_trait;
-use backoff::ExponentialBackoff;
-use chrono::TimeDelta;
 use reqwest::{Client, Url};
+use tracing::warn;
 
 #[cfg(test)]
 use mockall::automock;
 
 use crate::{
-    clients::{blobscan::types::ReorgedSlotsResponse, common::ClientResult},
-    json_get, json_put,
+    clients::{
+        backoff::{BackoffProfile, BackoffProfiles, OperationClass},
+        blobscan::types::ReorgedSlotsResponse,
+        body::StreamedJson,
+        common::{ClientError, ClientResult, Credential},
+        execution::AuthHeader,
+    },
+    json_get, json_post, json_put,
+    metrics::increment_counter,
 };
 
 use self::{
+    inflight::{serialized_size, InflightRequests},
     jwt_manager::{Config as JWTManagerConfig, JWTManager},
     types::{
-        Blob, Block, BlockchainSyncState, BlockchainSyncStateRequest, BlockchainSyncStateResponse,
-        IndexRequest, ReorgedSlotsRequest, Transaction,
+        ApiVersion, Blob, Block, BlockResponse, BlockchainSyncState, BlockchainSyncStateRequest,
+        BlockchainSyncStateResponse, IndexRequest, IndexerMetadata, ReorgedSlotsRequest,
+        Transaction,
     },
 };
 
-mod jwt_manager;
+mod inflight;
+
+pub mod jwt_manager;
 
 pub mod types;
 
@@ -40,20 +50,50 @@ pub trait CommonBlobscanClient: Send + Sync + Debug {
     async fn handle_reorged_slots(&self, slots: &[u32]) -> ClientResult<u32>;
     async fn update_sync_state(&self, sync_state: BlockchainSyncState) -> ClientResult<()>;
     async fn get_sync_state(&self) -> ClientResult<Option<BlockchainSyncState>>;
+    async fn get_block(&self, slot: u32) -> ClientResult<Option<BlockResponse>>;
+    async fn send_metadata(&self, metadata: &IndexerMetadata) -> ClientResult<()>;
+    /// Returns the API version, or `None` for versions predating the endpoint.
+    async fn get_api_version(&self) -> ClientResult<Option<ApiVersion>>;
 }
 
 #[derive(Debug, Clone)]
 pub struct BlobscanClient {
     base_url: Url,
     client: reqwest::Client,
-    jwt_manager: JWTManager,
-    exp_backoff: Option<ExponentialBackoff>,
+    authenticator: Authenticator,
+    backoff_profiles: Option<BackoffProfiles>,
+    inflight_requests: Arc<InflightRequests>,
+    drop_invalid_entities: bool,
+    omit_blob_data: bool,
+}
+
+/// How the indexer authenticates to the Blobscan API
+#[derive(Debug, Clone)]
+pub enum Auth {
+    /// JWTs signed with the secret key shared with the API
+    Jwt(JWTManagerConfig),
+    /// Static header, for deployments behind a gateway handling the auth, e.g.
+    /// through API keys
+    Header(AuthHeader),
+}
+
+#[derive(Debug, Clone)]
+enum Authenticator {
+    Jwt(JWTManager),
+    Header(AuthHeader),
 }
 
 pub struct Config {
     pub base_url: String,
-    pub secret_key: String,
-    pub exp_backoff: Option<ExponentialBackoff>,
+    pub auth: Auth,
+    pub backoff_profiles: Option<BackoffProfiles>,
+    /// Maximum combined size of the index request bodies being sent at once
+    pub max_inflight_request_bytes: Option<usize>,
+    /// Resubmit index requests rejected by validation errors without the
+    /// offending transactions and blobs
+    pub drop_invalid_entities: bool,
+    /// Send blobs without their contents, only with their metadata
+    pub omit_blob_data: bool,
 }
 
 #[async_trait]
@@ -61,18 +101,21 @@ pub struct Config {
 impl CommonBlobscanClient for BlobscanClient {
     fn try_with_client(client: Client, config: Config) -> ClientResult<Self> {
         let base_url = Url::parse(&format!("{}/", config.base_url))?;
-        let jwt_manager = JWTManager::new(JWTManagerConfig {
-            secret_key: config.secret_key,
-            refresh_interval: TimeDelta::try_hours(1).unwrap(),
-            safety_magin: None,
-        });
-        let exp_backoff = config.exp_backoff;
+        let authenticator = match config.auth {
+            Auth::Jwt(jwt) => Authenticator::Jwt(JWTManager::new(jwt)),
+            Auth::Header(header) => Authenticator::Header(header),
+        };
+        let backoff_profiles = config.backoff_profiles;
+        let inflight_requests = Arc::new(InflightRequests::new(config.max_inflight_request_bytes));
 
         Ok(Self {
             base_url,
             client,
-            jwt_manager,
-            exp_backoff,
+            authenticator,
+            backoff_profiles,
+            inflight_requests,
+            drop_invalid_entities: config.drop_invalid_entities,
+            omit_blob_data: config.omit_blob_data,
         })
     }
 
@@ -80,36 +123,98 @@ impl CommonBlobscanClient for BlobscanClient {
         &self,
         block: Block,
         transactions: Vec<Transaction>,
-        blobs: Vec<Blob>,
+        mut blobs: Vec<Blob>,
     ) -> ClientResult<()> {
-        let url = self.base_url.join("indexer/block-txs-blobs")?;
-        let token = self.jwt_manager.get_token()?;
-        let req = IndexRequest {
+        if self.omit_blob_data {
+            for blob in &mut blobs {
+                blob.data = None;
+            }
+        }
+
+        let mut req = Arc::new(IndexRequest {
             block,
             transactions,
             blobs,
-        };
+        });
+        let mut size = serialized_size(req.as_ref())?;
+        let _inflight_request = self.inflight_requests.acquire(size).await;
+
+        loop {
+            let error = match self
+                .send_index_request(StreamedJson::new(req.clone(), size))
+                .await
+            {
+                Err(ClientError::ApiError(error)) if !error.issues.is_empty() => error,
+                result => return result,
+            };
+
+            for issue in &error.issues {
+                warn!(
+                    slot = req.block.slot,
+                    entity = req.describe_entity(issue),
+                    %issue,
+                    "Blobscan API rejected indexed entity"
+                );
+            }
+
+            if !self.drop_invalid_entities {
+                return Err(ClientError::ApiError(error));
+            }
+
+            let Some((dropped_transactions, dropped_blobs)) =
+                Arc::make_mut(&mut req).remove_invalid_entities(&error.issues)
+            else {
+                return Err(ClientError::ApiError(error));
+            };
+
+            size = serialized_size(req.as_ref())?;
 
-        json_put!(&self.client, url, token, &req).map(|_: Option<()>| ())
+            increment_counter("blob_indexer_partial_index_resubmissions_total", &[]);
+
+            warn!(
+                slot = req.block.slot,
+                dropped_transactions,
+                dropped_blobs,
+                "Resubmitting block without the rejected entities…"
+            );
+        }
     }
 
     async fn handle_reorged_slots(&self, slots: &[u32]) -> ClientResult<u32> {
         let url = self.base_url.join("indexer/reorged-slots")?;
-        let token = self.jwt_manager.get_token()?;
         let req = ReorgedSlotsRequest {
             reorged_slots: slots.to_owned(),
         };
 
-        json_put!(&self.client, url, ReorgedSlotsResponse, token, &req)
+        self.with_credential(|credential| async {
+            json_put!(
+                &self.client,
+                url.clone(),
+                ReorgedSlotsResponse,
+                credential,
+                &req,
+                self.backoff_profile(OperationClass::BlobscanWrite)
+            )
             .map(|res: Option<ReorgedSlotsResponse>| res.unwrap().total_updated_slots)
+        })
+        .await
     }
 
     async fn update_sync_state(&self, sync_state: BlockchainSyncState) -> ClientResult<()> {
         let url = self.base_url.join("blockchain-sync-state")?;
-        let token = self.jwt_manager.get_token()?;
         let req: BlockchainSyncStateRequest = sync_state.into();
 
-        json_put!(&self.client, url, token, &req).map(|_: Option<()>| ())
+        self.with_credential(|credential| async {
+            json_put!(
+                &self.client,
+                url.clone(),
+                credential,
+                &req,
+                self.backoff_profile(OperationClass::BlobscanWrite)
+            )
+            .map(|_: Option<()>| ())
+        })
+        .await
     }
 
     async fn get_sync_state(&self) -> ClientResult<Option<BlockchainSyncState>> {
@@ -118,8 +223,211 @@ impl CommonBlobscanClient for BlobscanClient {
             &self.client,
             url,
             BlockchainSyncStateResponse,
-            self.exp_backoff.clone()
+            self.backoff_profile(OperationClass::BlobscanRead)
         )
         .map(|res: Option<BlockchainSyncStateResponse>| Some(res.unwrap().into()))
     }
+
+    async fn get_block(&self, slot: u32) -> ClientResult<Option<BlockResponse>> {
+        let url = self.base_url.join(&format!("slots/{slot}"))?;
+
+        json_get!(
+            &self.client,
+            url,
+            BlockResponse,
+            self.backoff_profile(OperationClass::BlobscanRead)
+        )
+    }
+
+    async fn send_metadata(&self, metadata: &IndexerMetadata) -> ClientResult<()> {
+        let url = self.base_url.join("indexer/metadata")?;
+
+        self.with_credential(|credential| async {
+            json_post!(&self.client, url.clone(), credential, metadata, None)
+                .map(|_: Option<()>| ())
+        })
+        .await
+    }
+
+    async fn get_api_version(&self) -> ClientResult<Option<ApiVersion>> {
+        let url = self.base_url.join("version")?;
+
+        // Not retried, so an unreachable API is reported right away at startup
+        json_get!(&self.client, url, ApiVersion, None)
+    }
+}
+
+impl BlobscanClient {
+    async fn send_index_request(&self, req: StreamedJson<IndexRequest>) -> ClientResult<()> {
+        let url = self.base_url.join("indexer/block-txs-blobs")?;
+
+        self.with_credential(|credential| async {
+            json_put!(
+                &self.client,
+                url.clone(),
+                credential,
+                req.clone(),
+                self.backoff_profile(OperationClass::BlobscanWrite)
+            )
+            .map(|_: Option<()>| ())
+        })
+        .await
+    }
+
+    /// Sends an authenticated request. If the API rejects the JWT, e.g. after
+    /// the secret was rotated or because of clock skew, a new one is created
+    /// and the request is retried once.
+    async fn with_credential<T, F, Fut>(&self, send: F) -> ClientResult<T>
+    where
+        F: Fn(Credential) -> Fut,
+        Fut: Future<Output = ClientResult<T>>,
+    {
+        let jwt_manager = match &self.authenticator {
+            Authenticator::Jwt(jwt_manager) => jwt_manager,
+            Authenticator::Header(header) => return send(Credential::Header(header.clone())).await,
+        };
+
+        match send(Credential::Bearer(jwt_manager.get_token()?)).await {
+            Err(ClientError::Unauthorized { .. }) => {
+                warn!("Blobscan API rejected the JWT. Refreshing it and retrying…");
+
+                increment_counter("blob_indexer_jwt_refreshes_total", &[]);
+
+                send(Credential::Bearer(jwt_manager.refresh_token()?)).await
+            }
+            result => result,
+        }
+    }
+
+    fn backoff_profile(&self, operation_class: OperationClass) -> Option<&BackoffProfile> {
+        self.backoff_profiles
+            .as_ref()
+            .map(|profiles| profiles.get(operation_class))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use hyper::{
+        header::AUTHORIZATION,
+        service::{make_service_fn, service_fn},
+        Body, Response, Server,
+    };
+
+    use super::{
+        jwt_manager::Config as JWTManagerConfig, types::BlockchainSyncState, Auth, Authenticator,
+        BlobscanClient, CommonBlobscanClient, Config,
+    };
+    use crate::{clients::common::ClientError, metrics};
+
+    /// Serves the given statuses in order, recording the authorization header
+    /// of every request.
+    fn serve(statuses: Vec<u16>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let authorizations = Arc::new(Mutex::new(vec![]));
+        let server_authorizations = authorizations.clone();
+        let make_service = make_service_fn(move |_| {
+            let authorizations = server_authorizations.clone();
+            let statuses = statuses.clone();
+
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| {
+                    let mut authorizations = authorizations.lock().unwrap();
+                    let status = statuses[authorizations.len().min(statuses.len() - 1)];
+
+                    authorizations.push(
+                        req.headers()
+                            .get(AUTHORIZATION)
+                            .and_then(|value| value.to_str().ok())
+                            .unwrap_or_default()
+                            .to_string(),
+                    );
+
+                    async move {
+                        Ok::<_, Infallible>(
+                            Response::builder()
+                                .status(status)
+                                .body(Body::from("null"))
+                                .unwrap(),
+                        )
+                    }
+                }))
+            }
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let url = format!("http://{}", server.local_addr());
+
+        tokio::spawn(server);
+
+        (url, authorizations)
+    }
+
+    fn jwt_client(base_url: String) -> BlobscanClient {
+        BlobscanClient::try_with_client(
+            reqwest::Client::new(),
+            Config {
+                base_url,
+                auth: Auth::Jwt(JWTManagerConfig::new("secret".to_string())),
+                backoff_profiles: None,
+                max_inflight_request_bytes: None,
+                drop_invalid_entities: false,
+                omit_blob_data: false,
+            },
+        )
+        .unwrap()
+    }
+
+    fn sync_state() -> BlockchainSyncState {
+        BlockchainSyncState {
+            last_finalized_block: None,
+            last_lower_synced_slot: Some(1),
+            last_upper_synced_slot: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn rejected_jwt_is_refreshed_once() {
+        let refreshes = metrics::counter("blob_indexer_jwt_refreshes_total", &[]);
+        let (url, authorizations) = serve(vec![401, 200]);
+        let client = jwt_client(url);
+        let Authenticator::Jwt(jwt_manager) = &client.authenticator else {
+            unreachable!();
+        };
+        let stale_token = jwt_manager.get_token().unwrap();
+
+        // Tokens only change once their expiration, in seconds, does
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+
+        client.update_sync_state(sync_state()).await.unwrap();
+
+        let authorizations = authorizations.lock().unwrap().clone();
+
+        assert_eq!(authorizations.len(), 2);
+        assert_eq!(authorizations[0], format!("Bearer {stale_token}"));
+        assert_ne!(authorizations[1], authorizations[0]);
+        assert_eq!(
+            authorizations[1],
+            format!("Bearer {}", jwt_manager.get_token().unwrap())
+        );
+        assert_eq!(
+            metrics::counter("blob_indexer_jwt_refreshes_total", &[]),
+            refreshes + 1
+        );
+
+        // A token rejected again isn't refreshed a second time
+        let (url, authorizations) = serve(vec![401]);
+        let result = jwt_client(url).update_sync_state(sync_state()).await;
+
+        assert!(matches!(result, Err(ClientError::Unauthorized { .. })));
+        assert_eq!(authorizations.lock().unwrap().len(), 2);
+        assert_eq!(
+            metrics::counter("blob_indexer_jwt_refreshes_total", &[]),
+            refreshes + 2
+        );
+    }
 }
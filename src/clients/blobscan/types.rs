@@ -1,4 +1,5 @@
 use core::fmt;
+use std::collections::HashSet;
 
 use alloy::primitives::{Address, BlockNumber, BlockTimestamp, Bytes, TxIndex, B256, U256};
 use alloy::rpc::types::{Block as ExecutionBlock, Transaction as ExecutionTransaction};
@@ -6,9 +7,22 @@ use anyhow::{Context, Result};
 
 use serde::{Deserialize, Serialize};
 
-use crate::{clients::beacon::types::Blob as BeaconBlob, utils::web3::calculate_versioned_hash};
+use crate::{
+    clients::{
+        beacon::types::{Blob as BeaconBlob, SignedBeaconBlockHeader},
+        common::ValidationIssue,
+    },
+    network::{Fork, Network},
+    utils::web3::{
+        calculate_blob_gas_price, calculate_versioned_hash, DENEB_BLOB_BASE_FEE_UPDATE_FRACTION,
+    },
+};
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Version of the payloads sent to the Blobscan API, bumped whenever they
+/// change in a way older API versions can't handle.
+pub const INDEXER_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Block {
     pub number: BlockNumber,
@@ -17,9 +31,23 @@ pub struct Block {
     pub slot: u32,
     pub blob_gas_used: U256,
     pub excess_blob_gas: U256,
+    /// Blob base fee derived from the excess blob gas
+    pub blob_gas_price: U256,
+    pub transactions_count: u32,
+    pub blob_transactions_ratio: f64,
+    /// Fork active at the block's slot, if the fork schedule is known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fork: Option<Fork>,
+    /// Index of the validator that proposed the beacon block
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proposer_index: Option<u32>,
+    /// Beacon block header signed by the proposer, taken from the block's
+    /// blob sidecars
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signed_block_header: Option<SignedBeaconBlockHeader>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Transaction {
     pub hash: B256,
@@ -30,26 +58,46 @@ pub struct Transaction {
     pub index: TxIndex,
     pub gas_price: U256,
     pub max_fee_per_blob_gas: U256,
+    /// Label of the rollup that sent the transaction, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rollup: Option<String>,
+    /// Batch format detected from the transaction's calldata, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Blob {
     pub versioned_hash: B256,
     pub commitment: String,
     pub proof: String,
-    pub data: Bytes,
+    /// Merkle branch proving the commitment is part of the beacon block body
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inclusion_proof: Vec<B256>,
+    /// Blob contents. Omitted when the blob bodies are stored elsewhere
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Bytes>,
+    /// Size of the blob contents in bytes
+    pub size: usize,
+    /// Bytes left once the trailing zero padding is stripped
+    pub usage_size: usize,
+    /// Share of the blob contents made of zero bytes
+    pub zero_bytes_ratio: f64,
     pub tx_hash: B256,
     pub index: u32,
+    /// Other transactions of the block referencing the same blob, which is
+    /// only sent once
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub references: Vec<BlobReference>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Position of a blob within a transaction referencing it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
-pub struct FailedSlotsChunk {
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub id: Option<u32>,
-    pub initial_slot: u32,
-    pub final_slot: u32,
+pub struct BlobReference {
+    pub tx_hash: B256,
+    pub index: u32,
 }
 
 #[derive(Serialize, Debug)]
@@ -70,6 +118,8 @@ pub struct BlockchainSyncStateResponse {
     pub last_lower_synced_slot: Option<u32>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub last_upper_synced_slot: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_finalized_block: Option<u32>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -79,13 +129,67 @@ pub struct BlockchainSyncState {
     pub last_upper_synced_slot: Option<u32>,
 }
 
-#[derive(Serialize, Debug)]
+/// Description of the indexer instance writing to Blobscan
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexerMetadata {
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_commit: Option<String>,
+    pub network: Network,
+    pub features: Vec<String>,
+    pub config_hash: String,
+    /// Settings of the instance, with secrets and credentials removed
+    pub config: serde_json::Value,
+}
+
+/// Version of the Blobscan API and the indexer payload schemas it accepts.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiVersion {
+    pub version: String,
+    pub indexer_schema_versions: Vec<u32>,
+}
+
+impl ApiVersion {
+    pub fn supports_indexer_schema(&self) -> bool {
+        self.indexer_schema_versions
+            .contains(&INDEXER_SCHEMA_VERSION)
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
 pub struct IndexRequest {
     pub block: Block,
     pub transactions: Vec<Transaction>,
     pub blobs: Vec<Blob>,
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockResponse {
+    pub hash: B256,
+    pub number: BlockNumber,
+    pub slot: u32,
+    #[serde(default)]
+    pub transactions: Vec<BlockTransactionResponse>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockTransactionResponse {
+    pub hash: B256,
+    #[serde(default)]
+    pub blobs: Vec<BlockBlobResponse>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockBlobResponse {
+    pub versioned_hash: B256,
+    pub index: u32,
+}
+
 #[derive(Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct ReorgedSlotsRequest {
@@ -98,26 +202,107 @@ pub struct ReorgedSlotsResponse {
     pub total_updated_slots: u32,
 }
 
+impl IndexRequest {
+    /// Removes the transactions and blobs rejected by the given validation
+    /// issues. Transactions are removed along with all their blobs so they're
+    /// never indexed partially. Returns the amount of removed transactions and
+    /// blobs, or `None` if an issue can't be solved by removing entities.
+    pub fn remove_invalid_entities(
+        &mut self,
+        issues: &[ValidationIssue],
+    ) -> Option<(usize, usize)> {
+        let mut invalid_tx_hashes = HashSet::new();
+
+        for issue in issues {
+            let tx_hash = match issue.entity()? {
+                ("transactions", Some(index)) => self.transactions.get(index)?.hash,
+                ("blobs", Some(index)) => {
+                    let blob = self.blobs.get(index)?;
+
+                    // Every transaction referencing an invalid blob is invalid too
+                    invalid_tx_hashes.extend(blob.references.iter().map(|r| r.tx_hash));
+
+                    blob.tx_hash
+                }
+                _ => return None,
+            };
+
+            invalid_tx_hashes.insert(tx_hash);
+        }
+
+        let transactions_count = self.transactions.len();
+        let blobs_count = self.blobs.len();
+
+        self.transactions
+            .retain(|tx| !invalid_tx_hashes.contains(&tx.hash));
+        self.blobs.retain_mut(|blob| {
+            blob.references
+                .retain(|reference| !invalid_tx_hashes.contains(&reference.tx_hash));
+
+            if !invalid_tx_hashes.contains(&blob.tx_hash) {
+                return true;
+            }
+
+            // Hand the blob over to another transaction referencing it, if any
+            if blob.references.is_empty() {
+                return false;
+            }
+
+            let reference = blob.references.remove(0);
+
+            blob.tx_hash = reference.tx_hash;
+            blob.index = reference.index;
+
+            true
+        });
+
+        if self.transactions.is_empty() {
+            return None;
+        }
+
+        Some((
+            transactions_count - self.transactions.len(),
+            blobs_count - self.blobs.len(),
+        ))
+    }
+
+    /// Describes the entity a validation issue refers to, for logging.
+    pub fn describe_entity(&self, issue: &ValidationIssue) -> String {
+        match issue.entity() {
+            Some(("block", _)) => format!("block {}", self.block.hash),
+            Some(("transactions", Some(index))) => match self.transactions.get(index) {
+                Some(tx) => format!("transaction {}", tx.hash),
+                None => format!("unknown transaction {index}"),
+            },
+            Some(("blobs", Some(index))) => match self.blobs.get(index) {
+                Some(blob) => format!(
+                    "blob {} of transaction {}",
+                    blob.versioned_hash, blob.tx_hash
+                ),
+                None => format!("unknown blob {index}"),
+            },
+            _ => "request".to_string(),
+        }
+    }
+}
+
 impl fmt::Debug for Blob {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "Blob {{ versioned_hash: {}, commitment: {}, tx_hash: {}, index: {}, data: [omitted] }}",
-            self.versioned_hash, self.commitment, self.tx_hash, self.index
+            "Blob {{ versioned_hash: {}, commitment: {}, tx_hash: {}, index: {}, references: {:?}, size: {}, usage_size: {}, zero_bytes_ratio: {}, data: [omitted] }}",
+            self.versioned_hash,
+            self.commitment,
+            self.tx_hash,
+            self.index,
+            self.references,
+            self.size,
+            self.usage_size,
+            self.zero_bytes_ratio
         )
     }
 }
 
-impl From<(u32, u32)> for FailedSlotsChunk {
-    fn from((initial_slot, final_slot): (u32, u32)) -> Self {
-        Self {
-            id: None,
-            initial_slot,
-            final_slot,
-        }
-    }
-}
-
 impl<'a> TryFrom<(&'a ExecutionBlock<ExecutionTransaction>, u32)> for Block {
     type Error = anyhow::Error;
 
@@ -147,6 +332,23 @@ impl<'a> TryFrom<(&'a ExecutionBlock<ExecutionTransaction>, u32)> for Block {
             ))
             }
         };
+        // Priced with Deneb's parameters until the block's fork is known
+        let blob_gas_price =
+            calculate_blob_gas_price(excess_blob_gas, DENEB_BLOB_BASE_FEE_UPDATE_FRACTION);
+
+        let transactions_count = execution_block.transactions.len() as u32;
+        let blob_transactions_count = match execution_block.transactions.as_transactions() {
+            Some(transactions) => transactions
+                .iter()
+                .filter(|tx| tx.blob_versioned_hashes.is_some())
+                .count() as u32,
+            None => 0,
+        };
+        let blob_transactions_ratio = if transactions_count > 0 {
+            blob_transactions_count as f64 / transactions_count as f64
+        } else {
+            0.0
+        };
 
         Ok(Self {
             number,
@@ -155,6 +357,12 @@ impl<'a> TryFrom<(&'a ExecutionBlock<ExecutionTransaction>, u32)> for Block {
             slot,
             blob_gas_used,
             excess_blob_gas,
+            blob_gas_price,
+            transactions_count,
+            blob_transactions_ratio,
+            fork: None,
+            proposer_index: None,
+            signed_block_header: None,
         })
     }
 }
@@ -209,6 +417,8 @@ impl<'a>
             to,
             gas_price,
             max_fee_per_blob_gas,
+            rollup: None,
+            category: None,
         })
     }
 }
@@ -219,12 +429,19 @@ impl<'a> TryFrom<(&'a BeaconBlob, u32, B256)> for Blob {
     fn try_from(
         (blob_data, index, tx_hash): (&'a BeaconBlob, u32, B256),
     ) -> Result<Self, Self::Error> {
+        let (usage_size, zero_bytes_ratio) = blob_usage(&blob_data.blob);
+
         Ok(Self {
             tx_hash,
             index,
+            references: vec![],
             commitment: blob_data.kzg_commitment.clone(),
             proof: blob_data.kzg_proof.clone(),
-            data: blob_data.blob.clone(),
+            inclusion_proof: blob_data.kzg_commitment_inclusion_proof.clone(),
+            data: Some(blob_data.blob.clone()),
+            size: blob_data.blob.len(),
+            usage_size,
+            zero_bytes_ratio,
             versioned_hash: calculate_versioned_hash(&blob_data.kzg_commitment)?,
         })
     }
@@ -234,21 +451,45 @@ impl<'a> From<(&'a BeaconBlob, &'a B256, usize, &'a B256)> for Blob {
     fn from(
         (blob_data, versioned_hash, index, tx_hash): (&'a BeaconBlob, &'a B256, usize, &'a B256),
     ) -> Self {
+        let (usage_size, zero_bytes_ratio) = blob_usage(&blob_data.blob);
+
         Self {
             tx_hash: *tx_hash,
             index: index as u32,
+            references: vec![],
             commitment: blob_data.kzg_commitment.clone(),
             proof: blob_data.kzg_proof.clone(),
-            data: blob_data.blob.clone(),
+            inclusion_proof: blob_data.kzg_commitment_inclusion_proof.clone(),
+            data: Some(blob_data.blob.clone()),
+            size: blob_data.blob.len(),
+            usage_size,
+            zero_bytes_ratio,
             versioned_hash: *versioned_hash,
         }
     }
 }
 
+/// Computes how much of a blob is actually used: the length of its contents
+/// up to the last non-zero byte, as blobs are zero-padded up to their fixed
+/// size, and the ratio of zero bytes in them.
+fn blob_usage(data: &[u8]) -> (usize, f64) {
+    if data.is_empty() {
+        return (0, 0.0);
+    }
+
+    let usage_size = data
+        .iter()
+        .rposition(|&byte| byte != 0)
+        .map_or(0, |position| position + 1);
+    let zero_bytes = data.iter().filter(|&&byte| byte == 0).count();
+
+    (usage_size, zero_bytes as f64 / data.len() as f64)
+}
+
 impl From<BlockchainSyncStateResponse> for BlockchainSyncState {
     fn from(response: BlockchainSyncStateResponse) -> Self {
         Self {
-            last_finalized_block: None,
+            last_finalized_block: response.last_finalized_block,
             last_lower_synced_slot: response.last_lower_synced_slot,
             last_upper_synced_slot: response.last_upper_synced_slot,
         }
@@ -264,3 +505,198 @@ impl From<BlockchainSyncState> for BlockchainSyncStateRequest {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::U256;
+    use serde_json::json;
+
+    use super::*;
+
+    const BLOB_GAS_PER_BLOB: u64 = 131072;
+
+    fn execution_block(
+        blob_gas_used: Option<u64>,
+        excess_blob_gas: Option<u64>,
+    ) -> ExecutionBlock<ExecutionTransaction> {
+        let zero_hash = B256::ZERO;
+        let mut block = json!({
+            "hash": B256::repeat_byte(0x11),
+            "parentHash": zero_hash,
+            "sha3Uncles": zero_hash,
+            "miner": Address::ZERO,
+            "stateRoot": zero_hash,
+            "transactionsRoot": zero_hash,
+            "receiptsRoot": zero_hash,
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "difficulty": "0x0",
+            "number": "0x1312d00",
+            "gasLimit": "0x1c9c380",
+            "gasUsed": "0x0",
+            "timestamp": "0x6600a1b7",
+            "extraData": "0x",
+            "mixHash": zero_hash,
+            "nonce": "0x0000000000000000",
+            "baseFeePerGas": "0x1",
+            "uncles": [],
+            "transactions": [],
+            "withdrawals": [],
+            "withdrawalsRoot": zero_hash,
+            "parentBeaconBlockRoot": zero_hash,
+        });
+
+        if let Some(blob_gas_used) = blob_gas_used {
+            block["blobGasUsed"] = json!(format!("{blob_gas_used:#x}"));
+        }
+
+        if let Some(excess_blob_gas) = excess_blob_gas {
+            block["excessBlobGas"] = json!(format!("{excess_blob_gas:#x}"));
+        }
+
+        serde_json::from_value(block).unwrap()
+    }
+
+    #[test]
+    fn block_conversion_copies_header_fields() {
+        let execution_block = execution_block(Some(3 * BLOB_GAS_PER_BLOB), Some(0));
+
+        let block = Block::try_from((&execution_block, 8_000_000)).unwrap();
+
+        assert_eq!(block.number, 20_000_000);
+        assert_eq!(block.hash, B256::repeat_byte(0x11));
+        assert_eq!(block.timestamp, 0x6600a1b7);
+        assert_eq!(block.slot, 8_000_000);
+        assert_eq!(block.blob_gas_used, U256::from(3 * BLOB_GAS_PER_BLOB));
+        assert_eq!(block.excess_blob_gas, U256::ZERO);
+        assert_eq!(block.transactions_count, 0);
+        assert_eq!(block.blob_transactions_ratio, 0.0);
+        assert_eq!(block.fork, None);
+    }
+
+    #[test]
+    fn block_conversion_computes_blob_gas_price() {
+        // Test vectors from the EIP-4844 reference implementation
+        for (excess_blob_gas, blob_gas_price) in [
+            (0, 1),
+            (2_314_057, 1),
+            (2_314_058, 2),
+            (10 * 1024 * 1024, 23),
+        ] {
+            let execution_block = execution_block(Some(0), Some(excess_blob_gas));
+
+            let block = Block::try_from((&execution_block, 0)).unwrap();
+
+            assert_eq!(
+                block.blob_gas_price,
+                U256::from(blob_gas_price),
+                "excess blob gas {excess_blob_gas}"
+            );
+        }
+    }
+
+    #[test]
+    fn block_conversion_fails_without_blob_gas_fields() {
+        assert!(Block::try_from((&execution_block(None, Some(0)), 0)).is_err());
+        assert!(Block::try_from((&execution_block(Some(0), None), 0)).is_err());
+    }
+
+    #[test]
+    fn block_serializes_blob_gas_price() {
+        let execution_block = execution_block(Some(0), Some(10 * 1024 * 1024));
+
+        let block = serde_json::to_value(Block::try_from((&execution_block, 0)).unwrap()).unwrap();
+
+        assert_eq!(block["blobGasPrice"], json!("0x17"));
+        assert!(block.get("fork").is_none());
+    }
+
+    #[test]
+    fn blob_usage_ignores_trailing_padding() {
+        let mut data = vec![0u8; 128];
+        data[0] = 0x01;
+        data[40] = 0x02;
+
+        let (usage_size, zero_bytes_ratio) = blob_usage(&data);
+
+        assert_eq!(usage_size, 41);
+        assert_eq!(zero_bytes_ratio, 126.0 / 128.0);
+    }
+
+    #[test]
+    fn blob_usage_of_empty_blobs() {
+        assert_eq!(blob_usage(&[0u8; 64]), (0, 1.0));
+        assert_eq!(blob_usage(&[]), (0, 0.0));
+        assert_eq!(blob_usage(&[0xff; 64]), (64, 0.0));
+    }
+
+    #[test]
+    fn removing_invalid_entities_hands_shared_blobs_over() {
+        let execution_block = execution_block(Some(BLOB_GAS_PER_BLOB), Some(0));
+        let transaction = |hash: B256| Transaction {
+            hash,
+            from: Address::ZERO,
+            to: None,
+            block_number: 20_000_000,
+            index: 0,
+            gas_price: U256::ZERO,
+            max_fee_per_blob_gas: U256::ZERO,
+            rollup: None,
+            category: None,
+        };
+        let blob: Blob = serde_json::from_value(json!({
+            "versionedHash": B256::repeat_byte(1),
+            "commitment": "0x",
+            "proof": "0x",
+            "size": 0,
+            "usageSize": 0,
+            "zeroBytesRatio": 0.0,
+            "txHash": B256::repeat_byte(0xa),
+            "index": 0,
+            "references": [
+                { "txHash": B256::repeat_byte(0xb), "index": 1 },
+                { "txHash": B256::repeat_byte(0xc), "index": 0 },
+            ],
+        }))
+        .unwrap();
+        let mut request = IndexRequest {
+            block: Block::try_from((&execution_block, 8_000_000)).unwrap(),
+            transactions: vec![
+                transaction(B256::repeat_byte(0xa)),
+                transaction(B256::repeat_byte(0xb)),
+                transaction(B256::repeat_byte(0xc)),
+            ],
+            blobs: vec![blob],
+        };
+        let issue: ValidationIssue =
+            serde_json::from_value(json!({ "path": ["transactions", 0, "hash"] })).unwrap();
+
+        assert_eq!(request.remove_invalid_entities(&[issue]), Some((1, 0)));
+        assert_eq!(request.blobs[0].tx_hash, B256::repeat_byte(0xb));
+        assert_eq!(request.blobs[0].index, 1);
+        assert_eq!(
+            request.blobs[0].references,
+            vec![BlobReference {
+                tx_hash: B256::repeat_byte(0xc),
+                index: 0,
+            }]
+        );
+    }
+
+    #[test]
+    fn api_version_supports_indexer_schema() {
+        let api_version: ApiVersion = serde_json::from_value(json!({
+            "version": "1.2.0",
+            "indexerSchemaVersions": [INDEXER_SCHEMA_VERSION, INDEXER_SCHEMA_VERSION + 1],
+        }))
+        .unwrap();
+
+        assert!(api_version.supports_indexer_schema());
+
+        let api_version = ApiVersion {
+            indexer_schema_versions: vec![INDEXER_SCHEMA_VERSION + 1],
+            ..api_version
+        };
+
+        assert!(!api_version.supports_indexer_schema());
+    }
+}
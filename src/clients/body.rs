@@ -0,0 +1,251 @@
+use std::{
+    fmt::{self, Debug, Write},
+    io,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use futures::Stream;
+use reqwest::{
+    header::{CONTENT_LENGTH, CONTENT_TYPE},
+    Body, RequestBuilder,
+};
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+/// Size of the chunks streamed JSON bodies are sent in
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Chunks serialized ahead of the ones being sent
+const BUFFERED_CHUNKS: usize = 4;
+
+pub const DEFAULT_MAX_LOGGED_BODY_LENGTH: usize = 1024;
+
+static MAX_LOGGED_BODY_LENGTH: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_LOGGED_BODY_LENGTH);
+
+/// Sets the length request bodies are truncated to when they're logged.
+pub fn set_max_logged_body_length(max_length: usize) {
+    MAX_LOGGED_BODY_LENGTH.store(max_length, Ordering::Relaxed);
+}
+
+/// Body of a request made through [`json_send!`](crate::json_send).
+pub trait JsonBody {
+    /// Attaches the body to the request. Called on every attempt, as streamed
+    /// bodies can't be cloned along with the request.
+    fn attach(&self, req: RequestBuilder) -> RequestBuilder;
+
+    /// Writes the representation of the body included in logs.
+    fn describe(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+}
+
+impl<T: Serialize + Debug + ?Sized> JsonBody for &T {
+    fn attach(&self, req: RequestBuilder) -> RequestBuilder {
+        req.json(*self)
+    }
+
+    fn describe(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(*self, f)
+    }
+}
+
+/// JSON body serialized on a blocking thread while it's being sent, so large
+/// payloads such as index requests carrying blob data are never held in memory
+/// as a whole string.
+pub struct StreamedJson<T> {
+    value: Arc<T>,
+    size: usize,
+}
+
+impl<T> StreamedJson<T> {
+    /// Wraps the value along with the length of its JSON representation, sent
+    /// as the body's content length.
+    pub fn new(value: Arc<T>, size: usize) -> Self {
+        Self { value, size }
+    }
+}
+
+impl<T> Clone for StreamedJson<T> {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            size: self.size,
+        }
+    }
+}
+
+impl<T: Serialize + Send + Sync + 'static> JsonBody for StreamedJson<T> {
+    fn attach(&self, req: RequestBuilder) -> RequestBuilder {
+        req.header(CONTENT_TYPE, "application/json")
+            .header(CONTENT_LENGTH, self.size)
+            .body(Body::wrap_stream(json_chunks(self.value.clone())))
+    }
+
+    fn describe(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<streamed JSON body of {} bytes>", self.size)
+    }
+}
+
+/// Request body as it's logged. It's only formatted when the event is
+/// actually recorded, and truncated to the configured max length.
+pub struct LoggedBody<'a, B>(pub Option<&'a B>);
+
+impl<B: JsonBody> fmt::Display for LoggedBody<'_, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(body) = self.0 else {
+            return Ok(());
+        };
+        let mut writer = TruncatingWriter {
+            f,
+            remaining: MAX_LOGGED_BODY_LENGTH.load(Ordering::Relaxed),
+            truncated: false,
+        };
+
+        match write!(writer, "{}", Described(body)) {
+            Err(_) if writer.truncated => writer.f.write_str("…[truncated]"),
+            result => result,
+        }
+    }
+}
+
+struct Described<'a, B>(&'a B);
+
+impl<B: JsonBody> fmt::Display for Described<'_, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.describe(f)
+    }
+}
+
+/// Forwards up to the given amount of bytes, then fails so the formatting of
+/// the rest of the body is cut short.
+struct TruncatingWriter<'a, 'b> {
+    f: &'a mut fmt::Formatter<'b>,
+    remaining: usize,
+    truncated: bool,
+}
+
+impl Write for TruncatingWriter<'_, '_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if s.len() <= self.remaining {
+            self.remaining -= s.len();
+
+            return self.f.write_str(s);
+        }
+
+        let mut end = self.remaining;
+
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        self.f.write_str(&s[..end])?;
+        self.remaining = 0;
+        self.truncated = true;
+
+        Err(fmt::Error)
+    }
+}
+
+/// Serializes the value in the background, yielding its JSON representation in
+/// chunks as they're consumed.
+fn json_chunks<T: Serialize + Send + Sync + 'static>(value: Arc<T>) -> ChunkStream {
+    let (tx, rx) = mpsc::channel(BUFFERED_CHUNKS);
+
+    tokio::task::spawn_blocking(move || {
+        let mut writer = ChunkWriter {
+            tx,
+            chunk: Vec::with_capacity(CHUNK_SIZE),
+        };
+        let result = serde_json::to_writer(&mut writer, value.as_ref())
+            .map_err(io::Error::from)
+            .and_then(|_| writer.send_chunk());
+
+        if let Err(error) = result {
+            // Nothing left to do if the request was dropped in the meantime
+            let _ = writer.tx.blocking_send(Err(error));
+        }
+    });
+
+    ChunkStream(rx)
+}
+
+struct ChunkWriter {
+    tx: mpsc::Sender<io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+}
+
+impl ChunkWriter {
+    fn send_chunk(&mut self) -> io::Result<()> {
+        if self.chunk.is_empty() {
+            return Ok(());
+        }
+
+        let chunk = std::mem::replace(&mut self.chunk, Vec::with_capacity(CHUNK_SIZE));
+
+        self.tx
+            .blocking_send(Ok(chunk))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "request body dropped"))
+    }
+}
+
+impl io::Write for ChunkWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.chunk.extend_from_slice(buf);
+
+        if self.chunk.len() >= CHUNK_SIZE {
+            self.send_chunk()?;
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct ChunkStream(mpsc::Receiver<io::Result<Vec<u8>>>);
+
+impl Stream for ChunkStream {
+    type Item = io::Result<Vec<u8>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.poll_recv(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use futures::TryStreamExt;
+    use serde_json::json;
+
+    use super::{json_chunks, LoggedBody, CHUNK_SIZE, DEFAULT_MAX_LOGGED_BODY_LENGTH};
+
+    #[tokio::test]
+    async fn streamed_chunks_add_up_to_the_serialized_value() {
+        let value = json!({ "data": "ab".repeat(CHUNK_SIZE), "index": 1 });
+        let chunks: Vec<Vec<u8>> = json_chunks(Arc::new(value.clone()))
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks.concat(), serde_json::to_vec(&value).unwrap());
+    }
+
+    #[test]
+    fn logged_bodies_are_truncated() {
+        let long_body = "a".repeat(2 * DEFAULT_MAX_LOGGED_BODY_LENGTH);
+        let logged_body = LoggedBody(Some(&long_body.as_str())).to_string();
+
+        assert!(logged_body.ends_with("…[truncated]"));
+        assert!(logged_body.len() < long_body.len());
+        assert_eq!(LoggedBody(Some(&"short")).to_string(), "\"short\"");
+        assert_eq!(LoggedBody::<&()>(None).to_string(), "");
+    }
+}
@@ -1,7 +1,14 @@
-use std::{fmt::Display, str::FromStr};
+use std::{fmt::Display, str::FromStr, time::Duration};
 
+use chrono::{DateTime, Utc};
+use reqwest::{
+    header::{HeaderMap, HeaderValue, RETRY_AFTER},
+    RequestBuilder, StatusCode, Url,
+};
 use serde::Deserialize;
 
+use crate::{clients::execution::AuthHeader, metrics::increment_counter};
+
 #[derive(Clone, Debug, Deserialize)]
 #[serde(untagged)]
 pub enum NumericOrTextCode {
@@ -16,6 +23,26 @@ pub struct ErrorResponse {
     /// Error message
     #[serde(default)]
     pub message: Option<String>,
+    /// Field-level validation failures of the request payload
+    #[serde(default)]
+    pub issues: Vec<ValidationIssue>,
+}
+
+/// Request field rejected by the API's input validation
+#[derive(Deserialize, Debug, Clone)]
+pub struct ValidationIssue {
+    /// Location of the field in the payload, e.g. `["blobs", 0, "commitment"]`
+    #[serde(default)]
+    pub path: Vec<PathSegment>,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum PathSegment {
+    Index(usize),
+    Field(String),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -28,6 +55,25 @@ pub enum ClientError {
     #[error("API usage error: {0}")]
     ApiError(ErrorResponse),
 
+    /// Unauthorized response
+    #[error("Unauthorized by the server{}", format_message(.message))]
+    Unauthorized { message: Option<String> },
+
+    /// Not Found response
+    #[error("Resource not found{}", format_message(.message))]
+    NotFound { message: Option<String> },
+
+    /// Too Many Requests response
+    #[error("Rate limited by the server{}", .retry_after.map(|d| format!(". Retry after {}s", d.as_secs())).unwrap_or_default())]
+    RateLimited { retry_after: Option<Duration> },
+
+    /// 5xx response
+    #[error("Server error {status}{}", format_message(.message))]
+    ServerError {
+        status: u16,
+        message: Option<String>,
+    },
+
     /// Other Error
     #[error(transparent)]
     Other(#[from] anyhow::Error),
@@ -39,6 +85,40 @@ pub enum ClientError {
     /// Serde Json deser Error
     #[error("{0}")]
     SerdeError(#[from] serde_json::Error),
+
+    /// Postgres Error
+    #[cfg(feature = "postgres")]
+    #[error(transparent)]
+    Postgres(#[from] tokio_postgres::Error),
+}
+
+/// Credential authenticating a request made through
+/// [`json_send!`](crate::json_send).
+#[derive(Debug, Clone)]
+pub enum Credential {
+    /// Token sent as `Authorization: Bearer <token>`
+    Bearer(String),
+    /// Static header, e.g. an API key checked by a gateway
+    Header(AuthHeader),
+}
+
+impl Credential {
+    pub fn authenticate(&self, req: RequestBuilder) -> RequestBuilder {
+        match self {
+            Credential::Bearer(token) => req.bearer_auth(token),
+            Credential::Header(header) => {
+                match HeaderValue::from_bytes(header.value.as_bytes()) {
+                    Ok(mut value) => {
+                        value.set_sensitive(true);
+
+                        req.header(header.name.as_str(), value)
+                    }
+                    // Already validated when parsing the header
+                    Err(_) => req,
+                }
+            }
+        }
+    }
 }
 
 /// API Response
@@ -55,6 +135,86 @@ pub enum ClientResponse<T> {
 
 pub type ClientResult<T> = Result<T, ClientError>;
 
+/// Turns a `429 Too Many Requests` response into a [`ClientError::RateLimited`]
+/// error carrying the delay suggested by the server, if any.
+pub(crate) fn check_rate_limit(resp: &reqwest::Response) -> ClientResult<()> {
+    if resp.status() == StatusCode::TOO_MANY_REQUESTS {
+        return Err(ClientError::RateLimited {
+            retry_after: parse_retry_after(resp.headers()),
+        });
+    }
+
+    Ok(())
+}
+
+/// Turns `401`, `404` and `5xx` responses into the error of their status
+/// family, carrying the message of the error body if there's one.
+pub(crate) fn check_status(status: StatusCode, text: &str) -> ClientResult<()> {
+    let message = || {
+        serde_json::from_str::<ErrorResponse>(text)
+            .ok()
+            .and_then(|error| error.message)
+    };
+
+    match status {
+        StatusCode::UNAUTHORIZED => Err(ClientError::Unauthorized { message: message() }),
+        StatusCode::NOT_FOUND => Err(ClientError::NotFound { message: message() }),
+        status if status.is_server_error() => Err(ClientError::ServerError {
+            status: status.as_u16(),
+            message: message(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn format_message(message: &Option<String>) -> String {
+    message
+        .as_ref()
+        .map(|message| format!(": {message}"))
+        .unwrap_or_default()
+}
+
+/// Parses the `Retry-After` header, given either in seconds or as an HTTP date.
+pub(crate) fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+
+    (date.with_timezone(&Utc) - Utc::now())
+        .to_std()
+        .ok()
+        .or(Some(Duration::ZERO))
+}
+
+/// Counts a response by method, host and status code, also recording the
+/// status on the current request span. Requests that failed before getting a
+/// response are counted with the `error` status.
+pub(crate) fn record_response(
+    method: &str,
+    url: &Url,
+    result: &Result<reqwest::Response, reqwest::Error>,
+) {
+    let status = match result {
+        Ok(resp) => resp.status().as_u16().to_string(),
+        Err(_) => "error".to_string(),
+    };
+
+    tracing::Span::current().record("status", status.as_str());
+
+    increment_counter(
+        "blob_indexer_http_responses_total",
+        &[
+            ("method", method),
+            ("host", url.host_str().unwrap_or_default()),
+            ("status", &status),
+        ],
+    );
+}
+
 impl<T> ClientResponse<T> {
     pub(crate) fn into_client_result(self) -> ClientResult<Option<T>> {
         match self {
@@ -90,6 +250,60 @@ impl From<ErrorResponse> for ClientError {
     }
 }
 
+impl ClientError {
+    /// Whether the request may succeed if retried later. Network failures,
+    /// server errors and throttling are recoverable, while authentication
+    /// failures, rejected payloads and responses not matching the expected
+    /// schema are not.
+    pub fn is_recoverable(&self) -> bool {
+        match self {
+            ClientError::Reqwest(error) => match error.status() {
+                Some(status) => is_recoverable_status(status.as_u16()),
+                None => !error.is_decode() && !error.is_builder(),
+            },
+            ClientError::ApiError(error) => error.is_recoverable(),
+            ClientError::Unauthorized { .. } => false,
+            ClientError::NotFound { .. } => false,
+            ClientError::RateLimited { .. } => true,
+            ClientError::ServerError { .. } => true,
+            ClientError::Other(_) => true,
+            ClientError::UrlParse(_) => false,
+            ClientError::SerdeError(_) => false,
+            // Errors reported by the database itself (constraints, missing
+            // tables…) won't go away, unlike connection ones
+            #[cfg(feature = "postgres")]
+            ClientError::Postgres(error) => error.as_db_error().is_none(),
+        }
+    }
+}
+
+impl ErrorResponse {
+    pub fn is_recoverable(&self) -> bool {
+        match &self.code {
+            NumericOrTextCode::Number(code) => {
+                u16::try_from(*code).is_ok_and(is_recoverable_status)
+            }
+            NumericOrTextCode::String(code) => match code.parse::<u16>() {
+                Ok(code) => is_recoverable_status(code),
+                Err(_) => matches!(
+                    code.as_str(),
+                    "INTERNAL_SERVER_ERROR"
+                        | "BAD_GATEWAY"
+                        | "SERVICE_UNAVAILABLE"
+                        | "GATEWAY_TIMEOUT"
+                        | "TIMEOUT"
+                        | "TOO_MANY_REQUESTS"
+                ),
+            },
+        }
+    }
+}
+
+/// Server errors, timeouts and throttling are the only transient statuses.
+pub(crate) fn is_recoverable_status(status: u16) -> bool {
+    status >= 500 || status == 408 || status == 429
+}
+
 impl Display for NumericOrTextCode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -104,6 +318,84 @@ impl Display for ErrorResponse {
             "Code: {}, Message: \"{}\"",
             self.code,
             self.message.as_deref().unwrap_or(""),
+        ))?;
+
+        if !self.issues.is_empty() {
+            let issues = self
+                .issues
+                .iter()
+                .map(|issue| issue.to_string())
+                .collect::<Vec<_>>()
+                .join("; ");
+
+            f.write_str(&format!(", Issues: {issues}"))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ValidationIssue {
+    /// Returns the top-level field of the payload the issue refers to and the
+    /// index of the rejected element within it, if it's a list.
+    pub fn entity(&self) -> Option<(&str, Option<usize>)> {
+        let field = match self.path.first()? {
+            PathSegment::Field(field) => field.as_str(),
+            PathSegment::Index(_) => return None,
+        };
+        let index = match self.path.get(1) {
+            Some(PathSegment::Index(index)) => Some(*index),
+            _ => None,
+        };
+
+        Some((field, index))
+    }
+}
+
+impl Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = self
+            .path
+            .iter()
+            .map(|segment| match segment {
+                PathSegment::Index(index) => format!("[{index}]"),
+                PathSegment::Field(field) => format!(".{field}"),
+            })
+            .collect::<String>();
+
+        f.write_str(&format!(
+            "{}: {}",
+            path.trim_start_matches('.'),
+            self.message.as_deref().unwrap_or("invalid value")
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+
+    use super::{check_status, ClientError};
+
+    #[test]
+    fn error_statuses_are_mapped_to_their_family() {
+        let body = r#"{"code":"UNAUTHORIZED","message":"Invalid token"}"#;
+
+        assert!(matches!(
+            check_status(StatusCode::UNAUTHORIZED, body),
+            Err(ClientError::Unauthorized { message: Some(message) }) if message == "Invalid token"
+        ));
+        assert!(matches!(
+            check_status(StatusCode::NOT_FOUND, ""),
+            Err(ClientError::NotFound { message: None })
+        ));
+        assert!(matches!(
+            check_status(StatusCode::BAD_GATEWAY, "<html>"),
+            Err(ClientError::ServerError {
+                status: 502,
+                message: None
+            })
+        ));
+        assert!(check_status(StatusCode::BAD_REQUEST, body).is_ok());
+    }
+}
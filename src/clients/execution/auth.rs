@@ -0,0 +1,205 @@
+use std::{
+    fmt,
+    str::FromStr,
+    task::{Context, Poll},
+};
+
+use alloy::{
+    rpc::json_rpc::{RequestPacket, ResponsePacket},
+    transports::{
+        http::reqwest::{
+            header::{HeaderName, HeaderValue},
+            Client, StatusCode,
+        },
+        TransportError, TransportErrorKind, TransportFut,
+    },
+};
+use chrono::Utc;
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use serde::{Deserialize, Deserializer, Serialize};
+use tower::Service;
+use url::Url;
+
+/// Header sent along every request to authenticate to the execution node or
+/// the Blobscan API, given as `<name>: <value>`, e.g. `x-api-key: 1234`.
+#[derive(Clone)]
+pub struct AuthHeader {
+    pub name: HeaderName,
+    pub value: HeaderValue,
+}
+
+/// Secret shared with the execution node to authenticate through engine API
+/// JWTs, given as the 32 bytes hex string found in the node's `jwt.hex` file.
+#[derive(Clone)]
+pub struct JwtSecret([u8; 32]);
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthHeaderError {
+    #[error("invalid auth header: expected <name>: <value>")]
+    InvalidFormat,
+    #[error("invalid auth header name \"{0}\"")]
+    InvalidName(String),
+    #[error("invalid auth header value")]
+    InvalidValue,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum JwtSecretError {
+    #[error("invalid JWT secret: expected a 32 bytes hex string")]
+    InvalidSecret,
+}
+
+#[derive(Debug, Serialize)]
+struct Claims {
+    iat: i64,
+}
+
+/// HTTP transport authenticating every request with a freshly issued engine
+/// API JWT, as nodes reject tokens issued more than a minute ago.
+#[derive(Clone)]
+pub struct JwtHttp {
+    client: Client,
+    url: Url,
+    jwt_secret: JwtSecret,
+}
+
+impl JwtSecret {
+    /// Returns the secret as given, so it can be kept out of the logs.
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
+
+    /// Issues a token valid for the next minute.
+    pub(super) fn issue_token(&self) -> Result<String, jsonwebtoken::errors::Error> {
+        let claims = Claims {
+            iat: Utc::now().timestamp(),
+        };
+
+        encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(&self.0),
+        )
+    }
+}
+
+impl JwtHttp {
+    pub fn new(client: Client, url: Url, jwt_secret: &JwtSecret) -> Self {
+        Self {
+            client,
+            url,
+            jwt_secret: jwt_secret.clone(),
+        }
+    }
+
+    fn request(&self, req: RequestPacket) -> TransportFut<'static> {
+        let this = self.clone();
+
+        Box::pin(async move {
+            let token = this
+                .jwt_secret
+                .issue_token()
+                .map_err(TransportErrorKind::custom)?;
+            let resp = this
+                .client
+                .post(this.url)
+                .bearer_auth(token)
+                .json(&req)
+                .send()
+                .await
+                .map_err(TransportErrorKind::custom)?;
+            let status = resp.status();
+            // The body may contain the JSON-RPC error even when the status isn't OK
+            let body = resp.bytes().await.map_err(TransportErrorKind::custom)?;
+
+            if status != StatusCode::OK {
+                return Err(TransportErrorKind::http_error(
+                    status.as_u16(),
+                    String::from_utf8_lossy(&body).into_owned(),
+                ));
+            }
+
+            serde_json::from_slice(&body)
+                .map_err(|err| TransportError::deser_err(err, String::from_utf8_lossy(&body)))
+        })
+    }
+}
+
+impl Service<RequestPacket> for JwtHttp {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: RequestPacket) -> Self::Future {
+        self.request(req)
+    }
+}
+
+impl FromStr for AuthHeader {
+    type Err = AuthHeaderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s.split_once(':').ok_or(AuthHeaderError::InvalidFormat)?;
+        let (name, value) = (name.trim(), value.trim());
+        let name = HeaderName::from_str(name)
+            .map_err(|_| AuthHeaderError::InvalidName(name.to_string()))?;
+        let mut value = HeaderValue::from_str(value).map_err(|_| AuthHeaderError::InvalidValue)?;
+
+        value.set_sensitive(true);
+
+        Ok(Self { name, value })
+    }
+}
+
+impl FromStr for JwtSecret {
+    type Err = JwtSecretError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let bytes = hex::decode(s.strip_prefix("0x").unwrap_or(s))
+            .map_err(|_| JwtSecretError::InvalidSecret)?;
+
+        <[u8; 32]>::try_from(bytes)
+            .map(Self)
+            .map_err(|_| JwtSecretError::InvalidSecret)
+    }
+}
+
+impl<'de> Deserialize<'de> for AuthHeader {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for JwtSecret {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+// Secrets are kept out of logs and panics
+impl fmt::Debug for AuthHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ******", self.name)
+    }
+}
+
+impl fmt::Debug for JwtSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("******")
+    }
+}
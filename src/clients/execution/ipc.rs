@@ -0,0 +1,122 @@
+use std::path::PathBuf;
+
+use alloy::{rpc::json_rpc::ResponsePacket, transports::TransportErrorKind};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::UnixStream,
+    sync::mpsc,
+};
+use tracing::{debug, warn};
+
+use super::multiplexer::{InflightRequest, MultiplexedTransport, PendingRequests};
+
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Connects to the IPC socket of a co-located execution node. The connection
+/// is opened on the first request and reopened on the next one whenever it
+/// drops. Must be called within a Tokio runtime.
+pub fn connect(path: PathBuf) -> MultiplexedTransport {
+    let (sender, receiver) = mpsc::unbounded_channel();
+
+    tokio::spawn(run_connection(path, receiver));
+
+    MultiplexedTransport::new(sender)
+}
+
+async fn run_connection(path: PathBuf, mut requests: mpsc::UnboundedReceiver<InflightRequest>) {
+    let mut pending = PendingRequests::default();
+
+    // Idle transports don't hold a connection until they get a request
+    while let Some(request) = requests.recv().await {
+        let stream = match UnixStream::connect(&path).await {
+            Ok(stream) => stream,
+            Err(error) => {
+                warn!(path = %path.display(), ?error, "Failed to connect to execution node IPC socket");
+
+                let _ = request
+                    .response
+                    .send(Err(TransportErrorKind::custom(error)));
+
+                continue;
+            }
+        };
+
+        debug!(path = %path.display(), "Connected to execution node IPC socket");
+
+        let (mut reader, mut writer) = stream.into_split();
+        let mut buffer = Vec::with_capacity(READ_BUFFER_SIZE);
+        let mut next_request = Some(request);
+
+        loop {
+            if let Some(request) = next_request.take() {
+                if let Some(serialized) = pending.register(request) {
+                    if let Err(error) = writer.write_all(serialized.get().as_bytes()).await {
+                        warn!(?error, "Failed to write to execution node IPC socket");
+
+                        break;
+                    }
+                }
+            }
+
+            tokio::select! {
+                request = requests.recv() => match request {
+                    Some(request) => next_request = Some(request),
+                    // Every transport clone was dropped
+                    None => return,
+                },
+                read = reader.read_buf(&mut buffer) => match read {
+                    Ok(0) => {
+                        debug!("Execution node closed the IPC connection");
+
+                        break;
+                    }
+                    Ok(_) => {
+                        if let Err(error) = dispatch_responses(&mut buffer, &mut pending) {
+                            warn!(?error, "Received invalid response from execution node IPC socket");
+
+                            break;
+                        }
+                    }
+                    Err(error) => {
+                        warn!(?error, "Failed to read from execution node IPC socket");
+
+                        break;
+                    }
+                },
+            }
+        }
+
+        pending.fail_all();
+    }
+}
+
+/// Resolves the requests answered by the complete responses in the buffer,
+/// leaving any partially received one in it.
+fn dispatch_responses(
+    buffer: &mut Vec<u8>,
+    pending: &mut PendingRequests,
+) -> Result<(), serde_json::Error> {
+    let mut responses = serde_json::Deserializer::from_slice(buffer).into_iter::<ResponsePacket>();
+    let mut consumed = 0;
+
+    loop {
+        match responses.next() {
+            Some(Ok(response)) => {
+                consumed = responses.byte_offset();
+
+                pending.resolve(response);
+            }
+            Some(Err(error)) if error.is_eof() => break,
+            Some(Err(error)) => return Err(error),
+            None => {
+                consumed = responses.byte_offset();
+
+                break;
+            }
+        }
+    }
+
+    buffer.drain(..consumed);
+
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use std::{path::PathBuf, time::Duration};
+
+use alloy::{
+    rpc::client::RpcClient,
+    transports::{
+        http::{
+            reqwest::{header::HeaderMap, Client as ProviderClient, NoProxy, Proxy},
+            Http,
+        },
+        utils::guess_local_url,
+        BoxTransport, Transport,
+    },
+};
+use anyhow::{anyhow, Result as AnyhowResult};
+use url::Url;
+use ws::WsConfig;
+
+use crate::clients::{headers::ExtraHeaders, proxy::proxy_for, tls::TlsSettings};
+
+pub use auth::{AuthHeader, AuthHeaderError, JwtHttp, JwtSecret, JwtSecretError};
+pub use multiplexer::MultiplexedTransport;
+
+mod auth;
+mod ipc;
+mod multiplexer;
+mod ws;
+
+const IPC_SCHEME: &str = "ipc://";
+
+pub struct Config {
+    /// HTTP or WebSocket URL, or `ipc://` path of the node's IPC socket
+    pub endpoint: String,
+    pub timeout: Duration,
+    pub response_compression: bool,
+    pub user_agent: String,
+    /// Headers sent along every request, besides the auth header
+    pub extra_headers: ExtraHeaders,
+    /// Overrides the proxies set in the environment
+    pub proxy_url: Option<Url>,
+    pub tls: TlsSettings,
+    /// Header sent along every request, for providers using header-based auth
+    pub auth_header: Option<AuthHeader>,
+    /// Authenticates requests with engine API JWTs signed with this secret
+    pub jwt_secret: Option<JwtSecret>,
+}
+
+/// Builds the RPC client the execution node provider sends its requests
+/// through, using the transport matching the endpoint's scheme.
+pub fn try_build_client(config: Config) -> AnyhowResult<RpcClient<BoxTransport>> {
+    if let Some(path) = config.endpoint.strip_prefix(IPC_SCHEME) {
+        if config.auth_header.is_some() || config.jwt_secret.is_some() {
+            return Err(anyhow!(
+                "Execution node authentication isn't supported over IPC"
+            ));
+        }
+
+        return Ok(RpcClient::new(
+            ipc::connect(PathBuf::from(path)).boxed(),
+            true,
+        ));
+    }
+
+    let url: Url = config.endpoint.parse()?;
+
+    if matches!(url.scheme(), "ws" | "wss") {
+        let proxy = proxy_for(&url, config.proxy_url.as_ref());
+        let transport = ws::connect(WsConfig {
+            url,
+            connect_timeout: config.timeout,
+            user_agent: config.user_agent,
+            extra_headers: config.extra_headers,
+            proxy,
+            tls: config.tls,
+            auth_header: config.auth_header,
+            jwt_secret: config.jwt_secret,
+        });
+
+        return Ok(RpcClient::new(
+            transport.boxed(),
+            guess_local_url(&config.endpoint),
+        ));
+    }
+
+    let mut headers = HeaderMap::new();
+
+    for header in config.extra_headers.iter() {
+        headers.append(header.name.clone(), header.value.clone());
+    }
+
+    if let Some(auth_header) = config.auth_header {
+        headers.insert(auth_header.name, auth_header.value);
+    }
+
+    let mut client_builder = ProviderClient::builder()
+        .timeout(config.timeout)
+        .user_agent(config.user_agent)
+        .gzip(config.response_compression)
+        .deflate(config.response_compression)
+        .default_headers(headers);
+
+    // Proxies set in the environment are used otherwise
+    if let Some(proxy_url) = &config.proxy_url {
+        client_builder =
+            client_builder.proxy(Proxy::all(proxy_url.as_str())?.no_proxy(NoProxy::from_env()));
+    }
+
+    let client = config
+        .tls
+        .apply_to_provider_client(client_builder)?
+        .build()?;
+    let transport = match config.jwt_secret {
+        Some(jwt_secret) => JwtHttp::new(client, url, &jwt_secret).boxed(),
+        None => Http::with_client(client, url).boxed(),
+    };
+
+    Ok(RpcClient::new(transport, guess_local_url(&config.endpoint)))
+}
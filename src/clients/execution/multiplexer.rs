@@ -0,0 +1,153 @@
+use std::{
+    collections::HashMap,
+    task::{Context, Poll},
+};
+
+use alloy::{
+    rpc::json_rpc::{Id, RequestPacket, ResponsePacket},
+    transports::{TransportError, TransportErrorKind, TransportFut, TransportResult},
+};
+use serde_json::value::RawValue;
+use tokio::sync::{mpsc, oneshot};
+use tower::Service;
+
+/// Request handed over to the task owning the connection, along with the
+/// channel its response is sent back through.
+pub(super) struct InflightRequest {
+    pub packet: RequestPacket,
+    pub response: oneshot::Sender<TransportResult<ResponsePacket>>,
+}
+
+/// Transport sending requests through a connection shared by all its clones,
+/// such as a Unix socket. The connection is owned by a background task that
+/// matches responses to their requests by id, so requests don't have to wait
+/// for the ones sent before them.
+#[derive(Clone, Debug)]
+pub struct MultiplexedTransport {
+    requests: mpsc::UnboundedSender<InflightRequest>,
+}
+
+/// Requests sent over a connection that are waiting for their response, keyed
+/// by the id of their first request.
+#[derive(Default)]
+pub(super) struct PendingRequests {
+    requests: HashMap<Id, PendingRequest>,
+}
+
+struct PendingRequest {
+    serialized: Box<RawValue>,
+    response: oneshot::Sender<TransportResult<ResponsePacket>>,
+}
+
+impl MultiplexedTransport {
+    pub(super) fn new(requests: mpsc::UnboundedSender<InflightRequest>) -> Self {
+        Self { requests }
+    }
+
+    fn request(&self, packet: RequestPacket) -> TransportFut<'static> {
+        let requests = self.requests.clone();
+
+        Box::pin(async move {
+            let (sender, receiver) = oneshot::channel();
+
+            requests
+                .send(InflightRequest {
+                    packet,
+                    response: sender,
+                })
+                .map_err(|_| TransportErrorKind::backend_gone())?;
+
+            receiver
+                .await
+                .map_err(|_| TransportErrorKind::backend_gone())?
+        })
+    }
+}
+
+impl Service<RequestPacket> for MultiplexedTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: RequestPacket) -> Self::Future {
+        self.request(req)
+    }
+}
+
+impl PendingRequests {
+    /// Keeps track of the request and returns it serialized, ready to be sent.
+    /// Requests that can't be sent are answered right away.
+    pub fn register(&mut self, request: InflightRequest) -> Option<Box<RawValue>> {
+        let id = match &request.packet {
+            RequestPacket::Single(req) => req.id().clone(),
+            RequestPacket::Batch(reqs) => match reqs.first() {
+                Some(req) => req.id().clone(),
+                None => {
+                    let _ = request.response.send(Ok(ResponsePacket::Batch(vec![])));
+
+                    return None;
+                }
+            },
+        };
+
+        match request.packet.serialize() {
+            Ok(serialized) => {
+                self.requests.insert(
+                    id,
+                    PendingRequest {
+                        serialized: serialized.clone(),
+                        response: request.response,
+                    },
+                );
+
+                Some(serialized)
+            }
+            Err(error) => {
+                let _ = request.response.send(Err(TransportError::ser_err(error)));
+
+                None
+            }
+        }
+    }
+
+    /// Hands the response over to the request it answers. Responses to batches
+    /// may come in any order, so any of their ids may identify the batch.
+    pub fn resolve(&mut self, packet: ResponsePacket) {
+        let request = match &packet {
+            ResponsePacket::Single(resp) => self.requests.remove(&resp.id),
+            ResponsePacket::Batch(resps) => {
+                resps.iter().find_map(|resp| self.requests.remove(&resp.id))
+            }
+        };
+
+        if let Some(request) = request {
+            let _ = request.response.send(Ok(packet));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    /// Serialized requests still waiting for their response, to be sent again
+    /// over a new connection.
+    pub fn unanswered(&self) -> impl Iterator<Item = &RawValue> {
+        self.requests
+            .values()
+            .map(|request| request.serialized.as_ref())
+    }
+
+    /// Fails every pending request, as their responses won't arrive once the
+    /// connection is gone.
+    pub fn fail_all(&mut self) {
+        for (_, request) in self.requests.drain() {
+            let _ = request
+                .response
+                .send(Err(TransportErrorKind::backend_gone()));
+        }
+    }
+}
@@ -0,0 +1,572 @@
+use std::{io, sync::Arc, time::Duration};
+
+use alloy::{rpc::json_rpc::ResponsePacket, transports::TransportErrorKind};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use rustls::ServerName;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    net::TcpStream,
+    sync::mpsc,
+};
+use tokio_rustls::TlsConnector;
+use tracing::{debug, warn};
+use url::Url;
+
+use super::{
+    multiplexer::{InflightRequest, MultiplexedTransport, PendingRequests},
+    AuthHeader, JwtSecret,
+};
+use crate::clients::{headers::ExtraHeaders, tls::TlsSettings};
+
+/// Largest message accepted from the node. Blocks with all their transactions
+/// are the biggest responses and stay well below it.
+const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+const MAX_RECONNECT_ATTEMPTS: u32 = 5;
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_millis(100);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+/// Pings keep idle connections from being closed by proxies and reveal dead
+/// ones before a request gets lost on them.
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+trait Connection: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Connection for T {}
+
+/// Settings used every time the connection gets (re)opened.
+pub struct WsConfig {
+    pub url: Url,
+    pub connect_timeout: Duration,
+    pub user_agent: String,
+    pub extra_headers: ExtraHeaders,
+    /// HTTP proxy the connection is tunneled through
+    pub proxy: Option<Url>,
+    pub tls: TlsSettings,
+    pub auth_header: Option<AuthHeader>,
+    pub jwt_secret: Option<JwtSecret>,
+}
+
+#[derive(Debug, thiserror::Error)]
+enum ConnectionError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("invalid WebSocket URL: {0}")]
+    InvalidUrl(&'static str),
+    #[error("failed to issue JWT: {0}")]
+    Jwt(#[from] jsonwebtoken::errors::Error),
+    #[error("WebSocket handshake rejected: {0}")]
+    HandshakeRejected(String),
+    #[error("proxy tunnel rejected: {0}")]
+    ProxyRejected(String),
+    #[error("timed out")]
+    Timeout,
+}
+
+/// Message received from the node, with fragmented ones already reassembled.
+enum Incoming {
+    Data(Vec<u8>),
+    Ping(Vec<u8>),
+}
+
+enum SessionEnd {
+    Disconnected,
+    /// Every transport clone was dropped
+    Closed,
+}
+
+/// Connects to the WebSocket endpoint of an execution node. The connection is
+/// opened on the first request and reopened whenever it drops, sending again
+/// the requests that were waiting for a response. Must be called within a
+/// Tokio runtime.
+pub fn connect(config: WsConfig) -> MultiplexedTransport {
+    let (sender, receiver) = mpsc::unbounded_channel();
+
+    tokio::spawn(run_connection(config, receiver));
+
+    MultiplexedTransport::new(sender)
+}
+
+async fn run_connection(config: WsConfig, mut requests: mpsc::UnboundedReceiver<InflightRequest>) {
+    let mut pending = PendingRequests::default();
+    let mut next_request = None;
+
+    loop {
+        // Idle transports don't hold a connection until they get a request
+        if pending.is_empty() {
+            match requests.recv().await {
+                Some(request) => next_request = Some(request),
+                None => return,
+            }
+        }
+
+        let connection = match connect_with_retries(&config).await {
+            Ok(connection) => connection,
+            Err(error) => {
+                warn!(url = %config.url, %error, "Failed to connect to execution node WebSocket");
+
+                pending.fail_all();
+
+                if let Some(request) = next_request.take() {
+                    let _ = request
+                        .response
+                        .send(Err(TransportErrorKind::custom(error)));
+                }
+
+                continue;
+            }
+        };
+
+        debug!(url = %config.url, "Connected to execution node WebSocket");
+
+        match run_session(connection, &mut requests, &mut pending, next_request.take()).await {
+            SessionEnd::Disconnected => continue,
+            SessionEnd::Closed => return,
+        }
+    }
+}
+
+async fn run_session(
+    connection: BufReader<Box<dyn Connection>>,
+    requests: &mut mpsc::UnboundedReceiver<InflightRequest>,
+    pending: &mut PendingRequests,
+    mut next_request: Option<InflightRequest>,
+) -> SessionEnd {
+    let (reader, mut writer) = tokio::io::split(connection);
+    let (incoming_sender, mut incoming) = mpsc::channel(64);
+    // Reading whole frames isn't cancel safe, so it's done by its own task
+    let reader_task = tokio::spawn(read_messages(reader, incoming_sender));
+    let mut ping_interval = tokio::time::interval(PING_INTERVAL);
+
+    let mut outgoing = pending
+        .unanswered()
+        .map(|request| encode_frame(OPCODE_TEXT, request.get().as_bytes()))
+        .collect::<Vec<_>>();
+
+    if !outgoing.is_empty() {
+        debug!(
+            requests = outgoing.len(),
+            "Resending requests left unanswered by the previous connection"
+        );
+    }
+
+    let end = loop {
+        if let Some(request) = next_request.take() {
+            if let Some(serialized) = pending.register(request) {
+                outgoing.push(encode_frame(OPCODE_TEXT, serialized.get().as_bytes()));
+            }
+        }
+
+        if let Err(error) = write_frames(&mut writer, outgoing.drain(..)).await {
+            warn!(?error, "Failed to write to execution node WebSocket");
+
+            break SessionEnd::Disconnected;
+        }
+
+        tokio::select! {
+            request = requests.recv() => match request {
+                Some(request) => next_request = Some(request),
+                None => break SessionEnd::Closed,
+            },
+            message = incoming.recv() => match message {
+                Some(Incoming::Data(data)) => match serde_json::from_slice::<ResponsePacket>(&data) {
+                    Ok(response) => pending.resolve(response),
+                    // Subscription notifications aren't responses to any request
+                    Err(error) => debug!(?error, "Ignoring WebSocket message not answering a request"),
+                },
+                Some(Incoming::Ping(payload)) => outgoing.push(encode_frame(OPCODE_PONG, &payload)),
+                None => {
+                    debug!("Execution node WebSocket connection closed");
+
+                    break SessionEnd::Disconnected;
+                }
+            },
+            _ = ping_interval.tick() => outgoing.push(encode_frame(OPCODE_PING, &[])),
+        }
+    };
+
+    reader_task.abort();
+
+    end
+}
+
+async fn write_frames<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    frames: impl Iterator<Item = Vec<u8>>,
+) -> io::Result<()> {
+    for frame in frames {
+        writer.write_all(&frame).await?;
+    }
+
+    Ok(())
+}
+
+async fn connect_with_retries(
+    config: &WsConfig,
+) -> Result<BufReader<Box<dyn Connection>>, ConnectionError> {
+    let mut delay = INITIAL_RECONNECT_DELAY;
+    let mut attempt = 1;
+
+    loop {
+        let result = tokio::time::timeout(config.connect_timeout, open_connection(config))
+            .await
+            .unwrap_or(Err(ConnectionError::Timeout));
+
+        match result {
+            Ok(connection) => return Ok(connection),
+            // Retrying won't make the node accept a handshake it rejected
+            Err(error @ ConnectionError::HandshakeRejected(_)) => return Err(error),
+            Err(error) if attempt >= MAX_RECONNECT_ATTEMPTS => return Err(error),
+            Err(error) => {
+                debug!(attempt, %error, "Failed to connect to execution node WebSocket, retrying in {delay:?}");
+
+                tokio::time::sleep(delay).await;
+
+                delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Opens the TCP connection, wrapped in TLS for `wss://` URLs, and performs
+/// the WebSocket opening handshake.
+async fn open_connection(
+    config: &WsConfig,
+) -> Result<BufReader<Box<dyn Connection>>, ConnectionError> {
+    let url = &config.url;
+    let host = url
+        .host_str()
+        .ok_or(ConnectionError::InvalidUrl("missing host"))?;
+    let port = url
+        .port_or_known_default()
+        .ok_or(ConnectionError::InvalidUrl("missing port"))?;
+    let tcp = match &config.proxy {
+        Some(proxy) => connect_through_proxy(proxy, host, port).await?,
+        None => TcpStream::connect((host, port)).await?,
+    };
+
+    tcp.set_nodelay(true)?;
+
+    let connection: Box<dyn Connection> = if url.scheme() == "wss" {
+        let server_name = ServerName::try_from(host.trim_matches(['[', ']']))
+            .map_err(|_| ConnectionError::InvalidUrl("invalid host"))?;
+        let connector = TlsConnector::from(Arc::new(config.tls.client_config()));
+
+        Box::new(connector.connect(server_name, tcp).await?)
+    } else {
+        Box::new(tcp)
+    };
+    let mut connection = BufReader::new(connection);
+
+    let mut path = url.path().to_string();
+
+    if let Some(query) = url.query() {
+        path = format!("{path}?{query}");
+    }
+
+    let host_header = match url.port() {
+        Some(port) => format!("{host}:{port}"),
+        None => host.to_string(),
+    };
+    let key = BASE64.encode(rand::random::<[u8; 16]>());
+    let mut request = format!(
+        "GET {path} HTTP/1.1\r\n\
+         Host: {host_header}\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Key: {key}\r\n\
+         Sec-WebSocket-Version: 13\r\n\
+         User-Agent: {}\r\n",
+        config.user_agent
+    );
+
+    for header in config.extra_headers.iter().chain(&config.auth_header) {
+        let value = header
+            .value
+            .to_str()
+            .map_err(|_| ConnectionError::InvalidUrl("non-ASCII header value"))?;
+
+        request.push_str(&format!("{}: {value}\r\n", header.name));
+    }
+
+    // The token is only checked during the handshake, so a fresh one is
+    // issued for every connection
+    if let Some(jwt_secret) = &config.jwt_secret {
+        request.push_str(&format!(
+            "Authorization: Bearer {}\r\n",
+            jwt_secret.issue_token()?
+        ));
+    }
+
+    request.push_str("\r\n");
+    connection.write_all(request.as_bytes()).await?;
+    connection.flush().await?;
+
+    read_handshake_response(&mut connection).await?;
+
+    Ok(connection)
+}
+
+/// Opens a tunnel to the node through the HTTP proxy with a `CONNECT` request.
+async fn connect_through_proxy(
+    proxy: &Url,
+    host: &str,
+    port: u16,
+) -> Result<TcpStream, ConnectionError> {
+    if proxy.scheme() != "http" {
+        return Err(ConnectionError::InvalidUrl(
+            "only http:// proxies are supported for WebSocket connections",
+        ));
+    }
+
+    let proxy_host = proxy
+        .host_str()
+        .ok_or(ConnectionError::InvalidUrl("missing proxy host"))?;
+    let proxy_port = proxy
+        .port_or_known_default()
+        .ok_or(ConnectionError::InvalidUrl("missing proxy port"))?;
+    let mut tcp = TcpStream::connect((proxy_host, proxy_port)).await?;
+    let mut request = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n");
+
+    if !proxy.username().is_empty() {
+        let credentials = format!(
+            "{}:{}",
+            percent_decode(proxy.username()),
+            percent_decode(proxy.password().unwrap_or_default())
+        );
+
+        request.push_str(&format!(
+            "Proxy-Authorization: Basic {}\r\n",
+            BASE64.encode(credentials)
+        ));
+    }
+
+    request.push_str("\r\n");
+    tcp.write_all(request.as_bytes()).await?;
+
+    // Nothing is sent through the tunnel until the handshake goes through it,
+    // so no bytes past the proxy's response are buffered
+    let mut reader = BufReader::new(&mut tcp);
+    let mut status_line = String::new();
+
+    reader.read_line(&mut status_line).await?;
+
+    loop {
+        let mut line = String::new();
+
+        if reader.read_line(&mut line).await? == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        if line.trim_end().is_empty() {
+            break;
+        }
+    }
+
+    let status_line = status_line.trim_end();
+
+    if status_line.split_whitespace().nth(1) != Some("200") {
+        return Err(ConnectionError::ProxyRejected(status_line.to_string()));
+    }
+
+    Ok(tcp)
+}
+
+/// Decodes the percent-encoded credentials of a URL.
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| value.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+async fn read_handshake_response(
+    connection: &mut BufReader<Box<dyn Connection>>,
+) -> Result<(), ConnectionError> {
+    let mut status_line = String::new();
+
+    connection.read_line(&mut status_line).await?;
+
+    let status_line = status_line.trim_end();
+    let is_switching = status_line
+        .split_whitespace()
+        .nth(1)
+        .is_some_and(|status| status == "101");
+    let mut is_upgraded = false;
+
+    loop {
+        let mut line = String::new();
+
+        if connection.read_line(&mut line).await? == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        let line = line.trim_end();
+
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("upgrade") {
+                is_upgraded = value.trim().eq_ignore_ascii_case("websocket");
+            }
+        }
+    }
+
+    if !is_switching || !is_upgraded {
+        return Err(ConnectionError::HandshakeRejected(status_line.to_string()));
+    }
+
+    Ok(())
+}
+
+/// Forwards the messages received from the node until the connection gets
+/// closed or fails.
+async fn read_messages<R: AsyncRead + Unpin>(mut reader: R, incoming: mpsc::Sender<Incoming>) {
+    let mut message = Vec::new();
+
+    loop {
+        let (is_final, opcode, payload) = match read_frame(&mut reader).await {
+            Ok(frame) => frame,
+            Err(error) => {
+                if error.kind() != io::ErrorKind::UnexpectedEof {
+                    warn!(?error, "Failed to read from execution node WebSocket");
+                }
+
+                return;
+            }
+        };
+
+        let forwarded = match opcode {
+            OPCODE_TEXT | OPCODE_BINARY | OPCODE_CONTINUATION => {
+                if message.len() + payload.len() > MAX_MESSAGE_SIZE {
+                    warn!("Execution node WebSocket message exceeds the maximum size");
+
+                    return;
+                }
+
+                message.extend_from_slice(&payload);
+
+                if !is_final {
+                    continue;
+                }
+
+                Incoming::Data(std::mem::take(&mut message))
+            }
+            OPCODE_PING => Incoming::Ping(payload),
+            OPCODE_PONG => continue,
+            OPCODE_CLOSE => return,
+            _ => {
+                warn!(opcode, "Received unknown WebSocket frame");
+
+                return;
+            }
+        };
+
+        if incoming.send(forwarded).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Reads a single frame, returning whether it's the last one of its message,
+/// its opcode and its unmasked payload.
+async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<(bool, u8, Vec<u8>)> {
+    let mut header = [0u8; 2];
+
+    reader.read_exact(&mut header).await?;
+
+    let is_final = header[0] & 0x80 != 0;
+    let opcode = header[0] & 0x0f;
+    let is_masked = header[1] & 0x80 != 0;
+    let len = match header[1] & 0x7f {
+        126 => reader.read_u16().await? as u64,
+        127 => reader.read_u64().await?,
+        len => len as u64,
+    };
+
+    if len > MAX_MESSAGE_SIZE as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("frame of {len} bytes exceeds the maximum message size"),
+        ));
+    }
+
+    let mut mask = [0u8; 4];
+
+    if is_masked {
+        reader.read_exact(&mut mask).await?;
+    }
+
+    let mut payload = vec![0u8; len as usize];
+
+    reader.read_exact(&mut payload).await?;
+
+    if is_masked {
+        apply_mask(&mut payload, mask);
+    }
+
+    Ok((is_final, opcode, payload))
+}
+
+/// Encodes a single final frame. Frames sent by clients must be masked.
+fn encode_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 14);
+
+    frame.push(0x80 | opcode);
+
+    match payload.len() {
+        len if len < 126 => frame.push(0x80 | len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(0x80 | 126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(0x80 | 127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+
+    let mask = rand::random::<[u8; 4]>();
+
+    frame.extend_from_slice(&mask);
+
+    let start = frame.len();
+
+    frame.extend_from_slice(payload);
+    apply_mask(&mut frame[start..], mask);
+
+    frame
+}
+
+fn apply_mask(payload: &mut [u8], mask: [u8; 4]) {
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+}
@@ -0,0 +1,99 @@
+use std::{fmt, str::FromStr};
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Deserializer};
+
+use super::execution::{AuthHeader, AuthHeaderError};
+
+/// Identifies the indexer, its version and the network it indexes to the
+/// nodes and APIs it talks to, e.g. `blob-indexer/0.1.0 (mainnet)`.
+pub fn default_user_agent(network: &str) -> String {
+    format!(
+        "{}/{} ({network})",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+/// Static headers sent along every outbound request, given as `<name>: <value>`
+/// pairs separated by semicolons, e.g. `x-client-id: blobscan; x-team: infra`.
+/// Some providers require them to attribute the requests to a quota.
+#[derive(Clone, Default)]
+pub struct ExtraHeaders(Vec<AuthHeader>);
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid extra header: {0}")]
+pub struct ExtraHeadersError(#[from] AuthHeaderError);
+
+impl ExtraHeaders {
+    pub fn iter(&self) -> impl Iterator<Item = &AuthHeader> {
+        self.0.iter()
+    }
+
+    /// Returns the headers for the clients built on the reqwest version the
+    /// beacon and Blobscan clients use.
+    pub fn header_map(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        for header in self.iter() {
+            // Already validated when parsing the headers
+            if let (Ok(name), Ok(mut value)) = (
+                HeaderName::from_bytes(header.name.as_str().as_bytes()),
+                HeaderValue::from_bytes(header.value.as_bytes()),
+            ) {
+                value.set_sensitive(true);
+                headers.append(name, value);
+            }
+        }
+
+        headers
+    }
+}
+
+impl FromStr for ExtraHeaders {
+    type Err = ExtraHeadersError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let headers = s
+            .split(';')
+            .map(str::trim)
+            .filter(|header| !header.is_empty())
+            .map(AuthHeader::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self(headers))
+    }
+}
+
+impl<'de> Deserialize<'de> for ExtraHeaders {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl fmt::Debug for ExtraHeaders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExtraHeaders;
+
+    #[test]
+    fn extra_headers_are_parsed_from_semicolon_separated_pairs() {
+        let headers: ExtraHeaders = "x-client-id: blobscan; x-team:infra;".parse().unwrap();
+        let header_map = headers.header_map();
+
+        assert_eq!(header_map.len(), 2);
+        assert_eq!(header_map["x-client-id"], "blobscan");
+        assert_eq!(header_map["x-team"], "infra");
+        assert!("x-client-id".parse::<ExtraHeaders>().is_err());
+    }
+}
@@ -2,11 +2,20 @@
 /// Make a GET request sending and expecting JSON.
 /// if JSON deser fails, emit a `WARN` level tracing event
 macro_rules! json_get {
-    ($client:expr, $url:expr, $expected:ty, $exp_backoff:expr) => {
-        json_get!($client, $url, $expected, "", $exp_backoff)
+    ($client:expr, $url:expr, $expected:ty, $backoff_profile:expr) => {
+        json_get!($client, $url, $expected, "", $backoff_profile)
     };
-    ($client:expr, $url:expr, $expected:ty, $auth_token:expr, $exp_backoff: expr) => {{
+    ($client:expr, $url:expr, $expected:ty, $auth_token:expr, $backoff_profile: expr) => {{
         let url = $url.clone();
+        let backoff_profile: Option<&$crate::clients::backoff::BackoffProfile> = $backoff_profile;
+
+        let http_span = tracing::debug_span!(
+            "http_request",
+            method = "GET",
+            host = url.host_str().unwrap_or_default(),
+            path = url.path(),
+            status = tracing::field::Empty
+        );
 
         tracing::trace!(method = "GET", url = url.as_str(), "Dispatching API request");
 
@@ -16,13 +25,31 @@ macro_rules! json_get {
           req = req.bearer_auth($auth_token);
         }
 
-        let resp = if $exp_backoff.is_some() {
+        let resp = if let Some(backoff_profile) = backoff_profile {
+            let req = req.timeout(backoff_profile.timeout);
+
             match backoff::future::retry_notify(
-                $exp_backoff.unwrap(),
+                backoff_profile.exponential_backoff(),
                 || {
                     let req = req.try_clone().unwrap();
+                    let url = &url;
+
+                    tracing::Instrument::instrument(async move {
+                        let result = req.send().await;
+
+                        $crate::clients::common::record_response("GET", url, &result);
+
+                        let resp = result.map_err(|err| backoff::Error::transient(err.into()))?;
 
-                    async move { req.send().await.map_err(|err| err.into()) }
+                        // Throttling is transient, so wait for as long as the server asks to
+                        match $crate::clients::common::check_rate_limit(&resp) {
+                            Ok(()) => Ok(resp),
+                            Err(error @ $crate::clients::common::ClientError::RateLimited {
+                                retry_after: Some(retry_after),
+                            }) => Err(backoff::Error::retry_after(error, retry_after)),
+                            Err(error) => Err(backoff::Error::transient(error)),
+                        }
+                    }, http_span.clone())
                 },
                 |error, duration: std::time::Duration| {
                     let duration = duration.as_secs();
@@ -49,7 +76,15 @@ macro_rules! json_get {
                 }
             }
         } else {
-            match req.send().await {
+            let result = tracing::Instrument::instrument(async {
+                let result = req.send().await;
+
+                $crate::clients::common::record_response("GET", &url, &result);
+
+                result
+            }, http_span.clone()).await;
+
+            match result {
                 Err(error) => {
                     tracing::warn!(
                         method = "GET",
@@ -60,7 +95,11 @@ macro_rules! json_get {
 
                     return Err(error.into())
                 },
-                Ok(resp) => resp
+                Ok(resp) => {
+                    $crate::clients::common::check_rate_limit(&resp)?;
+
+                    resp
+                }
             }
         };
 
@@ -71,6 +110,18 @@ macro_rules! json_get {
         };
 
         let text = resp.text().await?;
+
+        if let Err(error) = $crate::clients::common::check_status(status, &text) {
+            tracing::warn!(
+                method = "GET",
+                url = %url,
+                response = text.as_str(),
+                "Unexpected response from server"
+            );
+
+            return Err(error)
+        }
+
         let result: Result<$crate::clients::common::ClientResponse<$expected>, _> = serde_json::from_str(&text);
 
         match result {
@@ -95,27 +146,154 @@ macro_rules! json_get {
 /// Make a PUT request sending JSON.
 /// if JSON deser fails, emit a `WARN` level tracing event
 macro_rules! json_put {
-    ($client:expr, $url:expr, $auth_token:expr, $body:expr) => {
-        json_put!($client, $url, (), $auth_token, $body)
+    ($client:expr, $url:expr, $credential:expr, $body:expr, $backoff_profile:expr) => {
+        $crate::json_put!($client, $url, (), $credential, $body, $backoff_profile)
+    };
+    ($client:expr, $url:expr, $expected:ty, $credential:expr, $body:expr, $backoff_profile:expr) => {
+        $crate::json_send!(
+            $client,
+            reqwest::Method::PUT,
+            $url,
+            $expected,
+            $credential,
+            Some($body),
+            $backoff_profile
+        )
+    };
+}
+
+#[macro_export]
+/// Make a POST request sending JSON.
+/// Requests are retried when a backoff profile is given, so it should only be
+/// used with endpoints that are safe to call more than once.
+/// if JSON deser fails, emit a `WARN` level tracing event
+macro_rules! json_post {
+    ($client:expr, $url:expr, $credential:expr, $body:expr, $backoff_profile:expr) => {
+        $crate::json_post!($client, $url, (), $credential, $body, $backoff_profile)
+    };
+    ($client:expr, $url:expr, $expected:ty, $credential:expr, $body:expr, $backoff_profile:expr) => {
+        $crate::json_send!(
+            $client,
+            reqwest::Method::POST,
+            $url,
+            $expected,
+            $credential,
+            Some($body),
+            $backoff_profile
+        )
+    };
+}
+
+#[macro_export]
+/// Make a DELETE request without a body.
+/// if JSON deser fails, emit a `WARN` level tracing event
+macro_rules! json_delete {
+    ($client:expr, $url:expr, $credential:expr, $backoff_profile:expr) => {
+        $crate::json_delete!($client, $url, (), $credential, $backoff_profile)
+    };
+    ($client:expr, $url:expr, $expected:ty, $credential:expr, $backoff_profile:expr) => {
+        $crate::json_send!(
+            $client,
+            reqwest::Method::DELETE,
+            $url,
+            $expected,
+            $credential,
+            None::<&()>,
+            $backoff_profile
+        )
     };
-    ($client:expr, $url:expr, $expected:ty, $auth_token:expr, $body:expr) => {{
+}
+
+#[macro_export]
+/// Make an authenticated request of the given method, sending the body as JSON
+/// when there's one. The body is any [`JsonBody`](crate::clients::body::JsonBody),
+/// either a reference to a serializable value or a streamed one. Used by [`json_put!`], [`json_post!`] and [`json_delete!`].
+/// if JSON deser fails, emit a `WARN` level tracing event
+macro_rules! json_send {
+    ($client:expr, $method:expr, $url:expr, $expected:ty, $credential:expr, $body:expr, $backoff_profile:expr) => {{
+        let method: reqwest::Method = $method;
         let url = $url.clone();
-        let body = format!("{:?}", $body);
+        let json_body = $body;
+        let body = $crate::clients::body::LoggedBody(json_body.as_ref());
+        let backoff_profile: Option<&$crate::clients::backoff::BackoffProfile> = $backoff_profile;
 
-        tracing::trace!(method = "PUT", url = url.as_str(), body, "Dispatching API client request");
+        let http_span = tracing::debug_span!(
+            "http_request",
+            method = method.as_str(),
+            host = url.host_str().unwrap_or_default(),
+            path = url.path(),
+            status = tracing::field::Empty
+        );
 
+        tracing::trace!(method = method.as_str(), url = url.as_str(), body = %body, "Dispatching API client request");
 
-        let resp = match $client
-            .put($url)
-            .bearer_auth($auth_token)
-            .json($body)
-            .send()
-            .await {
+        let credential: $crate::clients::common::Credential = $credential;
+        let req = credential.authenticate($client.request(method.clone(), $url));
+        let with_body = |req: reqwest::RequestBuilder| match &json_body {
+            Some(json_body) => $crate::clients::body::JsonBody::attach(json_body, req),
+            None => req,
+        };
+
+        let resp = if let Some(backoff_profile) = backoff_profile {
+            let req = req.timeout(backoff_profile.timeout);
+
+            backoff::future::retry_notify(
+                backoff_profile.exponential_backoff(),
+                || {
+                    let req = with_body(req.try_clone().unwrap());
+                    let url = &url;
+                    let method = &method;
+
+                    tracing::Instrument::instrument(async move {
+                        let result = req.send().await;
+
+                        $crate::clients::common::record_response(method.as_str(), url, &result);
+
+                        let resp = result.map_err(|err| backoff::Error::transient(err.into()))?;
+
+                        // Throttling is transient, so wait for as long as the server asks to
+                        match $crate::clients::common::check_rate_limit(&resp) {
+                            Ok(()) => Ok(resp),
+                            Err(error @ $crate::clients::common::ClientError::RateLimited {
+                                retry_after: Some(retry_after),
+                            }) => Err(backoff::Error::retry_after(error, retry_after)),
+                            Err(error) => Err(backoff::Error::transient(error)),
+                        }
+                    }, http_span.clone())
+                },
+                |error, duration: std::time::Duration| {
+                    let duration = duration.as_secs();
+
+                    tracing::warn!(
+                        method = method.as_str(),
+                        url = %url,
+                        ?error,
+                        "Failed to send request. Retrying in {duration} seconds…"
+                    );
+                },
+            )
+            .await
+        } else {
+            let result = tracing::Instrument::instrument(async {
+                let result = with_body(req).send().await;
+
+                $crate::clients::common::record_response(method.as_str(), &url, &result);
+
+                result
+            }, http_span.clone()).await;
+
+            match result {
+                Ok(resp) => $crate::clients::common::check_rate_limit(&resp).map(|_| resp),
+                Err(error) => Err(error.into()),
+            }
+        };
+
+        let resp: reqwest::Response = match resp {
                 Err(error) => {
                     tracing::warn!(
-                        method = "PUT",
+                        method = method.as_str(),
                         url = %url,
-                        body = body,
+                        body = %body,
                         ?error,
                         "Failed to send request"
                     );
@@ -125,14 +303,28 @@ macro_rules! json_put {
                 Ok(resp) => resp
             };
 
+        let status = resp.status();
         let text = resp.text().await?;
+
+        if let Err(error) = $crate::clients::common::check_status(status, &text) {
+            tracing::warn!(
+                method = method.as_str(),
+                url = %url,
+                body = %body,
+                response = text.as_str(),
+                "Unexpected response from server"
+            );
+
+            return Err(error)
+        }
+
         let result: $crate::clients::common::ClientResponse<$expected> = text.parse()?;
 
         if result.is_err() {
             tracing::warn!(
-                method = "PUT",
+                method = method.as_str(),
                 url = %url,
-                body,
+                body = %body,
                 response = text.as_str(),
                 "Unexpected response from server"
             );
@@ -1,5 +1,12 @@
 mod macros;
 
+pub mod backoff;
 pub mod beacon;
 pub mod blobscan;
+pub mod body;
 pub mod common;
+pub mod execution;
+pub mod headers;
+pub mod priority;
+pub mod proxy;
+pub mod tls;
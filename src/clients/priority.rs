@@ -0,0 +1,52 @@
+use std::future::Future;
+
+tokio::task_local! {
+    static PRIORITY: RequestPriority;
+}
+
+/// Priority of the requests made while processing a slot. The indexer doesn't
+/// throttle the nodes itself, so requests only queue up on the caps shared by
+/// every task, such as the in-flight bytes sent to Blobscan. High priority
+/// requests skip those queues so the head isn't held up by thousands of
+/// backfill requests waiting their turn, at the cost of briefly exceeding the
+/// caps.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RequestPriority {
+    #[default]
+    Normal,
+    /// Requests of the head slots
+    High,
+}
+
+impl RequestPriority {
+    /// Priority of the requests made by the current task.
+    pub fn current() -> Self {
+        PRIORITY.try_with(|priority| *priority).unwrap_or_default()
+    }
+
+    pub fn is_high(&self) -> bool {
+        *self == RequestPriority::High
+    }
+
+    /// Runs the given future with this priority.
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        PRIORITY.scope(self, future).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RequestPriority;
+
+    #[tokio::test]
+    async fn priority_is_scoped_to_the_future() {
+        assert_eq!(RequestPriority::current(), RequestPriority::Normal);
+
+        let priority = RequestPriority::High
+            .scope(async { RequestPriority::current() })
+            .await;
+
+        assert_eq!(priority, RequestPriority::High);
+        assert_eq!(RequestPriority::current(), RequestPriority::Normal);
+    }
+}
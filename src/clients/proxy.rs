@@ -0,0 +1,71 @@
+use std::env;
+
+use url::Url;
+
+/// Returns the proxy requests to the given URL go through: the explicit one
+/// when set, or otherwise the one in `HTTPS_PROXY`, `HTTP_PROXY` or
+/// `ALL_PROXY` matching its scheme, unless its host is excluded by `NO_PROXY`.
+pub fn proxy_for(url: &Url, explicit_proxy: Option<&Url>) -> Option<Url> {
+    let host = url.host_str()?;
+
+    if env_var("NO_PROXY").is_some_and(|no_proxy| is_excluded(&no_proxy, host)) {
+        return None;
+    }
+
+    if let Some(proxy) = explicit_proxy {
+        return Some(proxy.clone());
+    }
+
+    let scheme_var = match url.scheme() {
+        "https" | "wss" => "HTTPS_PROXY",
+        _ => "HTTP_PROXY",
+    };
+
+    env_var(scheme_var)
+        .or_else(|| env_var("ALL_PROXY"))
+        .and_then(|proxy| proxy.parse().ok())
+}
+
+/// Whether the host matches any of the comma-separated entries of a
+/// `NO_PROXY` list, either exactly or as a subdomain. `*` matches every host.
+fn is_excluded(no_proxy: &str, host: &str) -> bool {
+    let host = host.trim_matches(['[', ']']);
+
+    no_proxy
+        .split(',')
+        .map(|entry| entry.trim().trim_start_matches('.'))
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| {
+            entry == "*"
+                || host.eq_ignore_ascii_case(entry)
+                || host
+                    .to_ascii_lowercase()
+                    .ends_with(&format!(".{}", entry.to_ascii_lowercase()))
+        })
+}
+
+/// Reads the variable in either case, as curl and reqwest do.
+fn env_var(name: &str) -> Option<String> {
+    env::var(name)
+        .or_else(|_| env::var(name.to_ascii_lowercase()))
+        .ok()
+        .filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_excluded;
+
+    #[test]
+    fn hosts_are_excluded_by_domain() {
+        let no_proxy = "localhost, .internal.example.com,10.0.0.1";
+
+        assert!(is_excluded(no_proxy, "localhost"));
+        assert!(is_excluded(no_proxy, "node.internal.example.com"));
+        assert!(is_excluded(no_proxy, "internal.example.com"));
+        assert!(is_excluded(no_proxy, "10.0.0.1"));
+        assert!(!is_excluded(no_proxy, "example.com"));
+        assert!(!is_excluded(no_proxy, "notlocalhost"));
+        assert!(is_excluded("*", "example.com"));
+    }
+}
@@ -0,0 +1,545 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::SystemTime,
+};
+
+use alloy::transports::http::reqwest as provider_reqwest;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use rustls::{
+    client::WantsClientCert,
+    client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier},
+    Certificate, CertificateError, ClientConfig, ConfigBuilder, OwnedTrustAnchor, PrivateKey,
+    RootCertStore, ServerName,
+};
+use serde::{Deserialize, Deserializer};
+use sha2::{Digest, Sha256};
+
+/// SHA-256 hash a server certificate is pinned to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TlsPin {
+    /// Hash of the certificate's DER-encoded subject public key info, given as
+    /// `sha256/<base64>` like `curl --pinnedpubkey` does. It survives
+    /// certificate renewals as long as the key is kept
+    Spki([u8; 32]),
+    /// Hash of the whole DER-encoded certificate, given as `cert-sha256/<hex>`
+    Certificate([u8; 32]),
+}
+
+/// Comma-separated list of [`TlsPin`]s. A certificate chain is accepted when
+/// any of its certificates matches any of the pins.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TlsPins(Vec<TlsPin>);
+
+#[derive(Debug, thiserror::Error)]
+pub enum TlsPinError {
+    #[error("invalid TLS pin \"{0}\": expected sha256/<base64> or cert-sha256/<hex>")]
+    InvalidFormat(String),
+    #[error("invalid TLS pin \"{0}\": expected a 32 bytes SHA-256 hash")]
+    InvalidHash(String),
+    #[error("no TLS pins given")]
+    Empty,
+}
+
+/// Extra trust anchors and client certificate used for the connections to the
+/// nodes and the Blobscan API, for endpoints fronted by a private PKI.
+#[derive(Clone, Default)]
+pub struct TlsSettings {
+    /// PEM certificates trusted along with the WebPKI roots
+    ca_bundle: Option<Vec<u8>>,
+    /// PEM certificate chain and PKCS#8 key presented to servers requesting
+    /// client authentication
+    client_identity: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TlsSettingsError {
+    #[error("failed to read {path}: {error}")]
+    ReadFailure { path: PathBuf, error: io::Error },
+    #[error("no PEM certificates found in {0}")]
+    NoCertificates(PathBuf),
+    #[error("no PEM encoded PKCS#8 private key found in {0}")]
+    NoPrivateKey(PathBuf),
+    #[error("a client certificate and its key must be given together")]
+    IncompleteClientIdentity,
+    #[error("invalid client certificate: {0}")]
+    InvalidClientIdentity(#[source] rustls::Error),
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("certificate pin mismatch for {server_name}: none of the certificates presented by the server matches the configured pins")]
+pub struct PinMismatchError {
+    server_name: String,
+}
+
+/// Certificate verifier that performs the regular WebPKI validation and then
+/// rejects chains not matching any of the pins.
+struct PinningVerifier {
+    inner: WebPkiVerifier,
+    pins: TlsPins,
+}
+
+impl TlsPin {
+    fn matches(&self, certificate: &Certificate) -> bool {
+        match self {
+            TlsPin::Certificate(hash) => Sha256::digest(&certificate.0).as_slice() == hash,
+            TlsPin::Spki(hash) => match x509_parser::parse_x509_certificate(&certificate.0) {
+                Ok((_, parsed)) => Sha256::digest(parsed.public_key().raw).as_slice() == hash,
+                Err(_) => false,
+            },
+        }
+    }
+}
+
+impl TlsPins {
+    /// Builds a TLS config that trusts the bundled WebPKI roots, along with
+    /// the settings' CA bundle, and only accepts certificate chains matching
+    /// the pins.
+    pub fn client_config(&self, settings: &TlsSettings) -> ClientConfig {
+        let verifier = PinningVerifier {
+            inner: WebPkiVerifier::new(settings.root_store(), None),
+            pins: self.clone(),
+        };
+
+        settings.with_client_auth(
+            ClientConfig::builder()
+                .with_safe_defaults()
+                .with_custom_certificate_verifier(Arc::new(verifier)),
+        )
+    }
+}
+
+impl TlsSettings {
+    /// Reads the CA bundle and the client certificate and key from the given
+    /// PEM files.
+    pub fn load(
+        ca_bundle: Option<&Path>,
+        client_cert: Option<&Path>,
+        client_key: Option<&Path>,
+    ) -> Result<Self, TlsSettingsError> {
+        let ca_bundle = ca_bundle.map(read_certificates).transpose()?;
+        let client_identity = match (client_cert, client_key) {
+            (Some(client_cert), Some(client_key)) => {
+                let cert = read_certificates(client_cert)?;
+                let key = read_file(client_key)?;
+
+                if pkcs8_key(&key).is_none() {
+                    return Err(TlsSettingsError::NoPrivateKey(client_key.to_path_buf()));
+                }
+
+                Some((cert, key))
+            }
+            (None, None) => None,
+            _ => return Err(TlsSettingsError::IncompleteClientIdentity),
+        };
+        let settings = Self {
+            ca_bundle,
+            client_identity,
+        };
+
+        // Catches certificates not matching their key before connecting
+        if let Some((cert, key)) = &settings.client_identity {
+            ClientConfig::builder()
+                .with_safe_defaults()
+                .with_root_certificates(RootCertStore::empty())
+                .with_client_auth_cert(
+                    der_certificates(cert),
+                    PrivateKey(pkcs8_key(key).unwrap_or_default()),
+                )
+                .map_err(TlsSettingsError::InvalidClientIdentity)?;
+        }
+
+        Ok(settings)
+    }
+
+    /// Adds the CA bundle and client certificate to a client built on the
+    /// reqwest version the beacon and Blobscan clients use.
+    pub fn apply(
+        &self,
+        mut builder: reqwest::ClientBuilder,
+    ) -> reqwest::Result<reqwest::ClientBuilder> {
+        if let Some(ca_bundle) = &self.ca_bundle {
+            for certificate in reqwest::Certificate::from_pem_bundle(ca_bundle)? {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        if let Some((cert, key)) = &self.client_identity {
+            // PEM identities are only supported by the rustls backend
+            builder = builder
+                .use_rustls_tls()
+                .identity(reqwest::Identity::from_pem(
+                    &[cert.as_slice(), key].concat(),
+                )?);
+        }
+
+        Ok(builder)
+    }
+
+    /// Adds the CA bundle and client certificate to a client built on the
+    /// reqwest version the execution node provider uses.
+    pub fn apply_to_provider_client(
+        &self,
+        mut builder: provider_reqwest::ClientBuilder,
+    ) -> provider_reqwest::Result<provider_reqwest::ClientBuilder> {
+        if let Some(ca_bundle) = &self.ca_bundle {
+            for certificate in provider_reqwest::Certificate::from_pem_bundle(ca_bundle)? {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        if let Some((cert, key)) = &self.client_identity {
+            builder = builder.identity(provider_reqwest::Identity::from_pkcs8_pem(cert, key)?);
+        }
+
+        Ok(builder)
+    }
+
+    /// Builds a TLS config that trusts the bundled WebPKI roots along with the
+    /// CA bundle, for connections not made through reqwest.
+    pub fn client_config(&self) -> ClientConfig {
+        let verifier = WebPkiVerifier::new(self.root_store(), None);
+
+        self.with_client_auth(
+            ClientConfig::builder()
+                .with_safe_defaults()
+                .with_custom_certificate_verifier(Arc::new(verifier)),
+        )
+    }
+
+    fn root_store(&self) -> RootCertStore {
+        let mut roots = webpki_roots();
+
+        if let Some(ca_bundle) = &self.ca_bundle {
+            roots.add_parsable_certificates(
+                &der_certificates(ca_bundle)
+                    .iter()
+                    .map(|certificate| certificate.0.clone())
+                    .collect::<Vec<_>>(),
+            );
+        }
+
+        roots
+    }
+
+    fn with_client_auth(
+        &self,
+        builder: ConfigBuilder<ClientConfig, WantsClientCert>,
+    ) -> ClientConfig {
+        match &self.client_identity {
+            // Already validated when loading the settings
+            Some((cert, key)) => match pkcs8_key(key) {
+                Some(key) => builder
+                    .with_client_auth_cert(der_certificates(cert), PrivateKey(key))
+                    .expect("client certificate validated when loading the TLS settings"),
+                None => builder.with_no_client_auth(),
+            },
+            None => builder.with_no_client_auth(),
+        }
+    }
+}
+
+impl fmt::Debug for TlsSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsSettings")
+            .field("ca_bundle", &self.ca_bundle.is_some())
+            .field("client_identity", &self.client_identity.is_some())
+            .finish()
+    }
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, TlsSettingsError> {
+    fs::read(path).map_err(|error| TlsSettingsError::ReadFailure {
+        path: path.to_path_buf(),
+        error,
+    })
+}
+
+fn read_certificates(path: &Path) -> Result<Vec<u8>, TlsSettingsError> {
+    let pem = read_file(path)?;
+
+    if der_certificates(&pem).is_empty() {
+        return Err(TlsSettingsError::NoCertificates(path.to_path_buf()));
+    }
+
+    Ok(pem)
+}
+
+fn der_certificates(pem: &[u8]) -> Vec<Certificate> {
+    rustls_pemfile::certs(&mut &pem[..])
+        .unwrap_or_default()
+        .into_iter()
+        .map(Certificate)
+        .collect()
+}
+
+fn pkcs8_key(pem: &[u8]) -> Option<Vec<u8>> {
+    rustls_pemfile::pkcs8_private_keys(&mut &pem[..])
+        .ok()?
+        .into_iter()
+        .next()
+}
+
+fn webpki_roots() -> RootCertStore {
+    let mut roots = RootCertStore::empty();
+
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| {
+        OwnedTrustAnchor::from_subject_spki_name_constraints(
+            anchor.subject,
+            anchor.spki,
+            anchor.name_constraints,
+        )
+    }));
+
+    roots
+}
+
+impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            scts,
+            ocsp_response,
+            now,
+        )?;
+
+        let is_pinned = std::iter::once(end_entity)
+            .chain(intermediates)
+            .any(|certificate| self.pins.0.iter().any(|pin| pin.matches(certificate)));
+
+        if is_pinned {
+            Ok(verified)
+        } else {
+            let server_name = match server_name {
+                ServerName::DnsName(name) => name.as_ref().to_string(),
+                ServerName::IpAddress(address) => address.to_string(),
+                _ => "unknown server".to_string(),
+            };
+
+            tracing::error!(server_name, "TLS certificate pin mismatch");
+
+            Err(rustls::Error::InvalidCertificate(CertificateError::Other(
+                Arc::new(PinMismatchError { server_name }),
+            )))
+        }
+    }
+}
+
+impl FromStr for TlsPin {
+    type Err = TlsPinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid_hash = || TlsPinError::InvalidHash(s.to_string());
+        let to_hash = |bytes: Vec<u8>| <[u8; 32]>::try_from(bytes).map_err(|_| invalid_hash());
+
+        if let Some(hash) = s.strip_prefix("sha256/") {
+            let bytes = BASE64.decode(hash).map_err(|_| invalid_hash())?;
+
+            Ok(TlsPin::Spki(to_hash(bytes)?))
+        } else if let Some(hash) = s.strip_prefix("cert-sha256/") {
+            let bytes = hex::decode(hash.replace(':', "")).map_err(|_| invalid_hash())?;
+
+            Ok(TlsPin::Certificate(to_hash(bytes)?))
+        } else {
+            Err(TlsPinError::InvalidFormat(s.to_string()))
+        }
+    }
+}
+
+impl FromStr for TlsPins {
+    type Err = TlsPinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pins = s
+            .split(',')
+            .map(str::trim)
+            .filter(|pin| !pin.is_empty())
+            .map(TlsPin::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if pins.is_empty() {
+            return Err(TlsPinError::Empty);
+        }
+
+        Ok(Self(pins))
+    }
+}
+
+impl<'de> Deserialize<'de> for TlsPins {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use rustls::{client::ServerCertVerifier, CertificateError, ServerName};
+
+    use super::{
+        der_certificates, PinningVerifier, TlsPin, TlsPinError, TlsPins, TlsSettings,
+        WebPkiVerifier,
+    };
+
+    const CA_CERTIFICATE: &str = "-----BEGIN CERTIFICATE-----
+MIIBijCCATGgAwIBAgIULgyH7x7qOxDSdPWeYewETvE/tsUwCgYIKoZIzj0EAwIw
+EjEQMA4GA1UEAwwHVGVzdCBDQTAgFw0yNjEwMTYxNTM5NDRaGA8yMTI2MDkyMjE1
+Mzk0NFowEjEQMA4GA1UEAwwHVGVzdCBDQTBZMBMGByqGSM49AgEGCCqGSM49AwEH
+A0IABB7BJnUJM1vEQQTQqllEYgCTVZIWbFgMAbzVMPfv1dTUCS2ac+Xbbgie9aLG
+lJK5+KIysDxDta6+AbhmorjFDE+jYzBhMB0GA1UdDgQWBBSUiK3Bxl0x/sDUp5Z7
+GXBqH4ZvADAfBgNVHSMEGDAWgBSUiK3Bxl0x/sDUp5Z7GXBqH4ZvADAPBgNVHRMB
+Af8EBTADAQH/MA4GA1UdDwEB/wQEAwICBDAKBggqhkjOPQQDAgNHADBEAiBmugLq
+drPr0+BtAVkTNGG5WhNphbYwC/M3SMF94Q1sfwIgZwHZImV153B2af6WICOsIuEZ
+iEGxpmI1gPjg7GRJHWg=
+-----END CERTIFICATE-----
+";
+    /// Issued by the CA above for `localhost`
+    const SERVER_CERTIFICATE: &str = "-----BEGIN CERTIFICATE-----
+MIIBozCCAUigAwIBAgIUXaDFVUR/czucJuk9mRSflVTi3ocwCgYIKoZIzj0EAwIw
+EjEQMA4GA1UEAwwHVGVzdCBDQTAgFw0yNjEwMTYxNTM5NDRaGA8yMTI2MDkyMjE1
+Mzk0NFowFDESMBAGA1UEAwwJbG9jYWxob3N0MFkwEwYHKoZIzj0CAQYIKoZIzj0D
+AQcDQgAEvIPDDn9XKy1VxiIWEmz/t9L/PaDz4eEQmFCV2b8szIheoZgzM//7UBMs
+0KaLojO1px0+8rvnKue9GpP35g3yyKN4MHYwFAYDVR0RBA0wC4IJbG9jYWxob3N0
+MAkGA1UdEwQCMAAwEwYDVR0lBAwwCgYIKwYBBQUHAwEwHQYDVR0OBBYEFE5F+k65
+1XwteOtOg6pxlbpAvj+eMB8GA1UdIwQYMBaAFJSIrcHGXTH+wNSnlnsZcGofhm8A
+MAoGCCqGSM49BAMCA0kAMEYCIQDvJ9Wz4fkPtlB5IAqgzoNXWlHnNqH4pL5AKkpv
+fPkRbQIhAINAfWy4gjHCmrjNTRbUxeHXFLHubBdW7GzmeO7nA2U7
+-----END CERTIFICATE-----
+";
+    const SERVER_SPKI_PIN: &str = "sha256/tes3QSnxUyXwbbJpgsDhfTnGE0/wz1/jAUb+DBTtB6A=";
+    const SERVER_CERTIFICATE_PIN: &str = "cert-sha256/46:34:EA:2C:B6:D5:DE:71:60:02:BB:AE:A7:2B:F3:AE:F8:50:F0:F0:DC:05:BA:50:08:02:71:FF:69:25:B6:36";
+    const CA_SPKI_PIN: &str = "sha256/DuL1Qo9067poOkQ+NFvT5L1O1uD78Y1tDHwmUf93z90=";
+    const OTHER_PIN: &str = "sha256/AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=";
+
+    #[test]
+    fn pins_are_parsed() {
+        assert!(matches!(SERVER_SPKI_PIN.parse(), Ok(TlsPin::Spki(_))));
+        assert!(matches!(
+            SERVER_CERTIFICATE_PIN.parse(),
+            Ok(TlsPin::Certificate(_))
+        ));
+        // Colons, as in the fingerprints printed by openssl, are optional
+        assert_eq!(
+            "cert-sha256/4634ea2cb6d5de716002bbaea72bf3aef850f0f0dc05ba50080271ff6925b636"
+                .parse::<TlsPin>()
+                .unwrap(),
+            SERVER_CERTIFICATE_PIN.parse().unwrap()
+        );
+        assert_eq!(
+            format!(" {SERVER_SPKI_PIN} ,{CA_SPKI_PIN},")
+                .parse::<TlsPins>()
+                .unwrap(),
+            TlsPins(vec![
+                SERVER_SPKI_PIN.parse().unwrap(),
+                CA_SPKI_PIN.parse().unwrap()
+            ])
+        );
+
+        for pin in [
+            "md5/tes3QSnxUyXwbbJpgsDhfTnGE0",
+            "tes3QSnxUyXwbbJpgsDhfTnGE0",
+        ] {
+            assert!(matches!(
+                pin.parse::<TlsPin>(),
+                Err(TlsPinError::InvalidFormat(_))
+            ));
+        }
+
+        for pin in [
+            // 31 bytes
+            "sha256/tes3QSnxUyXwbbJpgsDhfTnGE0/wz1/jAUb+DBTtBw==",
+            "sha256/not base64",
+            "cert-sha256/46:34:EA",
+            "cert-sha256/zz34ea2cb6d5de716002bbaea72bf3aef850f0f0dc05ba50080271ff6925b636",
+        ] {
+            assert!(matches!(
+                pin.parse::<TlsPin>(),
+                Err(TlsPinError::InvalidHash(_))
+            ));
+        }
+
+        for pins in ["", " , "] {
+            assert!(matches!(pins.parse::<TlsPins>(), Err(TlsPinError::Empty)));
+        }
+    }
+
+    #[test]
+    fn pins_match_their_certificate() {
+        let server_certificate = &der_certificates(SERVER_CERTIFICATE.as_bytes())[0];
+        let ca_certificate = &der_certificates(CA_CERTIFICATE.as_bytes())[0];
+
+        for pin in [SERVER_SPKI_PIN, SERVER_CERTIFICATE_PIN] {
+            let pin = pin.parse::<TlsPin>().unwrap();
+
+            assert!(pin.matches(server_certificate));
+            assert!(!pin.matches(ca_certificate));
+        }
+
+        assert!(CA_SPKI_PIN
+            .parse::<TlsPin>()
+            .unwrap()
+            .matches(ca_certificate));
+        assert!(!OTHER_PIN
+            .parse::<TlsPin>()
+            .unwrap()
+            .matches(server_certificate));
+    }
+
+    #[test]
+    fn verifier_rejects_chains_not_matching_any_pin() {
+        let settings = TlsSettings {
+            ca_bundle: Some(CA_CERTIFICATE.as_bytes().to_vec()),
+            client_identity: None,
+        };
+        let server_certificate = &der_certificates(SERVER_CERTIFICATE.as_bytes())[0];
+        let ca_certificate = der_certificates(CA_CERTIFICATE.as_bytes());
+        let server_name = ServerName::try_from("localhost").unwrap();
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_900_000_000);
+        let verify = |pins: &str, intermediates| {
+            let verifier = PinningVerifier {
+                inner: WebPkiVerifier::new(settings.root_store(), None),
+                pins: pins.parse().unwrap(),
+            };
+
+            verifier.verify_server_cert(
+                server_certificate,
+                intermediates,
+                &server_name,
+                &mut std::iter::empty(),
+                &[],
+                now,
+            )
+        };
+
+        assert!(verify(SERVER_CERTIFICATE_PIN, &[]).is_ok());
+        assert!(verify(&format!("{OTHER_PIN},{SERVER_SPKI_PIN}"), &[]).is_ok());
+        assert!(verify(CA_SPKI_PIN, &ca_certificate).is_ok());
+
+        for (pins, intermediates) in [(OTHER_PIN, &[][..]), (CA_SPKI_PIN, &[])] {
+            assert!(matches!(
+                verify(pins, intermediates),
+                Err(rustls::Error::InvalidCertificate(CertificateError::Other(
+                    _
+                )))
+            ));
+        }
+    }
+}
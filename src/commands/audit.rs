@@ -0,0 +1,327 @@
+use std::fmt;
+
+use alloy::{
+    primitives::B256,
+    rpc::types::BlockTransactionsKind,
+    transports::{Transport, TransportError},
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    clients::{beacon::types::BlockId, common::ClientError},
+    context::CommonContext,
+    utils::web3::calculate_versioned_hash,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuditError {
+    #[error(transparent)]
+    ClientError(#[from] ClientError),
+    #[error(transparent)]
+    Provider(#[from] TransportError),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Inconsistency {
+    /// The execution node doesn't know the block the beacon block points to
+    MissingExecutionBlock { slot: u32, block_hash: B256 },
+    /// The beacon block has KZG commitments but the execution block has no blob
+    /// transactions
+    MissingBlobTransactions {
+        slot: u32,
+        block_hash: B256,
+        commitments: usize,
+    },
+    /// The execution block has blob transactions but the beacon block has no
+    /// KZG commitments
+    MissingCommitments {
+        slot: u32,
+        block_hash: B256,
+        versioned_hashes: usize,
+    },
+    /// The amount of KZG commitments differs from the amount of versioned hashes
+    /// referenced by the blob transactions
+    BlobCountMismatch {
+        slot: u32,
+        block_hash: B256,
+        commitments: usize,
+        versioned_hashes: usize,
+    },
+    /// The commitment at the given position of the block doesn't match the
+    /// versioned hash referenced at the same position
+    VersionedHashMismatch {
+        slot: u32,
+        block_hash: B256,
+        index: usize,
+        commitment_versioned_hash: B256,
+        tx_versioned_hash: B256,
+    },
+}
+
+#[derive(Debug, Default)]
+pub struct AuditReport {
+    pub audited_slots: u32,
+    pub inconsistencies: Vec<Inconsistency>,
+}
+
+/// Walks the given slot range and checks that the beacon and execution nodes
+/// agree on the blobs of every block, without writing anything.
+pub async fn audit_slots<T>(
+    context: &dyn CommonContext<T>,
+    from_slot: u32,
+    to_slot: u32,
+) -> Result<AuditReport, AuditError>
+where
+    T: Transport + Clone + Send + Sync + 'static,
+{
+    let mut report = AuditReport::default();
+
+    info!(from_slot, to_slot, "Auditing slots…");
+
+    for slot in from_slot..to_slot {
+        let inconsistencies = audit_slot(context, slot).await?;
+
+        if inconsistencies.is_empty() {
+            debug!(slot, "Slot audited");
+        }
+
+        for inconsistency in inconsistencies.iter() {
+            warn!(slot, "{inconsistency}");
+        }
+
+        report.audited_slots += 1;
+        report.inconsistencies.extend(inconsistencies);
+    }
+
+    Ok(report)
+}
+
+pub async fn audit_slot<T>(
+    context: &dyn CommonContext<T>,
+    slot: u32,
+) -> Result<Vec<Inconsistency>, AuditError>
+where
+    T: Transport + Clone + Send + Sync + 'static,
+{
+    let Some(beacon_block) = context
+        .beacon_client()
+        .get_block(&BlockId::Slot(slot))
+        .await?
+    else {
+        return Ok(vec![]);
+    };
+    let Some(execution_payload) = beacon_block.message.body.execution_payload else {
+        return Ok(vec![]);
+    };
+    let block_hash = execution_payload.block_hash;
+    let commitments = beacon_block
+        .message
+        .body
+        .blob_kzg_commitments
+        .unwrap_or_default();
+
+    let Some(execution_block) = context
+        .provider()
+        .get_block(block_hash.into(), BlockTransactionsKind::Full)
+        .await?
+    else {
+        return Ok(vec![Inconsistency::MissingExecutionBlock {
+            slot,
+            block_hash,
+        }]);
+    };
+    // Blobs are laid out in the block in the same order as the transactions
+    // referencing them
+    let versioned_hashes = execution_block
+        .transactions
+        .as_transactions()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|tx| tx.blob_versioned_hashes.as_ref())
+        .flatten()
+        .copied()
+        .collect::<Vec<_>>();
+
+    let inconsistency = compare_blobs(slot, block_hash, &commitments, &versioned_hashes)?;
+
+    Ok(inconsistency.into_iter().collect())
+}
+
+/// Compares the KZG commitments of a beacon block with the versioned hashes
+/// referenced by the transactions of its execution block.
+fn compare_blobs(
+    slot: u32,
+    block_hash: B256,
+    commitments: &[String],
+    versioned_hashes: &[B256],
+) -> anyhow::Result<Option<Inconsistency>> {
+    let inconsistency = match (commitments.len(), versioned_hashes.len()) {
+        (commitments, 0) if commitments > 0 => Inconsistency::MissingBlobTransactions {
+            slot,
+            block_hash,
+            commitments,
+        },
+        (0, versioned_hashes) if versioned_hashes > 0 => Inconsistency::MissingCommitments {
+            slot,
+            block_hash,
+            versioned_hashes,
+        },
+        (commitments, versioned_hashes) if commitments != versioned_hashes => {
+            Inconsistency::BlobCountMismatch {
+                slot,
+                block_hash,
+                commitments,
+                versioned_hashes,
+            }
+        }
+        _ => {
+            for (index, (commitment, tx_versioned_hash)) in
+                commitments.iter().zip(versioned_hashes).enumerate()
+            {
+                let commitment_versioned_hash = calculate_versioned_hash(commitment)?;
+
+                if commitment_versioned_hash != *tx_versioned_hash {
+                    return Ok(Some(Inconsistency::VersionedHashMismatch {
+                        slot,
+                        block_hash,
+                        index,
+                        commitment_versioned_hash,
+                        tx_versioned_hash: *tx_versioned_hash,
+                    }));
+                }
+            }
+
+            return Ok(None);
+        }
+    };
+
+    Ok(Some(inconsistency))
+}
+
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Inconsistency::MissingExecutionBlock { slot, block_hash } => write!(
+                f,
+                "Slot {slot}: execution block {block_hash} not found in the execution node"
+            ),
+            Inconsistency::MissingBlobTransactions {
+                slot,
+                block_hash,
+                commitments,
+            } => write!(
+                f,
+                "Slot {slot}: beacon block has {commitments} KZG commitments but execution block {block_hash} has no blob transactions"
+            ),
+            Inconsistency::MissingCommitments {
+                slot,
+                block_hash,
+                versioned_hashes,
+            } => write!(
+                f,
+                "Slot {slot}: execution block {block_hash} references {versioned_hashes} blobs but the beacon block has no KZG commitments"
+            ),
+            Inconsistency::BlobCountMismatch {
+                slot,
+                block_hash,
+                commitments,
+                versioned_hashes,
+            } => write!(
+                f,
+                "Slot {slot}: beacon block has {commitments} KZG commitments but execution block {block_hash} references {versioned_hashes} blobs"
+            ),
+            Inconsistency::VersionedHashMismatch {
+                slot,
+                block_hash,
+                index,
+                commitment_versioned_hash,
+                tx_versioned_hash,
+            } => write!(
+                f,
+                "Slot {slot}: expected blob {commitment_versioned_hash} at index {index} of execution block {block_hash} but found {tx_versioned_hash}"
+            ),
+        }
+    }
+}
+
+impl fmt::Display for AuditReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Audited slots: {}", self.audited_slots)?;
+        writeln!(f, "Inconsistencies found: {}", self.inconsistencies.len())?;
+
+        for inconsistency in self.inconsistencies.iter() {
+            writeln!(f, "- {inconsistency}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::B256;
+
+    use super::{compare_blobs, Inconsistency};
+    use crate::utils::web3::calculate_versioned_hash;
+
+    #[test]
+    fn blobs_are_compared_in_block_order() {
+        let commitments = vec![
+            format!("0x{}", "11".repeat(48)),
+            format!("0x{}", "22".repeat(48)),
+        ];
+        let versioned_hashes = commitments
+            .iter()
+            .map(|commitment| calculate_versioned_hash(commitment).unwrap())
+            .collect::<Vec<_>>();
+        let block_hash = B256::ZERO;
+
+        assert_eq!(
+            compare_blobs(1, block_hash, &commitments, &versioned_hashes).unwrap(),
+            None
+        );
+        assert_eq!(
+            compare_blobs(1, block_hash, &commitments, &versioned_hashes[..1]).unwrap(),
+            Some(Inconsistency::BlobCountMismatch {
+                slot: 1,
+                block_hash,
+                commitments: 2,
+                versioned_hashes: 1
+            })
+        );
+        assert_eq!(
+            compare_blobs(1, block_hash, &commitments, &[]).unwrap(),
+            Some(Inconsistency::MissingBlobTransactions {
+                slot: 1,
+                block_hash,
+                commitments: 2
+            })
+        );
+        assert_eq!(
+            compare_blobs(1, block_hash, &[], &versioned_hashes).unwrap(),
+            Some(Inconsistency::MissingCommitments {
+                slot: 1,
+                block_hash,
+                versioned_hashes: 2
+            })
+        );
+        assert_eq!(
+            compare_blobs(
+                1,
+                block_hash,
+                &commitments,
+                &[versioned_hashes[1], versioned_hashes[0]]
+            )
+            .unwrap(),
+            Some(Inconsistency::VersionedHashMismatch {
+                slot: 1,
+                block_hash,
+                index: 0,
+                commitment_versioned_hash: versioned_hashes[0],
+                tx_versioned_hash: versioned_hashes[1],
+            })
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use alloy::transports::Transport;
+use anyhow::Context as AnyhowContext;
+use tracing::{debug, info};
+
+use crate::{
+    clients::common::ClientError,
+    context::CommonContext,
+    sinks::{channel::IndexedBlock, export::ExportSink},
+    synchronizer::{checkpoint::CheckpointPolicy, CheckpointType},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error("failed to read the export directory {path}")]
+    DirectoryReadFailure {
+        path: PathBuf,
+        #[source]
+        error: std::io::Error,
+    },
+    #[error("failed to import the block of slot {slot}")]
+    BlockImportFailure {
+        slot: u32,
+        #[source]
+        error: ClientError,
+    },
+    #[error("failed to save the sync checkpoint at slot {slot}")]
+    CheckpointSaveFailure {
+        slot: u32,
+        #[source]
+        error: ClientError,
+    },
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub imported_blocks: u32,
+    pub last_imported_slot: Option<u32>,
+}
+
+/// Pushes the blocks exported to the given directory with `--export-dir` to
+/// Blobscan, saving the sync state every `slots_per_save` blocks as the
+/// checkpoint policy dictates. Blocks are imported backwards for lower
+/// checkpoints, like the historical sync does, so the lower bound only covers
+/// imported slots.
+pub async fn import_blocks<T>(
+    context: &dyn CommonContext<T>,
+    dir: &Path,
+    slot_range: (Option<u32>, Option<u32>),
+    checkpoint: CheckpointType,
+    slots_per_save: u32,
+) -> Result<ImportReport, ImportError>
+where
+    T: Transport + Send + Sync + 'static,
+{
+    let (from_slot, to_slot) = slot_range;
+    let mut slots = exported_slots(dir)
+        .map_err(|error| ImportError::DirectoryReadFailure {
+            path: dir.to_path_buf(),
+            error,
+        })?
+        .into_iter()
+        .filter(|slot| from_slot.is_none_or(|from_slot| *slot >= from_slot))
+        .filter(|slot| to_slot.is_none_or(|to_slot| *slot < to_slot))
+        .collect::<Vec<_>>();
+
+    slots.sort_unstable();
+
+    if checkpoint == CheckpointType::Lower {
+        slots.reverse();
+    }
+
+    let mut report = ImportReport::default();
+    let Some(first_slot) = slots.first().copied() else {
+        info!(dir = %dir.display(), "No exported blocks to import");
+
+        return Ok(report);
+    };
+
+    info!(
+        dir = %dir.display(),
+        blocks = slots.len(),
+        first_slot,
+        ?checkpoint,
+        "Importing exported blocks…"
+    );
+
+    for chunk in slots.chunks(slots_per_save.max(1) as usize) {
+        for slot in chunk.iter().copied() {
+            let indexed_block = read_exported_block(dir, slot).await?;
+
+            context
+                .blobscan_client()
+                .index(
+                    indexed_block.block,
+                    indexed_block.transactions,
+                    indexed_block.blobs,
+                )
+                .await
+                .map_err(|error| ImportError::BlockImportFailure { slot, error })?;
+
+            debug!(slot, "Block imported");
+
+            report.imported_blocks += 1;
+            report.last_imported_slot = Some(slot);
+        }
+
+        let Some(last_slot) = chunk.last().copied() else {
+            continue;
+        };
+
+        if let Some(sync_state) = checkpoint.sync_state(first_slot, last_slot) {
+            context
+                .blobscan_client()
+                .update_sync_state(sync_state)
+                .await
+                .map_err(|error| ImportError::CheckpointSaveFailure {
+                    slot: last_slot,
+                    error,
+                })?;
+
+            debug!(last_slot, "Checkpoint saved");
+        }
+    }
+
+    Ok(report)
+}
+
+/// Slots of the blocks found in the export directory.
+fn exported_slots(dir: &Path) -> std::io::Result<Vec<u32>> {
+    let mut slots = vec![];
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        // Partially written files have a different extension
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            if let Some(slot) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse().ok())
+            {
+                slots.push(slot);
+            }
+        }
+    }
+
+    Ok(slots)
+}
+
+async fn read_exported_block(dir: &Path, slot: u32) -> Result<IndexedBlock, ImportError> {
+    let path = ExportSink::slot_path(dir, slot);
+
+    let indexed_block = tokio::task::spawn_blocking(move || {
+        fs::read(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| serde_json::from_slice(&contents).map_err(anyhow::Error::from))
+            .with_context(|| format!("Failed to read exported block {}", path.display()))
+    })
+    .await
+    .context("Exported block read task failed")??;
+
+    Ok(indexed_block)
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Imported blocks: {}", self.imported_blocks)?;
+
+        if let Some(last_imported_slot) = self.last_imported_slot {
+            writeln!(f, "Last imported slot: {last_imported_slot}")?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,6 @@
+pub mod audit;
+pub mod import;
+pub mod self_test;
+pub mod status;
+pub mod support_bundle;
+pub mod verify;
@@ -0,0 +1,236 @@
+use std::{
+    fmt,
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use alloy::{eips::BlockNumberOrTag, rpc::types::BlockTransactionsKind, transports::Transport};
+
+use crate::{
+    clients::{
+        beacon::types::BlockId,
+        blobscan::types::{BlockchainSyncState, INDEXER_SCHEMA_VERSION},
+    },
+    context::CommonContext,
+};
+
+/// Outcome of a single dependency check.
+#[derive(Debug)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub latency: Duration,
+    /// Description of what was found, or of the failure
+    pub result: Result<String, String>,
+}
+
+#[derive(Debug, Default)]
+pub struct SelfTestReport {
+    pub checks: Vec<CheckResult>,
+}
+
+/// Exercises every dependency of the indexer: the beacon node, the execution
+/// node and the Blobscan API, including an authenticated write. Every check is
+/// bounded by the given timeout so unreachable services fail instead of
+/// retrying indefinitely.
+pub async fn run_self_test<T>(context: &dyn CommonContext<T>, timeout: Duration) -> SelfTestReport
+where
+    T: Transport + Clone + Send + Sync + 'static,
+{
+    let mut report = SelfTestReport::default();
+    let beacon_client = context.beacon_client();
+    let blobscan_client = context.blobscan_client();
+
+    let head_slot = report
+        .check("Beacon head", timeout, async {
+            let header = beacon_client
+                .get_block_header(&BlockId::Head)
+                .await
+                .map_err(|error| error.to_string())?
+                .ok_or("no head block found")?;
+
+            let slot = header.header.message.slot;
+
+            Ok((slot, format!("slot {slot}")))
+        })
+        .await;
+
+    if let Some(head_slot) = head_slot {
+        report
+            .check("Beacon blob sidecars", timeout, async {
+                let blobs = beacon_client
+                    .get_blobs(&BlockId::Slot(head_slot))
+                    .await
+                    .map_err(|error| error.to_string())?;
+
+                let blobs = blobs.map(|blobs| blobs.len()).unwrap_or_default();
+
+                Ok(((), format!("{blobs} blobs at slot {head_slot}")))
+            })
+            .await;
+    } else {
+        report.skip("Beacon blob sidecars", "no beacon head to fetch them for");
+    }
+
+    report
+        .check("Execution block", timeout, async {
+            let block = context
+                .provider()
+                .get_block(
+                    BlockNumberOrTag::Latest.into(),
+                    BlockTransactionsKind::Hashes,
+                )
+                .await
+                .map_err(|error| error.to_string())?
+                .ok_or("no latest block found")?;
+
+            Ok(((), format!("block {}", block.header.number)))
+        })
+        .await;
+
+    report
+        .check("Blobscan read", timeout, async {
+            let sync_state = blobscan_client
+                .get_sync_state()
+                .await
+                .map_err(|error| error.to_string())?;
+            let last_upper_synced_slot =
+                sync_state.and_then(|sync_state| sync_state.last_upper_synced_slot);
+
+            Ok((
+                (),
+                match last_upper_synced_slot {
+                    Some(slot) => format!("last upper synced slot {slot}"),
+                    None => "no synced slots yet".to_string(),
+                },
+            ))
+        })
+        .await;
+
+    report
+        .check("Blobscan API version", timeout, async {
+            match blobscan_client
+                .get_api_version()
+                .await
+                .map_err(|error| error.to_string())?
+            {
+                Some(api_version) if api_version.supports_indexer_schema() => Ok((
+                    (),
+                    format!(
+                        "{} supports payload schema v{INDEXER_SCHEMA_VERSION}",
+                        api_version.version
+                    ),
+                )),
+                Some(api_version) => Err(format!(
+                    "{} doesn't support payload schema v{INDEXER_SCHEMA_VERSION} (supported: {:?})",
+                    api_version.version, api_version.indexer_schema_versions
+                )),
+                None => Ok(((), "version not reported".to_string())),
+            }
+        })
+        .await;
+
+    // Sending an empty sync state checks the credentials without changing anything
+    report
+        .check("Blobscan authenticated write", timeout, async {
+            blobscan_client
+                .update_sync_state(BlockchainSyncState {
+                    last_finalized_block: None,
+                    last_lower_synced_slot: None,
+                    last_upper_synced_slot: None,
+                })
+                .await
+                .map_err(|error| error.to_string())?;
+
+            Ok(((), "empty sync state update accepted".to_string()))
+        })
+        .await;
+
+    report
+}
+
+impl SelfTestReport {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.result.is_ok())
+    }
+
+    pub fn failed_checks(&self) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| check.result.is_err())
+            .count()
+    }
+
+    /// Runs the given check, recording its outcome and latency, and returns its
+    /// output if it passed. Checks resolve to their output along with a
+    /// description of it.
+    async fn check<R>(
+        &mut self,
+        name: &'static str,
+        timeout: Duration,
+        check: impl Future<Output = Result<(R, String), String>>,
+    ) -> Option<R> {
+        let started_at = Instant::now();
+        let result = match tokio::time::timeout(timeout, check).await {
+            Ok(Ok(output)) => Ok(output),
+            Ok(Err(error)) => Err(error),
+            Err(_) => Err(format!("timed out after {timeout:?}")),
+        };
+        let latency = started_at.elapsed();
+
+        match result {
+            Ok((output, details)) => {
+                self.checks.push(CheckResult {
+                    name,
+                    latency,
+                    result: Ok(details),
+                });
+
+                Some(output)
+            }
+            Err(error) => {
+                self.checks.push(CheckResult {
+                    name,
+                    latency,
+                    result: Err(error),
+                });
+
+                None
+            }
+        }
+    }
+
+    fn skip(&mut self, name: &'static str, reason: &str) {
+        self.checks.push(CheckResult {
+            name,
+            latency: Duration::ZERO,
+            result: Err(format!("skipped: {reason}")),
+        });
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name_width = self
+            .checks
+            .iter()
+            .map(|check| check.name.len())
+            .max()
+            .unwrap_or_default();
+
+        for check in self.checks.iter() {
+            let (status, details) = match &check.result {
+                Ok(details) => ("PASS", details),
+                Err(error) => ("FAIL", error),
+            };
+
+            writeln!(
+                f,
+                "{:<name_width$}  {status}  {:>6}ms  {details}",
+                check.name,
+                check.latency.as_millis(),
+            )?;
+        }
+
+        Ok(())
+    }
+}
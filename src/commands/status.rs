@@ -0,0 +1,126 @@
+use std::net::SocketAddr;
+
+use alloy::transports::Transport;
+
+use crate::{
+    clients::{beacon::types::BlockId, common::ClientResult},
+    context::CommonContext,
+    control::fetch_status,
+};
+
+/// Prints the sync state stored in Blobscan along with how far behind the
+/// beacon chain head it is.
+pub async fn print_status<T>(context: &dyn CommonContext<T>) -> ClientResult<()>
+where
+    T: Transport + Send + Sync + 'static,
+{
+    let sync_state = context.blobscan_client().get_sync_state().await?;
+    let head_slot = context
+        .beacon_client()
+        .get_block_header(&BlockId::Head)
+        .await?
+        .map(|header| header.header.message.slot);
+
+    let format_value = |value: Option<u32>| match value {
+        Some(value) => value.to_string(),
+        None => "-".to_string(),
+    };
+
+    let (last_lower_synced_slot, last_upper_synced_slot, last_finalized_block) = match sync_state {
+        Some(state) => (
+            state.last_lower_synced_slot,
+            state.last_upper_synced_slot,
+            state.last_finalized_block,
+        ),
+        None => (None, None, None),
+    };
+
+    println!(
+        "Last lower synced slot: {}",
+        format_value(last_lower_synced_slot)
+    );
+    println!(
+        "Last upper synced slot: {}",
+        format_value(last_upper_synced_slot)
+    );
+    println!(
+        "Last finalized block: {}",
+        format_value(last_finalized_block)
+    );
+    println!("Beacon head slot: {}", format_value(head_slot));
+
+    if let (Some(head_slot), Some(last_upper_synced_slot)) = (head_slot, last_upper_synced_slot) {
+        println!(
+            "Slots behind head: {}",
+            head_slot.saturating_sub(last_upper_synced_slot)
+        );
+    }
+
+    Ok(())
+}
+
+/// Prints the tasks, sync positions, lag and errors of the indexer whose
+/// control server listens on the given address.
+pub async fn print_instance_status(address: SocketAddr) -> Result<(), reqwest::Error> {
+    let report = fetch_status(address).await?;
+    let format_value = |value: Option<u32>| match value {
+        Some(value) => value.to_string(),
+        None => "-".to_string(),
+    };
+    let sync_state = report.sync_state.as_ref();
+
+    println!("Version: {}", report.version);
+    println!("Uptime: {}s", report.uptime_seconds);
+    println!(
+        "Last lower synced slot: {}",
+        format_value(sync_state.and_then(|state| state.last_lower_synced_slot))
+    );
+    println!(
+        "Last upper synced slot: {}",
+        format_value(sync_state.and_then(|state| state.last_upper_synced_slot))
+    );
+    println!(
+        "Last finalized block: {}",
+        format_value(sync_state.and_then(|state| state.last_finalized_block))
+    );
+    println!("Beacon head slot: {}", format_value(report.head_slot));
+    println!(
+        "Slots behind head: {}",
+        format_value(report.slots_behind_head)
+    );
+    println!(
+        "Backfill: {}",
+        if report.backfill_paused {
+            "paused"
+        } else {
+            "running"
+        }
+    );
+
+    if let Some(reorg) = &report.pending_reorg {
+        println!(
+            "Pending reorg: slot {}, {} slots deep, rewinding {} blocks and {} blobs, forwarding {} blocks and {} blobs",
+            reorg.slot,
+            reorg.depth,
+            reorg.rewound_blocks,
+            reorg.rewound_blobs,
+            reorg.forwarded_blocks,
+            reorg.forwarded_blobs
+        );
+    }
+
+    println!("Tasks:");
+
+    for task in &report.tasks {
+        println!(
+            "  {}: {:?}, {} restarts, {} errors",
+            task.task, task.state, task.restarts, task.errors
+        );
+
+        if let Some(last_error) = &task.last_error {
+            println!("    Last error: {last_error}");
+        }
+    }
+
+    Ok(())
+}
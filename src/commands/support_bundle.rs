@@ -0,0 +1,268 @@
+use std::{
+    fmt,
+    fs::{self, File},
+    future::Future,
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use alloy::transports::Transport;
+use flate2::{write::GzEncoder, Compression};
+use serde_json::{json, Value};
+
+use crate::{
+    clients::beacon::types::BlockId, context::CommonContext, env::Environment,
+    utils::redaction::remove_credentials_from_url,
+};
+
+const REDACTED: &str = "******";
+
+/// Upper bound for every query sent to the nodes and Blobscan so the bundle can
+/// still be created while they are unreachable
+const QUERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Names of the environment variables worth reporting in the diagnostics
+const DIAGNOSTIC_ENV_VARS: [&str; 4] = ["RUST_LOG", "RUST_BACKTRACE", "TZ", "HOSTNAME"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum SupportBundleError {
+    #[error("failed to read log file {path}")]
+    LogFileRead {
+        path: PathBuf,
+        #[source]
+        error: io::Error,
+    },
+    #[error("failed to write support bundle {path}")]
+    ArchiveWrite {
+        path: PathBuf,
+        #[source]
+        error: io::Error,
+    },
+}
+
+/// Gathers everything needed to troubleshoot an indexer instance into a
+/// `.tar.gz` archive that can be attached to GitHub issues. Sections that can't
+/// be collected, e.g. because a node is unreachable, contain the error instead.
+pub async fn create_support_bundle<T>(
+    context: &dyn CommonContext<T>,
+    env: &Environment,
+    output_dir: &Path,
+    log_file: Option<&Path>,
+    log_lines: usize,
+) -> Result<PathBuf, SupportBundleError>
+where
+    T: Transport + Clone + Send + Sync + 'static,
+{
+    let mut files = vec![
+        ("versions.json", to_json(&versions(context).await)),
+        ("config.json", to_json(&redacted_config(env))),
+        ("sync-state.json", to_json(&sync_state(context).await)),
+        ("diagnostics.json", to_json(&diagnostics())),
+    ];
+
+    if let Some(log_file) = log_file {
+        let logs =
+            fs::read_to_string(log_file).map_err(|error| SupportBundleError::LogFileRead {
+                path: log_file.to_path_buf(),
+                error,
+            })?;
+        let recent_logs = tail(&logs, log_lines);
+        let last_errors = tail(
+            &logs
+                .lines()
+                .filter(|line| line.contains("ERROR"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            log_lines,
+        );
+
+        files.push(("logs.txt", recent_logs));
+        files.push(("errors.txt", last_errors));
+    }
+
+    let timestamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ");
+    let path = output_dir.join(format!("blobscan-indexer-support-{timestamp}.tar.gz"));
+
+    write_archive(&path, &files).map_err(|error| SupportBundleError::ArchiveWrite {
+        path: path.clone(),
+        error,
+    })?;
+
+    Ok(path)
+}
+
+async fn versions<T>(context: &dyn CommonContext<T>) -> Value
+where
+    T: Transport + Clone + Send + Sync + 'static,
+{
+    let beacon_node_version = query(context.beacon_client().get_node_version(), |version| {
+        json!(version)
+    })
+    .await;
+    let execution_node_version = query(context.provider().get_client_version(), |version| {
+        json!(version)
+    })
+    .await;
+
+    json!({
+        "indexer": env!("CARGO_PKG_VERSION"),
+        "features": enabled_features(),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "beaconNode": beacon_node_version,
+        "executionNode": execution_node_version,
+    })
+}
+
+async fn sync_state<T>(context: &dyn CommonContext<T>) -> Value
+where
+    T: Transport + Clone + Send + Sync + 'static,
+{
+    let sync_state = query(context.blobscan_client().get_sync_state(), |state| {
+        json!(state.map(|state| json!({
+            "lastLowerSyncedSlot": state.last_lower_synced_slot,
+            "lastUpperSyncedSlot": state.last_upper_synced_slot,
+            "lastFinalizedBlock": state.last_finalized_block,
+        })))
+    })
+    .await;
+    let head_slot = query(
+        context.beacon_client().get_block_header(&BlockId::Head),
+        |header| json!(header.map(|header| header.header.message.slot)),
+    )
+    .await;
+
+    json!({
+        "syncState": sync_state,
+        "beaconHeadSlot": head_slot,
+    })
+}
+
+/// Runs the given query, returning its error or a timeout error as the value
+/// when it doesn't succeed.
+async fn query<R, E: fmt::Display>(
+    future: impl Future<Output = Result<R, E>>,
+    to_value: impl FnOnce(R) -> Value,
+) -> Value {
+    match tokio::time::timeout(QUERY_TIMEOUT, future).await {
+        Ok(Ok(result)) => to_value(result),
+        Ok(Err(error)) => json!({ "error": error.to_string() }),
+        Err(_) => json!({ "error": format!("timed out after {QUERY_TIMEOUT:?}") }),
+    }
+}
+
+/// Returns the environment config with secrets removed and credentials
+/// stripped from the endpoints.
+pub fn redacted_config(env: &Environment) -> Value {
+    let redact_url = |url: &str| remove_credentials_from_url(url).unwrap_or(REDACTED.to_string());
+
+    json!({
+        "networkName": env.network.to_string(),
+        "networksFile": env.networks_file,
+        "dencunForkSlot": env.dencun_fork_slot,
+        "blobscanApiEndpoint": redact_url(&env.blobscan_api_endpoint),
+        "blobscanApiTlsPinning": env.blobscan_api_tls_pins.is_some(),
+        "beaconNodeEndpoint": redact_url(&env.beacon_node_endpoint),
+        "beaconNodeBlobsEndpoint": env.beacon_node_blobs_endpoint.as_deref().map(redact_url),
+        "beaconNodeEventsEndpoint": env.beacon_node_events_endpoint.as_deref().map(redact_url),
+        "executionNodeEndpoint": redact_url(&env.execution_node_endpoint),
+        "executionNodeAuthHeader": env
+            .execution_node_auth_header
+            .as_ref()
+            .map(|auth_header| format!("{auth_header:?}")),
+        "executionNodeJwtSecret": env.execution_node_jwt_secret.as_ref().map(|_| REDACTED),
+        "secretKey": (!env.secret_key.is_empty()).then_some(REDACTED),
+        "blobscanApiAuthHeader": env
+            .blobscan_api_auth_header
+            .as_ref()
+            .map(|auth_header| format!("{auth_header:?}")),
+        "blobscanJwtRefreshInterval": env.blobscan_jwt_refresh_interval,
+        "blobscanJwtSafetyMargin": env.blobscan_jwt_safety_margin,
+        "blobscanJwtAlgorithm": format!("{:?}", env.blobscan_jwt_algorithm),
+        "sentryDsn": env.sentry_dsn.as_ref().map(|_| REDACTED),
+        "otelExporterOtlpEndpoint": env.otel_exporter_otlp_endpoint.as_deref().map(redact_url),
+        "otelServiceName": env.otel_service_name,
+        "databaseUrl": env.database_url.as_deref().map(redact_url),
+        "disableResponseCompression": env.disable_response_compression,
+        "httpUserAgent": env.user_agent(),
+        "httpExtraHeaders": format!("{:?}", env.http_extra_headers),
+        "proxyUrl": env.proxy_url.as_ref().map(|url| redact_url(url.as_str())),
+        "tlsCaBundle": env.tls_ca_bundle,
+        "tlsClientCert": env.tls_client_cert,
+        "tlsClientKey": env.tls_client_key,
+        "logLevel": env.log_level,
+        "tracingDebugSampleRate": env.tracing_debug_sample_rate,
+        "tracingMaxBodyLength": env.tracing_max_body_length,
+        "metricsServerAddress": env.metrics_server_address,
+        "controlServerAddress": env.control_server_address,
+        "maxHeadLagSlots": env.max_head_lag_slots,
+        "headLagWebhookUrl": env.head_lag_webhook_url.as_ref().map(|url| redact_url(url.as_str())),
+        "beaconReadBackoff": format!("{:?}", env.beacon_read_backoff),
+        "blobSidecarReadBackoff": format!("{:?}", env.blob_sidecar_read_backoff),
+        "executionReadBackoff": format!("{:?}", env.execution_read_backoff),
+        "blobscanReadBackoff": format!("{:?}", env.blobscan_read_backoff),
+        "blobscanWriteBackoff": format!("{:?}", env.blobscan_write_backoff),
+    })
+}
+
+fn diagnostics() -> Value {
+    let env_vars = DIAGNOSTIC_ENV_VARS
+        .iter()
+        .filter_map(|name| {
+            std::env::var(name)
+                .ok()
+                .map(|value| (name.to_string(), Value::String(value)))
+        })
+        .collect::<serde_json::Map<_, _>>();
+
+    json!({
+        "generatedAt": chrono::Utc::now().to_rfc3339(),
+        "availableParallelism": std::thread::available_parallelism()
+            .map(|parallelism| parallelism.get())
+            .ok(),
+        "currentDir": std::env::current_dir().ok(),
+        "envVars": env_vars,
+    })
+}
+
+fn enabled_features() -> Vec<&'static str> {
+    let mut features = vec![];
+
+    if cfg!(feature = "postgres") {
+        features.push("postgres");
+    }
+
+    features
+}
+
+fn to_json(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+fn tail(text: &str, lines: usize) -> String {
+    let all_lines = text.lines().collect::<Vec<_>>();
+    let start = all_lines.len().saturating_sub(lines);
+
+    all_lines[start..].join("\n")
+}
+
+fn write_archive(path: &Path, files: &[(&str, String)]) -> io::Result<()> {
+    let encoder = GzEncoder::new(File::create(path)?, Compression::default());
+    let mut archive = tar::Builder::new(encoder);
+
+    for (name, contents) in files {
+        let mut header = tar::Header::new_gnu();
+
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(chrono::Utc::now().timestamp() as u64);
+        header.set_cksum();
+
+        archive.append_data(&mut header, name, contents.as_bytes())?;
+    }
+
+    archive.into_inner()?.finish()?;
+
+    Ok(())
+}
@@ -0,0 +1,272 @@
+use std::{collections::HashMap, fmt};
+
+use alloy::{
+    primitives::B256,
+    rpc::types::BlockTransactionsKind,
+    transports::{Transport, TransportError},
+};
+use anyhow::anyhow;
+use tracing::{debug, info, warn};
+
+use crate::{
+    clients::{beacon::types::BlockId, common::ClientError},
+    context::CommonContext,
+    slots_processor::helpers::create_tx_hash_versioned_hashes_mapping,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum VerifyError {
+    #[error(transparent)]
+    ClientError(#[from] ClientError),
+    #[error(transparent)]
+    Provider(#[from] TransportError),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[derive(Debug)]
+pub enum Discrepancy {
+    /// The chain has a block with blobs for the slot but Blobscan doesn't
+    MissingBlock { slot: u32, expected_hash: B256 },
+    /// Blobscan stores a different execution block for the slot
+    BlockHashMismatch {
+        slot: u32,
+        expected_hash: B256,
+        found_hash: B256,
+    },
+    /// Blobscan stores a block for a slot without blobs on the chain
+    UnexpectedBlock { slot: u32, found_hash: B256 },
+    /// A blob transaction of the block is missing in Blobscan
+    MissingTransaction { slot: u32, tx_hash: B256 },
+    /// Blobscan stores a blob transaction that isn't part of the block
+    UnexpectedTransaction { slot: u32, tx_hash: B256 },
+    /// A blob of a transaction is missing in Blobscan
+    MissingBlob {
+        slot: u32,
+        tx_hash: B256,
+        index: u32,
+        versioned_hash: B256,
+    },
+    /// Blobscan stores a different blob at the given transaction index
+    VersionedHashMismatch {
+        slot: u32,
+        tx_hash: B256,
+        index: u32,
+        expected_versioned_hash: B256,
+        found_versioned_hash: B256,
+    },
+}
+
+#[derive(Debug, Default)]
+pub struct VerificationReport {
+    pub verified_slots: u32,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+/// Walks the given slot range and compares the blocks, transactions and blobs
+/// stored in Blobscan with the ones found on the chain.
+pub async fn verify_slots<T>(
+    context: &dyn CommonContext<T>,
+    from_slot: u32,
+    to_slot: u32,
+) -> Result<VerificationReport, VerifyError>
+where
+    T: Transport + Clone + Send + Sync + 'static,
+{
+    let mut report = VerificationReport::default();
+
+    info!(from_slot, to_slot, "Verifying slots…");
+
+    for slot in from_slot..to_slot {
+        let discrepancies = verify_slot(context, slot).await?;
+
+        if discrepancies.is_empty() {
+            debug!(slot, "Slot verified");
+        }
+
+        for discrepancy in discrepancies.iter() {
+            warn!(slot, "{discrepancy}");
+        }
+
+        report.verified_slots += 1;
+        report.discrepancies.extend(discrepancies);
+    }
+
+    Ok(report)
+}
+
+pub async fn verify_slot<T>(
+    context: &dyn CommonContext<T>,
+    slot: u32,
+) -> Result<Vec<Discrepancy>, VerifyError>
+where
+    T: Transport + Clone + Send + Sync + 'static,
+{
+    let expected_hash = match context
+        .beacon_client()
+        .get_block(&BlockId::Slot(slot))
+        .await?
+    {
+        Some(block) => {
+            let has_blobs = block
+                .message
+                .body
+                .blob_kzg_commitments
+                .is_some_and(|commitments| !commitments.is_empty());
+
+            match block.message.body.execution_payload {
+                Some(execution_payload) if has_blobs => Some(execution_payload.block_hash),
+                _ => None,
+            }
+        }
+        None => None,
+    };
+    let found_block = context.blobscan_client().get_block(slot).await?;
+
+    let (expected_hash, found_block) = match (expected_hash, found_block) {
+        (None, None) => return Ok(vec![]),
+        (Some(expected_hash), None) => {
+            return Ok(vec![Discrepancy::MissingBlock {
+                slot,
+                expected_hash,
+            }])
+        }
+        (None, Some(found_block)) => {
+            return Ok(vec![Discrepancy::UnexpectedBlock {
+                slot,
+                found_hash: found_block.hash,
+            }])
+        }
+        (Some(expected_hash), Some(found_block)) if expected_hash != found_block.hash => {
+            return Ok(vec![Discrepancy::BlockHashMismatch {
+                slot,
+                expected_hash,
+                found_hash: found_block.hash,
+            }])
+        }
+        (Some(expected_hash), Some(found_block)) => (expected_hash, found_block),
+    };
+
+    let execution_block = context
+        .provider()
+        .get_block(expected_hash.into(), BlockTransactionsKind::Full)
+        .await?
+        .ok_or_else(|| anyhow!("Execution block {expected_hash} not found"))?;
+    let expected_txs = create_tx_hash_versioned_hashes_mapping(&execution_block)?;
+    let found_txs = found_block
+        .transactions
+        .into_iter()
+        .map(|tx| (tx.hash, tx.blobs))
+        .collect::<HashMap<_, _>>();
+
+    let mut discrepancies = vec![];
+
+    for (tx_hash, versioned_hashes) in expected_txs.iter() {
+        let found_blobs = match found_txs.get(tx_hash) {
+            Some(found_blobs) => found_blobs,
+            None => {
+                discrepancies.push(Discrepancy::MissingTransaction {
+                    slot,
+                    tx_hash: *tx_hash,
+                });
+
+                continue;
+            }
+        };
+
+        for (index, versioned_hash) in versioned_hashes.iter().enumerate() {
+            let index = index as u32;
+
+            match found_blobs.iter().find(|blob| blob.index == index) {
+                None => discrepancies.push(Discrepancy::MissingBlob {
+                    slot,
+                    tx_hash: *tx_hash,
+                    index,
+                    versioned_hash: *versioned_hash,
+                }),
+                Some(found_blob) if found_blob.versioned_hash != *versioned_hash => discrepancies
+                    .push(Discrepancy::VersionedHashMismatch {
+                        slot,
+                        tx_hash: *tx_hash,
+                        index,
+                        expected_versioned_hash: *versioned_hash,
+                        found_versioned_hash: found_blob.versioned_hash,
+                    }),
+                Some(_) => {}
+            }
+        }
+    }
+
+    for tx_hash in found_txs.keys() {
+        if !expected_txs.contains_key(tx_hash) {
+            discrepancies.push(Discrepancy::UnexpectedTransaction {
+                slot,
+                tx_hash: *tx_hash,
+            });
+        }
+    }
+
+    Ok(discrepancies)
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Discrepancy::MissingBlock {
+                slot,
+                expected_hash,
+            } => write!(f, "Slot {slot}: block {expected_hash} is missing"),
+            Discrepancy::BlockHashMismatch {
+                slot,
+                expected_hash,
+                found_hash,
+            } => write!(
+                f,
+                "Slot {slot}: expected block {expected_hash} but found {found_hash}"
+            ),
+            Discrepancy::UnexpectedBlock { slot, found_hash } => write!(
+                f,
+                "Slot {slot}: found block {found_hash} but the slot has no blobs"
+            ),
+            Discrepancy::MissingTransaction { slot, tx_hash } => {
+                write!(f, "Slot {slot}: transaction {tx_hash} is missing")
+            }
+            Discrepancy::UnexpectedTransaction { slot, tx_hash } => write!(
+                f,
+                "Slot {slot}: found transaction {tx_hash} which isn't part of the block"
+            ),
+            Discrepancy::MissingBlob {
+                slot,
+                tx_hash,
+                index,
+                versioned_hash,
+            } => write!(
+                f,
+                "Slot {slot}: blob {versioned_hash} at index {index} of transaction {tx_hash} is missing"
+            ),
+            Discrepancy::VersionedHashMismatch {
+                slot,
+                tx_hash,
+                index,
+                expected_versioned_hash,
+                found_versioned_hash,
+            } => write!(
+                f,
+                "Slot {slot}: expected blob {expected_versioned_hash} at index {index} of transaction {tx_hash} but found {found_versioned_hash}"
+            ),
+        }
+    }
+}
+
+impl fmt::Display for VerificationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Verified slots: {}", self.verified_slots)?;
+        writeln!(f, "Discrepancies found: {}", self.discrepancies.len())?;
+
+        for discrepancy in self.discrepancies.iter() {
+            writeln!(f, "- {discrepancy}")?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,499 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use clap::{parser::ValueSource, ArgAction, ArgMatches, Args as _, CommandFactory, FromArgMatches};
+use serde::{de, Deserialize};
+use toml_edit::{DocumentMut, Item, Table, Value};
+
+use crate::{
+    args::{Args, ArgsError, IndexArgs, ProcessingArgs},
+    env::Environment,
+};
+
+/// Configuration of a run, layered from a TOML file, overridden by the
+/// environment variables, overridden by the command line flags:
+///
+/// ```toml
+/// [environment] # same names as the environment variables, in lowercase
+/// network_name = "holesky"
+/// beacon_node_endpoint = "http://localhost:3500"
+///
+/// [indexing] # same names as the indexing flags
+/// num_threads = 8
+/// finalized_only = true
+/// filter_to_addresses = ["0xff00000000000000000000000000000000000000"]
+/// ```
+#[derive(Debug)]
+pub struct Config {
+    pub env: Environment,
+    pub args: Args,
+    pub sources: ConfigSources,
+}
+
+/// Command line and environment variables a configuration was loaded from,
+/// kept to load it again once the config file changes.
+#[derive(Debug, Clone)]
+pub struct ConfigSources {
+    args: Vec<OsString>,
+    vars: Vec<(String, String)>,
+    path: Option<PathBuf>,
+}
+
+/// Options set in a config file, kept as the values they'd have on the
+/// environment or the command line.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ConfigFile {
+    environment: BTreeMap<String, String>,
+    indexing: Vec<(String, ConfigValue)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ConfigValue {
+    Scalar(String),
+    Bool(bool),
+    List(Vec<String>),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("failed to read config file {path}: {error}")]
+    ReadFailure {
+        path: PathBuf,
+        #[source]
+        error: io::Error,
+    },
+    #[error("failed to parse config file: {0}")]
+    Parse(#[from] toml_edit::TomlError),
+    #[error("unknown config section \"{0}\": expected environment or indexing")]
+    UnknownSection(String),
+    #[error("unknown {section} option \"{key}\"")]
+    UnknownOption { section: &'static str, key: String },
+    #[error("invalid value for {section} option \"{key}\": {reason}")]
+    InvalidValue {
+        section: &'static str,
+        key: String,
+        reason: String,
+    },
+    #[error("failed to load env variables")]
+    Environment(#[from] envy::Error),
+    #[error("invalid indexing option in config file")]
+    InvalidIndexingOption(#[source] clap::Error),
+    #[error(transparent)]
+    InvalidArgs(#[from] ArgsError),
+}
+
+impl Config {
+    /// Parses the command line, exiting on invalid flags as clap does, and
+    /// layers it over the environment and the config file it points to.
+    pub fn load() -> Result<Self, ConfigError> {
+        Self::load_from(env::args_os(), env::vars())
+    }
+
+    pub fn load_from(
+        args: impl IntoIterator<Item = impl Into<OsString>>,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, ConfigError> {
+        let args = args.into_iter().map(Into::into).collect::<Vec<OsString>>();
+        let vars = vars.into_iter().collect::<Vec<_>>();
+        let matches = Args::command().get_matches_from(&args);
+        let sources = ConfigSources {
+            args: args.clone(),
+            vars: vars.clone(),
+            path: matches.get_one::<PathBuf>("config").cloned(),
+        };
+        let Some(path) = &sources.path else {
+            return Self {
+                env: Environment::from_vars(vars)?,
+                args: Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit()),
+                sources,
+            }
+            .validated();
+        };
+        let file = ConfigFile::from_file(path)?;
+        let env = Environment::from_vars(file.environment.clone().into_iter().chain(vars))?;
+
+        // The indexing options are only taken when indexing, except for the
+        // ones about how blocks are processed, also taken when reindexing
+        let index_matches = match matches.subcommand() {
+            None => Some((
+                &matches,
+                IndexArgs::augment_args(clap::Command::new("index")),
+            )),
+            Some(("index", index_matches)) => Some((
+                index_matches,
+                IndexArgs::augment_args(clap::Command::new("index")),
+            )),
+            Some(("reindex", reindex_matches)) => Some((
+                reindex_matches,
+                ProcessingArgs::augment_args(clap::Command::new("reindex")),
+            )),
+            Some(_) => None,
+        };
+        let args = match index_matches {
+            Some((index_matches, command)) => {
+                let mut args = args;
+
+                args.extend(file.index_flags(index_matches, &command)?);
+
+                let matches = Args::command()
+                    .try_get_matches_from(args)
+                    .map_err(ConfigError::InvalidIndexingOption)?;
+
+                Args::from_arg_matches(&matches).map_err(ConfigError::InvalidIndexingOption)?
+            }
+            None => Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit()),
+        };
+
+        Self { env, args, sources }.validated()
+    }
+
+    fn validated(self) -> Result<Self, ConfigError> {
+        if let Some(index_args) = self.args.index_args() {
+            index_args.validate()?;
+        }
+
+        Ok(self)
+    }
+}
+
+impl ConfigSources {
+    /// Config file the configuration is layered on, if any.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn has_var(&self, name: &str) -> bool {
+        self.vars.iter().any(|(var, _)| var == name)
+    }
+
+    /// Loads the configuration again, with the current contents of the
+    /// config file.
+    pub fn load(&self) -> Result<Config, ConfigError> {
+        Config::load_from(self.args.clone(), self.vars.clone())
+    }
+}
+
+impl ConfigFile {
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        fs::read_to_string(path)
+            .map_err(|error| ConfigError::ReadFailure {
+                path: path.to_path_buf(),
+                error,
+            })?
+            .parse()
+    }
+
+    /// Returns the flags setting the indexing options of the file taken by
+    /// the given command, leaving out the ones already given on the command
+    /// line.
+    fn index_flags(
+        &self,
+        cli_matches: &ArgMatches,
+        command: &clap::Command,
+    ) -> Result<Vec<OsString>, ConfigError> {
+        let index_command = IndexArgs::augment_args(clap::Command::new("index"));
+        let mut flags = vec![];
+
+        for (key, value) in &self.indexing {
+            let id = key.replace('-', "_");
+            let unknown = || ConfigError::UnknownOption {
+                section: "indexing",
+                key: key.clone(),
+            };
+            let arg = index_command
+                .get_arguments()
+                .find(|arg| arg.get_id().as_str() == id)
+                .ok_or_else(unknown)?;
+            let long = arg.get_long().ok_or_else(unknown)?;
+
+            if !command
+                .get_arguments()
+                .any(|arg| arg.get_id().as_str() == id)
+            {
+                continue;
+            }
+
+            if cli_matches.value_source(&id) == Some(ValueSource::CommandLine) {
+                continue;
+            }
+
+            let invalid = |reason: &str| ConfigError::InvalidValue {
+                section: "indexing",
+                key: key.clone(),
+                reason: reason.to_string(),
+            };
+
+            match (arg.get_action(), value) {
+                (ArgAction::SetTrue, ConfigValue::Bool(true)) => {
+                    flags.push(format!("--{long}").into())
+                }
+                (ArgAction::SetTrue, ConfigValue::Bool(false)) => {}
+                (ArgAction::SetTrue, _) => return Err(invalid("expected a boolean")),
+                (_, ConfigValue::List(values)) if arg.get_value_delimiter().is_some() => {
+                    flags.push(format!("--{long}={}", values.join(",")).into())
+                }
+                (_, ConfigValue::List(_)) => return Err(invalid("expected a single value")),
+                (_, ConfigValue::Bool(value)) => flags.push(format!("--{long}={value}").into()),
+                (_, ConfigValue::Scalar(value)) => flags.push(format!("--{long}={value}").into()),
+            }
+        }
+
+        Ok(flags)
+    }
+}
+
+impl FromStr for ConfigFile {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let document = s.parse::<DocumentMut>()?;
+        let mut file = ConfigFile::default();
+
+        for (section, item) in document.iter() {
+            let table = item
+                .as_table()
+                .ok_or_else(|| ConfigError::UnknownSection(section.to_string()))?;
+
+            match section {
+                "environment" => {
+                    let fields = struct_fields::<Environment>();
+
+                    for (key, value) in options(table, "environment")? {
+                        let key = key.to_lowercase();
+
+                        if !fields.contains(&key.as_str()) {
+                            return Err(ConfigError::UnknownOption {
+                                section: "environment",
+                                key,
+                            });
+                        }
+
+                        let value = match value {
+                            ConfigValue::Scalar(value) => value,
+                            ConfigValue::Bool(value) => value.to_string(),
+                            ConfigValue::List(values) => values.join(","),
+                        };
+
+                        file.environment.insert(key, value);
+                    }
+                }
+                "indexing" => file.indexing = options(table, "indexing")?,
+                _ => return Err(ConfigError::UnknownSection(section.to_string())),
+            }
+        }
+
+        Ok(file)
+    }
+}
+
+fn options(
+    table: &Table,
+    section: &'static str,
+) -> Result<Vec<(String, ConfigValue)>, ConfigError> {
+    table
+        .iter()
+        .map(|(key, item)| {
+            let invalid = || ConfigError::InvalidValue {
+                section,
+                key: key.to_string(),
+                reason: "expected a string, number, boolean or list of them".to_string(),
+            };
+            let value = match item {
+                Item::Value(Value::Boolean(value)) => ConfigValue::Bool(*value.value()),
+                Item::Value(Value::Array(values)) => ConfigValue::List(
+                    values
+                        .iter()
+                        .map(|value| scalar(value).ok_or_else(invalid))
+                        .collect::<Result<_, _>>()?,
+                ),
+                Item::Value(value) => ConfigValue::Scalar(scalar(value).ok_or_else(invalid)?),
+                _ => return Err(invalid()),
+            };
+
+            Ok((key.to_string(), value))
+        })
+        .collect()
+}
+
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(value) => Some(value.value().clone()),
+        Value::Integer(value) => Some(value.value().to_string()),
+        Value::Float(value) => Some(value.value().to_string()),
+        Value::Boolean(value) => Some(value.value().to_string()),
+        _ => None,
+    }
+}
+
+/// Returns the names of the fields of a struct deserialized with serde, so
+/// options can be checked against them.
+fn struct_fields<'de, T: Deserialize<'de>>() -> &'static [&'static str] {
+    struct FieldsDeserializer<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> de::Deserializer<'de> for FieldsDeserializer<'_> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: de::Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("expected a struct"))
+        }
+
+        fn deserialize_struct<V: de::Visitor<'de>>(
+            self,
+            _: &'static str,
+            fields: &'static [&'static str],
+            _: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+
+            Err(de::Error::custom("only collecting the fields"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldsDeserializer(&mut fields));
+
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{ArgsError, Config, ConfigError, ConfigFile};
+    use crate::args::Command;
+
+    const VARS: [(&str, &str); 2] = [
+        ("SECRET_KEY", "secret"),
+        ("BEACON_NODE_ENDPOINT", "http://env-beacon:3500"),
+    ];
+
+    fn vars() -> Vec<(String, String)> {
+        VARS.iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn options_are_layered_from_file_env_and_cli() {
+        let path = std::env::temp_dir().join(format!("config-{}.toml", std::process::id()));
+
+        fs::write(
+            &path,
+            r#"
+            [environment]
+            network_name = "holesky"
+            beacon_node_endpoint = "http://file-beacon:3500"
+
+            [indexing]
+            num_threads = 8
+            finalized_only = true
+            slots_per_save = 50
+            filter_to_addresses = ["0xff00000000000000000000000000000000000000"]
+            "#,
+        )
+        .unwrap();
+
+        let config = Config::load_from(
+            [
+                "blob-indexer",
+                "--config",
+                path.to_str().unwrap(),
+                "--slots-per-save",
+                "10",
+            ],
+            vars(),
+        );
+        let _ = fs::remove_file(&path);
+        let config = config.unwrap();
+
+        assert_eq!(config.env.network_name, "holesky");
+        assert_eq!(config.env.beacon_node_endpoint, "http://env-beacon:3500");
+        assert_eq!(config.args.index.num_threads, Some(8));
+        assert!(config.args.index.finalized_only);
+        assert_eq!(config.args.index.slots_per_save, Some(10));
+        assert_eq!(config.args.index.processing.filter_to_addresses.len(), 1);
+    }
+
+    #[test]
+    fn processing_options_are_taken_when_reindexing() {
+        let path = std::env::temp_dir().join(format!("reindex-{}.toml", std::process::id()));
+
+        fs::write(
+            &path,
+            "[indexing]\nnum_threads = 8\nno_blob_data = true\nheader_cache_size = 16",
+        )
+        .unwrap();
+
+        let config = Config::load_from(
+            [
+                "blob-indexer",
+                "reindex",
+                "--config",
+                path.to_str().unwrap(),
+                "--slot",
+                "10",
+                "--header-cache-size",
+                "32",
+            ],
+            vars(),
+        );
+        let _ = fs::remove_file(&path);
+
+        let Some(Command::Reindex(reindex_args)) = config.unwrap().args.command else {
+            panic!("expected the reindex command");
+        };
+
+        assert_eq!(reindex_args.slot, Some(10));
+        assert!(reindex_args.processing.no_blob_data);
+        assert_eq!(reindex_args.processing.header_cache_size, 32);
+    }
+
+    #[test]
+    fn unknown_options_are_rejected() {
+        assert!(matches!(
+            "[environment]\nbeacon_node_endpont = \"http://localhost\"".parse::<ConfigFile>(),
+            Err(ConfigError::UnknownOption { .. })
+        ));
+        assert!(matches!(
+            "[indexers]\nnum_threads = 1".parse::<ConfigFile>(),
+            Err(ConfigError::UnknownSection(_))
+        ));
+    }
+
+    #[test]
+    fn nonsensical_indexing_options_are_rejected() {
+        let path = std::env::temp_dir().join(format!("invalid-{}.toml", std::process::id()));
+
+        fs::write(&path, "[indexing]\nnum_threads = 0").unwrap();
+
+        let config =
+            Config::load_from(["blob-indexer", "--config", path.to_str().unwrap()], vars());
+        let _ = fs::remove_file(&path);
+
+        assert!(matches!(config, Err(ConfigError::InvalidIndexingOption(_))));
+        assert!(matches!(
+            Config::load_from(
+                ["blob-indexer", "--from-slot", "10", "--to-slot", "10"],
+                vars()
+            ),
+            Err(ConfigError::InvalidArgs(_))
+        ));
+        assert!(matches!(
+            Config::load_from(["blob-indexer", "--ranges", "10-20,30-15"], vars()),
+            Err(ConfigError::InvalidArgs(ArgsError::OverlappingRanges(..)))
+        ));
+        assert!(Config::load_from(["blob-indexer", "--ranges", "10-20,30-20"], vars()).is_ok());
+    }
+}
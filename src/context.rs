@@ -1,44 +1,135 @@
-use std::{sync::Arc, time::Duration};
+use std::{path::PathBuf, sync::Arc, time::Duration};
 
 use alloy::{
     providers::{Provider, ProviderBuilder},
-    transports::http::ReqwestTransport,
+    transports::BoxTransport,
 };
-use anyhow::Result as AnyhowResult;
-use backoff::ExponentialBackoffBuilder;
+use anyhow::{anyhow, Context as AnyhowContext, Result as AnyhowResult};
 use dyn_clone::DynClone;
+use url::Url;
 
 use crate::{
     clients::{
+        backoff::{BackoffProfiles, OperationClass},
         beacon::{BeaconClient, CommonBeaconClient, Config as BeaconClientConfig},
-        blobscan::{BlobscanClient, CommonBlobscanClient, Config as BlobscanClientConfig},
+        blobscan::{
+            Auth as BlobscanAuth, BlobscanClient, CommonBlobscanClient,
+            Config as BlobscanClientConfig,
+        },
+        execution::{self, AuthHeader, Config as ExecutionClientConfig, JwtSecret},
+        headers::ExtraHeaders,
+        tls::{TlsPins, TlsSettings},
     },
     env::Environment,
+    era::EraArchive,
+    rollups::RollupRegistry,
+    sinks::{
+        analytics::AnalyticsSink, blobscan::BlobscanSink, export::ExportSink, CommonSink, SinkType,
+    },
+    slots_processor::{
+        blob_budget::BlobBudget, blob_retention::BlobRetention, claims::SlotClaims,
+        filter::AddressFilter,
+    },
+    tx_categories::TxCategoryRegistry,
 };
 
-// #[cfg(test)]
-// use crate::clients::{beacon::MockCommonBeaconClient, blobscan::MockCommonBlobscanClient};
+#[cfg(feature = "postgres")]
+use crate::sinks::postgres::{Config as PostgresSinkConfig, PostgresSink};
+
+#[cfg(feature = "postgres")]
+const POSTGRES_SINK_MAX_CONNECTIONS: usize = 16;
+
+pub const DEFAULT_BEACON_HEADER_CACHE_SIZE: usize = 256;
+
+#[cfg(test)]
+use crate::{
+    clients::{
+        beacon::MockCommonBeaconClient, blobscan::MockCommonBlobscanClient,
+        headers::default_user_agent,
+    },
+    sinks::MockCommonSink,
+};
 
 pub trait CommonContext<T>: Send + Sync + DynClone {
     fn beacon_client(&self) -> &dyn CommonBeaconClient;
     fn blobscan_client(&self) -> &dyn CommonBlobscanClient;
     fn provider(&self) -> &dyn Provider<T>;
+    fn sink(&self) -> &dyn CommonSink;
+    fn backoff_profiles(&self) -> &BackoffProfiles;
+    fn rollups(&self) -> &RollupRegistry;
+    fn tx_categories(&self) -> &TxCategoryRegistry;
+    fn address_filter(&self) -> &AddressFilter;
+    fn slot_claims(&self) -> Arc<SlotClaims>;
+    fn blob_budget(&self) -> Arc<BlobBudget>;
+    fn blob_retention(&self) -> Arc<BlobRetention>;
+    /// Era files the blocks of backfilled slots are read from, if any
+    fn era_archive(&self) -> Option<Arc<EraArchive>>;
 }
 
-dyn_clone::clone_trait_object!(CommonContext<ReqwestTransport>);
-// dyn_clone::clone_trait_object!(CommonContext<MockProvider>);
+dyn_clone::clone_trait_object!(CommonContext<BoxTransport>);
 
 pub struct Config {
     pub blobscan_api_endpoint: String,
+    /// Certificate pins the Blobscan API has to match. Only HTTPS endpoints are
+    /// allowed when set
+    pub blobscan_api_tls_pins: Option<TlsPins>,
     pub beacon_node_url: String,
+    /// Overrides the beacon node the blob sidecars are fetched from
+    pub beacon_node_blobs_url: Option<String>,
+    /// Overrides the beacon node the events are streamed from
+    pub beacon_node_events_url: Option<String>,
     pub execution_node_endpoint: String,
-    pub secret_key: String,
+    pub execution_node_auth_header: Option<AuthHeader>,
+    pub execution_node_jwt_secret: Option<JwtSecret>,
+    pub blobscan_auth: BlobscanAuth,
+    pub sink: SinkType,
+    /// Directory indexed entities are also exported to
+    pub export_dir: Option<PathBuf>,
+    /// Skip the sink, writing indexed entities to the export directory only
+    pub export_only: bool,
+    /// Directory the metadata of indexed blobs is written to
+    pub analytics_dir: Option<PathBuf>,
+    /// Directory of the beacon era files blocks are read from when covered
+    pub era_dir: Option<PathBuf>,
+    pub database_url: Option<String>,
+    pub response_compression: bool,
+    pub user_agent: String,
+    /// Headers sent along every request to the nodes and the Blobscan API
+    pub extra_headers: ExtraHeaders,
+    /// Overrides the proxies set in the environment
+    pub proxy_url: Option<Url>,
+    /// CA bundle and client certificate used for every connection
+    pub tls: TlsSettings,
+    pub max_inflight_request_bytes: Option<usize>,
+    /// Maximum combined size of the blobs held in memory by every worker
+    pub max_inflight_blob_bytes: Option<usize>,
+    pub drop_invalid_entities: bool,
+    /// Index blobs in Blobscan without their contents
+    pub omit_blob_data: bool,
+    /// Amount of recently fetched beacon block headers kept in memory
+    pub beacon_header_cache_size: usize,
+    pub backoff_profiles: BackoffProfiles,
+    /// Known rollup addresses used to label indexed transactions
+    pub rollups: RollupRegistry,
+    /// Calldata signatures used to categorize indexed transactions
+    pub tx_categories: TxCategoryRegistry,
+    /// Addresses the indexed blob transactions are restricted to
+    pub address_filter: AddressFilter,
 }
 
 struct ContextRef<T> {
     pub beacon_client: Box<dyn CommonBeaconClient>,
     pub blobscan_client: Box<dyn CommonBlobscanClient>,
     pub provider: Box<dyn Provider<T>>,
+    pub sink: Box<dyn CommonSink>,
+    pub backoff_profiles: BackoffProfiles,
+    pub rollups: RollupRegistry,
+    pub tx_categories: TxCategoryRegistry,
+    pub address_filter: AddressFilter,
+    pub slot_claims: Arc<SlotClaims>,
+    pub blob_budget: Arc<BlobBudget>,
+    pub blob_retention: Arc<BlobRetention>,
+    pub era_archive: Option<Arc<EraArchive>>,
 }
 
 #[derive(Clone)]
@@ -46,47 +137,182 @@ pub struct Context<T> {
     inner: Arc<ContextRef<T>>,
 }
 
-impl Context<ReqwestTransport> {
+impl Context<BoxTransport> {
     pub fn try_new(config: Config) -> AnyhowResult<Self> {
+        Self::try_build(config, None)
+    }
+
+    /// Creates a context whose indexed entities are written to the given sink
+    /// instead of the one set in the config.
+    pub fn try_with_sink(config: Config, sink: Box<dyn CommonSink>) -> AnyhowResult<Self> {
+        Self::try_build(config, Some(sink))
+    }
+
+    fn try_build(config: Config, custom_sink: Option<Box<dyn CommonSink>>) -> AnyhowResult<Self> {
         let Config {
             blobscan_api_endpoint,
+            blobscan_api_tls_pins,
             beacon_node_url,
+            beacon_node_blobs_url,
+            beacon_node_events_url,
             execution_node_endpoint,
-            secret_key,
+            execution_node_auth_header,
+            execution_node_jwt_secret,
+            blobscan_auth,
+            sink,
+            export_dir,
+            export_only,
+            analytics_dir,
+            era_dir,
+            database_url,
+            response_compression,
+            user_agent,
+            extra_headers,
+            proxy_url,
+            tls,
+            max_inflight_request_bytes,
+            max_inflight_blob_bytes,
+            drop_invalid_entities,
+            omit_blob_data,
+            beacon_header_cache_size,
+            backoff_profiles,
+            rollups,
+            tx_categories,
+            address_filter,
         } = config;
-        let exp_backoff = Some(ExponentialBackoffBuilder::default().build());
 
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(8))
-            .build()?;
+        let client_builder = || -> reqwest::Result<reqwest::ClientBuilder> {
+            let builder = reqwest::Client::builder()
+                .user_agent(&user_agent)
+                .default_headers(extra_headers.header_map())
+                .gzip(response_compression)
+                .deflate(response_compression);
+
+            // Proxies set in the environment are used otherwise
+            let builder = match &proxy_url {
+                Some(proxy_url) => builder.proxy(
+                    reqwest::Proxy::all(proxy_url.as_str())?.no_proxy(reqwest::NoProxy::from_env()),
+                ),
+                None => builder,
+            };
+
+            tls.apply(builder)
+        };
+        let client = client_builder()?.timeout(Duration::from_secs(8)).build()?;
+        let events_client = client_builder()?.build()?;
+        let blobscan_http_client = match blobscan_api_tls_pins {
+            Some(tls_pins) => {
+                if !blobscan_api_endpoint.starts_with("https://") {
+                    return Err(anyhow!(
+                        "BLOBSCAN_API_TLS_PINS requires an https Blobscan API endpoint"
+                    ));
+                }
+
+                client_builder()?
+                    .timeout(Duration::from_secs(8))
+                    .use_preconfigured_tls(tls_pins.client_config(&tls))
+                    .build()?
+            }
+            None => client.clone(),
+        };
+        let provider_client = execution::try_build_client(ExecutionClientConfig {
+            endpoint: execution_node_endpoint,
+            timeout: backoff_profiles.execution_read.timeout,
+            response_compression,
+            user_agent,
+            extra_headers,
+            proxy_url,
+            tls: tls.clone(),
+            auth_header: execution_node_auth_header,
+            jwt_secret: execution_node_jwt_secret,
+        })?;
+
+        let blobscan_client = BlobscanClient::try_with_client(
+            blobscan_http_client,
+            BlobscanClientConfig {
+                base_url: blobscan_api_endpoint,
+                auth: blobscan_auth,
+                backoff_profiles: Some(backoff_profiles.clone()),
+                max_inflight_request_bytes,
+                drop_invalid_entities,
+                omit_blob_data,
+            },
+        )?;
+        let sink: Box<dyn CommonSink> = match (custom_sink, sink) {
+            (Some(custom_sink), _) => custom_sink,
+            (None, SinkType::Blobscan) => Box::new(BlobscanSink::new(blobscan_client.clone())),
+            #[cfg(feature = "postgres")]
+            (None, SinkType::Postgres) => {
+                let database_url = database_url.ok_or_else(|| {
+                    anyhow!("DATABASE_URL is required when using the postgres sink")
+                })?;
+
+                Box::new(PostgresSink::try_new(PostgresSinkConfig {
+                    database_url,
+                    max_connections: POSTGRES_SINK_MAX_CONNECTIONS,
+                    tls: tls.clone(),
+                })?)
+            }
+            #[cfg(not(feature = "postgres"))]
+            (None, SinkType::Postgres) => {
+                let _ = database_url;
+
+                return Err(anyhow!(
+                    "The postgres sink requires the indexer to be built with the `postgres` feature"
+                ));
+            }
+        };
+
+        let sink: Box<dyn CommonSink> = match export_dir {
+            Some(export_dir) => Box::new(ExportSink::try_new(
+                export_dir,
+                (!export_only).then_some(sink),
+            )?),
+            None => sink,
+        };
+        let sink: Box<dyn CommonSink> = match analytics_dir {
+            Some(analytics_dir) => Box::new(AnalyticsSink::try_new(analytics_dir, sink)?),
+            None => sink,
+        };
+        let era_archive = era_dir
+            .map(|era_dir| {
+                EraArchive::open(&era_dir)
+                    .with_context(|| format!("Failed to open era directory {}", era_dir.display()))
+            })
+            .transpose()?
+            .map(Arc::new);
 
         Ok(Self {
             inner: Arc::new(ContextRef {
-                blobscan_client: Box::new(BlobscanClient::try_with_client(
-                    client.clone(),
-                    BlobscanClientConfig {
-                        base_url: blobscan_api_endpoint,
-                        secret_key,
-                        exp_backoff: exp_backoff.clone(),
-                    },
-                )?),
+                blobscan_client: Box::new(blobscan_client),
                 beacon_client: Box::new(BeaconClient::try_with_client(
                     client,
                     BeaconClientConfig {
                         base_url: beacon_node_url,
-                        exp_backoff,
+                        blob_sidecars_base_url: beacon_node_blobs_url,
+                        events_base_url: beacon_node_events_url,
+                        backoff_profiles: Some(backoff_profiles.clone()),
+                        header_cache_size: beacon_header_cache_size,
+                        events_client: Some(events_client),
                     },
                 )?),
                 // Provider::<HttpProvider>::try_from(execution_node_endpoint)?
-                provider: Box::new(
-                    ProviderBuilder::new().on_http(execution_node_endpoint.parse()?),
-                ),
+                provider: Box::new(ProviderBuilder::new().on_client(provider_client)),
+                sink,
+                backoff_profiles,
+                rollups,
+                tx_categories,
+                address_filter,
+                slot_claims: Arc::new(SlotClaims::new()),
+                blob_budget: Arc::new(BlobBudget::new(max_inflight_blob_bytes)),
+                blob_retention: Arc::new(BlobRetention::new()),
+                era_archive,
             }),
         })
     }
 }
 
-impl CommonContext<ReqwestTransport> for Context<ReqwestTransport> {
+impl CommonContext<BoxTransport> for Context<BoxTransport> {
     fn beacon_client(&self) -> &dyn CommonBeaconClient {
         self.inner.beacon_client.as_ref()
     }
@@ -95,52 +321,149 @@ impl CommonContext<ReqwestTransport> for Context<ReqwestTransport> {
         self.inner.blobscan_client.as_ref()
     }
 
-    fn provider(&self) -> &dyn Provider<ReqwestTransport> {
+    fn provider(&self) -> &dyn Provider<BoxTransport> {
         self.inner.provider.as_ref()
     }
+
+    fn sink(&self) -> &dyn CommonSink {
+        self.inner.sink.as_ref()
+    }
+
+    fn backoff_profiles(&self) -> &BackoffProfiles {
+        &self.inner.backoff_profiles
+    }
+
+    fn rollups(&self) -> &RollupRegistry {
+        &self.inner.rollups
+    }
+
+    fn tx_categories(&self) -> &TxCategoryRegistry {
+        &self.inner.tx_categories
+    }
+
+    fn address_filter(&self) -> &AddressFilter {
+        &self.inner.address_filter
+    }
+
+    fn slot_claims(&self) -> Arc<SlotClaims> {
+        self.inner.slot_claims.clone()
+    }
+
+    fn blob_budget(&self) -> Arc<BlobBudget> {
+        self.inner.blob_budget.clone()
+    }
+
+    fn blob_retention(&self) -> Arc<BlobRetention> {
+        self.inner.blob_retention.clone()
+    }
+
+    fn era_archive(&self) -> Option<Arc<EraArchive>> {
+        self.inner.era_archive.clone()
+    }
 }
 
 impl From<&Environment> for Config {
     fn from(env: &Environment) -> Self {
         Self {
             blobscan_api_endpoint: env.blobscan_api_endpoint.clone(),
+            blobscan_api_tls_pins: env.blobscan_api_tls_pins.clone(),
             beacon_node_url: env.beacon_node_endpoint.clone(),
+            beacon_node_blobs_url: env.beacon_node_blobs_endpoint.clone(),
+            beacon_node_events_url: env.beacon_node_events_endpoint.clone(),
             execution_node_endpoint: env.execution_node_endpoint.clone(),
-            secret_key: env.secret_key.clone(),
+            execution_node_auth_header: env.execution_node_auth_header.clone(),
+            execution_node_jwt_secret: env.execution_node_jwt_secret.clone(),
+            blobscan_auth: env.blobscan_auth(),
+            sink: SinkType::default(),
+            export_dir: None,
+            export_only: false,
+            analytics_dir: None,
+            era_dir: None,
+            database_url: env.database_url.clone(),
+            response_compression: !env.disable_response_compression,
+            user_agent: env.user_agent(),
+            extra_headers: env.http_extra_headers.clone(),
+            proxy_url: env.proxy_url.clone(),
+            tls: env.tls.clone(),
+            max_inflight_request_bytes: None,
+            max_inflight_blob_bytes: None,
+            drop_invalid_entities: false,
+            omit_blob_data: false,
+            beacon_header_cache_size: DEFAULT_BEACON_HEADER_CACHE_SIZE,
+            backoff_profiles: backoff_profiles_from_env(env),
+            rollups: RollupRegistry::default(),
+            tx_categories: TxCategoryRegistry::default(),
+            address_filter: AddressFilter::default(),
         }
     }
 }
 
-// #[cfg(test)]
-// impl Context<MockProvider> {
-//     pub fn new(
-//         beacon_client: Option<MockCommonBeaconClient>,
-//         blobscan_client: Option<MockCommonBlobscanClient>,
-//         provider: Option<Provider<MockProvider>>,
-//     ) -> Box<Self> {
-//         Box::new(Self {
-//             inner: Arc::new(ContextRef {
-//                 beacon_client: Box::new(beacon_client.unwrap_or(MockCommonBeaconClient::new())),
-//                 blobscan_client: Box::new(
-//                     blobscan_client.unwrap_or(MockCommonBlobscanClient::new()),
-//                 ),
-//                 provider: provider.unwrap_or(Provider::mocked().0),
-//             }),
-//         })
-//     }
-// }
-
-// #[cfg(test)]
-// impl CommonContext<MockProvider> for Context<MockProvider> {
-//     fn beacon_client(&self) -> &dyn CommonBeaconClient {
-//         self.inner.beacon_client.as_ref()
-//     }
-
-//     fn blobscan_client(&self) -> &dyn CommonBlobscanClient {
-//         self.inner.blobscan_client.as_ref()
-//     }
-
-//     fn provider(&self) -> &Provider<MockProvider> {
-//         &self.inner.provider
-//     }
-// }
+fn backoff_profiles_from_env(env: &Environment) -> BackoffProfiles {
+    let mut backoff_profiles = BackoffProfiles::default();
+    let overrides = [
+        (OperationClass::BeaconRead, &env.beacon_read_backoff),
+        (
+            OperationClass::BlobSidecarRead,
+            &env.blob_sidecar_read_backoff,
+        ),
+        (OperationClass::ExecutionRead, &env.execution_read_backoff),
+        (OperationClass::BlobscanRead, &env.blobscan_read_backoff),
+        (OperationClass::BlobscanWrite, &env.blobscan_write_backoff),
+    ];
+
+    for (operation_class, profile_override) in overrides {
+        if let Some(profile_override) = profile_override {
+            backoff_profiles
+                .get_mut(operation_class)
+                .apply(profile_override);
+        }
+    }
+
+    backoff_profiles
+}
+
+#[cfg(test)]
+impl Context<BoxTransport> {
+    /// Creates a context backed by the given mocks. Clients left out get a mock
+    /// without expectations, and the provider defaults to one whose node is
+    /// never reached unless a request is made.
+    pub fn with_mocks(
+        beacon_client: Option<MockCommonBeaconClient>,
+        blobscan_client: Option<MockCommonBlobscanClient>,
+        provider: Option<Box<dyn Provider<BoxTransport>>>,
+    ) -> Box<Self> {
+        let provider = provider.unwrap_or_else(|| {
+            let client = execution::try_build_client(ExecutionClientConfig {
+                endpoint: "http://localhost:8545".to_string(),
+                timeout: Duration::from_secs(1),
+                response_compression: false,
+                user_agent: default_user_agent("devnet"),
+                extra_headers: ExtraHeaders::default(),
+                proxy_url: None,
+                tls: TlsSettings::default(),
+                auth_header: None,
+                jwt_secret: None,
+            })
+            .expect("failed to build the mock provider client");
+
+            Box::new(ProviderBuilder::new().on_client(client))
+        });
+
+        Box::new(Self {
+            inner: Arc::new(ContextRef {
+                beacon_client: Box::new(beacon_client.unwrap_or_default()),
+                blobscan_client: Box::new(blobscan_client.unwrap_or_default()),
+                provider,
+                sink: Box::new(MockCommonSink::new()),
+                backoff_profiles: BackoffProfiles::default(),
+                rollups: RollupRegistry::default(),
+                tx_categories: TxCategoryRegistry::default(),
+                address_filter: AddressFilter::default(),
+                slot_claims: Arc::new(SlotClaims::new()),
+                blob_budget: Arc::new(BlobBudget::default()),
+                blob_retention: Arc::new(BlobRetention::new()),
+                era_archive: None,
+            }),
+        })
+    }
+}
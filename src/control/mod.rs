@@ -0,0 +1,89 @@
+use std::{net::SocketAddr, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::indexer::{reorg_confirmation::PendingReorg, status::TaskStatus};
+
+pub mod server;
+
+/// Upper bound for querying a running instance, so `status` and `backfill`
+/// don't hang on a stuck one
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPositions {
+    pub last_lower_synced_slot: Option<u32>,
+    pub last_upper_synced_slot: Option<u32>,
+    pub last_finalized_block: Option<u32>,
+}
+
+/// State of the backfill after pausing or resuming it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackfillState {
+    pub paused: bool,
+}
+
+/// State of a running indexer as reported by its control server.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusReport {
+    pub version: String,
+    pub uptime_seconds: u64,
+    pub tasks: Vec<TaskStatus>,
+    /// Whether the backfill is paused through the control server
+    #[serde(default)]
+    pub backfill_paused: bool,
+    /// Reorg deeper than the confirmation depth waiting to be confirmed
+    #[serde(default)]
+    pub pending_reorg: Option<PendingReorg>,
+    /// Unknown when Blobscan can't be reached
+    pub sync_state: Option<SyncPositions>,
+    /// Unknown when the beacon node can't be reached
+    pub head_slot: Option<u32>,
+    pub slots_behind_head: Option<u32>,
+}
+
+/// Fetches the status of the indexer whose control server listens on the
+/// given address.
+pub async fn fetch_status(address: SocketAddr) -> Result<StatusReport, reqwest::Error> {
+    reqwest::Client::new()
+        .get(format!("http://{address}/status"))
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+}
+
+/// Pauses or resumes the backfill of the indexer whose control server listens
+/// on the given address, returning its resulting state.
+pub async fn set_backfill_paused(
+    address: SocketAddr,
+    paused: bool,
+) -> Result<BackfillState, reqwest::Error> {
+    let action = if paused { "pause" } else { "resume" };
+
+    reqwest::Client::new()
+        .post(format!("http://{address}/backfill/{action}"))
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+}
+
+/// Lets the indexer whose control server listens on the given address handle
+/// the reorg it's holding, returning it, if any.
+pub async fn confirm_reorg(address: SocketAddr) -> Result<Option<PendingReorg>, reqwest::Error> {
+    reqwest::Client::new()
+        .post(format!("http://{address}/reorgs/confirm"))
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+}
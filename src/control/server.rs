@@ -0,0 +1,203 @@
+use std::{convert::Infallible, net::SocketAddr, sync::Arc, time::Duration};
+
+use alloy::transports::BoxTransport;
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use tracing::{error, info};
+
+use crate::{
+    clients::beacon::types::BlockId,
+    context::CommonContext,
+    indexer::{reorg_confirmation::ReorgConfirmation, status::IndexerStatus},
+    metrics::set_gauge,
+    synchronizer::pause::PauseSwitch,
+};
+
+use super::{BackfillState, StatusReport, SyncPositions};
+
+/// Upper bound for fetching the sync positions and the head slot, so `/status`
+/// stays responsive while Blobscan or the beacon node are unreachable
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(2);
+
+struct ControlState {
+    status: Arc<IndexerStatus>,
+    backfill_pause_switch: Arc<PauseSwitch>,
+    reorg_confirmation: Option<Arc<ReorgConfirmation>>,
+    context: Box<dyn CommonContext<BoxTransport>>,
+}
+
+/// Serves the `/status`, `/backfill/pause`, `/backfill/resume` and
+/// `/reorgs/confirm` endpoints of the running indexer until the process exits.
+/// Meant to be bound to a local address only.
+pub async fn run_control_server(
+    address: SocketAddr,
+    status: Arc<IndexerStatus>,
+    backfill_pause_switch: Arc<PauseSwitch>,
+    reorg_confirmation: Option<Arc<ReorgConfirmation>>,
+    context: Box<dyn CommonContext<BoxTransport>>,
+) -> Result<(), hyper::Error> {
+    let state = Arc::new(ControlState {
+        status,
+        backfill_pause_switch,
+        reorg_confirmation,
+        context,
+    });
+    let make_service = make_service_fn(move |_| {
+        let state = state.clone();
+
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                handle_request(request, state.clone())
+            }))
+        }
+    });
+
+    info!(%address, "Control server listening");
+
+    Server::bind(&address).serve(make_service).await
+}
+
+async fn handle_request(
+    request: Request<Body>,
+    state: Arc<ControlState>,
+) -> Result<Response<Body>, Infallible> {
+    let response = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/status") => status_response(&state).await,
+        (&Method::POST, "/backfill/pause") => set_backfill_paused(&state, true),
+        (&Method::POST, "/backfill/resume") => set_backfill_paused(&state, false),
+        (&Method::POST, "/reorgs/confirm") => confirm_reorg(&state),
+        _ => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap_or_default(),
+    };
+
+    Ok(response)
+}
+
+async fn status_response(state: &ControlState) -> Response<Body> {
+    let (sync_state, head_slot) = tokio::join!(
+        tokio::time::timeout(
+            UPSTREAM_TIMEOUT,
+            state.context.blobscan_client().get_sync_state()
+        ),
+        tokio::time::timeout(
+            UPSTREAM_TIMEOUT,
+            state
+                .context
+                .beacon_client()
+                .get_block_header(&BlockId::Head)
+        ),
+    );
+    let sync_state = match sync_state {
+        Ok(Ok(sync_state)) => sync_state.map(|sync_state| SyncPositions {
+            last_lower_synced_slot: sync_state.last_lower_synced_slot,
+            last_upper_synced_slot: sync_state.last_upper_synced_slot,
+            last_finalized_block: sync_state.last_finalized_block,
+        }),
+        Ok(Err(error)) => {
+            error!(?error, "Failed to fetch blobscan's sync state");
+
+            None
+        }
+        Err(_) => {
+            error!("Timed out fetching blobscan's sync state");
+
+            None
+        }
+    };
+    let head_slot = match head_slot {
+        Ok(Ok(header)) => header.map(|header| header.header.message.slot),
+        Ok(Err(error)) => {
+            error!(?error, "Failed to fetch the beacon head");
+
+            None
+        }
+        Err(_) => {
+            error!("Timed out fetching the beacon head");
+
+            None
+        }
+    };
+    let last_upper_synced_slot = sync_state
+        .as_ref()
+        .and_then(|sync_state| sync_state.last_upper_synced_slot);
+    let report = StatusReport {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        uptime_seconds: state.status.uptime().as_secs(),
+        tasks: state.status.tasks(),
+        backfill_paused: state.backfill_pause_switch.is_paused(),
+        pending_reorg: state
+            .reorg_confirmation
+            .as_ref()
+            .and_then(|reorg_confirmation| reorg_confirmation.pending()),
+        sync_state,
+        head_slot,
+        slots_behind_head: head_slot.zip(last_upper_synced_slot).map(
+            |(head_slot, last_upper_synced_slot)| head_slot.saturating_sub(last_upper_synced_slot),
+        ),
+    };
+
+    json_response(&report)
+}
+
+/// Holds the backfill between work units without touching the live indexing,
+/// which keeps following the head.
+fn set_backfill_paused(state: &ControlState, paused: bool) -> Response<Body> {
+    let switch = &state.backfill_pause_switch;
+    let changed = if paused {
+        switch.pause()
+    } else {
+        switch.resume()
+    };
+
+    if changed {
+        info!(
+            "Backfill {} through the control server",
+            if paused { "paused" } else { "resumed" }
+        );
+    }
+
+    set_gauge("blob_indexer_backfill_paused", &[], paused as u64);
+
+    json_response(&BackfillState { paused })
+}
+
+/// Lets the held reorg be handled, responding with it, or with `null` when
+/// there's none.
+fn confirm_reorg(state: &ControlState) -> Response<Body> {
+    let confirmed_reorg = state
+        .reorg_confirmation
+        .as_ref()
+        .and_then(|reorg_confirmation| reorg_confirmation.confirm());
+
+    if let Some(reorg) = &confirmed_reorg {
+        info!(
+            slot = reorg.slot,
+            depth = reorg.depth,
+            "Reorg confirmed through the control server"
+        );
+    }
+
+    json_response(&confirmed_reorg)
+}
+
+fn json_response<T: serde::Serialize>(value: &T) -> Response<Body> {
+    match serde_json::to_vec(value) {
+        Ok(body) => Response::builder()
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap_or_default(),
+        Err(error) => {
+            error!(?error, "Failed to serialize control response");
+
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::empty())
+                .unwrap_or_default()
+        }
+    }
+}
@@ -1,25 +1,132 @@
-use envy::Error::MissingValue;
+use std::{collections::BTreeMap, net::SocketAddr, path::PathBuf};
+
+use chrono::TimeDelta;
+use envy::Error::{Custom, MissingValue};
 use serde::Deserialize;
+use url::Url;
 
-use crate::network::Network;
+use crate::{
+    clients::{
+        backoff::BackoffProfileOverride,
+        blobscan::{
+            jwt_manager::{
+                Config as JWTManagerConfig, JwtAlgorithm, DEFAULT_REFRESH_INTERVAL,
+                DEFAULT_SAFETY_MARGIN,
+            },
+            Auth as BlobscanAuth,
+        },
+        body::DEFAULT_MAX_LOGGED_BODY_LENGTH,
+        execution::{AuthHeader, JwtSecret},
+        headers::{default_user_agent, ExtraHeaders},
+        tls::{TlsPins, TlsSettings},
+    },
+    network::{Network, NetworkPresets},
+};
 
 #[derive(Deserialize, Debug)]
 pub struct Environment {
-    #[serde(default = "default_network")]
-    pub network_name: Network,
+    #[serde(default = "default_network_name")]
+    pub network_name: String,
+    /// TOML file defining custom networks, see [`NetworkPresets`]
+    pub networks_file: Option<PathBuf>,
+    /// Network resolved from its name and the networks file
+    #[serde(skip)]
+    pub network: Network,
     #[serde(default = "default_blobscan_api_endpoint")]
     pub blobscan_api_endpoint: String,
+    pub blobscan_api_tls_pins: Option<TlsPins>,
     #[serde(default = "default_beacon_node_endpoint")]
     pub beacon_node_endpoint: String,
+    /// Beacon node the blob sidecars are fetched from instead, such as an
+    /// archive node for historical syncs
+    pub beacon_node_blobs_endpoint: Option<String>,
+    /// Beacon node the head and finalization events are streamed from instead,
+    /// such as a low-latency one for live indexing
+    pub beacon_node_events_endpoint: Option<String>,
     #[serde(default = "default_execution_node_endpoint")]
     pub execution_node_endpoint: String,
+    pub execution_node_auth_header: Option<AuthHeader>,
+    pub execution_node_jwt_secret: Option<JwtSecret>,
+    /// Secret the JWTs sent to the Blobscan API are signed with. Not needed
+    /// when authenticating through a static header
+    #[serde(default)]
     pub secret_key: String,
+    /// Header authenticating to the Blobscan API instead of JWTs, e.g. an API
+    /// key checked by a gateway
+    pub blobscan_api_auth_header: Option<AuthHeader>,
+    /// Lifetime in seconds of the JWTs sent to the Blobscan API
+    pub blobscan_jwt_refresh_interval: Option<u64>,
+    /// Seconds before their expiration the JWTs are refreshed
+    pub blobscan_jwt_safety_margin: Option<u64>,
+    #[serde(default)]
+    pub blobscan_jwt_algorithm: JwtAlgorithm,
     pub dencun_fork_slot: Option<u32>,
     pub sentry_dsn: Option<String>,
+    pub otel_exporter_otlp_endpoint: Option<String>,
+    #[serde(default = "default_otel_service_name")]
+    pub otel_service_name: String,
+    /// Blobscan database the postgres sink writes to. Connections use TLS,
+    /// trusting the same CAs as the other endpoints, when it sets
+    /// `sslmode=require`
+    pub database_url: Option<String>,
+    #[serde(default)]
+    pub disable_response_compression: bool,
+    /// User-Agent sent to the nodes and the Blobscan API, instead of the
+    /// default one naming the indexer, its version and network
+    pub http_user_agent: Option<String>,
+    /// Headers sent along every request to the nodes and the Blobscan API
+    #[serde(default)]
+    pub http_extra_headers: ExtraHeaders,
+    /// Proxy every request to the nodes and the Blobscan API goes through,
+    /// instead of the ones in `HTTP_PROXY` and `HTTPS_PROXY`. Hosts listed in
+    /// `NO_PROXY` are still reached directly
+    pub proxy_url: Option<Url>,
+    /// PEM bundle of the CAs trusted along with the public ones, for endpoints
+    /// fronted by a private PKI
+    pub tls_ca_bundle: Option<PathBuf>,
+    /// PEM certificate presented to the endpoints requesting client
+    /// authentication, along with its PKCS#8 key
+    pub tls_client_cert: Option<PathBuf>,
+    pub tls_client_key: Option<PathBuf>,
+    /// Loaded from the files above
+    #[serde(skip)]
+    pub tls: TlsSettings,
+    /// Filter directives of the logs, e.g. `info,blob_indexer=debug`, used
+    /// when `RUST_LOG` isn't set. Reloaded along with the config file
+    pub log_level: Option<String>,
+    #[serde(default = "default_tracing_debug_sample_rate")]
+    pub tracing_debug_sample_rate: f64,
+    /// Length request bodies are truncated to in logs
+    #[serde(default = "default_tracing_max_body_length")]
+    pub tracing_max_body_length: usize,
+    pub metrics_server_address: Option<SocketAddr>,
+    /// Local address the control server of the running indexer listens on
+    pub control_server_address: Option<SocketAddr>,
+    /// Slots behind the beacon head above which an alert is raised
+    pub max_head_lag_slots: Option<u32>,
+    /// URL the head lag alerts are posted to, along with Sentry
+    pub head_lag_webhook_url: Option<Url>,
+    pub beacon_read_backoff: Option<BackoffProfileOverride>,
+    pub blob_sidecar_read_backoff: Option<BackoffProfileOverride>,
+    pub execution_read_backoff: Option<BackoffProfileOverride>,
+    pub blobscan_read_backoff: Option<BackoffProfileOverride>,
+    pub blobscan_write_backoff: Option<BackoffProfileOverride>,
+}
+
+fn default_network_name() -> String {
+    "mainnet".to_string()
+}
+
+fn default_tracing_debug_sample_rate() -> f64 {
+    1.0
+}
+
+fn default_tracing_max_body_length() -> usize {
+    DEFAULT_MAX_LOGGED_BODY_LENGTH
 }
 
-fn default_network() -> Network {
-    Network::Mainnet
+fn default_otel_service_name() -> String {
+    "blob-indexer".to_string()
 }
 
 fn default_blobscan_api_endpoint() -> String {
@@ -36,21 +143,98 @@ fn default_execution_node_endpoint() -> String {
 
 impl Environment {
     pub fn from_env() -> Result<Self, envy::Error> {
-        match envy::from_env::<Environment>() {
-            Ok(config) => {
+        Self::from_vars(std::env::vars())
+    }
+
+    /// Loads the environment from the given variables, the later ones
+    /// overriding the earlier ones with the same name.
+    pub fn from_vars(
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, envy::Error> {
+        let vars = vars
+            .into_iter()
+            .map(|(name, value)| (name.to_lowercase(), value))
+            .collect::<BTreeMap<_, _>>();
+
+        match envy::from_iter::<_, Environment>(vars) {
+            Ok(mut config) => {
                 if config.beacon_node_endpoint.is_empty() {
                     return Err(MissingValue("BEACON_NODE_ENDPOINT"));
                 } else if config.blobscan_api_endpoint.is_empty() {
                     return Err(MissingValue("BLOBSCAN_API_ENDPOINT"));
                 } else if config.execution_node_endpoint.is_empty() {
                     return Err(MissingValue("EXECUTION_NODE_ENDPOINT"));
-                } else if config.secret_key.is_empty() {
+                } else if config.secret_key.is_empty() && config.blobscan_api_auth_header.is_none()
+                {
                     return Err(MissingValue("SECRET_KEY"));
                 }
 
+                let jwt = config.blobscan_jwt();
+
+                if jwt.refresh_interval <= TimeDelta::zero() {
+                    return Err(Custom(
+                        "BLOBSCAN_JWT_REFRESH_INTERVAL must be positive".to_string(),
+                    ));
+                } else if jwt.safety_margin.unwrap_or(DEFAULT_SAFETY_MARGIN) >= jwt.refresh_interval
+                {
+                    return Err(Custom(
+                        "BLOBSCAN_JWT_SAFETY_MARGIN must be shorter than the refresh interval"
+                            .to_string(),
+                    ));
+                }
+
+                let presets = match &config.networks_file {
+                    Some(path) => NetworkPresets::from_file(path)
+                        .map_err(|err| Custom(format!("{}: {err}", path.display())))?,
+                    None => NetworkPresets::default(),
+                };
+
+                config.network = Network::resolve(&config.network_name, &presets)
+                    .map_err(|err| Custom(err.to_string()))?;
+                config.tls = TlsSettings::load(
+                    config.tls_ca_bundle.as_deref(),
+                    config.tls_client_cert.as_deref(),
+                    config.tls_client_key.as_deref(),
+                )
+                .map_err(|err| Custom(err.to_string()))?;
+
                 Ok(config)
             }
             Err(err) => Err(err),
         }
     }
+
+    pub fn user_agent(&self) -> String {
+        match &self.http_user_agent {
+            Some(user_agent) => user_agent.clone(),
+            None => default_user_agent(&self.network.to_string()),
+        }
+    }
+
+    /// How the indexer authenticates to the Blobscan API, through the auth
+    /// header when it's set or JWTs otherwise
+    pub fn blobscan_auth(&self) -> BlobscanAuth {
+        match &self.blobscan_api_auth_header {
+            Some(header) => BlobscanAuth::Header(header.clone()),
+            None => BlobscanAuth::Jwt(self.blobscan_jwt()),
+        }
+    }
+
+    /// Settings of the JWTs authenticating the indexer to the Blobscan API
+    pub fn blobscan_jwt(&self) -> JWTManagerConfig {
+        // Capped so the expiration date of the tokens can't overflow
+        let seconds = |seconds: u64| TimeDelta::seconds(seconds.min(u32::MAX.into()) as i64);
+
+        JWTManagerConfig {
+            secret_key: self.secret_key.clone(),
+            refresh_interval: self
+                .blobscan_jwt_refresh_interval
+                .map_or(DEFAULT_REFRESH_INTERVAL, seconds),
+            safety_margin: Some(
+                self.blobscan_jwt_safety_margin
+                    .map_or(DEFAULT_SAFETY_MARGIN, seconds),
+            ),
+            algorithm: self.blobscan_jwt_algorithm,
+        }
+    }
 }
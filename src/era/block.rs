@@ -0,0 +1,224 @@
+use std::ops::Range;
+
+use alloy::{
+    consensus::{Transaction as _, TxEnvelope},
+    eips::eip2718::Decodable2718,
+    primitives::{Address, Bloom, Bytes, B256, U256},
+    rpc::types::{
+        Block as ExecutionBlock, BlockTransactions, Header, Transaction as ExecutionTransaction,
+    },
+};
+
+use crate::clients::beacon::types::{
+    Block as BeaconBlock, BlockBody, BlockMessage, ExecutionPayload,
+};
+
+use super::EraError;
+
+/// Size of the fixed part of the block bodies from the fork the execution
+/// payload was added at (Bellatrix), and from the one the blob KZG commitments
+/// were (Deneb) and the one the execution requests were (Electra). Bodies are
+/// told apart by it, as it's the offset of their first variable-size field.
+const BELLATRIX_BODY_SIZE: usize = 384;
+const DENEB_BODY_SIZE: usize = 392;
+const ELECTRA_BODY_SIZE: usize = 396;
+const KZG_COMMITMENT_SIZE: usize = 48;
+
+/// Beacon block read from an era file, along with its execution block when it
+/// has blobs, as the slots processor gets them from the nodes.
+#[derive(Debug)]
+pub struct EraBlock {
+    pub beacon_block: BeaconBlock,
+    pub execution_block: Option<ExecutionBlock<ExecutionTransaction>>,
+}
+
+/// Decodes an SSZ `SignedBeaconBlock`, reading only the fields the indexer
+/// needs.
+pub fn decode_signed_block(signed_block: &[u8]) -> Result<EraBlock, EraError> {
+    let message = variable_field(signed_block, 0, signed_block.len())?;
+    let proposer_index = u64_at(message, 8)?;
+    let body = variable_field(message, 80, message.len())?;
+    let body_size = offset_at(body, 200)?;
+
+    if body_size < BELLATRIX_BODY_SIZE {
+        return Ok(EraBlock {
+            beacon_block: beacon_block(proposer_index, None, None)?,
+            execution_block: None,
+        });
+    }
+
+    let payload_end = match body_size > BELLATRIX_BODY_SIZE {
+        true => offset_at(body, 384)?,
+        false => body.len(),
+    };
+    let payload = variable_field(body, 380, payload_end)?;
+    let commitments = match body_size >= DENEB_BODY_SIZE {
+        true => {
+            let commitments_end = match body_size >= ELECTRA_BODY_SIZE {
+                true => offset_at(body, 392)?,
+                false => body.len(),
+            };
+
+            variable_field(body, 388, commitments_end)?
+                .chunks(KZG_COMMITMENT_SIZE)
+                .map(|commitment| match commitment.len() {
+                    KZG_COMMITMENT_SIZE => Ok(format!("0x{}", hex::encode(commitment))),
+                    _ => Err(EraError::InvalidBlock("truncated blob KZG commitment")),
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        }
+        false => vec![],
+    };
+    let execution_payload = ExecutionPayload {
+        block_hash: B256::from_slice(field(payload, 472..504)?),
+        block_number: u64_at(payload, 404)?
+            .try_into()
+            .map_err(|_| EraError::InvalidBlock("execution block number out of range"))?,
+    };
+    // Blocks without blobs are skipped, so their transactions aren't decoded
+    let execution_block = match commitments.is_empty() {
+        true => None,
+        false => Some(decode_execution_payload(payload)?),
+    };
+
+    Ok(EraBlock {
+        beacon_block: beacon_block(proposer_index, Some(execution_payload), Some(commitments))?,
+        execution_block,
+    })
+}
+
+fn beacon_block(
+    proposer_index: u64,
+    execution_payload: Option<ExecutionPayload>,
+    blob_kzg_commitments: Option<Vec<String>>,
+) -> Result<BeaconBlock, EraError> {
+    Ok(BeaconBlock {
+        message: BlockMessage {
+            proposer_index: proposer_index
+                .try_into()
+                .map_err(|_| EraError::InvalidBlock("proposer index out of range"))?,
+            body: BlockBody {
+                execution_payload,
+                blob_kzg_commitments,
+            },
+        },
+    })
+}
+
+/// Decodes a Deneb `ExecutionPayload`, unchanged by the later forks, into the
+/// block the execution node would return.
+fn decode_execution_payload(
+    payload: &[u8],
+) -> Result<ExecutionBlock<ExecutionTransaction>, EraError> {
+    let number = u64_at(payload, 404)?;
+    let base_fee_per_gas = u64::try_from(U256::from_le_slice(field(payload, 440..472)?))
+        .map_err(|_| EraError::InvalidBlock("base fee out of range"))?;
+    let block_hash = B256::from_slice(field(payload, 472..504)?);
+    let transactions_start = offset_at(payload, 504)?;
+    let withdrawals_start = offset_at(payload, 508)?;
+    let extra_data = field(payload, offset_at(payload, 436)?..transactions_start)?;
+    let transactions = variable_list(field(payload, transactions_start..withdrawals_start)?)?
+        .into_iter()
+        .enumerate()
+        .map(|(index, mut transaction)| {
+            let envelope = TxEnvelope::decode_2718(&mut transaction)?;
+            // Mined transactions report the price they paid
+            let gas_price = envelope.gas_price().unwrap_or_else(|| {
+                base_fee_per_gas as u128
+                    + envelope
+                        .effective_tip_per_gas(base_fee_per_gas)
+                        .unwrap_or_default()
+            });
+
+            Ok(ExecutionTransaction {
+                hash: *envelope.tx_hash(),
+                nonce: envelope.nonce(),
+                block_hash: Some(block_hash),
+                block_number: Some(number),
+                transaction_index: Some(index as u64),
+                from: envelope.recover_signer()?,
+                to: envelope.to(),
+                value: envelope.value(),
+                gas_price: Some(gas_price),
+                gas: envelope.gas_limit(),
+                max_fee_per_gas: envelope
+                    .max_priority_fee_per_gas()
+                    .map(|_| envelope.max_fee_per_gas()),
+                max_priority_fee_per_gas: envelope.max_priority_fee_per_gas(),
+                max_fee_per_blob_gas: envelope.max_fee_per_blob_gas(),
+                input: envelope.input().clone(),
+                chain_id: envelope.chain_id(),
+                blob_versioned_hashes: envelope.blob_versioned_hashes().map(<[B256]>::to_vec),
+                access_list: envelope.access_list().cloned(),
+                transaction_type: Some(envelope.ty()),
+                ..Default::default()
+            })
+        })
+        .collect::<Result<Vec<_>, EraError>>()?;
+
+    Ok(ExecutionBlock {
+        header: Header {
+            hash: block_hash,
+            parent_hash: B256::from_slice(field(payload, 0..32)?),
+            miner: Address::from_slice(field(payload, 32..52)?),
+            state_root: B256::from_slice(field(payload, 52..84)?),
+            receipts_root: B256::from_slice(field(payload, 84..116)?),
+            logs_bloom: Bloom::from_slice(field(payload, 116..372)?),
+            mix_hash: Some(B256::from_slice(field(payload, 372..404)?)),
+            number,
+            gas_limit: u64_at(payload, 412)?,
+            gas_used: u64_at(payload, 420)?,
+            timestamp: u64_at(payload, 428)?,
+            extra_data: Bytes::copy_from_slice(extra_data),
+            base_fee_per_gas: Some(base_fee_per_gas),
+            blob_gas_used: Some(u64_at(payload, 512)?),
+            excess_blob_gas: Some(u64_at(payload, 520)?),
+            ..Default::default()
+        },
+        transactions: BlockTransactions::Full(transactions),
+        ..Default::default()
+    })
+}
+
+fn field(bytes: &[u8], range: Range<usize>) -> Result<&[u8], EraError> {
+    bytes
+        .get(range)
+        .ok_or(EraError::InvalidBlock("field out of bounds"))
+}
+
+fn u64_at(bytes: &[u8], position: usize) -> Result<u64, EraError> {
+    Ok(u64::from_le_bytes(
+        field(bytes, position..position + 8)?.try_into().unwrap(),
+    ))
+}
+
+fn offset_at(bytes: &[u8], position: usize) -> Result<usize, EraError> {
+    Ok(u32::from_le_bytes(field(bytes, position..position + 4)?.try_into().unwrap()) as usize)
+}
+
+/// Returns a variable-size field, spanning from the offset at the given
+/// position to the given end, the offset of the next one.
+fn variable_field(bytes: &[u8], position: usize, end: usize) -> Result<&[u8], EraError> {
+    field(bytes, offset_at(bytes, position)?..end)
+}
+
+/// Splits a list of variable-size items, led by the offset of every item.
+fn variable_list(bytes: &[u8]) -> Result<Vec<&[u8]>, EraError> {
+    if bytes.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let items = offset_at(bytes, 0)? / 4;
+    let offsets = (0..items)
+        .map(|item| offset_at(bytes, item * 4))
+        .chain([Ok(bytes.len())])
+        .collect::<Result<Vec<_>, _>>()?;
+
+    offsets
+        .windows(2)
+        .map(|item| match item[0] <= item[1] {
+            true => field(bytes, item[0]..item[1]),
+            false => Err(EraError::InvalidBlock("unordered list offsets")),
+        })
+        .collect()
+}
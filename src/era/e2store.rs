@@ -0,0 +1,119 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use super::EraError;
+
+/// Type of the entries holding a snappy-compressed SSZ `SignedBeaconBlock`.
+pub const COMPRESSED_SIGNED_BEACON_BLOCK: [u8; 2] = [0x01, 0x00];
+/// Type of the entries indexing the position of the other entries by slot.
+pub const SLOT_INDEX: [u8; 2] = [0x69, 0x32];
+
+/// Size of the header preceding every entry: its type, the length of its data
+/// as a little-endian u32 and two reserved bytes.
+const HEADER_SIZE: u64 = 8;
+/// Size of the slot index of the era state, the last entry of every era file.
+const STATE_INDEX_SIZE: u64 = HEADER_SIZE + 3 * 8;
+
+pub struct Entry {
+    pub entry_type: [u8; 2],
+    pub data: Vec<u8>,
+}
+
+/// Positions of the blocks of consecutive slots within an era file.
+#[derive(Debug)]
+pub struct SlotIndex {
+    pub start_slot: u64,
+    /// File position of every slot's block, or `None` for missed slots
+    pub positions: Vec<Option<u64>>,
+}
+
+pub fn read_entry<R: Read>(reader: &mut R) -> Result<Entry, EraError> {
+    let mut header = [0; HEADER_SIZE as usize];
+
+    reader.read_exact(&mut header)?;
+
+    let length = u32::from_le_bytes(header[2..6].try_into().unwrap());
+
+    if header[6..8] != [0, 0] {
+        return Err(EraError::InvalidFile("non-zero reserved header bytes"));
+    }
+
+    // The length isn't trusted to allocate the data, as it may be corrupt
+    let mut data = vec![];
+
+    reader.take(length as u64).read_to_end(&mut data)?;
+
+    if data.len() != length as usize {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+
+    Ok(Entry {
+        entry_type: [header[0], header[1]],
+        data,
+    })
+}
+
+/// Reads the block index of an era file, found right before the state index
+/// closing it. The era holding the genesis state has no blocks, nor index.
+pub fn read_block_index<R: Read + Seek>(reader: &mut R) -> Result<Option<SlotIndex>, EraError> {
+    let file_size = reader.seek(SeekFrom::End(0))?;
+
+    if file_size < STATE_INDEX_SIZE + HEADER_SIZE + 3 * 8 {
+        return Ok(None);
+    }
+
+    reader.seek(SeekFrom::Start(file_size - STATE_INDEX_SIZE - 8))?;
+
+    let count = read_u64(reader)?;
+    let index_size = count
+        .checked_mul(8)
+        .and_then(|offsets_size| offsets_size.checked_add(HEADER_SIZE + 2 * 8))
+        .filter(|index_size| *index_size <= file_size - STATE_INDEX_SIZE);
+    let Some(index_size) = index_size else {
+        return Ok(None);
+    };
+    let index_position = file_size - STATE_INDEX_SIZE - index_size;
+
+    reader.seek(SeekFrom::Start(index_position))?;
+
+    let entry = read_entry(reader)?;
+
+    if entry.entry_type != SLOT_INDEX {
+        return Ok(None);
+    }
+
+    if entry.data.len() as u64 != index_size - HEADER_SIZE {
+        return Err(EraError::InvalidFile(
+            "slot index length doesn't match its count",
+        ));
+    }
+
+    let values = entry
+        .data
+        .chunks_exact(8)
+        .map(|value| i64::from_le_bytes(value.try_into().unwrap()))
+        .collect::<Vec<_>>();
+    // Offsets are relative to the index, a zero one meaning the slot is empty
+    let positions = values[1..values.len() - 1]
+        .iter()
+        .map(|offset| match offset {
+            0 => Ok(None),
+            offset => index_position
+                .checked_add_signed(*offset)
+                .map(Some)
+                .ok_or(EraError::InvalidFile("slot index offset out of bounds")),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Some(SlotIndex {
+        start_slot: values[0] as u64,
+        positions,
+    }))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut value = [0; 8];
+
+    reader.read_exact(&mut value)?;
+
+    Ok(u64::from_le_bytes(value))
+}
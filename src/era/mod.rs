@@ -0,0 +1,292 @@
+//! Offline source of the blocks of backfilled slots, read from the beacon era
+//! files of a directory instead of being requested from the nodes.
+//!
+//! Era files hold every finalized beacon block of an era (8192 slots), along
+//! with the execution payload they carry, so the execution blocks can be built
+//! from them too. Execution era1 exports aren't read, as they only cover
+//! pre-merge blocks, which have no blobs. Era files don't hold blob sidecars,
+//! so blobs are still fetched from the beacon node.
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use alloy::{eips::eip2718::Eip2718Error, primitives::SignatureError};
+use tracing::{info, warn};
+
+use self::e2store::{read_block_index, read_entry, COMPRESSED_SIGNED_BEACON_BLOCK};
+
+pub use self::block::{decode_signed_block, EraBlock};
+
+mod block;
+mod e2store;
+
+#[derive(Debug, thiserror::Error)]
+pub enum EraError {
+    #[error("failed to read era file")]
+    Io(#[from] io::Error),
+    #[error("invalid era file: {0}")]
+    InvalidFile(&'static str),
+    #[error("invalid beacon block: {0}")]
+    InvalidBlock(&'static str),
+    #[error("failed to decode transaction")]
+    InvalidTransaction(#[from] Eip2718Error),
+    #[error("failed to recover transaction signer")]
+    InvalidSignature(#[from] SignatureError),
+    #[error("failed to read slot {slot} from era file {path}")]
+    SlotReadFailure {
+        slot: u32,
+        path: PathBuf,
+        #[source]
+        error: Box<EraError>,
+    },
+}
+
+/// Block of a slot covered by the era files.
+#[derive(Debug)]
+pub enum EraSlot {
+    /// The slot was missed
+    Missed,
+    Block(Box<EraBlock>),
+}
+
+#[derive(Debug)]
+struct EraFile {
+    path: PathBuf,
+    start_slot: u64,
+    positions: Vec<Option<u64>>,
+}
+
+/// Index of the blocks held by the era files of a directory, read on demand.
+#[derive(Debug)]
+pub struct EraArchive {
+    files: BTreeMap<u64, EraFile>,
+}
+
+impl EraArchive {
+    /// Indexes the `.era` files of the directory. Files without blocks, like
+    /// the genesis one, are skipped.
+    pub fn open(dir: &Path) -> Result<Self, EraError> {
+        let mut files = BTreeMap::new();
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+
+            if path.extension().is_none_or(|extension| extension != "era") {
+                continue;
+            }
+
+            match read_block_index(&mut BufReader::new(File::open(&path)?)) {
+                Ok(Some(index)) => {
+                    files.insert(
+                        index.start_slot,
+                        EraFile {
+                            path,
+                            start_slot: index.start_slot,
+                            positions: index.positions,
+                        },
+                    );
+                }
+                Ok(None) => {}
+                Err(error) => warn!(path = %path.display(), ?error, "Skipping invalid era file"),
+            }
+        }
+
+        let archive = Self { files };
+
+        if let Some((first_slot, last_slot)) = archive.slots() {
+            info!(
+                files = archive.files.len(),
+                first_slot, last_slot, "Era files indexed"
+            );
+        }
+
+        Ok(archive)
+    }
+
+    /// First and last slots covered by the era files.
+    pub fn slots(&self) -> Option<(u64, u64)> {
+        let first = self.files.values().next()?;
+        let last = self.files.values().next_back()?;
+
+        Some((
+            first.start_slot,
+            last.start_slot + last.positions.len() as u64 - 1,
+        ))
+    }
+
+    /// Reads the block of a slot, or returns `None` if no era file covers it.
+    pub fn block(&self, slot: u32) -> Result<Option<EraSlot>, EraError> {
+        let slot_number = slot as u64;
+        let Some((_, file)) = self.files.range(..=slot_number).next_back() else {
+            return Ok(None);
+        };
+        let Some(position) = file.positions.get((slot_number - file.start_slot) as usize) else {
+            return Ok(None);
+        };
+        let Some(position) = position else {
+            return Ok(Some(EraSlot::Missed));
+        };
+
+        read_block(&file.path, *position)
+            .map(|block| Some(EraSlot::Block(Box::new(block))))
+            .map_err(|error| EraError::SlotReadFailure {
+                slot,
+                path: file.path.clone(),
+                error: Box::new(error),
+            })
+    }
+}
+
+fn read_block(path: &Path, position: u64) -> Result<EraBlock, EraError> {
+    let mut file = File::open(path)?;
+
+    file.seek(SeekFrom::Start(position))?;
+
+    let entry = read_entry(&mut BufReader::new(file))?;
+
+    if entry.entry_type != COMPRESSED_SIGNED_BEACON_BLOCK {
+        return Err(EraError::InvalidFile("slot index doesn't point to a block"));
+    }
+
+    let mut signed_block = vec![];
+
+    snap::read::FrameDecoder::new(entry.data.as_slice()).read_to_end(&mut signed_block)?;
+
+    decode_signed_block(&signed_block)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Write};
+
+    use alloy::primitives::B256;
+
+    use super::{EraArchive, EraSlot};
+
+    const START_SLOT: u64 = 8_626_176;
+
+    fn offset(value: usize) -> [u8; 4] {
+        (value as u32).to_le_bytes()
+    }
+
+    /// Deneb `SignedBeaconBlock` with a blob KZG commitment and an execution
+    /// payload without transactions.
+    fn signed_block() -> Vec<u8> {
+        let mut payload = vec![0; 528];
+        payload[404..412].copy_from_slice(&19_426_587u64.to_le_bytes());
+        payload[472..504].copy_from_slice(&[0xaa; 32]);
+        payload[436..440].copy_from_slice(&offset(528));
+        payload[504..508].copy_from_slice(&offset(528));
+        payload[508..512].copy_from_slice(&offset(528));
+
+        let mut body = vec![0; 392];
+        body[200..204].copy_from_slice(&offset(392));
+        body[380..384].copy_from_slice(&offset(392));
+        body[384..388].copy_from_slice(&offset(392 + payload.len()));
+        body[388..392].copy_from_slice(&offset(392 + payload.len()));
+        body.extend(payload);
+        body.extend([0xbb; 48]);
+
+        let mut message = vec![0; 84];
+        message[0..8].copy_from_slice(&START_SLOT.to_le_bytes());
+        message[8..16].copy_from_slice(&1_234u64.to_le_bytes());
+        message[80..84].copy_from_slice(&offset(84));
+        message.extend(body);
+
+        let mut signed_block = offset(100).to_vec();
+        signed_block.extend([0; 96]);
+        signed_block.extend(message);
+
+        signed_block
+    }
+
+    fn entry(entry_type: [u8; 2], data: &[u8]) -> Vec<u8> {
+        let mut entry = entry_type.to_vec();
+        entry.extend((data.len() as u32).to_le_bytes());
+        entry.extend([0, 0]);
+        entry.extend(data);
+
+        entry
+    }
+
+    fn slot_index(start_slot: u64, offsets: &[i64]) -> Vec<u8> {
+        let mut data = start_slot.to_le_bytes().to_vec();
+        data.extend(offsets.iter().flat_map(|offset| offset.to_le_bytes()));
+        data.extend((offsets.len() as u64).to_le_bytes());
+
+        entry([0x69, 0x32], &data)
+    }
+
+    /// Era file holding the block of its first slot, the second one being
+    /// missed.
+    fn era_file() -> Vec<u8> {
+        let mut encoder = snap::write::FrameEncoder::new(vec![]);
+        encoder.write_all(&signed_block()).unwrap();
+
+        let mut file = entry([0x65, 0x32], &[]);
+        let block_position = file.len();
+        file.extend(entry([0x01, 0x00], &encoder.into_inner().unwrap()));
+
+        let index_position = file.len();
+        file.extend(slot_index(
+            START_SLOT,
+            &[block_position as i64 - index_position as i64, 0],
+        ));
+        file.extend(slot_index(START_SLOT + 2, &[-(index_position as i64)]));
+
+        file
+    }
+
+    #[test]
+    fn blocks_are_read_from_era_files() {
+        let dir = std::env::temp_dir().join(format!("era-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("mainnet-01053-00000000.era"), era_file()).unwrap();
+
+        let archive = EraArchive::open(&dir).unwrap();
+        let slots = (0..3)
+            .map(|slot| archive.block(START_SLOT as u32 + slot).unwrap())
+            .collect::<Vec<_>>();
+        let _ = fs::remove_dir_all(&dir);
+
+        let [Some(EraSlot::Block(block)), missed_slot, uncovered_slot] = slots.as_slice() else {
+            panic!("expected the block of the first slot");
+        };
+        let message = &block.beacon_block.message;
+        let execution_payload = message.body.execution_payload.as_ref().unwrap();
+        let execution_block = block.execution_block.as_ref().unwrap();
+
+        assert_eq!(archive.slots(), Some((START_SLOT, START_SLOT + 1)));
+        assert_eq!(message.proposer_index, 1_234);
+        assert_eq!(execution_payload.block_hash, B256::repeat_byte(0xaa));
+        assert_eq!(execution_payload.block_number, 19_426_587);
+        assert_eq!(
+            message.body.blob_kzg_commitments,
+            Some(vec![format!("0x{}", "bb".repeat(48))])
+        );
+        assert_eq!(execution_block.header.number, 19_426_587);
+        assert!(execution_block.transactions.is_empty());
+        assert!(matches!(missed_slot, Some(EraSlot::Missed)));
+        assert!(uncovered_slot.is_none());
+    }
+
+    #[test]
+    fn era_files_with_a_truncated_slot_index_are_skipped() {
+        let dir = std::env::temp_dir().join(format!("era-truncated-{}", std::process::id()));
+        let mut file = era_file();
+        // Shortens the block index, two slots long, to its start slot
+        let index_position = file.len() - 32 - 40;
+        file[index_position + 2..index_position + 6].copy_from_slice(&8u32.to_le_bytes());
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("mainnet-01053-00000000.era"), file).unwrap();
+
+        let archive = EraArchive::open(&dir);
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(archive.unwrap().slots(), None);
+    }
+}
@@ -0,0 +1,305 @@
+use alloy::transports::{RpcError, TransportErrorKind};
+
+use crate::{
+    clients::common::{ClientError, NumericOrTextCode},
+    config::ConfigError,
+    indexer::{
+        error::{
+            EventHandlerError, HistoricalIndexingError, IndexerError, IndexingError,
+            LiveIndexingError,
+        },
+        event_handlers::{
+            finalized_checkpoint::FinalizedCheckpointEventHandlerError, head::HeadEventHandlerError,
+        },
+    },
+    slots_processor::error::{SlotProcessingError, SlotsProcessorError},
+    synchronizer::error::SynchronizerError,
+};
+
+/// Exit code of the process, telling orchestrators and alerting what kind of
+/// failure stopped the indexer so they can react to each one differently, e.g.
+/// not restarting it until its configuration or credentials are fixed.
+///
+/// Invalid command line arguments exit with 2, as reported by `clap`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitCode {
+    /// Any failure not covered by a more specific code
+    Failure = 1,
+    /// Missing or invalid environment variables, mapping files or manifests, or
+    /// a Blobscan API version not supporting this indexer
+    Config = 10,
+    /// A service rejected the configured credentials
+    Auth = 11,
+    /// The beacon node can't be reached
+    BeaconUnreachable = 12,
+    /// The Blobscan API can't be reached
+    BlobscanUnreachable = 13,
+    /// The slots of a reorg couldn't be handled
+    ReorgFailure = 14,
+    /// A syncing task panicked
+    TaskPanic = 15,
+}
+
+#[derive(Clone, Copy)]
+enum Service {
+    Beacon,
+    Blobscan,
+}
+
+impl ExitCode {
+    /// Returns the exit code of the first error of the chain it knows about.
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        for cause in error.chain() {
+            if cause.downcast_ref::<envy::Error>().is_some()
+                || cause.downcast_ref::<ConfigError>().is_some()
+            {
+                return ExitCode::Config;
+            }
+
+            if let Some(error) = cause.downcast_ref::<IndexerError>() {
+                return indexer_exit_code(error);
+            }
+
+            if let Some(error) = cause.downcast_ref::<ClientError>() {
+                return client_exit_code(error, None);
+            }
+        }
+
+        ExitCode::Failure
+    }
+
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+}
+
+fn indexer_exit_code(error: &IndexerError) -> ExitCode {
+    match error {
+        IndexerError::CreationFailure(_)
+        | IndexerError::JobManifestLoadFailure { .. }
+        | IndexerError::RollupMappingLoadFailure { .. }
+        | IndexerError::TxCategoryMappingLoadFailure { .. }
+        | IndexerError::IndexedSlotsOpenFailure { .. }
+        | IndexerError::IntentLogOpenFailure(_)
+        | IndexerError::ReorgLogOpenFailure { .. }
+        | IndexerError::IncompatibleBlobscanApi { .. }
+        | IndexerError::UnknownDencunForkSlot
+        | IndexerError::ReorgConfirmationUnavailable => ExitCode::Config,
+        IndexerError::SyncingTaskError(error) => indexing_exit_code(error),
+        IndexerError::BlobscanSyncStateRetrievalError(error)
+        | IndexerError::BlobscanApiVersionRetrievalError(error) => {
+            client_exit_code(error, Some(Service::Blobscan))
+        }
+        IndexerError::BlockHeaderRetrievalError(_, error) => {
+            client_exit_code(error, Some(Service::Beacon))
+        }
+        IndexerError::ReindexingFailure(error) => slots_processor_exit_code(error),
+        IndexerError::SinkFlushFailure(error) => client_exit_code(error, None),
+        IndexerError::JobFailed { error, .. } => indexer_exit_code(error),
+        IndexerError::SyncingTaskMessageSendFailure(_)
+        | IndexerError::BlockHeaderNotFound(_)
+        | IndexerError::JobManifestSaveFailure { .. }
+        | IndexerError::IntentLogConfirmFailure(_)
+        | IndexerError::JobInterrupted { .. } => ExitCode::Failure,
+    }
+}
+
+fn indexing_exit_code(error: &IndexingError) -> ExitCode {
+    match error {
+        IndexingError::HistoricalIndexingFailure(HistoricalIndexingError::SynchronizerError(
+            error,
+        )) => synchronizer_exit_code(error),
+        IndexingError::LiveIndexingError(error) => match error {
+            LiveIndexingError::BeaconEventsConnectionFailure(error) => match error.as_ref() {
+                reqwest_eventsource::Error::InvalidStatusCode(status, _)
+                    if is_auth_status(status.as_u16()) =>
+                {
+                    ExitCode::Auth
+                }
+                reqwest_eventsource::Error::Transport(error)
+                    if error.is_connect() || error.is_timeout() =>
+                {
+                    ExitCode::BeaconUnreachable
+                }
+                _ => ExitCode::Failure,
+            },
+            LiveIndexingError::BeaconEventsSubscriptionError(error) => {
+                client_exit_code(error, Some(Service::Beacon))
+            }
+            LiveIndexingError::UnexpectedBeaconEvent(_) => ExitCode::Failure,
+            LiveIndexingError::BeaconEventHandlingError(error) => match error {
+                EventHandlerError::HeadEventHandlerError(error) => match error {
+                    HeadEventHandlerError::BlobscanReorgedSlotsFailure(_) => ExitCode::ReorgFailure,
+                    HeadEventHandlerError::BlockHeaderRetrievalError(_, error) => {
+                        client_exit_code(error, Some(Service::Beacon))
+                    }
+                    HeadEventHandlerError::BlobscanSyncStateUpdateError(error) => {
+                        client_exit_code(error, Some(Service::Blobscan))
+                    }
+                    HeadEventHandlerError::BlockSyncedError(error) => synchronizer_exit_code(error),
+                    HeadEventHandlerError::EventDeserializationFailure(_)
+                    | HeadEventHandlerError::BlockHeaderNotFound(_) => ExitCode::Failure,
+                },
+                EventHandlerError::FinalizedCheckpointHandlerError(error) => match error {
+                    FinalizedCheckpointEventHandlerError::BlockRetrievalError(_, error) => {
+                        client_exit_code(error, Some(Service::Beacon))
+                    }
+                    FinalizedCheckpointEventHandlerError::BlobscanFinalizedBlockUpdateFailure(
+                        error,
+                    ) => client_exit_code(error, Some(Service::Blobscan)),
+                    FinalizedCheckpointEventHandlerError::FinalizedBlocksSyncFailure(error) => {
+                        synchronizer_exit_code(error)
+                    }
+                    FinalizedCheckpointEventHandlerError::EventDeserializationFailure(_)
+                    | FinalizedCheckpointEventHandlerError::BlockNotFound(_) => ExitCode::Failure,
+                },
+            },
+        },
+    }
+}
+
+fn synchronizer_exit_code(error: &SynchronizerError) -> ExitCode {
+    match error {
+        SynchronizerError::FailedParallelSlotsProcessing { chunk_errors, .. } => {
+            let exit_codes = chunk_errors
+                .0
+                .iter()
+                .map(slots_processor_exit_code)
+                .collect::<Vec<_>>();
+
+            [ExitCode::TaskPanic, ExitCode::Auth]
+                .into_iter()
+                .find(|exit_code| exit_codes.contains(exit_code))
+                .unwrap_or(ExitCode::Failure)
+        }
+        SynchronizerError::FailedBlockIdResolution { error, .. } => {
+            client_exit_code(error, Some(Service::Beacon))
+        }
+        SynchronizerError::FailedSlotCheckpointSave { error, .. } => {
+            client_exit_code(error, Some(Service::Blobscan))
+        }
+        SynchronizerError::FailedSlotsProcessing(error) => slots_processor_exit_code(error),
+        SynchronizerError::FailedIntentLogUpdate(_) | SynchronizerError::Other(_) => {
+            ExitCode::Failure
+        }
+    }
+}
+
+/// Slots get processed with every service, so only failures not tied to one
+/// of them are told apart.
+fn slots_processor_exit_code(error: &SlotsProcessorError) -> ExitCode {
+    match error {
+        SlotsProcessorError::FailedSlotsProcessing { error, .. } => match error {
+            SlotProcessingError::ClientError(error) => client_exit_code(error, None),
+            SlotProcessingError::Provider(RpcError::Transport(TransportErrorKind::HttpError(
+                error,
+            ))) if is_auth_status(error.status) => ExitCode::Auth,
+            _ => ExitCode::Failure,
+        },
+        SlotsProcessorError::WorkerPanic(_) | SlotsProcessorError::SlotPanic { .. } => {
+            ExitCode::TaskPanic
+        }
+        SlotsProcessorError::Other(_) => ExitCode::Failure,
+    }
+}
+
+fn client_exit_code(error: &ClientError, service: Option<Service>) -> ExitCode {
+    let is_auth_failure = match error {
+        ClientError::ApiError(error) => match &error.code {
+            NumericOrTextCode::Number(code) => u16::try_from(*code).is_ok_and(is_auth_status),
+            NumericOrTextCode::String(code) => {
+                code.parse().is_ok_and(is_auth_status)
+                    || matches!(code.as_str(), "UNAUTHORIZED" | "FORBIDDEN")
+            }
+        },
+        ClientError::Reqwest(error) => error
+            .status()
+            .is_some_and(|status| is_auth_status(status.as_u16())),
+        ClientError::Unauthorized { .. } => true,
+        _ => false,
+    };
+
+    if is_auth_failure {
+        return ExitCode::Auth;
+    }
+
+    let is_unreachable =
+        matches!(error, ClientError::Reqwest(error) if error.is_connect() || error.is_timeout());
+
+    match service {
+        Some(Service::Beacon) if is_unreachable => ExitCode::BeaconUnreachable,
+        Some(Service::Blobscan) if is_unreachable => ExitCode::BlobscanUnreachable,
+        _ => ExitCode::Failure,
+    }
+}
+
+fn is_auth_status(status: u16) -> bool {
+    status == 401 || status == 403
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use anyhow::anyhow;
+
+    use crate::{
+        clients::common::{ClientError, ErrorResponse, NumericOrTextCode},
+        indexer::{
+            error::{EventHandlerError, IndexerError, IndexingError, LiveIndexingError},
+            event_handlers::head::HeadEventHandlerError,
+        },
+    };
+
+    use super::ExitCode;
+
+    fn api_error(code: usize) -> ClientError {
+        ClientError::ApiError(ErrorResponse {
+            code: NumericOrTextCode::Number(code),
+            message: None,
+            issues: vec![],
+        })
+    }
+
+    #[test]
+    fn missing_env_variables_are_config_errors() {
+        let error = anyhow::Error::new(envy::Error::MissingValue("SECRET_KEY"))
+            .context("Failed to load env variables");
+
+        assert_eq!(ExitCode::from_error(&error), ExitCode::Config);
+    }
+
+    #[test]
+    fn rejected_credentials_are_auth_errors() {
+        let error = anyhow!(IndexerError::BlobscanSyncStateRetrievalError(api_error(
+            401
+        )));
+
+        assert_eq!(ExitCode::from_error(&error), ExitCode::Auth);
+    }
+
+    #[test]
+    fn failed_jobs_exit_with_the_code_of_their_cause() {
+        let reorg_error = IndexerError::from(IndexingError::from(
+            LiveIndexingError::BeaconEventHandlingError(EventHandlerError::HeadEventHandlerError(
+                HeadEventHandlerError::BlobscanReorgedSlotsFailure(api_error(500)),
+            )),
+        ));
+        let error = anyhow!(IndexerError::JobFailed {
+            path: PathBuf::from("indexer-job.json"),
+            remaining_slots: 10,
+            failed_slot: None,
+            error: Box::new(reorg_error),
+        });
+
+        assert_eq!(ExitCode::from_error(&error), ExitCode::ReorgFailure);
+    }
+
+    #[test]
+    fn unknown_errors_are_generic_failures() {
+        assert_eq!(
+            ExitCode::from_error(&anyhow!("something went wrong")),
+            ExitCode::Failure
+        );
+    }
+}
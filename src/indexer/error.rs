@@ -1,6 +1,19 @@
+use std::path::PathBuf;
+
 use tokio::sync::mpsc::error::SendError;
 
-use crate::{clients::common::ClientError, synchronizer::error::SynchronizerError};
+use crate::{
+    clients::{
+        beacon::types::BlockId,
+        blobscan::types::INDEXER_SCHEMA_VERSION,
+        common::{is_recoverable_status, ClientError},
+    },
+    commands::verify::VerifyError,
+    rollups::RollupMappingError,
+    slots_processor::error::{SlotProcessingError, SlotsProcessorError},
+    synchronizer::{error::SynchronizerError, intent::IntentLogError, job::JobManifestError},
+    tx_categories::TxCategoryMappingError,
+};
 
 use super::{
     event_handlers::{
@@ -17,8 +30,82 @@ pub enum IndexerError {
     SyncingTaskError(#[from] IndexingError),
     #[error("failed to retrieve blobscan's sync state")]
     BlobscanSyncStateRetrievalError(#[source] ClientError),
+    #[error("failed to retrieve the blobscan api version")]
+    BlobscanApiVersionRetrievalError(#[source] ClientError),
+    #[error(
+        "blobscan api {api_version} doesn't support the payload schema v{INDEXER_SCHEMA_VERSION} emitted by this indexer (supported: {supported_schema_versions:?}), upgrade the api or use a compatible indexer version"
+    )]
+    IncompatibleBlobscanApi {
+        api_version: String,
+        supported_schema_versions: Vec<u32>,
+    },
     #[error("failed to send syncing task message")]
-    SyncingTaskMessageSendFailure(#[from] SendError<IndexerTaskMessage>),
+    SyncingTaskMessageSendFailure(#[source] Box<SendError<IndexerTaskMessage>>),
+    #[error("failed to retrieve header for block \"{0}\"")]
+    BlockHeaderRetrievalError(BlockId, #[source] ClientError),
+    #[error("header for block \"{0}\" not found")]
+    BlockHeaderNotFound(BlockId),
+    #[error("failed to reindex block")]
+    ReindexingFailure(#[from] SlotsProcessorError),
+    #[error("failed to flush the sink")]
+    SinkFlushFailure(#[source] ClientError),
+    #[error("failed to load job manifest {path}")]
+    JobManifestLoadFailure {
+        path: PathBuf,
+        #[source]
+        error: JobManifestError,
+    },
+    #[error("failed to save job manifest {path}")]
+    JobManifestSaveFailure {
+        path: PathBuf,
+        #[source]
+        error: JobManifestError,
+    },
+    #[error("failed to load rollup mapping {path}")]
+    RollupMappingLoadFailure {
+        path: PathBuf,
+        #[source]
+        error: RollupMappingError,
+    },
+    #[error("failed to load transaction category mapping {path}")]
+    TxCategoryMappingLoadFailure {
+        path: PathBuf,
+        #[source]
+        error: TxCategoryMappingError,
+    },
+    #[error("failed to open indexed slots bitmap {path}")]
+    IndexedSlotsOpenFailure {
+        path: PathBuf,
+        #[source]
+        error: std::io::Error,
+    },
+    #[error("failed to open intent log")]
+    IntentLogOpenFailure(#[source] IntentLogError),
+    #[error("failed to open reorg log {path}")]
+    ReorgLogOpenFailure {
+        path: PathBuf,
+        #[source]
+        error: std::io::Error,
+    },
+    #[error("failed to confirm replayed intent")]
+    IntentLogConfirmFailure(#[source] IntentLogError),
+    #[error("unknown Dencun fork slot: set DENCUN_FORK_SLOT or make sure the beacon node's fork schedule can be fetched")]
+    UnknownDencunForkSlot,
+    #[error("confirming deep reorgs requires the control server: set CONTROL_SERVER_ADDRESS")]
+    ReorgConfirmationUnavailable,
+    #[error("job interrupted, {remaining_slots} slots left to sync saved to {path}")]
+    JobInterrupted { path: PathBuf, remaining_slots: u64 },
+    #[error(
+        "job failed{}, {remaining_slots} slots left to sync saved to {path}. Run again with --resume to continue",
+        .failed_slot.map(|slot| format!(" at slot {slot}")).unwrap_or_default()
+    )]
+    JobFailed {
+        path: PathBuf,
+        remaining_slots: u64,
+        failed_slot: Option<u32>,
+        #[source]
+        error: Box<IndexerError>,
+    },
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -38,7 +125,7 @@ pub enum HistoricalIndexingError {
 #[derive(Debug, thiserror::Error)]
 pub enum LiveIndexingError {
     #[error("an error ocurred while receiving beacon events")]
-    BeaconEventsConnectionFailure(#[from] reqwest_eventsource::Error),
+    BeaconEventsConnectionFailure(#[source] Box<reqwest_eventsource::Error>),
     #[error("failed to subscribe to beacon events")]
     BeaconEventsSubscriptionError(#[source] ClientError),
     #[error("unexpected event \"{0}\" received")]
@@ -47,6 +134,24 @@ pub enum LiveIndexingError {
     BeaconEventHandlingError(#[from] EventHandlerError),
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum GapFillingError {
+    #[error("failed to retrieve blobscan's sync state")]
+    SyncStateRetrievalError(#[source] ClientError),
+    #[error("failed to check slot {slot}")]
+    SlotCheckFailure {
+        slot: u32,
+        #[source]
+        error: VerifyError,
+    },
+    #[error("failed to fill slot {slot}")]
+    SlotFillFailure {
+        slot: u32,
+        #[source]
+        error: SlotProcessingError,
+    },
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum EventHandlerError {
     #[error(transparent)]
@@ -55,6 +160,88 @@ pub enum EventHandlerError {
     FinalizedCheckpointHandlerError(#[from] FinalizedCheckpointEventHandlerError),
 }
 
+impl IndexingError {
+    /// Returns the slots whose processing made the task fail, in ascending
+    /// order.
+    pub fn failed_slots(&self) -> Vec<u32> {
+        match self {
+            IndexingError::HistoricalIndexingFailure(
+                HistoricalIndexingError::SynchronizerError(error),
+            ) => error.failed_slots(),
+            IndexingError::LiveIndexingError(_) => vec![],
+        }
+    }
+
+    /// Whether the task failed because of a transient issue (network failures,
+    /// server errors, throttling) and can be restarted, as opposed to a fatal
+    /// one (authentication failures, schema mismatches) that will keep failing.
+    pub fn is_recoverable(&self) -> bool {
+        match self {
+            IndexingError::HistoricalIndexingFailure(
+                HistoricalIndexingError::SynchronizerError(error),
+            ) => error.is_recoverable(),
+            IndexingError::LiveIndexingError(error) => error.is_recoverable(),
+        }
+    }
+}
+
+impl LiveIndexingError {
+    pub fn is_recoverable(&self) -> bool {
+        match self {
+            LiveIndexingError::BeaconEventsConnectionFailure(error) => match error.as_ref() {
+                reqwest_eventsource::Error::InvalidStatusCode(status, _) => {
+                    is_recoverable_status(status.as_u16())
+                }
+                reqwest_eventsource::Error::InvalidContentType(..) => false,
+                _ => true,
+            },
+            LiveIndexingError::BeaconEventsSubscriptionError(error) => error.is_recoverable(),
+            LiveIndexingError::UnexpectedBeaconEvent(_) => false,
+            LiveIndexingError::BeaconEventHandlingError(error) => error.is_recoverable(),
+        }
+    }
+}
+
+impl EventHandlerError {
+    pub fn is_recoverable(&self) -> bool {
+        match self {
+            EventHandlerError::HeadEventHandlerError(error) => match error {
+                HeadEventHandlerError::EventDeserializationFailure(_) => false,
+                HeadEventHandlerError::BlockHeaderRetrievalError(_, error)
+                | HeadEventHandlerError::BlobscanReorgedSlotsFailure(error)
+                | HeadEventHandlerError::BlobscanSyncStateUpdateError(error) => {
+                    error.is_recoverable()
+                }
+                HeadEventHandlerError::BlockHeaderNotFound(_) => true,
+                HeadEventHandlerError::BlockSyncedError(error) => error.is_recoverable(),
+            },
+            EventHandlerError::FinalizedCheckpointHandlerError(error) => match error {
+                FinalizedCheckpointEventHandlerError::EventDeserializationFailure(_) => false,
+                FinalizedCheckpointEventHandlerError::BlockRetrievalError(_, error)
+                | FinalizedCheckpointEventHandlerError::BlobscanFinalizedBlockUpdateFailure(
+                    error,
+                ) => error.is_recoverable(),
+                FinalizedCheckpointEventHandlerError::BlockNotFound(_) => true,
+                FinalizedCheckpointEventHandlerError::FinalizedBlocksSyncFailure(error) => {
+                    error.is_recoverable()
+                }
+            },
+        }
+    }
+}
+
+impl From<SendError<IndexerTaskMessage>> for IndexerError {
+    fn from(err: SendError<IndexerTaskMessage>) -> Self {
+        IndexerError::SyncingTaskMessageSendFailure(Box::new(err))
+    }
+}
+
+impl From<reqwest_eventsource::Error> for LiveIndexingError {
+    fn from(err: reqwest_eventsource::Error) -> Self {
+        LiveIndexingError::BeaconEventsConnectionFailure(Box::new(err))
+    }
+}
+
 impl From<HeadEventHandlerError> for LiveIndexingError {
     fn from(err: HeadEventHandlerError) -> Self {
         LiveIndexingError::BeaconEventHandlingError(EventHandlerError::HeadEventHandlerError(err))
@@ -1,5 +1,5 @@
 use alloy::transports::Transport;
-use tracing::info;
+use tracing::{debug, info};
 
 use crate::{
     clients::{
@@ -8,6 +8,7 @@ use crate::{
         common::ClientError,
     },
     context::CommonContext,
+    synchronizer::{error::SynchronizerError, CommonSynchronizer},
     utils::web3::get_full_hash,
 };
 
@@ -21,10 +22,16 @@ pub enum FinalizedCheckpointEventHandlerError {
     BlockNotFound(String),
     #[error("failed to update last finalized block")]
     BlobscanFinalizedBlockUpdateFailure(#[source] ClientError),
+    #[error("failed to index finalized blocks")]
+    FinalizedBlocksSyncFailure(#[from] SynchronizerError),
 }
 
 pub struct FinalizedCheckpointHandler<T> {
     context: Box<dyn CommonContext<T>>,
+    /// Indexes the newly finalized blocks in finalized-only mode
+    synchronizer: Option<Box<dyn CommonSynchronizer>>,
+    start_block_id: BlockId,
+    last_synced_slot: Option<u32>,
 }
 
 impl<T> FinalizedCheckpointHandler<T>
@@ -32,39 +39,154 @@ where
     T: Transport + Send + Sync + 'static,
 {
     pub fn new(context: Box<dyn CommonContext<T>>) -> Self {
-        FinalizedCheckpointHandler { context }
+        FinalizedCheckpointHandler {
+            context,
+            synchronizer: None,
+            start_block_id: BlockId::Finalized,
+            last_synced_slot: None,
+        }
+    }
+
+    /// Indexes the blocks up to every finalized checkpoint with the given
+    /// synchronizer, starting from the given block, so only finalized blocks
+    /// get indexed and reorgs never need to be handled.
+    pub fn with_synchronizer(
+        mut self,
+        synchronizer: Box<dyn CommonSynchronizer>,
+        start_block_id: BlockId,
+    ) -> Self {
+        self.synchronizer = Some(synchronizer);
+        self.start_block_id = start_block_id;
+
+        self
     }
 
     pub async fn handle(
-        &self,
+        &mut self,
         event_data: String,
     ) -> Result<(), FinalizedCheckpointEventHandlerError> {
         let finalized_checkpoint_data =
             serde_json::from_str::<FinalizedCheckpointEventData>(&event_data)?;
         let block_hash = finalized_checkpoint_data.block;
-        let full_block_hash = get_full_hash(&block_hash);
+
+        self.sync_finalized_blocks(&BlockId::Hash(block_hash), get_full_hash(&block_hash))
+            .await?;
+
+        let last_finalized_block_number = self
+            .update_last_finalized_block(&BlockId::Hash(block_hash), get_full_hash(&block_hash))
+            .await?;
+
+        info!(
+            finalized_execution_block = last_finalized_block_number,
+            "Finalized checkpoint event received. Updated last finalized block number"
+        );
+
+        Ok(())
+    }
+
+    /// Updates the last finalized block with the current finalized block of the
+    /// beacon node, so checkpoints missed while no events were received are
+    /// accounted for without waiting for the next one.
+    pub async fn sync_finalized_block(
+        &mut self,
+    ) -> Result<(), FinalizedCheckpointEventHandlerError> {
+        let block_id = BlockId::Finalized;
+
+        self.sync_finalized_blocks(&block_id, block_id.to_string())
+            .await?;
+
+        let last_finalized_block_number = self
+            .update_last_finalized_block(&block_id, block_id.to_string())
+            .await?;
+
+        info!(
+            finalized_execution_block = last_finalized_block_number,
+            "Updated last finalized block number"
+        );
+
+        Ok(())
+    }
+
+    /// Indexes the blocks between the last synced one and the given finalized
+    /// block, when indexing finalized blocks only.
+    async fn sync_finalized_blocks(
+        &mut self,
+        block_id: &BlockId,
+        block_name: String,
+    ) -> Result<(), FinalizedCheckpointEventHandlerError> {
+        let Some(synchronizer) = &self.synchronizer else {
+            return Ok(());
+        };
+
+        let finalized_slot = match self
+            .context
+            .beacon_client()
+            .get_block_header(block_id)
+            .await
+            .map_err(|err| {
+                FinalizedCheckpointEventHandlerError::BlockRetrievalError(block_name.clone(), err)
+            })? {
+            Some(header) => header.header.message.slot,
+            None => {
+                return Err(FinalizedCheckpointEventHandlerError::BlockNotFound(
+                    block_name,
+                ))
+            }
+        };
+        let initial_block_id = match self.last_synced_slot {
+            Some(slot) => BlockId::Slot(slot + 1),
+            None => self.start_block_id.clone(),
+        };
+
+        // Blocks past the finalized one may have been indexed before switching
+        // to finalized-only mode
+        if matches!(initial_block_id, BlockId::Slot(slot) if slot > finalized_slot) {
+            debug!(
+                finalized_slot,
+                "Finalized blocks already indexed. Skipping…"
+            );
+
+            return Ok(());
+        }
+
+        synchronizer
+            .run(&initial_block_id, &BlockId::Slot(finalized_slot + 1))
+            .await?;
+
+        self.last_synced_slot = Some(finalized_slot);
+
+        info!(
+            finalized_slot,
+            "Indexed blocks up to the finalized checkpoint"
+        );
+
+        Ok(())
+    }
+
+    async fn update_last_finalized_block(
+        &self,
+        block_id: &BlockId,
+        block_name: String,
+    ) -> Result<u32, FinalizedCheckpointEventHandlerError> {
         let last_finalized_block_number = match self
             .context
             .beacon_client()
-            .get_block(&BlockId::Hash(block_hash))
+            .get_block(block_id)
             .await
             .map_err(|err| {
-                FinalizedCheckpointEventHandlerError::BlockRetrievalError(
-                    full_block_hash.clone(),
-                    err,
-                )
+                FinalizedCheckpointEventHandlerError::BlockRetrievalError(block_name.clone(), err)
             })? {
             Some(block) => match block.message.body.execution_payload {
                 Some(execution_payload) => execution_payload.block_number,
                 None => {
                     return Err(FinalizedCheckpointEventHandlerError::BlockNotFound(
-                        full_block_hash,
+                        block_name,
                     ))
                 }
             },
             None => {
                 return Err(FinalizedCheckpointEventHandlerError::BlockNotFound(
-                    full_block_hash,
+                    block_name,
                 ))
             }
         };
@@ -79,11 +201,6 @@ where
             .await
             .map_err(FinalizedCheckpointEventHandlerError::BlobscanFinalizedBlockUpdateFailure)?;
 
-        info!(
-            finalized_execution_block = last_finalized_block_number,
-            "Finalized checkpoint event received. Updated last finalized block number"
-        );
-
-        Ok(())
+        Ok(last_finalized_block_number)
     }
 }
@@ -1,18 +1,40 @@
-use std::cmp;
+use std::{
+    cmp,
+    collections::BTreeMap,
+    ops::{Range, RangeInclusive},
+    sync::Arc,
+};
 
 use alloy::{primitives::B256, transports::Transport};
-use tracing::info;
+use futures::future::join_all;
+use tracing::{debug, field::display, info, warn, Span};
 
 use crate::{
     clients::{
-        beacon::types::{BlockHeader, BlockId, HeadEventData},
+        beacon::types::{BlockHeader, BlockId, ChainReorgEventData, HeadEventData},
         blobscan::types::BlockchainSyncState,
         common::ClientError,
     },
     context::CommonContext,
+    indexer::{
+        reorg_confirmation::ReorgConfirmation,
+        reorg_log::{ReorgBlock, ReorgLog, ReorgRecord, ReorgTrigger},
+    },
+    metrics::set_gauge,
+    network::SlotClock,
+    slots_processor::indexed_slots::IndexedSlots,
     synchronizer::{error::SynchronizerError, CommonSynchronizer},
 };
 
+/// Amount of recently synced head blocks kept to find the common ancestor of
+/// a reorg
+const MAX_RECENT_BLOCKS: usize = 64;
+/// Amount of headers fetched at the same time when looking for the common
+/// ancestor of a reorg
+const ANCESTOR_SEARCH_BATCH_SIZE: usize = 8;
+/// Slots per epoch of the networks following the mainnet preset
+const DEFAULT_SLOTS_PER_EPOCH: u32 = 32;
+
 #[derive(Debug, thiserror::Error)]
 pub enum HeadEventHandlerError {
     #[error(transparent)]
@@ -34,6 +56,16 @@ pub struct HeadEventHandler<T> {
     synchronizer: Box<dyn CommonSynchronizer>,
     start_block_id: BlockId,
     last_block_hash: Option<B256>,
+    last_synced_slot: Option<u32>,
+    /// Roots of the latest synced head blocks, keyed by slot
+    recent_blocks: BTreeMap<u32, B256>,
+    catch_up_synchronizer: Option<Box<dyn CommonSynchronizer>>,
+    catch_up_threshold: u32,
+    slots_per_epoch: u32,
+    indexed_slots: Option<Arc<IndexedSlots>>,
+    slot_clock: Option<SlotClock>,
+    reorg_log: Option<Arc<ReorgLog>>,
+    reorg_confirmation: Option<Arc<ReorgConfirmation>>,
 }
 
 impl<T> HeadEventHandler<T>
@@ -50,83 +82,486 @@ where
             synchronizer,
             start_block_id,
             last_block_hash: None,
+            last_synced_slot: None,
+            recent_blocks: BTreeMap::new(),
+            catch_up_synchronizer: None,
+            catch_up_threshold: 0,
+            slots_per_epoch: DEFAULT_SLOTS_PER_EPOCH,
+            indexed_slots: None,
+            slot_clock: None,
+            reorg_log: None,
+            reorg_confirmation: None,
         }
     }
 
+    /// Uses the given synchronizer instead of the default one whenever more than
+    /// `threshold` slots need to be synced to reach the head, e.g. after a
+    /// reconnection.
+    pub fn with_catch_up_synchronizer(
+        mut self,
+        synchronizer: Box<dyn CommonSynchronizer>,
+        threshold: u32,
+    ) -> Self {
+        self.catch_up_synchronizer = Some(synchronizer);
+        self.catch_up_threshold = threshold;
+
+        self
+    }
+
+    /// Sets the epoch length the catch-up ranges are split by.
+    pub fn with_slots_per_epoch(mut self, slots_per_epoch: u32) -> Self {
+        self.slots_per_epoch = slots_per_epoch.max(1);
+
+        self
+    }
+
+    /// Reports how far behind the wall clock the synced head is, in seconds,
+    /// when the network's clock is known.
+    pub fn with_slot_clock(mut self, slot_clock: Option<SlotClock>) -> Self {
+        self.slot_clock = slot_clock;
+
+        self
+    }
+
+    /// Forgets the reorged slots in the given indexed slots so the blocks of
+    /// the new chain get indexed.
+    pub fn with_indexed_slots(mut self, indexed_slots: Arc<IndexedSlots>) -> Self {
+        self.indexed_slots = Some(indexed_slots);
+
+        self
+    }
+
+    /// Appends every handled reorg to the given log.
+    pub fn with_reorg_log(mut self, reorg_log: Arc<ReorgLog>) -> Self {
+        self.reorg_log = Some(reorg_log);
+
+        self
+    }
+
+    /// Holds the reorgs deeper than the confirmation depth of the given gate
+    /// until an operator confirms them.
+    pub fn with_reorg_confirmation(mut self, reorg_confirmation: Arc<ReorgConfirmation>) -> Self {
+        self.reorg_confirmation = Some(reorg_confirmation);
+
+        self
+    }
+
     pub async fn handle(&mut self, event_data: String) -> Result<(), HeadEventHandlerError> {
         let head_block_data = serde_json::from_str::<HeadEventData>(&event_data)?;
 
         let head_block_slot = head_block_data.slot;
         let head_block_hash = head_block_data.block;
 
+        Span::current()
+            .record("slot", head_block_slot)
+            .record("block_root", display(head_block_hash));
+
+        // The head may have already been synced when handling a chain reorg event
+        if self.last_block_hash == Some(head_block_hash) {
+            return Ok(());
+        }
+
         let head_block_id = BlockId::Slot(head_block_data.slot);
-        let initial_block_id = if self.last_block_hash.is_none() {
-            self.start_block_id.clone()
-        } else {
-            head_block_id.clone()
+        // Resume right after the last synced slot so slots missed in between are synced too
+        let mut initial_block_id = match self.last_synced_slot {
+            Some(slot) if slot < head_block_slot => BlockId::Slot(slot + 1),
+            Some(_) => head_block_id.clone(),
+            None => self.start_block_id.clone(),
         };
 
         let head_block_header = self.get_block_header(&head_block_id).await?.header;
+        let mut reorg = None;
 
         if let Some(last_block_hash) = self.last_block_hash {
             if last_block_hash != head_block_header.message.parent_root {
-                let parent_block_header = self
-                    .get_block_header(&BlockId::Hash(head_block_header.message.parent_root))
-                    .await?
-                    .header;
-                let parent_block_slot = parent_block_header.message.slot;
-                let reorg_start_slot = parent_block_slot + 1;
-                let reorg_final_slot = head_block_slot;
-                let reorged_slots = (reorg_start_slot..reorg_final_slot).collect::<Vec<u32>>();
-
-                let result: Result<(), HeadEventHandlerError> = async {
-                    let total_updated_slots = self.context
+                // Cached headers of the recently synced slots may belong to the old chain
+                if let Some(&oldest_recent_slot) = self.recent_blocks.keys().next() {
+                    self.context
+                        .beacon_client()
+                        .invalidate_block_headers(oldest_recent_slot);
+                }
+
+                let ancestor_slot = match self.find_common_ancestor(head_block_slot).await? {
+                    Some(ancestor_slot) => ancestor_slot,
+                    // Assume the new head forked off its parent when the
+                    // ancestor isn't among the recently synced blocks
+                    None => {
+                        self.get_block_header(&BlockId::Hash(head_block_header.message.parent_root))
+                            .await?
+                            .header
+                            .message
+                            .slot
+                    }
+                };
+
+                // The last synced block is still canonical, so the events of
+                // the blocks in between were just missed
+                if Some(ancestor_slot) != self.last_synced_slot {
+                    let reorg_start_slot = ancestor_slot + 1;
+                    let reorg_final_slot = head_block_slot;
+                    let reorged_slots = (reorg_start_slot..reorg_final_slot).collect::<Vec<u32>>();
+
+                    let mut reorg_record = ReorgRecord::new(
+                        ReorgTrigger::ParentMismatch,
+                        head_block_slot,
+                        ancestor_slot,
+                        head_block_hash,
+                    );
+
+                    reorg_record.old_head_block = self.last_block_hash;
+                    reorg_record.reorged_slots = reorged_slots.clone();
+                    self.compute_reorg_diff(&mut reorg_record, reorg_start_slot..=head_block_slot)
+                        .await;
+                    self.confirm_reorg(&reorg_record).await;
+
+                    // The ancestor is indexed again along with the new chain
+                    self.forget_indexed_slots(ancestor_slot..=head_block_slot);
+                    self.recent_blocks.split_off(&reorg_start_slot);
+
+                    let total_updated_slots = match self
+                        .context
                         .blobscan_client()
                         .handle_reorged_slots(reorged_slots.as_slice())
                         .await
-                        .map_err(HeadEventHandlerError::BlobscanReorgedSlotsFailure)?;
-
-
-                    info!(slot=head_block_slot, "Reorganization detected. Found the following reorged slots: {:#?}. Total slots marked as reorged: {total_updated_slots}", reorged_slots);
+                    {
+                        Ok(total_updated_slots) => total_updated_slots,
+                        Err(error) => {
+                            // Try to update the latest synced slot to the last known slot before the reorg
+                            self.context
+                                .blobscan_client()
+                                .update_sync_state(BlockchainSyncState {
+                                    last_finalized_block: None,
+                                    last_lower_synced_slot: None,
+                                    last_upper_synced_slot: Some(ancestor_slot.saturating_sub(1)),
+                                })
+                                .await
+                                .map_err(HeadEventHandlerError::BlobscanSyncStateUpdateError)?;
+
+                            return Err(HeadEventHandlerError::BlobscanReorgedSlotsFailure(error));
+                        }
+                    };
+
+                    info!(slot=head_block_slot, ancestor_slot, "Reorganization detected. Found the following reorged slots: {:#?}. Total slots marked as reorged: {total_updated_slots}", reorged_slots);
+
+                    reorg = Some(reorg_record);
+
+                    // Sync the whole new chain, starting from the ancestor as it may be marked as reorged
+                    initial_block_id = BlockId::Slot(ancestor_slot);
+                }
+            }
+        }
 
-                    // Re-index parent block as it may be mark as reorged and not indexed
-                    self.synchronizer
-                        .run(
-                            &BlockId::Slot(parent_block_slot),
-                            &BlockId::Slot(parent_block_slot + 1),
-                        )
+        match (&initial_block_id, &self.catch_up_synchronizer) {
+            (BlockId::Slot(initial_slot), Some(catch_up_synchronizer))
+                if head_block_slot.saturating_sub(*initial_slot) > self.catch_up_threshold =>
+            {
+                let epochs = epoch_ranges(*initial_slot..head_block_slot + 1, self.slots_per_epoch);
+                let total_epochs = epochs.len();
+
+                info!(
+                    initial_slot,
+                    head_slot = head_block_slot,
+                    total_epochs,
+                    initial_slot_timestamp = self
+                        .slot_clock
+                        .map(|clock| clock.slot_timestamp(*initial_slot)),
+                    "Head is far ahead. Catching up in parallel…"
+                );
+
+                // Each epoch is synced and checkpointed on its own, so a failure
+                // only syncs its epoch again once the task is restarted
+                for (i, (epoch, slots)) in epochs.into_iter().enumerate() {
+                    catch_up_synchronizer
+                        .run(&BlockId::Slot(slots.start), &BlockId::Slot(slots.end))
                         .await?;
 
-                    Ok(())
+                    info!(
+                        epoch,
+                        from_slot = slots.start,
+                        to_slot = slots.end - 1,
+                        "Caught up epoch {}/{total_epochs}",
+                        i + 1
+                    );
                 }
-                .await;
+            }
+            _ => {
+                self.synchronizer
+                    .run(&initial_block_id, &BlockId::Slot(head_block_slot + 1))
+                    .await?;
+            }
+        }
 
-                if let Err(err) = result {
-                    // If an error occurred while handling the reorg try to update the latest synced slot to the last known slot before the reorg
-                    self.context
-                        .blobscan_client()
-                        .update_sync_state(BlockchainSyncState {
-                            last_finalized_block: None,
-                            last_lower_synced_slot: None,
-                            last_upper_synced_slot: Some(cmp::max(parent_block_slot - 1, 0)),
-                        })
-                        .await
-                        .map_err(HeadEventHandlerError::BlobscanSyncStateUpdateError)?;
+        if let Some(reorg) = reorg {
+            self.record_reorg(&reorg);
+        }
 
-                    return Err(err);
-                }
-            }
+        self.last_block_hash = Some(head_block_hash);
+        self.last_synced_slot = Some(head_block_slot);
+        self.track_recent_block(head_block_slot, head_block_hash);
+
+        if let Some(clock) = self.slot_clock {
+            set_gauge(
+                "blob_indexer_head_slot_timestamp_seconds",
+                &[],
+                clock.slot_timestamp(head_block_slot),
+            );
+            set_gauge(
+                "blob_indexer_head_lag_seconds",
+                &[],
+                clock.seconds_since(head_block_slot),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Handles a reorg reported by the beacon node right away, instead of
+    /// waiting for the next head whose parent doesn't match the last synced
+    /// block. Slots past the common ancestor are marked as reorged and synced
+    /// again from the new chain.
+    pub async fn handle_chain_reorg(
+        &mut self,
+        event_data: String,
+    ) -> Result<(), HeadEventHandlerError> {
+        let reorg_data = serde_json::from_str::<ChainReorgEventData>(&event_data)?;
+
+        Span::current()
+            .record("slot", reorg_data.slot)
+            .record("block_root", display(reorg_data.new_head_block));
+
+        if self.last_block_hash == Some(reorg_data.new_head_block) {
+            debug!(slot = reorg_data.slot, "Reorg already handled. Skipping…");
+
+            return Ok(());
+        }
+
+        let last_synced_slot = match self.last_synced_slot {
+            Some(slot) => slot,
+            // Nothing synced yet, so the next head event will sync the new chain
+            None => return Ok(()),
+        };
+
+        let ancestor_slot = reorg_data.slot.saturating_sub(reorg_data.depth);
+        // Every slot synced past the common ancestor may belong to the old chain
+        let reorged_slots = (ancestor_slot + 1..=last_synced_slot).collect::<Vec<u32>>();
+        let mut reorg_record = ReorgRecord::new(
+            ReorgTrigger::ChainReorgEvent,
+            reorg_data.slot,
+            ancestor_slot,
+            reorg_data.new_head_block,
+        );
+
+        reorg_record.old_head_block = Some(reorg_data.old_head_block);
+        reorg_record.reorged_slots = reorged_slots.clone();
+
+        self.context
+            .beacon_client()
+            .invalidate_block_headers(ancestor_slot + 1);
+
+        self.compute_reorg_diff(&mut reorg_record, ancestor_slot + 1..=reorg_data.slot)
+            .await;
+        self.confirm_reorg(&reorg_record).await;
+
+        self.forget_indexed_slots(ancestor_slot..=cmp::max(last_synced_slot, reorg_data.slot));
+
+        if !reorged_slots.is_empty() {
+            let total_updated_slots = self
+                .context
+                .blobscan_client()
+                .handle_reorged_slots(reorged_slots.as_slice())
+                .await
+                .map_err(HeadEventHandlerError::BlobscanReorgedSlotsFailure)?;
+
+            info!(
+                slot = reorg_data.slot,
+                depth = reorg_data.depth,
+                old_head_block = %reorg_data.old_head_block,
+                new_head_block = %reorg_data.new_head_block,
+                "Chain reorg event received. Reorged slots: {:#?}. Total slots marked as reorged: {total_updated_slots}",
+                reorged_slots
+            );
         }
 
         self.synchronizer
-            .run(&initial_block_id, &BlockId::Slot(head_block_slot + 1))
+            .run(
+                &BlockId::Slot(ancestor_slot),
+                &BlockId::Slot(reorg_data.slot + 1),
+            )
             .await?;
 
-        self.last_block_hash = Some(head_block_hash);
+        self.record_reorg(&reorg_record);
+
+        self.last_block_hash = Some(reorg_data.new_head_block);
+        self.last_synced_slot = Some(reorg_data.slot);
+        self.recent_blocks.split_off(&(ancestor_slot + 1));
+        self.track_recent_block(reorg_data.slot, reorg_data.new_head_block);
 
         Ok(())
     }
 
+    /// Looks for the latest recently synced block still in the canonical chain
+    /// below the given slot, fetching the canonical headers at the slots of
+    /// several of those blocks at the same time.
+    async fn find_common_ancestor(
+        &self,
+        head_block_slot: u32,
+    ) -> Result<Option<u32>, HeadEventHandlerError> {
+        let candidates = self
+            .recent_blocks
+            .range(..head_block_slot)
+            .rev()
+            .map(|(&slot, &root)| (slot, root))
+            .collect::<Vec<_>>();
+
+        for batch in candidates.chunks(ANCESTOR_SEARCH_BATCH_SIZE) {
+            let block_ids = batch
+                .iter()
+                .map(|(slot, _)| BlockId::Slot(*slot))
+                .collect::<Vec<_>>();
+            let canonical_headers = join_all(
+                block_ids
+                    .iter()
+                    .map(|block_id| self.context.beacon_client().get_block_header(block_id)),
+            )
+            .await;
+
+            for ((slot, root), canonical_header) in batch.iter().zip(canonical_headers) {
+                let canonical_header = canonical_header.map_err(|err| {
+                    HeadEventHandlerError::BlockHeaderRetrievalError(BlockId::Slot(*slot), err)
+                })?;
+
+                // Empty slots in the new chain can't be the ancestor
+                if canonical_header.is_some_and(|header| header.root == *root) {
+                    return Ok(Some(*slot));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Fills the reorg in with the synced blocks it rewinds and the blocks of
+    /// the new chain in the given slots it forwards, along with their blobs.
+    async fn compute_reorg_diff(
+        &self,
+        reorg: &mut ReorgRecord,
+        forwarded_slots: RangeInclusive<u32>,
+    ) {
+        let blobscan_client = self.context.blobscan_client();
+        let beacon_client = self.context.beacon_client();
+        let rewound_blocks =
+            self.recent_blocks
+                .range(reorg.ancestor_slot + 1..)
+                .map(|(&slot, &root)| async move {
+                    // Blobscan still holds the blocks of the old chain
+                    let blobs = blobscan_client
+                        .get_block(slot)
+                        .await
+                        .ok()
+                        .flatten()
+                        .map(|block| {
+                            block
+                                .transactions
+                                .iter()
+                                .map(|transaction| transaction.blobs.len())
+                                .sum()
+                        });
+
+                    ReorgBlock { slot, root, blobs }
+                });
+        let forwarded_blocks = forwarded_slots.map(|slot| async move {
+            let header = beacon_client
+                .get_block_header(&BlockId::Slot(slot))
+                .await
+                .ok()
+                .flatten()?;
+            let blobs = beacon_client
+                .get_block(&BlockId::Hash(header.root))
+                .await
+                .ok()
+                .flatten()
+                .map(|block| {
+                    block
+                        .message
+                        .body
+                        .blob_kzg_commitments
+                        .map_or(0, |commitments| commitments.len())
+                });
+
+            Some(ReorgBlock {
+                slot: header.header.message.slot,
+                root: header.root,
+                blobs,
+            })
+        });
+
+        reorg.rewound_blocks = join_all(rewound_blocks).await;
+        // Empty slots and the headers that fail to be fetched are left out
+        reorg.forwarded_blocks = join_all(forwarded_blocks)
+            .await
+            .into_iter()
+            .flatten()
+            .collect();
+    }
+
+    /// Logs the diff of the reorg and, when it's deeper than the confirmation
+    /// depth, waits for an operator to confirm it before handling it.
+    async fn confirm_reorg(&self, reorg: &ReorgRecord) {
+        reorg.log_diff();
+
+        let Some(reorg_confirmation) = &self.reorg_confirmation else {
+            return;
+        };
+
+        // Nothing indexed gets rewound
+        if reorg.reorged_slots.is_empty() || !reorg_confirmation.requires_confirmation(reorg) {
+            return;
+        }
+
+        warn!(
+            slot = reorg.slot,
+            depth = reorg.depth,
+            "Reorg is deeper than the confirmation depth. Waiting for it to be confirmed through the control server…"
+        );
+        set_gauge("blob_indexer_pending_reorg_depth", &[], reorg.depth as u64);
+
+        reorg_confirmation.wait_for_confirmation(reorg.into()).await;
+
+        set_gauge("blob_indexer_pending_reorg_depth", &[], 0);
+        info!(slot = reorg.slot, depth = reorg.depth, "Reorg confirmed");
+    }
+
+    /// Updates the reorg metrics and appends the reorg to the reorg log.
+    fn record_reorg(&self, reorg: &ReorgRecord) {
+        reorg.record_metrics();
+
+        let Some(reorg_log) = &self.reorg_log else {
+            return;
+        };
+
+        if let Err(error) = reorg_log.append(reorg) {
+            warn!(
+                path = %reorg_log.path().display(),
+                ?error,
+                "Failed to append the reorg to the reorg log"
+            );
+        }
+    }
+
+    fn track_recent_block(&mut self, slot: u32, root: B256) {
+        self.recent_blocks.insert(slot, root);
+
+        while self.recent_blocks.len() > MAX_RECENT_BLOCKS {
+            self.recent_blocks.pop_first();
+        }
+    }
+
+    fn forget_indexed_slots(&self, slots: impl IntoIterator<Item = u32>) {
+        if let Some(indexed_slots) = &self.indexed_slots {
+            indexed_slots.remove(slots);
+        }
+    }
+
     async fn get_block_header(
         &self,
         block_id: &BlockId,
@@ -145,562 +580,486 @@ where
     }
 }
 
-// #[cfg(test)]
-// mod tests {
-//     use alloy::primitives::B256;
-//     use anyhow::anyhow;
-//     use mockall::predicate::eq;
-
-//     use super::HeadEventHandler;
-//     use crate::{
-//         clients::{
-//             beacon::{
-//                 types::{BlockHeader, BlockHeaderMessage, BlockId, InnerBlockHeader},
-//                 MockCommonBeaconClient,
-//             },
-//             blobscan::{types::BlockchainSyncState, MockCommonBlobscanClient},
-//         },
-//         context::Context,
-//         synchronizer::MockCommonSynchronizer,
-//     };
-
-//     #[derive(Clone, Debug)]
-//     struct BlockData {
-//         slot: u32,
-//         hash: B256,
-//         parent_hash: Option<B256>,
-//     }
-
-//     impl BlockData {
-//         pub fn to_head_event(self) -> String {
-//             format!(
-//                 r#"{{"slot": "{}", "block": "{}"}}"#,
-//                 self.slot,
-//                 format!("0x{:x}", self.hash)
-//             )
-//         }
-//     }
-
-//     #[tokio::test]
-//     async fn test_handler_on_initial_event() {
-//         let mut mock_synchronizer = Box::new(MockCommonSynchronizer::new());
-//         let mut mock_beacon_client = MockCommonBeaconClient::new();
-
-//         let initial_start_block_id = BlockId::Slot(1);
-
-//         let block_data = Box::new(BlockData {
-//             slot: 4,
-//             hash: _create_hash("4"),
-//             parent_hash: None,
-//         });
-
-//         _prepare_handler_calls(
-//             &mut mock_beacon_client,
-//             &mut mock_synchronizer,
-//             &block_data,
-//             Some(initial_start_block_id.clone()),
-//         );
-
-//         let mock_context = Context::new(Some(mock_beacon_client), None, None);
-
-//         let mut head_event_handler =
-//             HeadEventHandler::new(mock_context, mock_synchronizer, initial_start_block_id);
-
-//         let result = head_event_handler.handle(block_data.to_head_event()).await;
-
-//         assert!(result.is_ok())
-//     }
-
-//     #[tokio::test]
-//     async fn test_handler_after_first_event() {
-//         let mut mock_synchronizer = Box::new(MockCommonSynchronizer::new());
-//         let mut mock_beacon_client = MockCommonBeaconClient::new();
-
-//         let initial_start_block_id = BlockId::Slot(1);
-
-//         let first_head_block = BlockData {
-//             hash: _create_hash("5"),
-//             slot: 5,
-//             parent_hash: None,
-//         };
-//         let second_head_block = BlockData {
-//             hash: _create_hash("6"),
-//             slot: 6,
-//             parent_hash: Some(first_head_block.hash),
-//         };
-
-//         _prepare_handler_calls(
-//             &mut mock_beacon_client,
-//             &mut mock_synchronizer,
-//             &first_head_block,
-//             Some(initial_start_block_id.clone()),
-//         );
-
-//         _prepare_handler_calls(
-//             &mut mock_beacon_client,
-//             &mut mock_synchronizer,
-//             &second_head_block,
-//             None,
-//         );
-
-//         let mock_context = Context::new(Some(mock_beacon_client), None, None);
-
-//         let mut head_event_handler =
-//             HeadEventHandler::new(mock_context, mock_synchronizer, initial_start_block_id);
-
-//         let result = head_event_handler
-//             .handle(first_head_block.to_head_event())
-//             .await;
-
-//         assert!(
-//             result.is_ok(),
-//             "Expected first head event handler to succeed"
-//         );
-
-//         let result = head_event_handler
-//             .handle(second_head_block.to_head_event())
-//             .await;
-
-//         assert!(
-//             result.is_ok(),
-//             "Expected second head event handler to succeed"
-//         );
-//     }
-
-//     #[tokio::test]
-//     async fn test_handler_on_reorg() {
-//         let mut mock_synchronizer = Box::new(MockCommonSynchronizer::new());
-//         let mut mock_beacon_client = MockCommonBeaconClient::new();
-//         let mut mock_blobscan_client = MockCommonBlobscanClient::new();
-
-//         let initial_start_block_id = BlockId::Slot(1);
-
-//         let before_reorg_block = BlockData {
-//             slot: 2,
-//             hash: _create_hash("2"),
-//             parent_hash: Some(_create_hash("1")),
-//         };
-//         let reorged_block = BlockData {
-//             slot: 5,
-//             hash: _create_hash("5"),
-//             parent_hash: Some(_create_hash("4")),
-//         };
-//         let after_reorg_block = BlockData {
-//             slot: 6,
-//             hash: _create_hash("3b"),
-//             parent_hash: Some(before_reorg_block.hash),
-//         };
-
-//         _stub_get_block_header(&mut mock_beacon_client, &before_reorg_block);
-
-//         _prepare_handler_calls(
-//             &mut mock_beacon_client,
-//             &mut mock_synchronizer,
-//             &reorged_block,
-//             Some(initial_start_block_id.clone()),
-//         );
-
-//         _prepare_handler_calls(
-//             &mut mock_beacon_client,
-//             &mut mock_synchronizer,
-//             &after_reorg_block,
-//             None,
-//         );
-
-//         _stub_handle_reorged_slots(
-//             &mut mock_blobscan_client,
-//             (before_reorg_block.slot + 1..after_reorg_block.slot).collect::<Vec<u32>>(),
-//         );
-
-//         // We're expecting the synchronizer to re-sync the parent block of the reorged block
-//         _stub_synchronizer_run(
-//             &mut mock_synchronizer,
-//             BlockId::Slot(before_reorg_block.slot),
-//             BlockId::Slot(before_reorg_block.slot + 1),
-//         );
-
-//         let mock_context = Context::new(Some(mock_beacon_client), Some(mock_blobscan_client), None);
-//         let mut head_event_handler =
-//             HeadEventHandler::new(mock_context, mock_synchronizer, initial_start_block_id);
-
-//         let result = head_event_handler
-//             .handle(reorged_block.to_head_event())
-//             .await;
-
-//         assert!(
-//             result.is_ok(),
-//             "Expected first head event handling to succeed"
-//         );
-
-//         let result = head_event_handler
-//             .handle(after_reorg_block.to_head_event())
-//             .await;
-
-//         assert!(
-//             result.is_ok(),
-//             "Expected reorged head event handling to succeed"
-//         );
-//     }
-
-//     #[tokio::test]
-//     async fn test_handler_on_one_depth_reorg() {
-//         // Slots:
-//         // 4 -> 5
-//         //      6 -> 7 -> ...
-//         let mut mock_synchronizer = Box::new(MockCommonSynchronizer::new());
-//         let mut mock_beacon_client = MockCommonBeaconClient::new();
-//         let mut mock_blobscan_client = MockCommonBlobscanClient::new();
-
-//         let initial_start_block_id = BlockId::Slot(1);
-
-//         let block_before_reorg = BlockData {
-//             slot: 4,
-//             hash: _create_hash("4"),
-//             parent_hash: None,
-//         };
-//         let reorged_block = BlockData {
-//             slot: 5,
-//             hash: _create_hash("50"),
-//             parent_hash: Some(block_before_reorg.hash),
-//         };
-//         let block_after_reorg = BlockData {
-//             slot: 6,
-//             hash: _create_hash("5"),
-//             parent_hash: Some(block_before_reorg.hash),
-//         };
-
-//         _prepare_handler_calls(
-//             &mut mock_beacon_client,
-//             &mut mock_synchronizer,
-//             &reorged_block,
-//             Some(initial_start_block_id.clone()),
-//         );
-//         _prepare_handler_calls(
-//             &mut mock_beacon_client,
-//             &mut mock_synchronizer,
-//             &block_after_reorg,
-//             None,
-//         );
-
-//         _stub_get_block_header(&mut mock_beacon_client, &block_before_reorg);
-
-//         _stub_handle_reorged_slots(&mut mock_blobscan_client, vec![reorged_block.slot]);
-
-//         _stub_synchronizer_run(
-//             &mut mock_synchronizer,
-//             BlockId::Slot(block_before_reorg.slot),
-//             BlockId::Slot(block_before_reorg.slot + 1),
-//         );
-
-//         let mock_context = Context::new(Some(mock_beacon_client), Some(mock_blobscan_client), None);
-
-//         let mut head_event_handler =
-//             HeadEventHandler::new(mock_context, mock_synchronizer, initial_start_block_id);
-
-//         let result = head_event_handler
-//             .handle(reorged_block.to_head_event())
-//             .await;
-
-//         assert!(
-//             result.is_ok(),
-//             "Expected first head event handling to succeed"
-//         );
-
-//         let result = head_event_handler
-//             .handle(block_after_reorg.to_head_event())
-//             .await;
-
-//         assert!(
-//             result.is_ok(),
-//             "Expected reorged head event handling to succeed"
-//         );
-//     }
-
-//     #[tokio::test]
-//     async fn test_handler_on_one_depth_former_reorg() {
-//         // Reorged block is reorged back to its former parent
-//         // Slots:
-//         // 4 -> 5 -> 7 -> ...
-//         //      6
-//         let mut mock_synchronizer = Box::new(MockCommonSynchronizer::new());
-//         let mut mock_beacon_client = MockCommonBeaconClient::new();
-//         let mut mock_blobscan_client = MockCommonBlobscanClient::new();
-
-//         let initial_start_block_id = BlockId::Slot(1);
-
-//         let before_reorg_parent_block = BlockData {
-//             slot: 4,
-//             hash: _create_hash("4"),
-//             parent_hash: None,
-//         };
-//         let before_reorg_block = BlockData {
-//             slot: 5,
-//             hash: _create_hash("50"),
-//             parent_hash: Some(before_reorg_parent_block.hash),
-//         };
-//         let reorged_block = BlockData {
-//             slot: 6,
-//             hash: _create_hash("5"),
-//             parent_hash: Some(before_reorg_parent_block.hash),
-//         };
-//         let after_reorg_block = BlockData {
-//             slot: 7,
-//             hash: _create_hash("7"),
-//             parent_hash: Some(before_reorg_block.hash),
-//         };
-
-//         _prepare_handler_calls(
-//             &mut mock_beacon_client,
-//             &mut mock_synchronizer,
-//             &before_reorg_block,
-//             Some(initial_start_block_id.clone()),
-//         );
-//         _prepare_handler_calls(
-//             &mut mock_beacon_client,
-//             &mut mock_synchronizer,
-//             &reorged_block,
-//             None,
-//         );
-//         _prepare_handler_calls(
-//             &mut mock_beacon_client,
-//             &mut mock_synchronizer,
-//             &after_reorg_block,
-//             None,
-//         );
-
-//         _stub_get_block_header(&mut mock_beacon_client, &before_reorg_parent_block);
-
-//         _stub_handle_reorged_slots(&mut mock_blobscan_client, vec![before_reorg_block.slot]);
-
-//         _stub_synchronizer_run(
-//             &mut mock_synchronizer,
-//             BlockId::Slot(before_reorg_parent_block.slot),
-//             BlockId::Slot(before_reorg_parent_block.slot + 1),
-//         );
-
-//         _stub_handle_reorged_slots(&mut mock_blobscan_client, vec![reorged_block.slot]);
-
-//         _stub_synchronizer_run(
-//             &mut mock_synchronizer,
-//             BlockId::Slot(before_reorg_block.slot),
-//             BlockId::Slot(before_reorg_block.slot + 1),
-//         );
-
-//         let mock_context = Context::new(Some(mock_beacon_client), Some(mock_blobscan_client), None);
-
-//         let mut head_event_handler =
-//             HeadEventHandler::new(mock_context, mock_synchronizer, initial_start_block_id);
-
-//         let result = head_event_handler
-//             .handle(before_reorg_block.to_head_event())
-//             .await;
-
-//         assert!(
-//             result.is_ok(),
-//             "Expected first head event handling to succeed"
-//         );
-
-//         let result = head_event_handler
-//             .handle(reorged_block.to_head_event())
-//             .await;
-
-//         assert!(
-//             result.is_ok(),
-//             "Expected reorged head event handling to succeed"
-//         );
-
-//         let result = head_event_handler
-//             .handle(after_reorg_block.to_head_event())
-//             .await;
-
-//         assert!(
-//             result.is_ok(),
-//             "Expected after reorged head event handling to succeed"
-//         );
-//     }
-
-//     #[tokio::test]
-//     async fn test_handler_on_reorg_with_error() {
-//         let mut mock_synchronizer = Box::new(MockCommonSynchronizer::new());
-//         let mut mock_beacon_client = MockCommonBeaconClient::new();
-//         let mut mock_blobscan_client = MockCommonBlobscanClient::new();
-
-//         let initial_start_block_id = BlockId::Slot(1);
-
-//         let before_reorg_parent_block = BlockData {
-//             slot: 3,
-//             hash: _create_hash("3"),
-//             parent_hash: None,
-//         };
-//         let before_reorg_block = BlockData {
-//             slot: 4,
-//             hash: _create_hash("4"),
-//             parent_hash: Some(before_reorg_parent_block.hash),
-//         };
-//         let first_block = BlockData {
-//             slot: 5,
-//             hash: _create_hash("5"),
-//             parent_hash: Some(before_reorg_block.hash),
-//         };
-//         let reorged_block = BlockData {
-//             slot: 6,
-//             hash: _create_hash("999"),
-//             parent_hash: Some(before_reorg_block.hash),
-//         };
-
-//         _prepare_handler_calls(
-//             &mut mock_beacon_client,
-//             &mut mock_synchronizer,
-//             &first_block,
-//             Some(initial_start_block_id.clone()),
-//         );
-
-//         _stub_get_block_header(&mut mock_beacon_client, &reorged_block);
-
-//         _stub_get_block_header(&mut mock_beacon_client, &before_reorg_block);
-
-//         mock_blobscan_client
-//             .expect_handle_reorged_slots()
-//             .returning(|_x| {
-//                 Box::pin(async move {
-//                     Err(crate::clients::common::ClientError::Other(anyhow!(
-//                         "Internal blobscan client error"
-//                     )))
-//                 })
-//             });
-
-//         mock_blobscan_client
-//             .expect_update_sync_state()
-//             .times(1)
-//             .with(eq(BlockchainSyncState {
-//                 last_finalized_block: None,
-//                 last_lower_synced_slot: None,
-//                 last_upper_synced_slot: Some(before_reorg_parent_block.slot),
-//             }))
-//             .returning(|_x| Box::pin(async move { Ok(()) }));
-
-//         let mock_context = Context::new(Some(mock_beacon_client), Some(mock_blobscan_client), None);
-
-//         let mut head_event_handler =
-//             HeadEventHandler::new(mock_context, mock_synchronizer, initial_start_block_id);
-
-//         let result = head_event_handler.handle(first_block.to_head_event()).await;
-
-//         assert!(
-//             result.is_ok(),
-//             "Expected first head event handling to succeed"
-//         );
-
-//         let result = head_event_handler
-//             .handle(reorged_block.to_head_event())
-//             .await;
-
-//         assert!(
-//             result.is_err(),
-//             "Expected reorged head event handling to fail"
-//         );
-//     }
-
-//     fn _prepare_handler_calls(
-//         mock_beacon_client: &mut MockCommonBeaconClient,
-//         mock_synchronizer: &mut MockCommonSynchronizer,
-//         head_block_data: &BlockData,
-//         initial_block_id: Option<BlockId>,
-//     ) {
-//         let slot = head_block_data.slot;
-
-//         _stub_get_block_header(mock_beacon_client, head_block_data);
-
-//         _stub_synchronizer_run(
-//             mock_synchronizer,
-//             initial_block_id.unwrap_or(BlockId::Slot(slot)),
-//             BlockId::Slot(slot + 1),
-//         )
-//     }
-
-//     fn _stub_get_block_header(
-//         mock_beacon_client: &mut MockCommonBeaconClient,
-//         block_data: &BlockData,
-//     ) {
-//         let root = block_data.hash;
-//         let slot = block_data.slot;
-//         let parent_root = block_data
-//             .parent_hash
-//             .unwrap_or(_create_hash((slot - 1).to_string().as_str()));
-
-//         mock_beacon_client
-//             .expect_get_block_header()
-//             .with(eq(BlockId::Slot(block_data.slot)))
-//             .returning(move |_x| {
-//                 Box::pin(async move {
-//                     Ok(Some(BlockHeader {
-//                         root,
-//                         header: InnerBlockHeader {
-//                             message: BlockHeaderMessage { parent_root, slot },
-//                         },
-//                     }))
-//                 })
-//             });
-//         mock_beacon_client
-//             .expect_get_block_header()
-//             .with(eq(BlockId::Hash(block_data.hash)))
-//             .returning(move |_x| {
-//                 Box::pin(async move {
-//                     Ok(Some(BlockHeader {
-//                         root,
-//                         header: InnerBlockHeader {
-//                             message: BlockHeaderMessage { parent_root, slot },
-//                         },
-//                     }))
-//                 })
-//             });
-//     }
-
-//     fn _stub_handle_reorged_slots(
-//         mock_blobscan_client: &mut MockCommonBlobscanClient,
-//         reorged_slots: Vec<u32>,
-//     ) {
-//         let reorged_slots_len = reorged_slots.len() as u32;
-
-//         mock_blobscan_client
-//             .expect_handle_reorged_slots()
-//             .with(eq(reorged_slots))
-//             .returning(move |_x| Box::pin(async move { Ok(reorged_slots_len) }));
-//     }
-
-//     fn _stub_synchronizer_run(
-//         mock_synchronizer: &mut MockCommonSynchronizer,
-//         initial_block_id: BlockId,
-//         final_block_id: BlockId,
-//     ) {
-//         mock_synchronizer
-//             .expect_run()
-//             .times(1)
-//             .with(eq(initial_block_id.clone()), eq(final_block_id))
-//             .returning(|_x, _y| Box::pin(async { Ok(()) }));
-//     }
-
-//     fn _create_hash(input: &str) -> B256 {
-//         // Ensure the input string is at most 64 characters
-//         let truncated_input = if input.len() > 64 {
-//             &input[0..64]
-//         } else {
-//             input
-//         };
-
-//         // Format the string to have a length of 64 characters by padding with zeros
-//         let hash = format!("0x{:0>64}", truncated_input);
-
-//         hash.parse().unwrap()
-//     }
-
-//     fn _create_head_event(slot: u32, block_hash: B256) -> String {
-//         let head_event = format!(
-//             r#"{{"slot": "{}", "block": "{}"}}"#,
-//             slot,
-//             format!("0x{:x}", block_hash)
-//         );
-
-//         head_event
-//     }
-// }
+/// Splits the given slots at the epoch boundaries, pairing each part with its
+/// epoch.
+fn epoch_ranges(slots: Range<u32>, slots_per_epoch: u32) -> Vec<(u32, Range<u32>)> {
+    let mut ranges = vec![];
+    let mut start = slots.start;
+
+    while start < slots.end {
+        let epoch = start / slots_per_epoch;
+        let end = cmp::min((epoch + 1).saturating_mul(slots_per_epoch), slots.end);
+
+        ranges.push((epoch, start..end));
+        start = end;
+    }
+
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BTreeMap, HashMap},
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use alloy::{primitives::B256, transports::BoxTransport};
+    use anyhow::anyhow;
+    use mockall::predicate::eq;
+
+    use super::HeadEventHandler;
+    use crate::{
+        clients::{
+            beacon::{
+                types::{BlockHeader, BlockHeaderMessage, BlockId, InnerBlockHeader},
+                MockCommonBeaconClient,
+            },
+            blobscan::{types::BlockchainSyncState, MockCommonBlobscanClient},
+            common::ClientError,
+        },
+        context::Context,
+        indexer::reorg_confirmation::ReorgConfirmation,
+        synchronizer::MockCommonSynchronizer,
+    };
+
+    #[derive(Clone, Debug)]
+    struct BlockData {
+        slot: u32,
+        hash: B256,
+        parent_hash: B256,
+    }
+
+    impl BlockData {
+        fn new(slot: u32, hash: &str, parent: Option<&BlockData>) -> Self {
+            Self {
+                slot,
+                hash: create_hash(hash),
+                parent_hash: parent
+                    .map(|parent| parent.hash)
+                    .unwrap_or_else(|| create_hash(&(slot - 1).to_string())),
+            }
+        }
+
+        fn to_head_event(&self) -> String {
+            format!(
+                r#"{{"slot": "{}", "block": "{:#x}"}}"#,
+                self.slot, self.hash
+            )
+        }
+
+        fn to_header(&self) -> BlockHeader {
+            BlockHeader {
+                root: self.hash,
+                header: InnerBlockHeader {
+                    message: BlockHeaderMessage {
+                        parent_root: self.parent_hash,
+                        slot: self.slot,
+                    },
+                },
+            }
+        }
+    }
+
+    /// Chain served by the mocked beacon client. Blocks can always be looked up
+    /// by root, while slots only resolve to the blocks of the canonical chain.
+    #[derive(Clone, Default)]
+    struct Chain(Arc<Mutex<ChainState>>);
+
+    #[derive(Default)]
+    struct ChainState {
+        blocks: HashMap<B256, BlockData>,
+        canonical: BTreeMap<u32, B256>,
+    }
+
+    impl Chain {
+        fn set_canonical(&self, blocks: &[&BlockData]) {
+            let mut state = self.0.lock().unwrap();
+
+            state.canonical.clear();
+
+            for block in blocks {
+                state.canonical.insert(block.slot, block.hash);
+                state.blocks.insert(block.hash, (*block).clone());
+            }
+        }
+
+        fn header(&self, block_id: &BlockId) -> Option<BlockHeader> {
+            let state = self.0.lock().unwrap();
+            let root = match block_id {
+                BlockId::Slot(slot) => *state.canonical.get(slot)?,
+                BlockId::Hash(root) => *root,
+                _ => return None,
+            };
+
+            state.blocks.get(&root).map(BlockData::to_header)
+        }
+    }
+
+    fn mock_beacon_client(chain: &Chain) -> MockCommonBeaconClient {
+        let mut mock_beacon_client = MockCommonBeaconClient::new();
+        let chain = chain.clone();
+
+        mock_beacon_client
+            .expect_get_block_header()
+            .returning(move |block_id| {
+                let header = chain.header(block_id);
+
+                Box::pin(async move { Ok(header) })
+            });
+        mock_beacon_client
+            .expect_invalidate_block_headers()
+            .return_const(());
+        mock_beacon_client
+            .expect_get_block()
+            .returning(|_| Box::pin(async { Ok(None) }));
+
+        mock_beacon_client
+    }
+
+    fn expect_synchronizer_run(
+        mock_synchronizer: &mut MockCommonSynchronizer,
+        initial_slot: u32,
+        final_slot: u32,
+    ) {
+        mock_synchronizer
+            .expect_run()
+            .times(1)
+            .with(
+                eq(BlockId::Slot(initial_slot)),
+                eq(BlockId::Slot(final_slot)),
+            )
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+    }
+
+    fn expect_reorged_slots(mock_blobscan_client: &mut MockCommonBlobscanClient, slots: Vec<u32>) {
+        let total_updated_slots = slots.len() as u32;
+
+        mock_blobscan_client
+            .expect_handle_reorged_slots()
+            .times(1)
+            .with(eq(slots))
+            .returning(move |_| Box::pin(async move { Ok(total_updated_slots) }));
+    }
+
+    fn create_handler(
+        chain: &Chain,
+        mut mock_blobscan_client: MockCommonBlobscanClient,
+        mock_synchronizer: MockCommonSynchronizer,
+    ) -> HeadEventHandler<BoxTransport> {
+        mock_blobscan_client
+            .expect_get_block()
+            .returning(|_| Box::pin(async { Ok(None) }));
+
+        let context = Context::with_mocks(
+            Some(mock_beacon_client(chain)),
+            Some(mock_blobscan_client),
+            None,
+        );
+
+        HeadEventHandler::new(context, Box::new(mock_synchronizer), BlockId::Slot(1))
+    }
+
+    fn create_hash(input: &str) -> B256 {
+        format!("0x{input:0>64}").parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_handler_on_initial_event() {
+        let chain = Chain::default();
+        let mut mock_synchronizer = MockCommonSynchronizer::new();
+        let head_block = BlockData::new(4, "4", None);
+
+        chain.set_canonical(&[&head_block]);
+        expect_synchronizer_run(&mut mock_synchronizer, 1, 5);
+
+        let mut handler =
+            create_handler(&chain, MockCommonBlobscanClient::new(), mock_synchronizer);
+
+        assert!(handler.handle(head_block.to_head_event()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_handler_catches_up_epoch_by_epoch() {
+        let chain = Chain::default();
+        let mut mock_catch_up_synchronizer = MockCommonSynchronizer::new();
+        let head_block = BlockData::new(20, "20", None);
+
+        chain.set_canonical(&[&head_block]);
+        expect_synchronizer_run(&mut mock_catch_up_synchronizer, 1, 8);
+        expect_synchronizer_run(&mut mock_catch_up_synchronizer, 8, 16);
+        expect_synchronizer_run(&mut mock_catch_up_synchronizer, 16, 21);
+
+        let mut handler = create_handler(
+            &chain,
+            MockCommonBlobscanClient::new(),
+            MockCommonSynchronizer::new(),
+        )
+        .with_catch_up_synchronizer(Box::new(mock_catch_up_synchronizer), 4)
+        .with_slots_per_epoch(8);
+
+        assert!(handler.handle(head_block.to_head_event()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_handler_after_first_event() {
+        let chain = Chain::default();
+        let mut mock_synchronizer = MockCommonSynchronizer::new();
+        let first_head_block = BlockData::new(5, "5", None);
+        let second_head_block = BlockData::new(6, "6", Some(&first_head_block));
+
+        chain.set_canonical(&[&first_head_block, &second_head_block]);
+        expect_synchronizer_run(&mut mock_synchronizer, 1, 6);
+        expect_synchronizer_run(&mut mock_synchronizer, 6, 7);
+
+        let mut handler =
+            create_handler(&chain, MockCommonBlobscanClient::new(), mock_synchronizer);
+
+        assert!(handler
+            .handle(first_head_block.to_head_event())
+            .await
+            .is_ok());
+        assert!(handler
+            .handle(second_head_block.to_head_event())
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_handler_on_missed_head_events() {
+        // Slots:
+        // 5 -> 6 -> 7, where the event of 6 never arrives
+        let chain = Chain::default();
+        let mut mock_synchronizer = MockCommonSynchronizer::new();
+        let first_head_block = BlockData::new(5, "5", None);
+        let missed_block = BlockData::new(6, "6", Some(&first_head_block));
+        let second_head_block = BlockData::new(7, "7", Some(&missed_block));
+
+        chain.set_canonical(&[&first_head_block, &missed_block, &second_head_block]);
+        expect_synchronizer_run(&mut mock_synchronizer, 1, 6);
+        expect_synchronizer_run(&mut mock_synchronizer, 6, 8);
+
+        // No reorg is reported as the last synced block is still canonical
+        let mut handler =
+            create_handler(&chain, MockCommonBlobscanClient::new(), mock_synchronizer);
+
+        assert!(handler
+            .handle(first_head_block.to_head_event())
+            .await
+            .is_ok());
+        assert!(handler
+            .handle(second_head_block.to_head_event())
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_handler_on_one_depth_reorg() {
+        // Slots:
+        // 4 -> 5
+        //   -> 6 -> 7 -> ...
+        let chain = Chain::default();
+        let mut mock_synchronizer = MockCommonSynchronizer::new();
+        let mut mock_blobscan_client = MockCommonBlobscanClient::new();
+        let block_before_reorg = BlockData::new(4, "4", None);
+        let reorged_block = BlockData::new(5, "50", Some(&block_before_reorg));
+        let block_after_reorg = BlockData::new(6, "6", Some(&block_before_reorg));
+
+        chain.set_canonical(&[&block_before_reorg, &reorged_block]);
+        expect_synchronizer_run(&mut mock_synchronizer, 1, 6);
+        expect_reorged_slots(&mut mock_blobscan_client, vec![reorged_block.slot]);
+        // The common ancestor is synced again along with the new head
+        expect_synchronizer_run(&mut mock_synchronizer, 4, 7);
+
+        let mut handler = create_handler(&chain, mock_blobscan_client, mock_synchronizer);
+
+        assert!(handler.handle(reorged_block.to_head_event()).await.is_ok());
+
+        chain.set_canonical(&[&block_before_reorg, &block_after_reorg]);
+
+        assert!(handler
+            .handle(block_after_reorg.to_head_event())
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_handler_on_one_depth_former_reorg() {
+        // Reorged block is reorged back to its former parent
+        // Slots:
+        // 4 -> 5 -> 7 -> ...
+        //   -> 6
+        let chain = Chain::default();
+        let mut mock_synchronizer = MockCommonSynchronizer::new();
+        let mut mock_blobscan_client = MockCommonBlobscanClient::new();
+        let before_reorg_parent_block = BlockData::new(4, "4", None);
+        let before_reorg_block = BlockData::new(5, "50", Some(&before_reorg_parent_block));
+        let reorged_block = BlockData::new(6, "6", Some(&before_reorg_parent_block));
+        let after_reorg_block = BlockData::new(7, "7", Some(&before_reorg_block));
+
+        chain.set_canonical(&[&before_reorg_parent_block, &before_reorg_block]);
+        expect_synchronizer_run(&mut mock_synchronizer, 1, 6);
+        expect_reorged_slots(&mut mock_blobscan_client, vec![before_reorg_block.slot]);
+        expect_synchronizer_run(&mut mock_synchronizer, 4, 7);
+        expect_reorged_slots(&mut mock_blobscan_client, vec![reorged_block.slot]);
+        expect_synchronizer_run(&mut mock_synchronizer, 5, 8);
+
+        let mut handler = create_handler(&chain, mock_blobscan_client, mock_synchronizer);
+
+        assert!(handler
+            .handle(before_reorg_block.to_head_event())
+            .await
+            .is_ok());
+
+        chain.set_canonical(&[&before_reorg_parent_block, &reorged_block]);
+
+        assert!(handler.handle(reorged_block.to_head_event()).await.is_ok());
+
+        chain.set_canonical(&[
+            &before_reorg_parent_block,
+            &before_reorg_block,
+            &after_reorg_block,
+        ]);
+
+        assert!(handler
+            .handle(after_reorg_block.to_head_event())
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_handler_on_deep_reorg() {
+        // Slots:
+        // 4 -> 5 -> 6 -> 7
+        //        -> 8 -> 9
+        let chain = Chain::default();
+        let mut mock_synchronizer = MockCommonSynchronizer::new();
+        let mut mock_blobscan_client = MockCommonBlobscanClient::new();
+        let old_chain = [
+            BlockData::new(4, "4", None),
+            BlockData::new(5, "5", None),
+            BlockData::new(6, "6", None),
+            BlockData::new(7, "7", None),
+        ];
+        let new_block = BlockData::new(8, "80", Some(&old_chain[1]));
+        let new_head_block = BlockData::new(9, "90", Some(&new_block));
+
+        chain.set_canonical(&old_chain.iter().collect::<Vec<_>>());
+        expect_synchronizer_run(&mut mock_synchronizer, 1, 5);
+
+        for block in &old_chain[1..] {
+            expect_synchronizer_run(&mut mock_synchronizer, block.slot, block.slot + 1);
+        }
+
+        // The common ancestor is found among the recently synced blocks
+        expect_reorged_slots(&mut mock_blobscan_client, vec![6, 7, 8]);
+        expect_synchronizer_run(&mut mock_synchronizer, 5, 10);
+
+        let mut handler = create_handler(&chain, mock_blobscan_client, mock_synchronizer);
+
+        for block in &old_chain {
+            assert!(handler.handle(block.to_head_event()).await.is_ok());
+        }
+
+        chain.set_canonical(&[&old_chain[0], &old_chain[1], &new_block, &new_head_block]);
+
+        assert!(handler.handle(new_head_block.to_head_event()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_handler_holds_deep_reorg_until_confirmed() {
+        // Slots:
+        // 4 -> 5 -> 6
+        //   -> 7
+        let chain = Chain::default();
+        let mut mock_synchronizer = MockCommonSynchronizer::new();
+        let mut mock_blobscan_client = MockCommonBlobscanClient::new();
+        let old_chain = [
+            BlockData::new(4, "4", None),
+            BlockData::new(5, "5", None),
+            BlockData::new(6, "6", None),
+        ];
+        let new_head_block = BlockData::new(7, "70", Some(&old_chain[0]));
+        let reorg_confirmation = Arc::new(ReorgConfirmation::new(2));
+
+        chain.set_canonical(&old_chain.iter().collect::<Vec<_>>());
+        expect_synchronizer_run(&mut mock_synchronizer, 1, 5);
+        expect_synchronizer_run(&mut mock_synchronizer, 5, 6);
+        expect_synchronizer_run(&mut mock_synchronizer, 6, 7);
+        expect_reorged_slots(&mut mock_blobscan_client, vec![5, 6]);
+        expect_synchronizer_run(&mut mock_synchronizer, 4, 8);
+
+        let mut handler = create_handler(&chain, mock_blobscan_client, mock_synchronizer)
+            .with_reorg_confirmation(reorg_confirmation.clone());
+
+        for block in &old_chain {
+            assert!(handler.handle(block.to_head_event()).await.is_ok());
+        }
+
+        chain.set_canonical(&[&old_chain[0], &new_head_block]);
+
+        let handling =
+            tokio::spawn(async move { handler.handle(new_head_block.to_head_event()).await });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // The reorg is 3 slots deep
+        assert!(!handling.is_finished());
+        assert_eq!(
+            reorg_confirmation.confirm().map(|reorg| reorg.depth),
+            Some(3)
+        );
+        assert!(handling.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_handler_on_reorg_with_error() {
+        let chain = Chain::default();
+        let mut mock_synchronizer = MockCommonSynchronizer::new();
+        let mut mock_blobscan_client = MockCommonBlobscanClient::new();
+        let before_reorg_parent_block = BlockData::new(3, "3", None);
+        let before_reorg_block = BlockData::new(4, "4", Some(&before_reorg_parent_block));
+        let first_block = BlockData::new(5, "5", Some(&before_reorg_block));
+        let reorged_block = BlockData::new(6, "999", Some(&before_reorg_block));
+
+        chain.set_canonical(&[
+            &before_reorg_parent_block,
+            &before_reorg_block,
+            &first_block,
+        ]);
+        expect_synchronizer_run(&mut mock_synchronizer, 1, 6);
+        mock_blobscan_client
+            .expect_handle_reorged_slots()
+            .times(1)
+            .returning(|_| {
+                Box::pin(async move {
+                    Err(ClientError::Other(anyhow!(
+                        "Internal blobscan client error"
+                    )))
+                })
+            });
+        // The sync state is rolled back to the last slot known before the reorg
+        mock_blobscan_client
+            .expect_update_sync_state()
+            .times(1)
+            .with(eq(BlockchainSyncState {
+                last_finalized_block: None,
+                last_lower_synced_slot: None,
+                last_upper_synced_slot: Some(before_reorg_parent_block.slot),
+            }))
+            .returning(|_| Box::pin(async move { Ok(()) }));
+
+        let mut handler = create_handler(&chain, mock_blobscan_client, mock_synchronizer);
+
+        assert!(handler.handle(first_block.to_head_event()).await.is_ok());
+
+        chain.set_canonical(&[
+            &before_reorg_parent_block,
+            &before_reorg_block,
+            &reorged_block,
+        ]);
+
+        assert!(handler.handle(reorged_block.to_head_event()).await.is_err());
+    }
+}
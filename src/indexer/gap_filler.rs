@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use alloy::transports::BoxTransport;
+use tracing::{debug, info, warn};
+
+use crate::{
+    commands::verify::{verify_slot, Discrepancy},
+    context::CommonContext,
+    network::ForkSchedule,
+    slots_processor::SlotsProcessor,
+};
+
+use super::error::GapFillingError;
+
+/// Amount of slots checked on every round
+const GAP_FILL_BATCH_SIZE: u32 = 100;
+
+/// Periodically walks the range of slots already marked as synced in Blobscan
+/// looking for blocks that are missing or incomplete and reindexes them.
+pub struct GapFiller<T> {
+    context: Box<dyn CommonContext<T>>,
+    interval: Duration,
+    cursor: Option<u32>,
+    fork_schedule: Option<ForkSchedule>,
+}
+
+impl GapFiller<BoxTransport> {
+    pub fn new(context: Box<dyn CommonContext<BoxTransport>>, interval: Duration) -> Self {
+        Self {
+            context,
+            interval,
+            cursor: None,
+            fork_schedule: None,
+        }
+    }
+
+    /// Checks the blobs of reindexed blocks against the limit of their fork
+    /// and tags the blocks with it.
+    pub fn with_fork_schedule(mut self, fork_schedule: ForkSchedule) -> Self {
+        self.fork_schedule = Some(fork_schedule);
+
+        self
+    }
+
+    pub async fn run(&mut self) {
+        let mut interval = tokio::time::interval(self.interval);
+
+        loop {
+            interval.tick().await;
+
+            match self.fill_next_batch().await {
+                Ok(filled_slots) if filled_slots > 0 => {
+                    info!(filled_slots, "Gaps filled");
+                }
+                Ok(_) => {}
+                Err(error) => {
+                    warn!(?error, "Gap filling round failed. Retrying on next round…");
+                }
+            }
+        }
+    }
+
+    /// Checks the next batch of synced slots and reindexes the ones with
+    /// missing data. Returns the amount of reindexed slots.
+    async fn fill_next_batch(&mut self) -> Result<u32, GapFillingError> {
+        let sync_state = self
+            .context
+            .blobscan_client()
+            .get_sync_state()
+            .await
+            .map_err(GapFillingError::SyncStateRetrievalError)?;
+
+        let (lower_slot, upper_slot) = match sync_state {
+            Some(state) => match (state.last_lower_synced_slot, state.last_upper_synced_slot) {
+                (Some(lower), Some(upper)) if lower <= upper => (lower, upper),
+                _ => return Ok(0),
+            },
+            None => return Ok(0),
+        };
+
+        let from_slot = match self.cursor {
+            Some(cursor) if cursor >= lower_slot && cursor <= upper_slot => cursor,
+            _ => lower_slot,
+        };
+        let to_slot = from_slot
+            .saturating_add(GAP_FILL_BATCH_SIZE)
+            .min(upper_slot + 1);
+
+        debug!(from_slot, to_slot, "Looking for gaps…");
+
+        let mut slots_processor = SlotsProcessor::new(self.context.clone());
+
+        if let Some(fork_schedule) = &self.fork_schedule {
+            slots_processor = slots_processor.with_fork_schedule(fork_schedule.clone());
+        }
+        let mut filled_slots = 0;
+
+        for slot in from_slot..to_slot {
+            let discrepancies = verify_slot(self.context.as_ref(), slot)
+                .await
+                .map_err(|error| GapFillingError::SlotCheckFailure { slot, error })?;
+
+            if !discrepancies.iter().any(is_fillable) {
+                continue;
+            }
+
+            info!(slot, "Gap detected. Reindexing slot…");
+
+            slots_processor
+                .process_slot(slot)
+                .await
+                .map_err(|error| GapFillingError::SlotFillFailure { slot, error })?;
+
+            filled_slots += 1;
+        }
+
+        // Start over from the lower bound once the whole synced range was checked
+        self.cursor = if to_slot > upper_slot {
+            None
+        } else {
+            Some(to_slot)
+        };
+
+        Ok(filled_slots)
+    }
+}
+
+/// Data that is present on the chain but not in Blobscan can be filled by
+/// reindexing the slot. Unexpected data can't be removed that way.
+fn is_fillable(discrepancy: &Discrepancy) -> bool {
+    !matches!(
+        discrepancy,
+        Discrepancy::UnexpectedBlock { .. } | Discrepancy::UnexpectedTransaction { .. }
+    )
+}
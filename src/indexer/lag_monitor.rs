@@ -0,0 +1,180 @@
+use std::{sync::Arc, time::Duration};
+
+use alloy::transports::BoxTransport;
+use serde::Serialize;
+use tracing::{error, info, warn};
+use url::Url;
+
+use crate::{
+    clients::beacon::types::BlockId,
+    context::CommonContext,
+    metrics::{increment_counter, set_gauge},
+    tunables::Tunables,
+};
+
+/// Webhook payload sent when the indexer falls behind the head and once it
+/// catches up again.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct HeadLagAlert<'a> {
+    network: &'a str,
+    resolved: bool,
+    head_slot: u32,
+    last_synced_slot: u32,
+    lag_slots: u32,
+    max_lag_slots: u32,
+}
+
+/// Periodically compares the last slot synced into Blobscan with the beacon
+/// head, so operators learn about silent stalls. Alerts are raised once when
+/// the lag exceeds the threshold, as Sentry events and webhook calls, and
+/// resolved once it's back under it.
+pub struct LagMonitor<T> {
+    context: Box<dyn CommonContext<T>>,
+    network: String,
+    interval: Duration,
+    max_lag_slots: u32,
+    webhook_url: Option<Url>,
+    tunables: Option<Arc<Tunables>>,
+    client: reqwest::Client,
+    alerting: bool,
+}
+
+impl LagMonitor<BoxTransport> {
+    pub fn new(
+        context: Box<dyn CommonContext<BoxTransport>>,
+        network: String,
+        interval: Duration,
+        max_lag_slots: u32,
+    ) -> Self {
+        Self {
+            context,
+            network,
+            interval,
+            max_lag_slots,
+            webhook_url: None,
+            tunables: None,
+            client: reqwest::Client::new(),
+            alerting: false,
+        }
+    }
+
+    /// Also posts the alerts to the given URL.
+    pub fn with_webhook_url(mut self, webhook_url: Option<Url>) -> Self {
+        self.webhook_url = webhook_url;
+
+        self
+    }
+
+    /// Follows the threshold of the given tunables as it changes. Alerts stop
+    /// being raised once it's unset.
+    pub fn with_tunables(mut self, tunables: Arc<Tunables>) -> Self {
+        self.tunables = Some(tunables);
+
+        self
+    }
+
+    pub async fn run(&mut self) {
+        let mut interval = tokio::time::interval(self.interval);
+
+        loop {
+            interval.tick().await;
+
+            let (sync_state, head_header) = tokio::join!(
+                self.context.blobscan_client().get_sync_state(),
+                self.context
+                    .beacon_client()
+                    .get_block_header(&BlockId::Head)
+            );
+            let last_synced_slot = match sync_state {
+                Ok(sync_state) => sync_state.and_then(|state| state.last_upper_synced_slot),
+                Err(error) => {
+                    warn!(
+                        ?error,
+                        "Failed to fetch the sync state to check the head lag"
+                    );
+
+                    continue;
+                }
+            };
+            let head_slot = match head_header {
+                Ok(header) => header.map(|header| header.header.message.slot),
+                Err(error) => {
+                    warn!(
+                        ?error,
+                        "Failed to fetch the beacon head to check the head lag"
+                    );
+
+                    continue;
+                }
+            };
+
+            if let (Some(head_slot), Some(last_synced_slot)) = (head_slot, last_synced_slot) {
+                self.check(head_slot, last_synced_slot).await;
+            }
+        }
+    }
+
+    async fn check(&mut self, head_slot: u32, last_synced_slot: u32) {
+        let max_lag_slots = match &self.tunables {
+            Some(tunables) => tunables.max_head_lag_slots(),
+            None => Some(self.max_lag_slots),
+        };
+        let lag_slots = head_slot.saturating_sub(last_synced_slot);
+        let is_lagging = max_lag_slots.is_some_and(|max_lag_slots| lag_slots > max_lag_slots);
+
+        if let Some(max_lag_slots) = max_lag_slots {
+            self.max_lag_slots = max_lag_slots;
+        }
+
+        set_gauge("blob_indexer_head_lag_slots", &[], lag_slots as u64);
+        set_gauge("blob_indexer_head_lag_alert", &[], is_lagging as u64);
+
+        if is_lagging == self.alerting {
+            return;
+        }
+
+        self.alerting = is_lagging;
+
+        if is_lagging {
+            increment_counter("blob_indexer_head_lag_alerts_total", &[]);
+
+            // Reported to Sentry as an event
+            error!(
+                head_slot,
+                last_synced_slot,
+                lag_slots,
+                max_lag_slots = self.max_lag_slots,
+                "Indexer fell behind the beacon head"
+            );
+        } else {
+            info!(
+                head_slot,
+                last_synced_slot, lag_slots, "Indexer caught up with the beacon head"
+            );
+        }
+
+        if let Some(webhook_url) = &self.webhook_url {
+            let alert = HeadLagAlert {
+                network: &self.network,
+                resolved: !is_lagging,
+                head_slot,
+                last_synced_slot,
+                lag_slots,
+                max_lag_slots: self.max_lag_slots,
+            };
+            let result = self
+                .client
+                .post(webhook_url.clone())
+                .timeout(self.interval)
+                .json(&alert)
+                .send()
+                .await
+                .and_then(|resp| resp.error_for_status());
+
+            if let Err(error) = result {
+                warn!(?error, "Failed to call the head lag alert webhook");
+            }
+        }
+    }
+}
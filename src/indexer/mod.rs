@@ -1,128 +1,328 @@
-use std::thread;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
 
-use alloy::transports::http::ReqwestTransport;
+use alloy::transports::BoxTransport;
 use anyhow::anyhow;
 use event_handlers::{finalized_checkpoint::FinalizedCheckpointHandler, head::HeadEventHandler};
 use futures::StreamExt;
+use gap_filler::GapFiller;
+use lag_monitor::LagMonitor;
+use reorg_confirmation::ReorgConfirmation;
+use reorg_log::ReorgLog;
 use reqwest_eventsource::Event;
-use tokio::{sync::mpsc, task::JoinHandle};
-use tracing::{debug, error, info, Instrument};
+use tokio::{
+    sync::mpsc,
+    task::JoinHandle,
+    time::{timeout_at, Instant},
+};
+use tracing::{debug, error, info, warn, Instrument};
+use url::Url;
 
 use crate::{
-    args::Args,
-    clients::beacon::types::{BlockId, Topic},
+    args::{IndexArgs, ProcessingArgs},
+    clients::{
+        beacon::types::{BlockId, Topic},
+        blobscan::types::{BlockchainSyncState, IndexerMetadata},
+        priority::RequestPriority,
+    },
+    commands::support_bundle::redacted_config,
     context::{CommonContext, Config as ContextConfig, Context},
     env::Environment,
     indexer::error::HistoricalIndexingError,
-    synchronizer::{CheckpointType, CommonSynchronizer, SynchronizerBuilder},
+    metrics::{increment_counter, server::ServerInfo},
+    network::{
+        discover_fork_schedule, discover_genesis_time, Fork, ForkSchedule, Network, SlotClock,
+    },
+    rollups::RollupRegistry,
+    slots_processor::{
+        blob_verifier::BlobVerifier,
+        filter::AddressFilter,
+        indexed_slots::{IndexedSlots, SlotsTask},
+        stats::SlotStats,
+    },
+    synchronizer::{
+        backfill::{BackfillDirection, ForwardBackfillCheckpoint},
+        checkpoint::CheckpointPolicy,
+        concurrency::AdaptiveConcurrency,
+        intent::IntentLog,
+        job::{JobManifest, JobProgress, SlotRange},
+        pause::PauseSwitch,
+        CheckpointType, CommonSynchronizer, SynchronizerBuilder,
+    },
+    tunables::Tunables,
+    tx_categories::TxCategoryRegistry,
 };
 
 use self::{
-    error::{IndexerError, LiveIndexingError},
-    types::{IndexerResult, IndexerTaskMessage},
+    error::{IndexerError, IndexingError, LiveIndexingError},
+    status::IndexerStatus,
+    types::{IndexerResult, IndexerTask, IndexerTaskMessage, RestartPolicy},
 };
 
 pub mod error;
 pub mod event_handlers;
+pub mod gap_filler;
+pub mod lag_monitor;
+pub mod reindex;
+pub mod reorg_confirmation;
+pub mod reorg_log;
+pub mod status;
 pub mod types;
 
+/// Distance to the head, in slots, above which live indexing syncs in parallel
+const DEFAULT_CATCH_UP_THRESHOLD: u32 = 4;
+/// Slots without head events after which the events stream is considered stalled
+const DEFAULT_SSE_STALL_SLOTS: u32 = 5;
+/// Times the available parallelism the number of threads can exceed before a
+/// warning is logged. Workers mostly wait on the nodes, so some oversubscription
+/// is expected
+const OVERSUBSCRIBED_THREADS_FACTOR: u32 = 8;
+
 pub struct Indexer<T> {
     context: Box<dyn CommonContext<T>>,
-    dencun_fork_slot: u32,
+    network: Network,
+    fork_schedule: ForkSchedule,
+    /// Known once the genesis time of the network is
+    slot_clock: Option<SlotClock>,
+    /// Overrides the Dencun fork slot of the fork schedule
+    dencun_fork_slot: Option<u32>,
     disable_sync_historical: bool,
+    finalized_only: bool,
+    backfill_direction: BackfillDirection,
+    backfill_checkpoint_path: PathBuf,
 
     checkpoint_slots: Option<u32>,
     disabled_checkpoint: Option<CheckpointType>,
     num_threads: u32,
+    gap_fill_interval: Option<Duration>,
+    max_head_lag_slots: Option<u32>,
+    head_lag_webhook_url: Option<Url>,
+    catch_up_threshold: u32,
+    sse_stall_timeout: Duration,
+    worker_stall_timeout: Option<Duration>,
+    slot_timeout: Option<Duration>,
+    slow_slot_threshold: Duration,
+    concurrency: Option<Arc<AdaptiveConcurrency>>,
+    blob_verifier: Option<BlobVerifier>,
+    job_manifest_path: PathBuf,
+    job_progress: Option<Arc<JobProgress>>,
+    /// Progress of each of the ranges synced at the same time by `run_ranges`
+    range_progress: Vec<Arc<JobProgress>>,
+    /// Slowest slots and skip reasons of bounded jobs, reported once done
+    slot_stats: Option<Arc<SlotStats>>,
+    metadata: IndexerMetadata,
+    intent_log: Option<Arc<IntentLog>>,
+    reorg_log: Option<Arc<ReorgLog>>,
+    reorg_confirmation: Option<Arc<ReorgConfirmation>>,
+    tunables: Arc<Tunables>,
+    /// Slots already indexed, skipped by every task
+    indexed_slots: Arc<IndexedSlots>,
+    restart_policy: RestartPolicy,
+    status: Arc<IndexerStatus>,
+    /// Holds the backfills while paused through the control server
+    backfill_pause_switch: Arc<PauseSwitch>,
 }
 
-impl Indexer<ReqwestTransport> {
-    pub fn try_new(env: &Environment, args: &Args) -> IndexerResult<Self> {
-        let context = match Context::try_new(ContextConfig::from(env)) {
-            Ok(c) => c,
-            Err(error) => {
-                error!(?error, "Failed to create context");
-
-                return Err(IndexerError::CreationFailure(anyhow!(
-                    "Failed to create context: {:?}",
-                    error
-                )));
-            }
-        };
+impl Indexer<BoxTransport> {
+    pub fn try_new(env: &Environment, args: &IndexArgs) -> IndexerResult<Self> {
+        let context = create_context(env, &args.processing)?;
 
         let checkpoint_slots = args.slots_per_save;
-        let disabled_checkpoint = if args.disable_sync_checkpoint_save {
-            Some(CheckpointType::Disabled)
+        // Exported blocks aren't in Blobscan until they're imported
+        let disabled_checkpoint =
+            if args.disable_sync_checkpoint_save || args.processing.export_only {
+                Some(CheckpointType::Disabled)
+            } else {
+                None
+            };
+        let available_parallelism = thread::available_parallelism()
+            .map_err(|err| {
+                IndexerError::CreationFailure(anyhow!(
+                    "Failed to get number of available threads: {:?}",
+                    err
+                ))
+            })?
+            .get() as u32;
+        let num_threads = args.num_threads.unwrap_or(available_parallelism);
+
+        if num_threads > available_parallelism * OVERSUBSCRIBED_THREADS_FACTOR {
+            warn!(
+                num_threads,
+                available_parallelism,
+                "The number of threads far exceeds the available parallelism, which may slow down indexing and overload the nodes"
+            );
+        }
+        let concurrency = if args.adaptive_concurrency {
+            Some(Arc::new(AdaptiveConcurrency::new(1, num_threads)))
         } else {
             None
         };
-        let num_threads = match args.num_threads {
-            Some(num_threads) => num_threads,
-            None => thread::available_parallelism()
-                .map_err(|err| {
-                    IndexerError::CreationFailure(anyhow!(
-                        "Failed to get number of available threads: {:?}",
-                        err
-                    ))
+        let blob_verifier = create_blob_verifier(&args.processing);
+        let disable_sync_historical = args.disable_sync_historical;
+        let finalized_only = args.finalized_only;
+        let gap_fill_interval = args.gap_fill_interval.map(Duration::from_secs);
+        let catch_up_threshold = args
+            .catch_up_threshold
+            .unwrap_or(DEFAULT_CATCH_UP_THRESHOLD);
+
+        let mut sse_stall_timeout = Duration::from_secs(
+            env.network.seconds_per_slot()
+                * args.sse_stall_slots.unwrap_or(DEFAULT_SSE_STALL_SLOTS) as u64,
+        );
+
+        // Finalized checkpoints are only received once per epoch
+        if finalized_only {
+            sse_stall_timeout *= env.network.slots_per_epoch();
+        }
+
+        let worker_stall_timeout = args.worker_stall_timeout.map(Duration::from_secs);
+        let slot_timeout = args.slot_timeout.map(Duration::from_secs);
+        let slow_slot_threshold = Duration::from_secs(args.slow_slot_threshold);
+
+        let intent_log = match &args.intent_log {
+            Some(path) => Some(Arc::new(
+                IntentLog::open(path).map_err(IndexerError::IntentLogOpenFailure)?,
+            )),
+            None => None,
+        };
+
+        let reorg_log = match &args.reorg_log {
+            Some(path) => Some(Arc::new(ReorgLog::open(path).map_err(|error| {
+                IndexerError::ReorgLogOpenFailure {
+                    path: path.clone(),
+                    error,
+                }
+            })?)),
+            None => None,
+        };
+
+        let reorg_confirmation = match args.reorg_confirmation_depth {
+            Some(_) if env.control_server_address.is_none() => {
+                return Err(IndexerError::ReorgConfirmationUnavailable)
+            }
+            Some(depth) => Some(Arc::new(ReorgConfirmation::new(depth))),
+            None => None,
+        };
+
+        let indexed_slots = match args.skip_indexed_slots {
+            Some(source) => {
+                IndexedSlots::open(source, &args.indexed_slots_bitmap).map_err(|error| {
+                    IndexerError::IndexedSlotsOpenFailure {
+                        path: args.indexed_slots_bitmap.clone(),
+                        error,
+                    }
                 })?
-                .get() as u32,
+            }
+            None => IndexedSlots::new(),
         };
-        let disable_sync_historical = args.disable_sync_historical;
 
-        let dencun_fork_slot = env
-            .dencun_fork_slot
-            .unwrap_or(env.network_name.dencun_fork_slot());
+        let info = ServerInfo::new(env, args);
+        let metadata = IndexerMetadata {
+            version: info.version.to_string(),
+            git_commit: info.git_commit.map(str::to_string),
+            network: info.network,
+            features: info
+                .features
+                .iter()
+                .map(|feature| feature.to_string())
+                .collect(),
+            config_hash: info.config_hash,
+            config: redacted_config(env),
+        };
 
         Ok(Self {
             context: Box::new(context),
-            dencun_fork_slot,
+            network: env.network.clone(),
+            fork_schedule: env.network.fork_schedule(),
+            slot_clock: env.network.slot_clock(),
+            dencun_fork_slot: env.dencun_fork_slot,
             disable_sync_historical,
+            finalized_only,
+            backfill_direction: args.backfill_direction,
+            backfill_checkpoint_path: args.backfill_checkpoint.clone(),
             checkpoint_slots,
             disabled_checkpoint,
             num_threads,
+            gap_fill_interval,
+            max_head_lag_slots: env.max_head_lag_slots,
+            head_lag_webhook_url: env.head_lag_webhook_url.clone(),
+            catch_up_threshold,
+            sse_stall_timeout,
+            worker_stall_timeout,
+            slot_timeout,
+            slow_slot_threshold,
+            concurrency,
+            blob_verifier,
+            job_manifest_path: args.job_manifest.clone(),
+            job_progress: None,
+            range_progress: vec![],
+            slot_stats: None,
+            metadata,
+            intent_log,
+            reorg_log,
+            reorg_confirmation,
+            tunables: Arc::new(Tunables::new(env, args)),
+            indexed_slots: Arc::new(indexed_slots),
+            restart_policy: RestartPolicy {
+                max_restarts: args.max_task_restarts,
+                initial_delay: Duration::from_secs(args.task_restart_delay),
+                ..RestartPolicy::default()
+            },
+            status: Arc::new(IndexerStatus::new()),
+            backfill_pause_switch: Arc::new(PauseSwitch::new()),
         })
     }
 
+    /// Returns the live status of the indexer's tasks.
+    pub fn status(&self) -> Arc<IndexerStatus> {
+        self.status.clone()
+    }
+
+    /// Returns the switch pausing the backfills, leaving live indexing running.
+    pub fn backfill_pause_switch(&self) -> Arc<PauseSwitch> {
+        self.backfill_pause_switch.clone()
+    }
+
+    /// Follows the given tunables instead of the ones set when created, e.g.
+    /// to apply the changes to the config file while running.
+    pub fn with_tunables(mut self, tunables: Arc<Tunables>) -> Self {
+        self.tunables = tunables;
+
+        self
+    }
+
+    /// Returns the gate holding the deep reorgs until they're confirmed, when
+    /// a confirmation depth is set.
+    pub fn reorg_confirmation(&self) -> Option<Arc<ReorgConfirmation>> {
+        self.reorg_confirmation.clone()
+    }
+
     pub async fn run(
         &mut self,
         start_block_id: Option<BlockId>,
         end_block_id: Option<BlockId>,
     ) -> IndexerResult<()> {
-        let sync_state = match self.context.blobscan_client().get_sync_state().await {
-            Ok(state) => state,
-            Err(error) => {
-                error!(?error, "Failed to fetch blobscan's sync state");
+        check_blobscan_api(self.context.as_ref()).await?;
+        self.report_metadata().await;
+        self.replay_pending_intents().await?;
 
-                return Err(IndexerError::BlobscanSyncStateRetrievalError(error));
-            }
-        };
+        self.refresh_fork_schedule().await;
+        self.refresh_slot_clock().await;
 
-        let current_lower_block_id = match start_block_id.clone() {
-            Some(block_id) => block_id,
-            None => match &sync_state {
-                Some(state) => match state.last_lower_synced_slot {
-                    Some(slot) => BlockId::Slot(slot - 1),
-                    None => match state.last_upper_synced_slot {
-                        Some(slot) => BlockId::Slot(slot - 1),
-                        None => BlockId::Head,
-                    },
-                },
-                None => BlockId::Head,
-            },
-        };
-        let current_upper_block_id = match start_block_id {
-            Some(block_id) => block_id,
-            None => match &sync_state {
-                Some(state) => match state.last_upper_synced_slot {
-                    Some(slot) => BlockId::Slot(slot + 1),
-                    None => match state.last_lower_synced_slot {
-                        Some(slot) => BlockId::Slot(slot + 1),
-                        None => BlockId::Head,
-                    },
-                },
-                None => BlockId::Head,
-            },
-        };
+        let dencun_fork_slot = self.dencun_fork_slot()?;
+
+        let sync_state = self.fetch_sync_state().await?;
+        let current_lower_block_id = start_block_id
+            .clone()
+            .unwrap_or_else(|| self.bounded_by_finality(lower_block_id(&sync_state)));
+        let current_upper_block_id = start_block_id
+            .clone()
+            .unwrap_or_else(|| self.bounded_by_finality(upper_block_id(&sync_state)));
 
         info!(
             ?current_lower_block_id,
@@ -131,70 +331,469 @@ impl Indexer<ReqwestTransport> {
         );
 
         let (tx, mut rx) = mpsc::channel(32);
-        let tx1 = tx.clone();
         let mut total_tasks = 0;
 
+        let is_bounded = end_block_id.is_some();
+
+        // Bounded jobs save what's left to sync when terminated so they can be resumed
+        if is_bounded {
+            self.job_progress = Some(Arc::new(JobProgress::new()));
+            self.slot_stats = Some(Arc::new(SlotStats::new()));
+        }
+
         if end_block_id.is_none() {
-            self.start_live_indexing_task(tx, current_upper_block_id);
+            self.start_live_indexing_task(tx.clone(), current_upper_block_id);
             total_tasks += 1;
+
+            if let Some(interval) = self.gap_fill_interval {
+                self.start_gap_filling_task(interval);
+            }
+
+            if let Some(max_head_lag_slots) = self.max_head_lag_slots {
+                self.start_lag_monitoring_task(max_head_lag_slots);
+            }
         }
 
-        let default_end_block = BlockId::Slot(self.dencun_fork_slot - 1);
+        let default_end_block = BlockId::Slot(dencun_fork_slot.saturating_sub(1));
         let end_block_id = end_block_id.unwrap_or(default_end_block);
         let historical_sync_completed =
-            matches!(current_lower_block_id, BlockId::Slot(slot) if slot < self.dencun_fork_slot);
+            matches!(current_lower_block_id, BlockId::Slot(slot) if slot < dencun_fork_slot);
+        // Without live indexing nor anything synced yet, the backfilled range is
+        // the only one, so it's both the lower and upper synced bounds
+        let is_first_range = total_tasks == 0
+            && !matches!(
+                &sync_state,
+                Some(state)
+                    if state.last_lower_synced_slot.is_some()
+                        || state.last_upper_synced_slot.is_some()
+            );
+        let historical_checkpoint_type = if is_first_range {
+            CheckpointType::Both
+        } else {
+            CheckpointType::Lower
+        };
 
         if !self.disable_sync_historical && !historical_sync_completed {
-            self.start_historical_indexing_task(tx1, current_lower_block_id, end_block_id);
+            let (start_block_id, end_block_id, checkpoint_policy) = self
+                .backfill_range(
+                    current_lower_block_id,
+                    end_block_id.clone(),
+                    historical_checkpoint_type,
+                    is_bounded,
+                )
+                .await?;
+
+            self.start_historical_indexing_task(
+                tx.clone(),
+                start_block_id,
+                end_block_id,
+                checkpoint_policy,
+            );
 
             total_tasks += 1;
         }
 
         let mut completed_tasks = 0;
+        let mut task_restarts: HashMap<IndexerTask, u32> = HashMap::new();
+
+        loop {
+            let message = tokio::select! {
+                message = rx.recv() => message,
+                _ = shutdown_signal() => {
+                    return match self.job_progress {
+                        Some(_) => Err(self.save_job_manifest()),
+                        None => {
+                            info!("Shutting down…");
+
+                            Ok(())
+                        }
+                    };
+                }
+            };
+            let Some(message) = message else {
+                break;
+            };
 
-        while let Some(message) = rx.recv().await {
             match message {
-                IndexerTaskMessage::Done => {
+                IndexerTaskMessage::Done(task) => {
+                    self.status.task_done(task);
+
                     completed_tasks += 1;
 
+                    self.report_overlapping_slots();
+
                     if completed_tasks == total_tasks {
+                        self.report_slot_stats();
+
                         return Ok(());
                     }
                 }
-                IndexerTaskMessage::Error(error) => {
-                    error!(?error, "An error occurred while running a syncing task");
+                IndexerTaskMessage::Error(task, error) => {
+                    let restarts = task_restarts.entry(task).or_default();
+
+                    if !error.is_recoverable() {
+                        error!(%task, ?error, "A fatal error occurred while running a syncing task");
+                        self.status.task_failed(task, &error, false);
+
+                        return Err(self.fail_job(error));
+                    }
+
+                    if !self.restart_policy.allows_restart(*restarts) {
+                        error!(
+                            %task,
+                            ?error,
+                            restarts = *restarts,
+                            "An error occurred while running a syncing task and it can't be restarted anymore"
+                        );
+                        self.status.task_failed(task, &error, false);
+
+                        return Err(self.fail_job(error));
+                    }
+
+                    *restarts += 1;
+
+                    self.status.task_failed(task, &error, true);
+
+                    let delay = self.restart_policy.delay(*restarts);
+
+                    warn!(
+                        %task,
+                        ?error,
+                        restart = *restarts,
+                        max_restarts = ?self.restart_policy.max_restarts,
+                        "Syncing task failed. Restarting it in {} seconds…",
+                        delay.as_secs()
+                    );
+                    increment_counter(
+                        "blob_indexer_task_restarts_total",
+                        &[("task", &task.to_string())],
+                    );
+
+                    tokio::select! {
+                        _ = tokio::time::sleep(delay) => {}
+                        _ = shutdown_signal(), if self.job_progress.is_some() => {
+                            return Err(self.save_job_manifest());
+                        }
+                    }
+
+                    // Tasks started from the sync state resume from its latest version
+                    let sync_state = match &start_block_id {
+                        Some(_) => None,
+                        None => self.fetch_sync_state().await?,
+                    };
+
+                    match task {
+                        IndexerTask::Historical => {
+                            let lower_block_id = start_block_id.clone().unwrap_or_else(|| {
+                                self.bounded_by_finality(lower_block_id(&sync_state))
+                            });
+                            let (start_block_id, end_block_id, checkpoint_policy) = self
+                                .backfill_range(
+                                    lower_block_id,
+                                    end_block_id.clone(),
+                                    historical_checkpoint_type,
+                                    is_bounded,
+                                )
+                                .await?;
+
+                            self.start_historical_indexing_task(
+                                tx.clone(),
+                                start_block_id,
+                                end_block_id,
+                                checkpoint_policy,
+                            );
+                        }
+                        IndexerTask::Live => {
+                            self.start_live_indexing_task(
+                                tx.clone(),
+                                start_block_id.clone().unwrap_or_else(|| {
+                                    self.bounded_by_finality(upper_block_id(&sync_state))
+                                }),
+                            );
+                        }
+                        IndexerTask::Range(_) => {
+                            unreachable!("ranges aren't synced along with the other tasks")
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Logs how many slots each task skipped because another one had already
+    /// indexed them.
+    fn report_overlapping_slots(&self) {
+        for (task, overlaps) in self.indexed_slots.overlaps() {
+            info!(%task, overlaps, "Skipped slots already indexed by another task");
+        }
+    }
+
+    /// Starts syncing from the finalized block instead of the head when
+    /// indexing finalized blocks only.
+    fn bounded_by_finality(&self, block_id: BlockId) -> BlockId {
+        match block_id {
+            BlockId::Head if self.finalized_only => BlockId::Finalized,
+            block_id => block_id,
+        }
+    }
 
-                    return Err(error.into());
+    fn report_slot_stats(&self) {
+        if let Some(slot_stats) = &self.slot_stats {
+            slot_stats.log_report();
+        }
+    }
+
+    async fn fetch_sync_state(&self) -> IndexerResult<Option<BlockchainSyncState>> {
+        match self.context.blobscan_client().get_sync_state().await {
+            Ok(state) => Ok(state),
+            Err(error) => {
+                error!(?error, "Failed to fetch blobscan's sync state");
+
+                Err(IndexerError::BlobscanSyncStateRetrievalError(error))
+            }
+        }
+    }
+
+    /// Syncs the ranges left by an interrupted bounded job, in the same order
+    /// they would have been synced.
+    pub async fn resume_job(&mut self, manifest_path: &Path) -> IndexerResult<()> {
+        let manifest = JobManifest::load(manifest_path).map_err(|error| {
+            IndexerError::JobManifestLoadFailure {
+                path: manifest_path.to_path_buf(),
+                error,
+            }
+        })?;
+        let job_progress = Arc::new(JobProgress::new());
+
+        check_blobscan_api(self.context.as_ref()).await?;
+        self.job_progress = Some(job_progress.clone());
+        self.slot_stats = Some(Arc::new(SlotStats::new()));
+        self.report_metadata().await;
+
+        info!(
+            ranges = manifest.ranges.len(),
+            created_at = manifest.created_at,
+            "Resuming job…"
+        );
+
+        for (i, range) in manifest.ranges.iter().enumerate() {
+            job_progress.set_queued(manifest.ranges[i + 1..].to_vec());
+
+            let synchronizer = self.create_synchronizer(
+                SlotsTask::Backfill,
+                CheckpointType::Lower,
+                self.num_threads,
+            );
+            let (initial_block_id, final_block_id) =
+                (BlockId::Slot(range.from), BlockId::Slot(range.to));
+
+            tokio::select! {
+                result = synchronizer.run(&initial_block_id, &final_block_id) => {
+                    if let Err(error) = result {
+                        let error = IndexingError::from(HistoricalIndexingError::SynchronizerError(error));
+
+                        return Err(self.fail_job(error));
+                    }
+                }
+                _ = shutdown_signal() => {
+                    return Err(self.save_job_manifest());
                 }
             }
         }
 
+        info!("Job completed successfully");
+
+        self.report_slot_stats();
+
         Ok(())
     }
 
+    /// Syncs the given ranges at the same time, each in its own task, as a
+    /// bounded job that saves what's left of every range when interrupted.
+    pub async fn run_ranges(&mut self, ranges: &[SlotRange]) -> IndexerResult<()> {
+        check_blobscan_api(self.context.as_ref()).await?;
+        self.slot_stats = Some(Arc::new(SlotStats::new()));
+        self.report_metadata().await;
+
+        info!(ranges = ranges.len(), "Syncing ranges…");
+
+        let (tx, mut rx) = mpsc::channel(32);
+
+        for range in ranges {
+            let job_progress = Arc::new(JobProgress::new());
+
+            self.range_progress.push(job_progress.clone());
+            self.start_range_indexing_task(tx.clone(), *range, job_progress);
+        }
+
+        let mut completed_ranges = 0;
+
+        while completed_ranges < ranges.len() {
+            let message = tokio::select! {
+                message = rx.recv() => message,
+                _ = shutdown_signal() => {
+                    return Err(self.save_job_manifest());
+                }
+            };
+            let Some(message) = message else {
+                break;
+            };
+
+            match message {
+                IndexerTaskMessage::Done(task) => {
+                    self.status.task_done(task);
+
+                    completed_ranges += 1;
+
+                    info!(
+                        %task,
+                        remaining_ranges = ranges.len() - completed_ranges,
+                        "Range synced"
+                    );
+                }
+                IndexerTaskMessage::Error(task, error) => {
+                    error!(%task, ?error, "Failed to sync range");
+                    self.status.task_failed(task, &error, false);
+
+                    return Err(self.fail_job(error));
+                }
+            }
+        }
+
+        info!("Job completed successfully");
+
+        self.report_overlapping_slots();
+        self.report_slot_stats();
+
+        Ok(())
+    }
+
+    /// Returns the range the backfill syncs, between the lowest synced block
+    /// and the end block, along with the policy saving its progress. Bounded
+    /// jobs go in the direction given by their slots instead.
+    async fn backfill_range(
+        &self,
+        lower_block_id: BlockId,
+        end_block_id: BlockId,
+        checkpoint_type: CheckpointType,
+        is_bounded: bool,
+    ) -> IndexerResult<(BlockId, BlockId, Arc<dyn CheckpointPolicy>)> {
+        if is_bounded || self.backfill_direction == BackfillDirection::Backward {
+            return Ok((lower_block_id, end_block_id, Arc::new(checkpoint_type)));
+        }
+
+        // The same slots as backwards, from the other end
+        let start_slot = resolve_slot(self.context.as_ref(), end_block_id).await?;
+        let end_slot = resolve_slot(self.context.as_ref(), lower_block_id).await?;
+        let checkpoint = ForwardBackfillCheckpoint::new(
+            self.backfill_checkpoint_path.clone(),
+            start_slot,
+            end_slot,
+            checkpoint_type,
+        );
+        let last_synced_slot = match checkpoint.load() {
+            Ok(slot) => slot.filter(|slot| (start_slot..end_slot).contains(slot)),
+            Err(error) => {
+                warn!(
+                    path = %self.backfill_checkpoint_path.display(),
+                    ?error,
+                    "Failed to load the forward backfill checkpoint. Starting the backfill over"
+                );
+
+                None
+            }
+        };
+        let from_slot = match last_synced_slot {
+            Some(slot) => {
+                info!(slot, "Resuming the forward backfill from its checkpoint");
+
+                slot + 1
+            }
+            None => start_slot,
+        };
+
+        Ok((
+            BlockId::Slot(from_slot),
+            BlockId::Slot(end_slot),
+            Arc::new(checkpoint),
+        ))
+    }
+
+    fn start_range_indexing_task(
+        &self,
+        tx: mpsc::Sender<IndexerTaskMessage>,
+        range: SlotRange,
+        job_progress: Arc<JobProgress>,
+    ) -> JoinHandle<IndexerResult<()>> {
+        let task = IndexerTask::Range(range);
+
+        self.status.task_started(task);
+
+        // The ranges may be anywhere, so they can't be saved as the synced bounds
+        let synchronizer = self
+            .synchronizer_builder(
+                SlotsTask::Backfill,
+                Arc::new(CheckpointType::Disabled),
+                self.num_threads,
+            )
+            .with_job_progress(job_progress)
+            .build(self.context.clone());
+
+        tokio::spawn(
+            async move {
+                let (initial_block_id, final_block_id) =
+                    (BlockId::Slot(range.from), BlockId::Slot(range.to));
+                let message = match synchronizer.run(&initial_block_id, &final_block_id).await {
+                    Ok(()) => IndexerTaskMessage::Done(task),
+                    Err(error) => IndexerTaskMessage::Error(
+                        task,
+                        HistoricalIndexingError::SynchronizerError(error).into(),
+                    ),
+                };
+
+                tx.send(message).await?;
+
+                Ok(())
+            }
+            .instrument(tracing::info_span!("indexer:range", task = %task)),
+        )
+    }
+
     fn start_historical_indexing_task(
         &self,
         tx: mpsc::Sender<IndexerTaskMessage>,
         start_block_id: BlockId,
         end_block_id: BlockId,
+        checkpoint_policy: Arc<dyn CheckpointPolicy>,
     ) -> JoinHandle<IndexerResult<()>> {
-        let synchronizer = self.create_synchronizer(CheckpointType::Lower);
+        self.status.task_started(IndexerTask::Historical);
+
+        let synchronizer = self.create_synchronizer_with_policy(
+            SlotsTask::Backfill,
+            checkpoint_policy,
+            self.num_threads,
+        );
 
         tokio::spawn(async move {
-            let historical_syc_thread_span = tracing::info_span!("indexer:historical");
+            let historical_syc_thread_span =
+                tracing::info_span!("indexer:historical", task = "historical");
 
             let result: Result<(), IndexerError> = async move {
                 let result = synchronizer.run(&start_block_id, &end_block_id).await;
 
                 if let Err(error) = result {
                     tx.send(IndexerTaskMessage::Error(
+                        IndexerTask::Historical,
                         HistoricalIndexingError::SynchronizerError(error).into(),
                     ))
                     .await?;
                 } else {
                     info!("Historical syncing completed successfully");
 
-                    tx.send(IndexerTaskMessage::Done).await?;
+                    tx.send(IndexerTaskMessage::Done(IndexerTask::Historical))
+                        .await?;
                 }
 
                 Ok(())
@@ -213,18 +812,51 @@ impl Indexer<ReqwestTransport> {
         tx: mpsc::Sender<IndexerTaskMessage>,
         start_block_id: BlockId,
     ) -> JoinHandle<IndexerResult<()>> {
+        self.status.task_started(IndexerTask::Live);
+
         let task_context = self.context.clone();
-        let synchronizer = self.create_synchronizer(CheckpointType::Upper);
-        let realtime_sync_task_span = tracing::info_span!("indexer:live");
+        let synchronizer = self.create_synchronizer(SlotsTask::Head, CheckpointType::Upper, 1);
+        let catch_up_synchronizer =
+            self.create_synchronizer(SlotsTask::CatchUp, CheckpointType::Upper, self.num_threads);
+        let realtime_sync_task_span = tracing::info_span!("indexer:live", task = "live");
+        let sse_stall_timeout = self.sse_stall_timeout;
 
         let mut head_event_handler =
-            HeadEventHandler::new(task_context.clone(), synchronizer, start_block_id);
-        let finalized_checkpoint_event_handler =
+            HeadEventHandler::new(task_context.clone(), synchronizer, start_block_id.clone())
+                .with_catch_up_synchronizer(catch_up_synchronizer, self.catch_up_threshold)
+                .with_slots_per_epoch(self.network.slots_per_epoch())
+                .with_slot_clock(self.slot_clock)
+                .with_indexed_slots(self.indexed_slots.clone());
+
+        if let Some(reorg_log) = &self.reorg_log {
+            head_event_handler = head_event_handler.with_reorg_log(reorg_log.clone());
+        }
+
+        if let Some(reorg_confirmation) = &self.reorg_confirmation {
+            head_event_handler =
+                head_event_handler.with_reorg_confirmation(reorg_confirmation.clone());
+        }
+
+        let mut finalized_checkpoint_event_handler =
             FinalizedCheckpointHandler::new(task_context.clone());
+        let finalized_only = self.finalized_only;
+
+        if finalized_only {
+            // Blocks are synced an epoch at a time
+            let finalized_synchronizer =
+                self.create_synchronizer(SlotsTask::Head, CheckpointType::Upper, self.num_threads);
+
+            finalized_checkpoint_event_handler = finalized_checkpoint_event_handler
+                .with_synchronizer(finalized_synchronizer, start_block_id.clone());
+        }
 
         tokio::spawn(async move {
             let result: Result<(), LiveIndexingError> = async {
-                let topics = vec![Topic::Head, Topic::FinalizedCheckpoint];
+                let topics = if finalized_only {
+                    vec![Topic::FinalizedCheckpoint]
+                } else {
+                    vec![Topic::Head, Topic::FinalizedCheckpoint, Topic::ChainReorg]
+                };
                 let events = topics
                     .iter()
                     .map(|topic| topic.into())
@@ -239,7 +871,41 @@ impl Indexer<ReqwestTransport> {
 
                     info!("Subscribed to beacon SSE stream: {}", events);
 
-                    while let Some(event) = event_source.next().await {
+                    // Finalized checkpoints may have been missed while disconnected
+                    if let Err(error) = finalized_checkpoint_event_handler
+                        .sync_finalized_block()
+                        .instrument(tracing::info_span!("finalized_checkpoint"))
+                        .await
+                    {
+                        warn!(?error, "Failed to update last finalized block");
+                    }
+
+                    // Some proxies hang the connection without emitting an error, so
+                    // resubscribe when head events stop arriving
+                    let mut last_head_event_at = Instant::now();
+
+                    loop {
+                        let event = match timeout_at(
+                            last_head_event_at + sse_stall_timeout,
+                            event_source.next(),
+                        )
+                        .await
+                        {
+                            Ok(Some(event)) => event,
+                            Ok(None) => break,
+                            Err(_) => {
+                                warn!(
+                                    stalled_for = ?sse_stall_timeout,
+                                    "No events received. Resubscribing to beacon SSE stream…"
+                                );
+                                increment_counter("blob_indexer_sse_stalled_reconnects_total", &[]);
+
+                                event_source.close();
+
+                                break;
+                            }
+                        };
+
                         match event {
                             Ok(Event::Open) => {
                                 debug!("Subscription connection opened");
@@ -249,12 +915,32 @@ impl Indexer<ReqwestTransport> {
 
                                 match event_name {
                                     "head" => {
+                                        last_head_event_at = Instant::now();
+
                                         head_event_handler
                                             .handle(event.data)
-                                            .instrument(tracing::info_span!("head_block"))
+                                            .instrument(tracing::info_span!(
+                                                "head_block",
+                                                slot = tracing::field::Empty,
+                                                block_root = tracing::field::Empty
+                                            ))
+                                            .await?;
+                                    }
+                                    "chain_reorg" => {
+                                        head_event_handler
+                                            .handle_chain_reorg(event.data)
+                                            .instrument(tracing::info_span!(
+                                                "chain_reorg",
+                                                slot = tracing::field::Empty,
+                                                block_root = tracing::field::Empty
+                                            ))
                                             .await?;
                                     }
                                     "finalized_checkpoint" => {
+                                        if finalized_only {
+                                            last_head_event_at = Instant::now();
+                                        }
+
                                         finalized_checkpoint_event_handler
                                             .handle(event.data)
                                             .instrument(tracing::info_span!("finalized_checkpoint"))
@@ -286,28 +972,502 @@ impl Indexer<ReqwestTransport> {
             .await;
 
             if let Err(error) = result {
-                tx.send(IndexerTaskMessage::Error(error.into())).await?;
+                tx.send(IndexerTaskMessage::Error(IndexerTask::Live, error.into()))
+                    .await?;
             } else {
-                tx.send(IndexerTaskMessage::Done).await?;
+                tx.send(IndexerTaskMessage::Done(IndexerTask::Live)).await?;
             }
 
             Ok(())
         })
     }
 
-    fn create_synchronizer(&self, checkpoint_type: CheckpointType) -> Box<dyn CommonSynchronizer> {
+    fn start_gap_filling_task(&self, interval: Duration) -> JoinHandle<()> {
+        let mut gap_filler = GapFiller::new(self.context.clone(), interval)
+            .with_fork_schedule(self.fork_schedule.clone());
+
+        tokio::spawn(
+            async move { gap_filler.run().await }
+                .instrument(tracing::info_span!("indexer:gaps", task = "gaps")),
+        )
+    }
+
+    fn start_lag_monitoring_task(&self, max_head_lag_slots: u32) -> JoinHandle<()> {
+        let mut lag_monitor = LagMonitor::new(
+            self.context.clone(),
+            self.network.to_string(),
+            Duration::from_secs(self.network.seconds_per_slot()),
+            max_head_lag_slots,
+        )
+        .with_webhook_url(self.head_lag_webhook_url.clone())
+        .with_tunables(self.tunables.clone());
+
+        tokio::spawn(
+            async move { lag_monitor.run().await }
+                .instrument(tracing::info_span!("indexer:lag", task = "lag")),
+        )
+    }
+
+    /// Returns the slots the bounded job being run has left to sync, if any.
+    fn job_manifest(&self) -> Option<JobManifest> {
+        if self.job_progress.is_none() && self.range_progress.is_empty() {
+            return None;
+        }
+
+        let ranges = self
+            .job_progress
+            .iter()
+            .chain(&self.range_progress)
+            .flat_map(|job_progress| job_progress.remaining_ranges())
+            .collect();
+
+        Some(JobManifest::new(ranges))
+    }
+
+    /// Persists the slots the current job has left to sync, returning the error
+    /// the interrupted job ends with.
+    fn save_job_manifest(&self) -> IndexerError {
+        let Some(manifest) = self.job_manifest() else {
+            return IndexerError::CreationFailure(anyhow!("no job in progress"));
+        };
+        let path = self.job_manifest_path.clone();
+        let remaining_slots = manifest.ranges.iter().map(|range| range.len() as u64).sum();
+
+        warn!(
+            path = %path.display(),
+            remaining_slots,
+            "Termination signal received. Saving job manifest…"
+        );
+
+        match manifest.save(&path) {
+            Ok(()) => IndexerError::JobInterrupted {
+                path,
+                remaining_slots,
+            },
+            Err(error) => IndexerError::JobManifestSaveFailure { path, error },
+        }
+    }
+
+    /// Persists the slots the failed job has left to sync, which start at the
+    /// failed slot, so it can be continued with `--resume`. Returns the error
+    /// the job ends with.
+    fn fail_job(&self, error: IndexingError) -> IndexerError {
+        let Some(manifest) = self.job_manifest() else {
+            return error.into();
+        };
+        let path = self.job_manifest_path.clone();
+        let remaining_slots = manifest.ranges.iter().map(|range| range.len() as u64).sum();
+        let failed_slot = error.failed_slots().first().copied();
+
+        error!(
+            ?failed_slot,
+            path = %path.display(),
+            remaining_slots,
+            "Job failed. Saving job manifest…"
+        );
+
+        match manifest.save(&path) {
+            Ok(()) => IndexerError::JobFailed {
+                path,
+                remaining_slots,
+                failed_slot,
+                error: Box::new(error.into()),
+            },
+            Err(save_error) => {
+                error!(?save_error, path = %path.display(), "Failed to save job manifest");
+
+                error.into()
+            }
+        }
+    }
+
+    /// Syncs again the ranges a previous run recorded in the intent log but
+    /// didn't confirm, as they may have been indexed only partially.
+    async fn replay_pending_intents(&self) -> IndexerResult<()> {
+        let Some(intent_log) = &self.intent_log else {
+            return Ok(());
+        };
+
+        for range in intent_log.pending() {
+            warn!(
+                initial_slot = range.from,
+                final_slot = range.to,
+                "Found interrupted slots range. Syncing it again…"
+            );
+
+            // The sync state already covers the range or will once it's synced
+            let synchronizer = self.create_synchronizer(
+                SlotsTask::Backfill,
+                CheckpointType::Disabled,
+                self.num_threads,
+            );
+
+            synchronizer
+                .run(&BlockId::Slot(range.from), &BlockId::Slot(range.to))
+                .await
+                .map_err(|error| {
+                    IndexingError::from(HistoricalIndexingError::SynchronizerError(error))
+                })?;
+
+            intent_log
+                .confirm(range)
+                .map_err(IndexerError::IntentLogConfirmFailure)?;
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the network's preset fork schedule with the one followed by
+    /// the beacon node, keeping the preset if it can't be fetched.
+    async fn refresh_fork_schedule(&mut self) {
+        match discover_fork_schedule(self.context.beacon_client()).await {
+            Ok(fork_schedule) => {
+                let preset_slot = self.fork_schedule.fork_slot(Fork::Deneb);
+
+                let Some(discovered_slot) = fork_schedule.fork_slot(Fork::Deneb) else {
+                    warn!("Beacon node has no Deneb fork scheduled. Using the network's fork schedule…");
+
+                    return;
+                };
+
+                if preset_slot.is_some_and(|preset_slot| preset_slot != discovered_slot) {
+                    warn!(
+                        network = %self.network,
+                        discovered_slot,
+                        preset_slot,
+                        "Dencun fork slot scheduled by the beacon node differs from the network's one"
+                    );
+                }
+
+                debug!(?fork_schedule, "Fork schedule discovered from the beacon node");
+
+                self.fork_schedule = fork_schedule;
+            }
+            Err(error) => warn!(
+                ?error,
+                "Failed to discover the fork schedule from the beacon node. Using the network's one…"
+            ),
+        }
+    }
+
+    /// Sets the genesis time of the slot clock to the beacon node's one,
+    /// keeping the network's preset if it can't be fetched.
+    async fn refresh_slot_clock(&mut self) {
+        match discover_genesis_time(self.context.beacon_client()).await {
+            Ok(genesis_time) => {
+                if let Some(preset_genesis_time) = self
+                    .network
+                    .genesis_time()
+                    .filter(|&preset_genesis_time| preset_genesis_time != genesis_time)
+                {
+                    warn!(
+                        network = %self.network,
+                        genesis_time,
+                        preset_genesis_time,
+                        "Genesis time of the beacon node differs from the network's one"
+                    );
+                }
+
+                self.slot_clock = Some(SlotClock::new(
+                    genesis_time,
+                    self.network.seconds_per_slot(),
+                ));
+            }
+            Err(error) => warn!(
+                ?error,
+                "Failed to fetch the genesis time from the beacon node. Using the network's one…"
+            ),
+        }
+    }
+
+    fn dencun_fork_slot(&self) -> IndexerResult<u32> {
+        self.dencun_fork_slot
+            .or_else(|| self.fork_schedule.fork_slot(Fork::Deneb))
+            .ok_or(IndexerError::UnknownDencunForkSlot)
+    }
+
+    /// Writes out what the sink holds back, e.g. the analytics rows of the
+    /// current day. Meant to be called once the indexer stops, however it did.
+    pub async fn flush_sink(&self) -> IndexerResult<()> {
+        self.context
+            .sink()
+            .flush()
+            .await
+            .map_err(IndexerError::SinkFlushFailure)
+    }
+
+    /// Lets Blobscan know which indexer instance is writing to it. Older API
+    /// versions don't support it, so failures are only logged.
+    async fn report_metadata(&self) {
+        match self
+            .context
+            .blobscan_client()
+            .send_metadata(&self.metadata)
+            .await
+        {
+            Ok(()) => debug!("Indexer metadata sent to Blobscan"),
+            Err(error) => warn!(?error, "Failed to send indexer metadata to Blobscan"),
+        }
+    }
+
+    fn create_synchronizer(
+        &self,
+        task: SlotsTask,
+        checkpoint_type: CheckpointType,
+        num_threads: u32,
+    ) -> Box<dyn CommonSynchronizer> {
+        self.create_synchronizer_with_policy(task, Arc::new(checkpoint_type), num_threads)
+    }
+
+    fn create_synchronizer_with_policy(
+        &self,
+        task: SlotsTask,
+        checkpoint_policy: Arc<dyn CheckpointPolicy>,
+        num_threads: u32,
+    ) -> Box<dyn CommonSynchronizer> {
+        let synchronizer_builder = self.synchronizer_builder(task, checkpoint_policy, num_threads);
+
+        Box::new(synchronizer_builder.build(self.context.clone()))
+    }
+
+    fn synchronizer_builder(
+        &self,
+        task: SlotsTask,
+        checkpoint_policy: Arc<dyn CheckpointPolicy>,
+        num_threads: u32,
+    ) -> SynchronizerBuilder {
         let mut synchronizer_builder = SynchronizerBuilder::new();
 
         if let Some(checkpoint_slots) = self.checkpoint_slots {
             synchronizer_builder.with_slots_checkpoint(checkpoint_slots);
         }
 
-        let checkpoint_type = self.disabled_checkpoint.unwrap_or(checkpoint_type);
+        match self.disabled_checkpoint {
+            Some(checkpoint_type) => synchronizer_builder.with_checkpoint_type(checkpoint_type),
+            None => synchronizer_builder.with_checkpoint_policy(checkpoint_policy),
+        };
 
-        synchronizer_builder.with_checkpoint_type(checkpoint_type);
+        synchronizer_builder.with_fork_schedule(self.fork_schedule.clone());
 
-        synchronizer_builder.with_num_threads(self.num_threads);
+        synchronizer_builder.with_num_threads(num_threads);
 
-        Box::new(synchronizer_builder.build(self.context.clone()))
+        if let Some(blob_verifier) = &self.blob_verifier {
+            synchronizer_builder.with_blob_verifier(blob_verifier.clone());
+        }
+
+        if let Some(worker_stall_timeout) = self.worker_stall_timeout {
+            synchronizer_builder.with_worker_stall_timeout(worker_stall_timeout);
+        }
+
+        if let Some(slot_timeout) = self.slot_timeout {
+            synchronizer_builder.with_slot_timeout(slot_timeout);
+        }
+
+        synchronizer_builder.with_slow_slot_threshold(self.slow_slot_threshold);
+
+        if let Some(intent_log) = &self.intent_log {
+            synchronizer_builder.with_intent_log(intent_log.clone());
+        }
+
+        if let Some(job_progress) = &self.job_progress {
+            synchronizer_builder.with_job_progress(job_progress.clone());
+        }
+
+        if let Some(slot_stats) = &self.slot_stats {
+            synchronizer_builder.with_slot_stats(slot_stats.clone());
+        }
+
+        synchronizer_builder.with_indexed_slots(self.indexed_slots.clone(), task);
+        synchronizer_builder.with_tunables(self.tunables.clone());
+
+        if task == SlotsTask::Backfill {
+            synchronizer_builder.with_pause_switch(self.backfill_pause_switch.clone());
+        }
+
+        // Keeps the head from queueing up behind the backfill
+        if task == SlotsTask::Head {
+            synchronizer_builder.with_request_priority(RequestPriority::High);
+        }
+
+        // Single-threaded synchronizers have nothing to adapt
+        if let Some(concurrency) = self.concurrency.as_ref().filter(|_| num_threads > 1) {
+            synchronizer_builder.with_adaptive_concurrency(concurrency.clone());
+        }
+
+        synchronizer_builder
     }
 }
+
+/// Creates the context slots are processed with, writing them to the sink and
+/// tagging them as configured.
+fn create_context(
+    env: &Environment,
+    args: &ProcessingArgs,
+) -> IndexerResult<Context<BoxTransport>> {
+    let rollups = match &args.rollup_mapping {
+        Some(path) => {
+            RollupRegistry::load(path).map_err(|error| IndexerError::RollupMappingLoadFailure {
+                path: path.clone(),
+                error,
+            })?
+        }
+        None => RollupRegistry::default(),
+    };
+    let tx_categories = match &args.tx_category_mapping {
+        Some(path) => TxCategoryRegistry::load(path).map_err(|error| {
+            IndexerError::TxCategoryMappingLoadFailure {
+                path: path.clone(),
+                error,
+            }
+        })?,
+        None => TxCategoryRegistry::default(),
+    };
+    let context_config = ContextConfig {
+        sink: args.sink,
+        export_dir: args.export_dir.clone(),
+        export_only: args.export_only,
+        analytics_dir: args.analytics_dir.clone(),
+        era_dir: args.era_dir.clone(),
+        max_inflight_request_bytes: args.max_inflight_request_bytes,
+        max_inflight_blob_bytes: args.max_inflight_blob_bytes,
+        drop_invalid_entities: args.drop_invalid_entities,
+        omit_blob_data: args.no_blob_data,
+        beacon_header_cache_size: args.header_cache_size,
+        rollups,
+        tx_categories,
+        address_filter: AddressFilter::new(
+            args.filter_from_addresses.iter().copied(),
+            args.filter_to_addresses.iter().copied(),
+        ),
+        ..ContextConfig::from(env)
+    };
+
+    Context::try_new(context_config).map_err(|error| {
+        error!(?error, "Failed to create context");
+
+        IndexerError::CreationFailure(anyhow!("Failed to create context: {:?}", error))
+    })
+}
+
+fn create_blob_verifier(args: &ProcessingArgs) -> Option<BlobVerifier> {
+    if !args.verify_blobs {
+        return None;
+    }
+
+    let max_parallelism = match args.blob_verification_threads {
+        Some(threads) => threads,
+        None => thread::available_parallelism()
+            .map(|threads| threads.get())
+            .unwrap_or(1),
+    };
+
+    Some(BlobVerifier::new(max_parallelism))
+}
+
+/// Makes sure the Blobscan API is reachable and accepts the payloads this
+/// indexer emits before anything gets synced.
+async fn check_blobscan_api(context: &dyn CommonContext<BoxTransport>) -> IndexerResult<()> {
+    let api_version = context
+        .blobscan_client()
+        .get_api_version()
+        .await
+        .map_err(|error| {
+            error!(?error, "Failed to reach the Blobscan API");
+
+            IndexerError::BlobscanApiVersionRetrievalError(error)
+        })?;
+
+    match api_version {
+        Some(api_version) if api_version.supports_indexer_schema() => {
+            debug!(
+                api_version = api_version.version,
+                "Blobscan API supports the indexer payload schema"
+            );
+
+            Ok(())
+        }
+        Some(api_version) => Err(IndexerError::IncompatibleBlobscanApi {
+            api_version: api_version.version,
+            supported_schema_versions: api_version.indexer_schema_versions,
+        }),
+        None => {
+            warn!("Blobscan API doesn't report its version, skipping the payload schema compatibility check");
+
+            Ok(())
+        }
+    }
+}
+
+async fn resolve_slot(
+    context: &dyn CommonContext<BoxTransport>,
+    block_id: BlockId,
+) -> IndexerResult<u32> {
+    match block_id {
+        BlockId::Slot(slot) => Ok(slot),
+        _ => match context
+            .beacon_client()
+            .get_block_header(&block_id)
+            .await
+            .map_err(|err| IndexerError::BlockHeaderRetrievalError(block_id.clone(), err))?
+        {
+            Some(block_header) => Ok(block_header.header.message.slot),
+            None => Err(IndexerError::BlockHeaderNotFound(block_id)),
+        },
+    }
+}
+
+/// Returns the block the historical sync continues from: right below the
+/// lowest synced slot.
+fn lower_block_id(sync_state: &Option<BlockchainSyncState>) -> BlockId {
+    match sync_state {
+        Some(state) => match state.last_lower_synced_slot {
+            Some(slot) => BlockId::Slot(slot - 1),
+            None => match state.last_upper_synced_slot {
+                Some(slot) => BlockId::Slot(slot - 1),
+                None => BlockId::Head,
+            },
+        },
+        None => BlockId::Head,
+    }
+}
+
+/// Returns the block the live sync continues from: right above the highest
+/// synced slot.
+fn upper_block_id(sync_state: &Option<BlockchainSyncState>) -> BlockId {
+    match sync_state {
+        Some(state) => match state.last_upper_synced_slot {
+            Some(slot) => BlockId::Slot(slot + 1),
+            None => match state.last_lower_synced_slot {
+                Some(slot) => BlockId::Slot(slot + 1),
+                None => BlockId::Head,
+            },
+        },
+        None => BlockId::Head,
+    }
+}
+
+/// Resolves once the process is asked to terminate, either with Ctrl-C or, on
+/// Unix, with `SIGTERM` as container runtimes do.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            Err(error) => {
+                warn!(?error, "Failed to listen for SIGTERM");
+
+                tokio::signal::ctrl_c().await.ok();
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await.ok();
+}
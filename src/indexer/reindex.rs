@@ -0,0 +1,61 @@
+use alloy::transports::BoxTransport;
+use tracing::info;
+
+use crate::{
+    args::ReindexArgs,
+    clients::beacon::types::BlockId,
+    context::CommonContext,
+    env::Environment,
+    network::ForkSchedule,
+    slots_processor::{blob_verifier::BlobVerifier, SlotsProcessor},
+};
+
+use super::{
+    check_blobscan_api, create_blob_verifier, create_context, error::IndexerError, resolve_slot,
+    types::IndexerResult,
+};
+
+/// Forces the reprocessing of single blocks, even if they were already
+/// indexed. Only takes the options affecting how blocks are processed and
+/// where they're written to, as nothing is synced.
+pub struct Reindexer<T> {
+    context: Box<dyn CommonContext<T>>,
+    fork_schedule: ForkSchedule,
+    blob_verifier: Option<BlobVerifier>,
+}
+
+impl Reindexer<BoxTransport> {
+    pub fn try_new(env: &Environment, args: &ReindexArgs) -> IndexerResult<Self> {
+        Ok(Self {
+            context: Box::new(create_context(env, &args.processing)?),
+            fork_schedule: env.network.fork_schedule(),
+            blob_verifier: create_blob_verifier(&args.processing),
+        })
+    }
+
+    pub async fn reindex(&self, block_id: BlockId) -> IndexerResult<()> {
+        check_blobscan_api(self.context.as_ref()).await?;
+
+        let slot = resolve_slot(self.context.as_ref(), block_id).await?;
+
+        info!(slot, "Reindexing block…");
+
+        let mut slots_processor = SlotsProcessor::new(self.context.clone())
+            .with_fork_schedule(self.fork_schedule.clone());
+
+        if let Some(blob_verifier) = &self.blob_verifier {
+            slots_processor = slots_processor.with_blob_verifier(blob_verifier.clone());
+        }
+
+        slots_processor.process_slots(slot, slot + 1).await?;
+        self.context
+            .sink()
+            .flush()
+            .await
+            .map_err(IndexerError::SinkFlushFailure)?;
+
+        info!(slot, "Block reindexed");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use super::reorg_log::ReorgRecord;
+
+/// Reorg held until an operator confirms it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingReorg {
+    pub slot: u32,
+    pub depth: u32,
+    pub ancestor_slot: u32,
+    pub rewound_blocks: usize,
+    pub rewound_blobs: usize,
+    pub forwarded_blocks: usize,
+    pub forwarded_blobs: usize,
+}
+
+/// Gate holding the reorgs deeper than the confirmation depth until they're
+/// confirmed through the control server, so a misbehaving node can't make the
+/// indexer rewind a large part of the chain on its own.
+#[derive(Debug)]
+pub struct ReorgConfirmation {
+    depth: u32,
+    pending: watch::Sender<Option<PendingReorg>>,
+}
+
+impl ReorgConfirmation {
+    pub fn new(depth: u32) -> Self {
+        let (pending, _) = watch::channel(None);
+
+        Self { depth, pending }
+    }
+
+    /// Whether the reorg is deep enough to need a confirmation.
+    pub fn requires_confirmation(&self, reorg: &ReorgRecord) -> bool {
+        reorg.depth > self.depth
+    }
+
+    pub fn pending(&self) -> Option<PendingReorg> {
+        self.pending.borrow().clone()
+    }
+
+    /// Holds the given reorg until it's confirmed.
+    pub async fn wait_for_confirmation(&self, reorg: PendingReorg) {
+        let mut pending = self.pending.subscribe();
+
+        self.pending.send_replace(Some(reorg));
+
+        // The sender lives as long as `self`, so this can't fail
+        let _ = pending.wait_for(Option::is_none).await;
+    }
+
+    /// Lets the pending reorg be handled, returning it, if any.
+    pub fn confirm(&self) -> Option<PendingReorg> {
+        self.pending.send_replace(None)
+    }
+}
+
+impl From<&ReorgRecord> for PendingReorg {
+    fn from(reorg: &ReorgRecord) -> Self {
+        Self {
+            slot: reorg.slot,
+            depth: reorg.depth,
+            ancestor_slot: reorg.ancestor_slot,
+            rewound_blocks: reorg.rewound_blocks.len(),
+            rewound_blobs: reorg.rewound_blobs(),
+            forwarded_blocks: reorg.forwarded_blocks.len(),
+            forwarded_blobs: reorg.forwarded_blobs(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use alloy::primitives::B256;
+
+    use super::ReorgConfirmation;
+    use crate::indexer::reorg_log::{ReorgRecord, ReorgTrigger};
+
+    #[tokio::test]
+    async fn deep_reorgs_wait_for_confirmation() {
+        let confirmation = Arc::new(ReorgConfirmation::new(2));
+        let shallow_reorg =
+            ReorgRecord::new(ReorgTrigger::ParentMismatch, 12, 10, B256::repeat_byte(1));
+        let deep_reorg =
+            ReorgRecord::new(ReorgTrigger::ParentMismatch, 12, 5, B256::repeat_byte(1));
+
+        assert!(!confirmation.requires_confirmation(&shallow_reorg));
+        assert!(confirmation.requires_confirmation(&deep_reorg));
+        assert_eq!(confirmation.confirm(), None);
+
+        let waiting = tokio::spawn({
+            let confirmation = confirmation.clone();
+            let pending_reorg = (&deep_reorg).into();
+
+            async move { confirmation.wait_for_confirmation(pending_reorg).await }
+        });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert!(!waiting.is_finished());
+        assert_eq!(confirmation.pending().map(|reorg| reorg.depth), Some(7));
+        assert!(confirmation.confirm().is_some());
+
+        tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(confirmation.pending(), None);
+    }
+}
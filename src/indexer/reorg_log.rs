@@ -0,0 +1,202 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+
+use alloy::primitives::B256;
+use chrono::Utc;
+use serde::Serialize;
+use tracing::{debug, info};
+
+use crate::metrics::{add_to_counter, increment_counter, set_gauge};
+
+/// How a reorg was noticed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReorgTrigger {
+    /// A head whose parent isn't the last synced block
+    ParentMismatch,
+    /// A `chain_reorg` event sent by the beacon node
+    ChainReorgEvent,
+}
+
+/// Beacon block rewound or forwarded by a reorg.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ReorgBlock {
+    pub slot: u32,
+    pub root: B256,
+    /// Unknown when the block couldn't be fetched
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blobs: Option<usize>,
+}
+
+/// Everything the indexer did to handle a reorg.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReorgRecord {
+    pub detected_at: String,
+    pub trigger: ReorgTrigger,
+    pub slot: u32,
+    pub depth: u32,
+    pub ancestor_slot: u32,
+    pub old_head_block: Option<B256>,
+    pub new_head_block: B256,
+    /// Slots marked as reorged in Blobscan
+    pub reorged_slots: Vec<u32>,
+    /// Blocks of the old chain the indexer had synced
+    pub rewound_blocks: Vec<ReorgBlock>,
+    /// Blocks of the new chain synced in their place
+    pub forwarded_blocks: Vec<ReorgBlock>,
+}
+
+/// Append-only log of the handled reorgs, one JSON record per line, for
+/// post-incident analysis of what was rewound and when.
+#[derive(Debug)]
+pub struct ReorgLog {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl ReorgRecord {
+    pub fn new(trigger: ReorgTrigger, slot: u32, ancestor_slot: u32, new_head_block: B256) -> Self {
+        Self {
+            detected_at: Utc::now().to_rfc3339(),
+            trigger,
+            slot,
+            depth: slot.saturating_sub(ancestor_slot),
+            ancestor_slot,
+            old_head_block: None,
+            new_head_block,
+            reorged_slots: vec![],
+            rewound_blocks: vec![],
+            forwarded_blocks: vec![],
+        }
+    }
+
+    /// Updates the reorg metrics with this reorg.
+    pub fn record_metrics(&self) {
+        let trigger = match self.trigger {
+            ReorgTrigger::ParentMismatch => "parent_mismatch",
+            ReorgTrigger::ChainReorgEvent => "chain_reorg_event",
+        };
+
+        increment_counter("blob_indexer_reorgs_total", &[("trigger", trigger)]);
+        add_to_counter(
+            "blob_indexer_reorged_slots_total",
+            &[],
+            self.reorged_slots.len() as u64,
+        );
+        set_gauge("blob_indexer_last_reorg_depth", &[], self.depth as u64);
+    }
+
+    pub fn rewound_blobs(&self) -> usize {
+        total_blobs(&self.rewound_blocks)
+    }
+
+    pub fn forwarded_blobs(&self) -> usize {
+        total_blobs(&self.forwarded_blocks)
+    }
+
+    /// Logs the blocks and blobs handling the reorg rewinds and forwards.
+    pub fn log_diff(&self) {
+        info!(
+            slot = self.slot,
+            depth = self.depth,
+            ancestor_slot = self.ancestor_slot,
+            rewound_blocks = self.rewound_blocks.len(),
+            rewound_blobs = self.rewound_blobs(),
+            forwarded_blocks = self.forwarded_blocks.len(),
+            forwarded_blobs = self.forwarded_blobs(),
+            "Reorg diff"
+        );
+
+        for block in &self.rewound_blocks {
+            debug!(slot = block.slot, root = %block.root, blobs = block.blobs, "- Rewound block");
+        }
+
+        for block in &self.forwarded_blocks {
+            debug!(slot = block.slot, root = %block.root, blobs = block.blobs, "+ Forwarded block");
+        }
+    }
+}
+
+impl ReorgLog {
+    /// Opens the log at the given path for appending, creating it if needed.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, record: &ReorgRecord) -> io::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+
+        line.push(b'\n');
+
+        let mut file = self.lock();
+
+        file.write_all(&line)?;
+        file.flush()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, File> {
+        match self.file.lock() {
+            Ok(file) => file,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+fn total_blobs(blocks: &[ReorgBlock]) -> usize {
+    blocks.iter().filter_map(|block| block.blobs).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use alloy::primitives::B256;
+
+    use super::{ReorgBlock, ReorgLog, ReorgRecord, ReorgTrigger};
+
+    #[test]
+    fn records_are_appended_as_json_lines() {
+        let path = env::temp_dir().join(format!("reorg-log-{}.jsonl", process::id()));
+        let log = ReorgLog::open(&path).unwrap();
+        let mut record =
+            ReorgRecord::new(ReorgTrigger::ChainReorgEvent, 12, 10, B256::repeat_byte(2));
+
+        record.reorged_slots = vec![11, 12];
+        record.rewound_blocks = vec![ReorgBlock {
+            slot: 11,
+            root: B256::repeat_byte(1),
+            blobs: Some(3),
+        }];
+
+        log.append(&record).unwrap();
+        log.append(&record).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        let lines = content.lines().collect::<Vec<_>>();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(lines.len(), 2);
+
+        let line: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+
+        assert_eq!(line["trigger"], "chain_reorg_event");
+        assert_eq!(line["depth"], 2);
+        assert_eq!(line["reorgedSlots"], serde_json::json!([11, 12]));
+        assert_eq!(line["rewoundBlocks"][0]["slot"], 11);
+        assert_eq!(line["rewoundBlocks"][0]["blobs"], 3);
+    }
+}
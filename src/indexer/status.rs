@@ -0,0 +1,107 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::types::IndexerTask;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskState {
+    Running,
+    /// Waiting to be restarted after failing
+    Restarting,
+    Done,
+    Failed,
+}
+
+/// What a syncing task is doing and how it has been failing so far.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskStatus {
+    pub task: String,
+    pub state: TaskState,
+    pub restarts: u32,
+    pub errors: u64,
+    pub last_error: Option<String>,
+}
+
+/// Live view of the tasks of a running indexer, shared with the control server
+/// so it can be inspected without restarting the process.
+#[derive(Debug)]
+pub struct IndexerStatus {
+    started_at: Instant,
+    tasks: Mutex<BTreeMap<String, TaskStatus>>,
+}
+
+impl IndexerStatus {
+    pub fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+            tasks: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    pub fn task_started(&self, task: IndexerTask) {
+        self.update(task, |status| status.state = TaskState::Running);
+    }
+
+    pub fn task_done(&self, task: IndexerTask) {
+        self.update(task, |status| status.state = TaskState::Done);
+    }
+
+    /// Records the error the task failed with, and whether it's going to be
+    /// restarted.
+    pub fn task_failed(&self, task: IndexerTask, error: &dyn Display, restarting: bool) {
+        self.update(task, |status| {
+            status.errors += 1;
+            status.last_error = Some(error.to_string());
+
+            if restarting {
+                status.state = TaskState::Restarting;
+                status.restarts += 1;
+            } else {
+                status.state = TaskState::Failed;
+            }
+        });
+    }
+
+    pub fn tasks(&self) -> Vec<TaskStatus> {
+        self.lock().values().cloned().collect()
+    }
+
+    fn update(&self, task: IndexerTask, update: impl FnOnce(&mut TaskStatus)) {
+        let task = task.to_string();
+        let mut tasks = self.lock();
+        let status = tasks.entry(task.clone()).or_insert_with(|| TaskStatus {
+            task,
+            state: TaskState::Running,
+            restarts: 0,
+            errors: 0,
+            last_error: None,
+        });
+
+        update(status);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, TaskStatus>> {
+        match self.tasks.lock() {
+            Ok(tasks) => tasks,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+impl Default for IndexerStatus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,8 +1,70 @@
+use std::{fmt, time::Duration};
+
+use crate::synchronizer::job::SlotRange;
+
 use super::error::{IndexerError, IndexingError};
 
 pub type IndexerResult<T> = Result<T, IndexerError>;
 
+/// Long-running syncing tasks supervised by the indexer
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IndexerTask {
+    /// Backfills the slots below the lowest synced one
+    Historical,
+    /// Follows the chain head through the beacon events stream
+    Live,
+    /// Syncs one of the ranges given with `--ranges`
+    Range(SlotRange),
+}
+
 pub enum IndexerTaskMessage {
-    Done,
-    Error(IndexingError),
+    Done(IndexerTask),
+    Error(IndexerTask, IndexingError),
+}
+
+/// How many times tasks that failed with a recoverable error are restarted and
+/// how long to wait before each restart. The delay doubles with every restart
+/// of the same task.
+#[derive(Clone, Copy, Debug)]
+pub struct RestartPolicy {
+    /// Unlimited when `None`
+    pub max_restarts: Option<u32>,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RestartPolicy {
+    pub fn allows_restart(&self, restarts: u32) -> bool {
+        self.max_restarts
+            .is_none_or(|max_restarts| restarts < max_restarts)
+    }
+
+    /// Returns how long to wait before the given restart, starting at 1.
+    pub fn delay(&self, restart: u32) -> Duration {
+        let factor = 2u32.saturating_pow(restart.saturating_sub(1));
+
+        self.initial_delay
+            .saturating_mul(factor)
+            .min(self.max_delay)
+    }
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: None,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl fmt::Display for IndexerTask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexerTask::Historical => f.write_str("historical"),
+            IndexerTask::Live => f.write_str("live"),
+            IndexerTask::Range(range) => write!(f, "range {range}"),
+        }
+    }
 }
@@ -0,0 +1,23 @@
+// The startup banner is a large json! literal
+#![recursion_limit = "256"]
+
+pub mod args;
+pub mod clients;
+pub mod commands;
+pub mod config;
+pub mod context;
+pub mod control;
+pub mod env;
+pub mod era;
+pub mod exit_code;
+pub mod indexer;
+pub mod metrics;
+pub mod network;
+pub mod rollups;
+pub mod sinks;
+pub mod slots_processor;
+pub mod stream;
+pub mod synchronizer;
+pub mod tunables;
+pub mod tx_categories;
+pub mod utils;
@@ -1,29 +1,50 @@
 use anyhow::{anyhow, Result as AnyhowResult};
-use args::Args;
-use clap::Parser;
-use env::Environment;
-use indexer::Indexer;
-use utils::{
-    banner::print_banner,
-    telemetry::{get_subscriber, init_subscriber},
+use blob_indexer::{
+    args::{BackfillAction, Command},
+    clients::body::set_max_logged_body_length,
+    commands::{
+        audit::audit_slots,
+        import::import_blocks,
+        self_test::run_self_test,
+        status::{print_instance_status, print_status},
+        support_bundle::create_support_bundle,
+        verify::verify_slots,
+    },
+    config::Config,
+    context::{Config as ContextConfig, Context},
+    control::{confirm_reorg, server::run_control_server, set_backfill_paused},
+    env::Environment,
+    exit_code::ExitCode,
+    indexer::{reindex::Reindexer, Indexer},
+    metrics::server::{run_server, ServerInfo},
+    tunables::ConfigWatcher,
+    utils::{
+        banner::{config_dump, print_banner},
+        otlp::OtlpExporter,
+        redaction::Redactor,
+        telemetry::{get_subscriber, init_subscriber},
+    },
 };
-
-mod args;
-mod clients;
-mod context;
-mod env;
-mod indexer;
-mod network;
-mod slots_processor;
-mod synchronizer;
-mod utils;
+use std::time::Duration;
 
 async fn run() -> AnyhowResult<()> {
     dotenv::dotenv().ok();
-    let env = match Environment::from_env() {
-        Ok(env) => env,
-        Err(err) => return Err(anyhow!(format!("Failed to load env variables: {}", err))),
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(err) => return Err(anyhow::Error::new(err).context("Failed to load the configuration")),
     };
+    let env = &config.env;
+
+    if config.args.print_config {
+        let index_args = config.args.index_args().unwrap_or(&config.args.index);
+
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&config_dump(index_args, env))?
+        );
+
+        return Ok(());
+    }
 
     let mut _guard;
 
@@ -32,28 +53,233 @@ async fn run() -> AnyhowResult<()> {
             sentry_dsn,
             sentry::ClientOptions {
                 release: sentry::release_name!(),
+                // Releases are told apart per network through the environment
+                environment: Some(env.network.to_string().into()),
                 ..Default::default()
             },
         ));
+
+        sentry::configure_scope(|scope| {
+            scope.set_tag("network", &env.network);
+
+            if let Some(git_commit) = option_env!("GIT_COMMIT") {
+                scope.set_tag("git_commit", git_commit);
+            }
+        });
     }
 
-    let subscriber = get_subscriber("info".into(), std::io::stdout);
+    set_max_logged_body_length(env.tracing_max_body_length);
+
+    let otlp_exporter = env
+        .otel_exporter_otlp_endpoint
+        .as_deref()
+        .map(|endpoint| OtlpExporter::spawn(endpoint, &env.otel_service_name));
+    let (subscriber, log_filter) = get_subscriber(
+        env.log_level.clone().unwrap_or_else(|| "info".into()),
+        env.tracing_debug_sample_rate,
+        std::io::stdout,
+        otlp_exporter.clone(),
+        Redactor::from_env(env),
+    );
     init_subscriber(subscriber);
 
-    let args = Args::parse();
+    let config_watcher = ConfigWatcher::new(&config, log_filter);
+    let result = run_command(config.args.into_command(), &config.env, config_watcher).await;
+
+    if let Some(otlp_exporter) = otlp_exporter {
+        otlp_exporter.flush().await;
+    }
+
+    result
+}
+
+async fn run_command(
+    command: Command,
+    env: &Environment,
+    config_watcher: Option<ConfigWatcher>,
+) -> AnyhowResult<()> {
+    match command {
+        Command::Index(index_args) => {
+            print_banner(&index_args, env);
+
+            if let Some(address) = env.metrics_server_address {
+                let info = ServerInfo::new(env, &index_args);
+                let context = Context::try_new(ContextConfig::from(env))?;
+
+                tokio::spawn(async move {
+                    if let Err(error) = run_server(address, info, Box::new(context)).await {
+                        tracing::error!(?error, "Metrics server failed");
+                    }
+                });
+            }
+
+            let mut indexer = Indexer::try_new(env, &index_args)?;
+
+            if let Some(config_watcher) = config_watcher {
+                indexer = indexer.with_tunables(config_watcher.tunables());
+
+                tokio::spawn(config_watcher.run());
+            }
+
+            if let Some(address) = env.control_server_address {
+                let status = indexer.status();
+                let backfill_pause_switch = indexer.backfill_pause_switch();
+                let reorg_confirmation = indexer.reorg_confirmation();
+                let context = Context::try_new(ContextConfig::from(env))?;
 
-    print_banner(&args, &env);
+                tokio::spawn(async move {
+                    if let Err(error) = run_control_server(
+                        address,
+                        status,
+                        backfill_pause_switch,
+                        reorg_confirmation,
+                        Box::new(context),
+                    )
+                    .await
+                    {
+                        tracing::error!(?error, "Control server failed");
+                    }
+                });
+            }
 
-    Indexer::try_new(&env, &args)?
-        .run(args.from_slot, args.to_slot)
-        .await
-        .map_err(|err| anyhow!(err))
+            let manifest_path = match &index_args.resume_job {
+                Some(manifest_path) => Some(manifest_path),
+                None => index_args.resume.then_some(&index_args.job_manifest),
+            };
+
+            let result = match manifest_path {
+                Some(manifest_path) => indexer.resume_job(manifest_path).await,
+                None if !index_args.ranges.is_empty() => {
+                    indexer.run_ranges(&index_args.ranges).await
+                }
+                None => indexer.run(index_args.from_slot, index_args.to_slot).await,
+            };
+            let flushed = indexer.flush_sink().await;
+
+            result.and(flushed).map_err(|err| anyhow!(err))
+        }
+        Command::Reindex(reindex_args) => Reindexer::try_new(env, &reindex_args)?
+            .reindex(reindex_args.block_id())
+            .await
+            .map_err(|err| anyhow!(err)),
+        Command::Verify(verify_args) => {
+            let context = Context::try_new(ContextConfig::from(env))?;
+            let report = verify_slots(&context, verify_args.from_slot, verify_args.to_slot).await?;
+
+            print!("{report}");
+
+            if report.discrepancies.is_empty() {
+                Ok(())
+            } else {
+                Err(anyhow!(
+                    "Found {} discrepancies",
+                    report.discrepancies.len()
+                ))
+            }
+        }
+        Command::Audit(audit_args) => {
+            let context = Context::try_new(ContextConfig::from(env))?;
+            let report = audit_slots(&context, audit_args.from_slot, audit_args.to_slot).await?;
+
+            print!("{report}");
+
+            if report.inconsistencies.is_empty() {
+                Ok(())
+            } else {
+                Err(anyhow!(
+                    "Found {} inconsistencies",
+                    report.inconsistencies.len()
+                ))
+            }
+        }
+        Command::Import(import_args) => {
+            let context = Context::try_new(ContextConfig::from(env))?;
+            let report = import_blocks(
+                &context,
+                &import_args.dir,
+                (import_args.from_slot, import_args.to_slot),
+                import_args.checkpoint,
+                import_args.slots_per_save,
+            )
+            .await?;
+
+            print!("{report}");
+
+            Ok(())
+        }
+        Command::Status(status_args) => {
+            if let Some(address) = status_args.instance {
+                return print_instance_status(address)
+                    .await
+                    .map_err(|err| anyhow!(err));
+            }
+
+            let context = Context::try_new(ContextConfig::from(env))?;
+
+            print_status(&context).await.map_err(|err| anyhow!(err))
+        }
+        Command::Backfill(backfill_args) => {
+            let paused = backfill_args.action == BackfillAction::Pause;
+            let state = set_backfill_paused(backfill_args.instance, paused)
+                .await
+                .map_err(|err| anyhow!(err))?;
+
+            println!(
+                "Backfill {}",
+                if state.paused { "paused" } else { "running" }
+            );
+
+            Ok(())
+        }
+        Command::ConfirmReorg(confirm_reorg_args) => {
+            let confirmed_reorg = confirm_reorg(confirm_reorg_args.instance)
+                .await
+                .map_err(|err| anyhow!(err))?;
+
+            match confirmed_reorg {
+                Some(reorg) => println!(
+                    "Confirmed reorg at slot {} ({} slots deep, rewinding {} blocks and {} blobs)",
+                    reorg.slot, reorg.depth, reorg.rewound_blocks, reorg.rewound_blobs
+                ),
+                None => println!("No reorg waiting to be confirmed"),
+            }
+
+            Ok(())
+        }
+        Command::SupportBundle(bundle_args) => {
+            let context = Context::try_new(ContextConfig::from(env))?;
+            let path = create_support_bundle(
+                &context,
+                env,
+                &bundle_args.output_dir,
+                bundle_args.log_file.as_deref(),
+                bundle_args.log_lines,
+            )
+            .await?;
+
+            println!("Support bundle written to {}", path.display());
+
+            Ok(())
+        }
+        Command::SelfTest(self_test_args) => {
+            let context = Context::try_new(ContextConfig::from(env))?;
+            let report = run_self_test(&context, Duration::from_secs(self_test_args.timeout)).await;
+
+            print!("{report}");
+
+            if report.passed() {
+                Ok(())
+            } else {
+                Err(anyhow!("{} checks failed", report.failed_checks()))
+            }
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() {
     if let Err(err) = run().await {
         eprintln!("Error: {err:?}");
-        std::process::exit(1);
+        std::process::exit(ExitCode::from_error(&err).code());
     }
 }
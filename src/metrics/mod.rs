@@ -0,0 +1,91 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Mutex, OnceLock},
+};
+
+pub mod server;
+
+type MetricKey = (&'static str, String);
+
+static COUNTERS: OnceLock<Mutex<BTreeMap<MetricKey, u64>>> = OnceLock::new();
+static GAUGES: OnceLock<Mutex<BTreeMap<MetricKey, u64>>> = OnceLock::new();
+
+fn counters() -> &'static Mutex<BTreeMap<MetricKey, u64>> {
+    COUNTERS.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+fn gauges() -> &'static Mutex<BTreeMap<MetricKey, u64>> {
+    GAUGES.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+fn format_labels(labels: &[(&str, &str)]) -> String {
+    labels
+        .iter()
+        .map(|(key, value)| format!("{key}=\"{value}\""))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Increments the counter identified by the given name and labels.
+pub fn increment_counter(name: &'static str, labels: &[(&str, &str)]) {
+    add_to_counter(name, labels, 1);
+}
+
+/// Adds the given amount to the counter identified by the given name and
+/// labels, as for the running totals of durations.
+pub fn add_to_counter(name: &'static str, labels: &[(&str, &str)], amount: u64) {
+    if let Ok(mut counters) = counters().lock() {
+        *counters.entry((name, format_labels(labels))).or_insert(0) += amount;
+    }
+}
+
+/// Current value of the counter identified by the given name and labels.
+#[cfg(test)]
+pub fn counter(name: &'static str, labels: &[(&str, &str)]) -> u64 {
+    counters()
+        .lock()
+        .ok()
+        .and_then(|counters| counters.get(&(name, format_labels(labels))).copied())
+        .unwrap_or_default()
+}
+
+/// Sets the current value of the gauge identified by the given name and labels.
+pub fn set_gauge(name: &'static str, labels: &[(&str, &str)], value: u64) {
+    if let Ok(mut gauges) = gauges().lock() {
+        gauges.insert((name, format_labels(labels)), value);
+    }
+}
+
+/// Renders every registered metric using the Prometheus text format.
+pub fn render() -> String {
+    let mut output = String::new();
+
+    if let Ok(counters) = counters().lock() {
+        render_metrics(&mut output, "counter", &counters);
+    }
+
+    if let Ok(gauges) = gauges().lock() {
+        render_metrics(&mut output, "gauge", &gauges);
+    }
+
+    output
+}
+
+fn render_metrics(output: &mut String, metric_type: &str, metrics: &BTreeMap<MetricKey, u64>) {
+    let mut last_name = None;
+
+    for ((name, labels), value) in metrics.iter() {
+        if last_name != Some(*name) {
+            let _ = writeln!(output, "# TYPE {name} {metric_type}");
+
+            last_name = Some(*name);
+        }
+
+        if labels.is_empty() {
+            let _ = writeln!(output, "{name} {value}");
+        } else {
+            let _ = writeln!(output, "{name}{{{labels}}} {value}");
+        }
+    }
+}
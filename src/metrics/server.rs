@@ -0,0 +1,175 @@
+use std::{convert::Infallible, net::SocketAddr, sync::Arc, time::Duration};
+
+use alloy::transports::BoxTransport;
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tracing::{error, info};
+
+use crate::{
+    args::IndexArgs, context::CommonContext, env::Environment, network::Network,
+    utils::redaction::remove_credentials_from_url,
+};
+
+/// Upper bound for fetching the sync positions so `/info` stays responsive
+/// while Blobscan is unreachable
+const SYNC_STATE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Static description of the running indexer instance
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerInfo {
+    pub version: &'static str,
+    /// Commit the indexer was built from, if known at build time
+    pub git_commit: Option<&'static str>,
+    pub network: Network,
+    pub features: Vec<&'static str>,
+    pub config_hash: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SyncPositions {
+    last_lower_synced_slot: Option<u32>,
+    last_upper_synced_slot: Option<u32>,
+    last_finalized_block: Option<u32>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct InfoResponse<'a> {
+    #[serde(flatten)]
+    info: &'a ServerInfo,
+    sync_state: Option<SyncPositions>,
+}
+
+struct ServerState {
+    info: ServerInfo,
+    context: Box<dyn CommonContext<BoxTransport>>,
+}
+
+impl ServerInfo {
+    pub fn new(env: &Environment, args: &IndexArgs) -> Self {
+        let mut features = vec![];
+
+        if cfg!(feature = "postgres") {
+            features.push("postgres");
+        }
+
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            git_commit: option_env!("GIT_COMMIT"),
+            network: env.network.clone(),
+            features,
+            config_hash: config_hash(env, args),
+        }
+    }
+}
+
+/// Hashes the settings that affect what gets indexed and where, leaving secrets
+/// and credentials out so the hash can be shared freely.
+fn config_hash(env: &Environment, args: &IndexArgs) -> String {
+    let effective_config = format!(
+        "{:?}|{:?}|{}|{:?}|{:?}|{}|{:?}",
+        env.network,
+        env.dencun_fork_slot,
+        env.blobscan_api_endpoint,
+        remove_credentials_from_url(&env.beacon_node_endpoint),
+        remove_credentials_from_url(&env.execution_node_endpoint),
+        env.disable_response_compression,
+        args,
+    );
+
+    hex::encode(Sha256::digest(effective_config.as_bytes()))
+}
+
+/// Serves the `/health`, `/metrics` and `/info` endpoints until the process
+/// exits.
+pub async fn run_server(
+    address: SocketAddr,
+    info: ServerInfo,
+    context: Box<dyn CommonContext<BoxTransport>>,
+) -> Result<(), hyper::Error> {
+    let state = Arc::new(ServerState { info, context });
+    let make_service = make_service_fn(move |_| {
+        let state = state.clone();
+
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                handle_request(request, state.clone())
+            }))
+        }
+    });
+
+    info!(%address, "Metrics server listening");
+
+    Server::bind(&address).serve(make_service).await
+}
+
+async fn handle_request(
+    request: Request<Body>,
+    state: Arc<ServerState>,
+) -> Result<Response<Body>, Infallible> {
+    let response = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/health") => Response::new(Body::from("OK")),
+        (&Method::GET, "/metrics") => Response::builder()
+            .header(CONTENT_TYPE, "text/plain; version=0.0.4")
+            .body(Body::from(super::render()))
+            .unwrap_or_default(),
+        (&Method::GET, "/info") => info_response(&state).await,
+        _ => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap_or_default(),
+    };
+
+    Ok(response)
+}
+
+async fn info_response(state: &ServerState) -> Response<Body> {
+    let sync_state = match tokio::time::timeout(
+        SYNC_STATE_TIMEOUT,
+        state.context.blobscan_client().get_sync_state(),
+    )
+    .await
+    {
+        Ok(Ok(sync_state)) => sync_state.map(|sync_state| SyncPositions {
+            last_lower_synced_slot: sync_state.last_lower_synced_slot,
+            last_upper_synced_slot: sync_state.last_upper_synced_slot,
+            last_finalized_block: sync_state.last_finalized_block,
+        }),
+        Ok(Err(error)) => {
+            error!(?error, "Failed to fetch blobscan's sync state");
+
+            None
+        }
+        Err(_) => {
+            error!("Timed out fetching blobscan's sync state");
+
+            None
+        }
+    };
+    let body = InfoResponse {
+        info: &state.info,
+        sync_state,
+    };
+
+    match serde_json::to_vec(&body) {
+        Ok(body) => Response::builder()
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap_or_default(),
+        Err(error) => {
+            error!(?error, "Failed to serialize info response");
+
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::empty())
+                .unwrap_or_default()
+        }
+    }
+}
@@ -1,8 +1,26 @@
-use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, fs,
+    path::Path,
+    str::FromStr,
+};
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(rename_all = "lowercase")]
+use anyhow::anyhow;
+use chrono::Utc;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+use toml_edit::{DocumentMut, Item, Table};
+
+use crate::clients::{beacon::CommonBeaconClient, common::ClientResult};
+
+/// Epoch beacon nodes give to forks that aren't scheduled yet
+const FAR_FUTURE_EPOCH: u64 = u64::MAX;
+/// Blob base fee update fraction set by EIP-7691 for Electra's 9 blobs
+const ELECTRA_BLOB_BASE_FEE_UPDATE_FRACTION: u64 = 5_007_716;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum Network {
+    #[default]
     Mainnet,
     Goerli,
     Sepolia,
@@ -10,18 +28,617 @@ pub enum Network {
     Devnet,
     Gnosis,
     Chiado,
+    /// Network defined in a networks file
+    Custom(CustomNetwork),
+}
+
+/// Network defined by its operator, for devnets and L2s not covered by the
+/// built-in presets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomNetwork {
+    pub name: String,
+    pub chain_id: Option<u64>,
+    /// Discovered from the beacon node when not given
+    pub dencun_fork_slot: Option<u32>,
+    /// Unix timestamp of the genesis, discovered from the beacon node when not
+    /// given
+    pub genesis_time: Option<u64>,
+    pub seconds_per_slot: u64,
+    pub slots_per_epoch: u32,
+    /// Activation slots of the forks after Dencun
+    pub fork_slots: BTreeMap<Fork, u32>,
+    /// Overrides the default blob limits of the forks
+    pub max_blobs_per_block: BTreeMap<Fork, u32>,
+}
+
+/// Upgrades changing how blobs are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Fork {
+    Deneb,
+    Electra,
+    Fulu,
+}
+
+/// Activation slots of the forks of a network along with the maximum amount
+/// of blobs per block allowed from each slot on.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ForkSchedule {
+    fork_slots: BTreeMap<Fork, u32>,
+    /// Keyed by the slot the limit applies from, as blob-parameter-only (BPO)
+    /// forks raise it without being forks of their own
+    blob_limits: BTreeMap<u32, u32>,
+}
+
+/// Custom networks loaded from a TOML file defining each network in its own
+/// table, e.g.:
+///
+/// ```toml
+/// [my-devnet]
+/// chain_id = 7032118028
+/// dencun_fork_slot = 0 # discovered from the beacon node if omitted
+/// genesis_time = 1700000000 # discovered from the beacon node if omitted
+/// seconds_per_slot = 6 # defaults to 12
+/// slots_per_epoch = 8 # defaults to 32
+///
+/// [my-devnet.fork_slots]
+/// electra = 2048
+///
+/// [my-devnet.max_blobs_per_block] # defaults to the forks' limits
+/// electra = 12
+/// ```
+///
+/// Networks named after a built-in preset replace it.
+#[derive(Debug, Clone, Default)]
+pub struct NetworkPresets(BTreeMap<String, CustomNetwork>);
+
+/// Converts between slots and the Unix timestamps they start at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotClock {
+    genesis_time: u64,
+    seconds_per_slot: u64,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum NetworkPresetsError {
+    #[error("failed to read networks file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to parse networks file: {0}")]
+    Parse(#[from] toml_edit::TomlError),
+    #[error("invalid network \"{network}\": {reason}")]
+    InvalidNetwork { network: String, reason: String },
 }
 
+#[derive(Debug, thiserror::Error)]
+#[error("unknown fork \"{0}\": expected deneb, electra or fulu")]
+pub struct UnknownForkError(String);
+
+#[derive(Debug, thiserror::Error)]
+#[error("unknown network \"{0}\": expected one of the built-in networks or one defined in the networks file")]
+pub struct UnknownNetworkError(String);
+
 impl Network {
-    pub fn dencun_fork_slot(&self) -> u32 {
+    pub fn dencun_fork_slot(&self) -> Option<u32> {
+        self.fork_schedule().fork_slot(Fork::Deneb)
+    }
+
+    pub fn seconds_per_slot(&self) -> u64 {
+        match self {
+            Network::Gnosis | Network::Chiado => 5,
+            Network::Custom(network) => network.seconds_per_slot,
+            _ => 12,
+        }
+    }
+
+    /// Unix timestamp of the genesis of the built-in networks, which should be
+    /// taken from the beacon node when possible.
+    pub fn genesis_time(&self) -> Option<u64> {
+        match self {
+            Network::Mainnet => Some(1606824023),
+            Network::Goerli => Some(1616508000),
+            Network::Sepolia => Some(1655733600),
+            Network::Holesky => Some(1695902400),
+            Network::Devnet => None,
+            Network::Gnosis => Some(1638993340),
+            Network::Chiado => Some(1665396300),
+            Network::Custom(network) => network.genesis_time,
+        }
+    }
+
+    /// Clock of the network, if its genesis time is known.
+    pub fn slot_clock(&self) -> Option<SlotClock> {
+        self.genesis_time()
+            .map(|genesis_time| SlotClock::new(genesis_time, self.seconds_per_slot()))
+    }
+
+    pub fn slots_per_epoch(&self) -> u32 {
+        match self {
+            Network::Gnosis | Network::Chiado => 16,
+            Network::Custom(network) => network.slots_per_epoch,
+            _ => 32,
+        }
+    }
+
+    pub fn chain_id(&self) -> Option<u64> {
+        match self {
+            Network::Mainnet => Some(1),
+            Network::Goerli => Some(5),
+            Network::Sepolia => Some(11155111),
+            Network::Holesky => Some(17000),
+            Network::Devnet => None,
+            Network::Gnosis => Some(100),
+            Network::Chiado => Some(10200),
+            Network::Custom(network) => network.chain_id,
+        }
+    }
+
+    /// Fork schedule of the network. Only the forks already scheduled when
+    /// the presets were last updated are included, so it should be refreshed
+    /// from the beacon node when possible.
+    pub fn fork_schedule(&self) -> ForkSchedule {
+        let mut schedule = ForkSchedule::default();
+        let epoch_slot = |epoch: u32| epoch * 32;
+
         match self {
-            Network::Mainnet => 8626176, // Epoch 269568
-            Network::Goerli => 7413760,  // Epoch 231680
-            Network::Sepolia => 4243456, // Epoch 132608
-            Network::Holesky => 950272,  // Epoch 29696
-            Network::Devnet => 0,
-            Network::Gnosis => 14237696, // Epoch 889856
-            Network::Chiado => 8265728,  // Epoch 516608
+            Network::Mainnet => schedule
+                .with_fork(Fork::Deneb, epoch_slot(269568), None)
+                .with_fork(Fork::Electra, epoch_slot(364032), None)
+                .with_fork(Fork::Fulu, epoch_slot(411392), None)
+                .with_blob_limit(epoch_slot(412672), 15) // BPO1
+                .with_blob_limit(epoch_slot(419072), 21), // BPO2
+            Network::Sepolia => schedule
+                .with_fork(Fork::Deneb, epoch_slot(132608), None)
+                .with_fork(Fork::Electra, epoch_slot(222464), None)
+                .with_fork(Fork::Fulu, epoch_slot(272640), None)
+                .with_blob_limit(epoch_slot(274176), 15) // BPO1
+                .with_blob_limit(epoch_slot(275712), 21), // BPO2
+            Network::Holesky => schedule
+                .with_fork(Fork::Deneb, epoch_slot(29696), None)
+                .with_fork(Fork::Electra, epoch_slot(115968), None)
+                .with_fork(Fork::Fulu, epoch_slot(165120), None)
+                .with_blob_limit(epoch_slot(166400), 15) // BPO1
+                .with_blob_limit(epoch_slot(167936), 21), // BPO2
+            // Gnosis chains have 16 slots per epoch and keep blobs scarce
+            Network::Gnosis => schedule
+                .with_fork(Fork::Deneb, 889856 * 16, Some(2))
+                .with_fork(Fork::Electra, 1337856 * 16, Some(2)),
+            Network::Chiado => schedule
+                .with_fork(Fork::Deneb, 516608 * 16, Some(2))
+                .with_fork(Fork::Electra, 948224 * 16, Some(2)),
+            Network::Goerli => schedule.with_fork(Fork::Deneb, epoch_slot(231680), None),
+            Network::Devnet => schedule.with_fork(Fork::Deneb, 0, None),
+            Network::Custom(network) => {
+                if let Some(slot) = network.dencun_fork_slot {
+                    schedule = schedule.with_fork(
+                        Fork::Deneb,
+                        slot,
+                        network.max_blobs_per_block.get(&Fork::Deneb).copied(),
+                    );
+                }
+
+                for (&fork, &slot) in &network.fork_slots {
+                    schedule = schedule.with_fork(
+                        fork,
+                        slot,
+                        network.max_blobs_per_block.get(&fork).copied(),
+                    );
+                }
+
+                schedule
+            }
+        }
+    }
+
+    /// Resolves a network by name, preferring the ones defined in the presets
+    /// over the built-in ones.
+    pub fn resolve(name: &str, presets: &NetworkPresets) -> Result<Self, UnknownNetworkError> {
+        if let Some(network) = presets.0.get(name) {
+            return Ok(Network::Custom(network.clone()));
+        }
+
+        name.parse()
+    }
+}
+
+impl NetworkPresets {
+    pub fn from_file(path: &Path) -> Result<Self, NetworkPresetsError> {
+        fs::read_to_string(path)?.parse()
+    }
+}
+
+impl FromStr for NetworkPresets {
+    type Err = NetworkPresetsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let document = s.parse::<DocumentMut>()?;
+        let mut networks = BTreeMap::new();
+
+        for (name, item) in document.iter() {
+            let invalid = |reason: &str| NetworkPresetsError::InvalidNetwork {
+                network: name.to_string(),
+                reason: reason.to_string(),
+            };
+            let table = item.as_table().ok_or_else(|| invalid("expected a table"))?;
+            let dencun_fork_slot =
+                get_u32(table, "dencun_fork_slot").map_err(|reason| invalid(&reason))?;
+            let genesis_time = match get_integer(table, "genesis_time") {
+                Ok(genesis_time) => genesis_time
+                    .map(u64::try_from)
+                    .transpose()
+                    .map_err(|_| invalid("genesis_time must be positive"))?,
+                Err(reason) => return Err(invalid(&reason)),
+            };
+            let seconds_per_slot = match get_integer(table, "seconds_per_slot") {
+                Ok(Some(seconds)) if seconds > 0 => seconds as u64,
+                Ok(Some(_)) => return Err(invalid("seconds_per_slot must be positive")),
+                Ok(None) => 12,
+                Err(reason) => return Err(invalid(&reason)),
+            };
+            let slots_per_epoch = match get_u32(table, "slots_per_epoch") {
+                Ok(Some(slots)) if slots > 0 => slots,
+                Ok(Some(_)) => return Err(invalid("slots_per_epoch must be positive")),
+                Ok(None) => 32,
+                Err(reason) => return Err(invalid(&reason)),
+            };
+            let chain_id = match get_integer(table, "chain_id") {
+                Ok(chain_id) => chain_id
+                    .map(u64::try_from)
+                    .transpose()
+                    .map_err(|_| invalid("chain_id must be positive"))?,
+                Err(reason) => return Err(invalid(&reason)),
+            };
+            let fork_slots =
+                get_fork_table(table, "fork_slots").map_err(|reason| invalid(&reason))?;
+            let max_blobs_per_block =
+                get_fork_table(table, "max_blobs_per_block").map_err(|reason| invalid(&reason))?;
+
+            networks.insert(
+                name.to_string(),
+                CustomNetwork {
+                    name: name.to_string(),
+                    chain_id,
+                    dencun_fork_slot,
+                    genesis_time,
+                    seconds_per_slot,
+                    slots_per_epoch,
+                    fork_slots,
+                    max_blobs_per_block,
+                },
+            );
         }
+
+        Ok(Self(networks))
+    }
+}
+
+/// Fetches the fork schedule followed by the beacon node. Forks are named by
+/// matching the versions in the node's fork schedule with the
+/// `<FORK>_FORK_VERSION` values of its spec, which also provides the epochs of
+/// the forks missing from the schedule and the blob limits, including the ones
+/// of BPO forks.
+pub async fn discover_fork_schedule(
+    beacon_client: &dyn CommonBeaconClient,
+) -> ClientResult<ForkSchedule> {
+    let (spec, scheduled_forks) =
+        tokio::try_join!(beacon_client.get_spec(), beacon_client.get_fork_schedule())?;
+    let spec = spec.ok_or_else(|| anyhow!("beacon node spec not found"))?;
+    let slots_per_epoch = spec
+        .get("SLOTS_PER_EPOCH")
+        .and_then(parse_spec_u64)
+        .ok_or_else(|| anyhow!("SLOTS_PER_EPOCH missing from beacon node spec"))?;
+    let epoch_slot = |epoch: u64| {
+        if epoch == FAR_FUTURE_EPOCH {
+            return None;
+        }
+
+        u32::try_from(epoch.checked_mul(slots_per_epoch)?).ok()
+    };
+    let fork_names = spec
+        .iter()
+        .filter_map(|(key, value)| {
+            let fork = key.strip_suffix("_FORK_VERSION")?.to_lowercase();
+
+            Some((value.as_str()?.to_lowercase(), fork.parse::<Fork>().ok()?))
+        })
+        .collect::<HashMap<_, _>>();
+    let mut fork_epochs = spec
+        .iter()
+        .filter_map(|(key, value)| {
+            let fork = key.strip_suffix("_FORK_EPOCH")?.to_lowercase();
+
+            Some((fork.parse::<Fork>().ok()?, parse_spec_u64(value)?))
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    // The schedule is what the node actually follows
+    for scheduled_fork in scheduled_forks.unwrap_or_default() {
+        if let (Some(&fork), Ok(epoch)) = (
+            fork_names.get(&scheduled_fork.current_version.to_lowercase()),
+            scheduled_fork.epoch.parse::<u64>(),
+        ) {
+            fork_epochs.insert(fork, epoch);
+        }
+    }
+
+    let mut schedule = ForkSchedule::default();
+
+    for (fork, epoch) in fork_epochs {
+        let Some(slot) = epoch_slot(epoch) else {
+            continue;
+        };
+        let max_blobs_per_block = match fork {
+            Fork::Deneb => spec.get("MAX_BLOBS_PER_BLOCK"),
+            Fork::Electra => spec.get("MAX_BLOBS_PER_BLOCK_ELECTRA"),
+            Fork::Fulu => None,
+        }
+        .and_then(parse_spec_u64)
+        .and_then(|max| u32::try_from(max).ok());
+
+        schedule = schedule.with_fork(fork, slot, max_blobs_per_block);
+    }
+
+    if let Some(Value::Array(blob_schedule)) = spec.get("BLOB_SCHEDULE") {
+        for entry in blob_schedule {
+            let slot = entry
+                .get("EPOCH")
+                .and_then(parse_spec_u64)
+                .and_then(epoch_slot);
+            let max_blobs_per_block = entry
+                .get("MAX_BLOBS_PER_BLOCK")
+                .and_then(parse_spec_u64)
+                .and_then(|max| u32::try_from(max).ok());
+
+            if let (Some(slot), Some(max_blobs_per_block)) = (slot, max_blobs_per_block) {
+                schedule = schedule.with_blob_limit(slot, max_blobs_per_block);
+            }
+        }
+    }
+
+    Ok(schedule)
+}
+
+/// Fetches the Unix timestamp of the genesis of the beacon node's chain.
+pub async fn discover_genesis_time(beacon_client: &dyn CommonBeaconClient) -> ClientResult<u64> {
+    let genesis = beacon_client
+        .get_genesis()
+        .await?
+        .ok_or_else(|| anyhow!("beacon node genesis not found"))?;
+
+    genesis
+        .genesis_time
+        .parse()
+        .map_err(|_| anyhow!("invalid genesis time \"{}\"", genesis.genesis_time).into())
+}
+
+pub(crate) fn parse_spec_u64(value: &Value) -> Option<u64> {
+    match value {
+        Value::String(value) => value.parse().ok(),
+        value => value.as_u64(),
+    }
+}
+
+fn get_integer(table: &Table, key: &str) -> Result<Option<i64>, String> {
+    match table.get(key) {
+        None | Some(Item::None) => Ok(None),
+        Some(item) => item
+            .as_integer()
+            .map(Some)
+            .ok_or_else(|| format!("{key} must be an integer")),
+    }
+}
+
+fn get_fork_table(table: &Table, key: &str) -> Result<BTreeMap<Fork, u32>, String> {
+    let Some(item) = table.get(key) else {
+        return Ok(BTreeMap::new());
+    };
+    let forks = item
+        .as_table()
+        .ok_or_else(|| format!("{key} must be a table"))?;
+
+    forks
+        .iter()
+        .map(|(fork, _)| {
+            let value = get_u32(forks, fork)?.unwrap_or_default();
+            let fork = fork.parse::<Fork>().map_err(|error| error.to_string())?;
+
+            Ok((fork, value))
+        })
+        .collect()
+}
+
+fn get_u32(table: &Table, key: &str) -> Result<Option<u32>, String> {
+    get_integer(table, key)?
+        .map(|value| {
+            u32::try_from(value).map_err(|_| format!("{key} must be a positive 32-bit integer"))
+        })
+        .transpose()
+}
+
+impl SlotClock {
+    pub fn new(genesis_time: u64, seconds_per_slot: u64) -> Self {
+        Self {
+            genesis_time,
+            seconds_per_slot: seconds_per_slot.max(1),
+        }
+    }
+
+    pub fn genesis_time(&self) -> u64 {
+        self.genesis_time
+    }
+
+    /// Unix timestamp the slot starts at.
+    pub fn slot_timestamp(&self, slot: u32) -> u64 {
+        self.genesis_time + slot as u64 * self.seconds_per_slot
+    }
+
+    /// Slot going on at the given Unix timestamp, if it's after the genesis.
+    pub fn slot_at(&self, timestamp: u64) -> Option<u32> {
+        let elapsed = timestamp.checked_sub(self.genesis_time)?;
+
+        u32::try_from(elapsed / self.seconds_per_slot).ok()
+    }
+
+    pub fn current_slot(&self) -> Option<u32> {
+        self.slot_at(Utc::now().timestamp().max(0) as u64)
+    }
+
+    /// Seconds elapsed since the slot started.
+    pub fn seconds_since(&self, slot: u32) -> u64 {
+        (Utc::now().timestamp().max(0) as u64).saturating_sub(self.slot_timestamp(slot))
+    }
+}
+
+impl Fork {
+    /// Limit set by the fork itself, later raised by BPO forks
+    fn default_max_blobs_per_block(&self) -> u32 {
+        match self {
+            Fork::Deneb => 6,
+            Fork::Electra | Fork::Fulu => 9,
+        }
+    }
+}
+
+impl ForkSchedule {
+    pub fn fork_slot(&self, fork: Fork) -> Option<u32> {
+        self.fork_slots.get(&fork).copied()
+    }
+
+    /// Fork active at the slot, if any.
+    pub fn fork_at(&self, slot: u32) -> Option<Fork> {
+        self.fork_slots
+            .iter()
+            .filter(|(_, &fork_slot)| fork_slot <= slot)
+            .max_by_key(|(&fork, &fork_slot)| (fork_slot, fork))
+            .map(|(&fork, _)| fork)
+    }
+
+    /// Maximum amount of blobs a block at the slot may carry.
+    pub fn max_blobs_per_block(&self, slot: u32) -> Option<u32> {
+        self.blob_limits
+            .range(..=slot)
+            .next_back()
+            .map(|(_, &max)| max)
+    }
+
+    /// Update fraction of the blob base fee at the slot. Forks scale it along
+    /// with the blob limit so the fee moves by the same ~12.5% per block at
+    /// most, which gives the fractions of every fork and BPO fork scheduled so
+    /// far from Electra's `5007716` for 9 blobs.
+    pub fn blob_base_fee_update_fraction(&self, slot: u32) -> Option<u64> {
+        self.max_blobs_per_block(slot)
+            .map(|max| (max as u64 * ELECTRA_BLOB_BASE_FEE_UPDATE_FRACTION * 2 + 9) / 18)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fork_slots.is_empty()
+    }
+
+    /// Schedules the fork, along with its blob limit unless it keeps the one
+    /// of the previous fork.
+    fn with_fork(mut self, fork: Fork, slot: u32, max_blobs_per_block: Option<u32>) -> Self {
+        self.fork_slots.insert(fork, slot);
+
+        let max_blobs_per_block = match (fork, max_blobs_per_block) {
+            (_, Some(max)) => Some(max),
+            (Fork::Fulu, None) => None,
+            (fork, None) => Some(fork.default_max_blobs_per_block()),
+        };
+
+        match max_blobs_per_block {
+            Some(max) => self.with_blob_limit(slot, max),
+            None => self,
+        }
+    }
+
+    fn with_blob_limit(mut self, slot: u32, max_blobs_per_block: u32) -> Self {
+        self.blob_limits.insert(slot, max_blobs_per_block);
+
+        self
+    }
+}
+
+impl FromStr for Fork {
+    type Err = UnknownForkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "deneb" => Ok(Fork::Deneb),
+            "electra" => Ok(Fork::Electra),
+            "fulu" => Ok(Fork::Fulu),
+            _ => Err(UnknownForkError(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Fork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Fork::Deneb => "deneb",
+            Fork::Electra => "electra",
+            Fork::Fulu => "fulu",
+        };
+
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Network {
+    type Err = UnknownNetworkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mainnet" => Ok(Network::Mainnet),
+            "goerli" => Ok(Network::Goerli),
+            "sepolia" => Ok(Network::Sepolia),
+            "holesky" => Ok(Network::Holesky),
+            "devnet" => Ok(Network::Devnet),
+            "gnosis" => Ok(Network::Gnosis),
+            "chiado" => Ok(Network::Chiado),
+            _ => Err(UnknownNetworkError(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Network::Mainnet => "mainnet",
+            Network::Goerli => "goerli",
+            Network::Sepolia => "sepolia",
+            Network::Holesky => "holesky",
+            Network::Devnet => "devnet",
+            Network::Gnosis => "gnosis",
+            Network::Chiado => "chiado",
+            Network::Custom(network) => &network.name,
+        };
+
+        f.write_str(name)
+    }
+}
+
+impl Serialize for Network {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Network, SlotClock};
+
+    #[test]
+    fn slots_convert_to_and_from_timestamps() {
+        let clock = Network::Mainnet.slot_clock().unwrap();
+
+        assert_eq!(clock.slot_timestamp(0), 1606824023);
+        assert_eq!(
+            clock.slot_at(clock.slot_timestamp(8626176) + 11),
+            Some(8626176)
+        );
+        assert_eq!(clock.slot_at(clock.genesis_time() - 1), None);
+        assert_eq!(SlotClock::new(100, 5).slot_timestamp(3), 115);
     }
 }
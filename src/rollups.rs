@@ -0,0 +1,95 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+use alloy::primitives::Address;
+use serde::Deserialize;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RollupMappingError {
+    #[error("failed to read rollup mapping file")]
+    Io(#[from] io::Error),
+    #[error("failed to parse rollup mapping file")]
+    Serde(#[from] serde_json::Error),
+    #[error("address {address} is mapped to both {first} and {second}")]
+    DuplicateAddress {
+        address: Address,
+        first: String,
+        second: String,
+    },
+}
+
+/// Addresses a rollup sends its blob transactions from or to.
+#[derive(Deserialize, Debug, Default)]
+struct RollupAddresses {
+    #[serde(default)]
+    senders: Vec<Address>,
+    #[serde(default)]
+    recipients: Vec<Address>,
+}
+
+/// Maps blob transaction addresses to the rollups they belong to.
+///
+/// The mapping file is a JSON object keyed by rollup label:
+///
+/// ```json
+/// {
+///   "base": { "senders": ["0x5050f69a9786f081509234f1a7f4684b5e5b76c9"] },
+///   "optimism": { "recipients": ["0xff00000000000000000000000000000000000010"] }
+/// }
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct RollupRegistry {
+    senders: HashMap<Address, String>,
+    recipients: HashMap<Address, String>,
+}
+
+impl RollupRegistry {
+    pub fn load(path: &Path) -> Result<Self, RollupMappingError> {
+        let rollups: HashMap<String, RollupAddresses> =
+            serde_json::from_str(&fs::read_to_string(path)?)?;
+        let mut registry = Self::default();
+
+        for (rollup, addresses) in rollups {
+            for sender in addresses.senders {
+                insert_unique(&mut registry.senders, sender, &rollup)?;
+            }
+
+            for recipient in addresses.recipients {
+                insert_unique(&mut registry.recipients, recipient, &rollup)?;
+            }
+        }
+
+        Ok(registry)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.senders.is_empty() && self.recipients.is_empty()
+    }
+
+    /// Returns the rollup a transaction belongs to. Senders take precedence as
+    /// they identify the batcher submitting the blobs.
+    pub fn rollup(&self, from: &Address, to: Option<&Address>) -> Option<&str> {
+        self.senders
+            .get(from)
+            .or_else(|| to.and_then(|to| self.recipients.get(to)))
+            .map(String::as_str)
+    }
+}
+
+fn insert_unique(
+    addresses: &mut HashMap<Address, String>,
+    address: Address,
+    rollup: &str,
+) -> Result<(), RollupMappingError> {
+    match addresses.get(&address) {
+        Some(existing) if existing != rollup => Err(RollupMappingError::DuplicateAddress {
+            address,
+            first: existing.clone(),
+            second: rollup.to_string(),
+        }),
+        _ => {
+            addresses.insert(address, rollup.to_string());
+
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,367 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File},
+    mem,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use alloy::primitives::U256;
+use anyhow::{anyhow, Context as AnyhowContext};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate};
+use parquet::{
+    basic::Compression,
+    data_type::{ByteArray, ByteArrayType, Int32Type, Int64Type},
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::parser::parse_message_type,
+};
+
+use crate::{
+    clients::{
+        blobscan::types::{Blob, Block, Transaction},
+        common::ClientResult,
+    },
+    utils::web3::GAS_PER_BLOB,
+};
+
+use super::CommonSink;
+
+/// Fees are kept as decimal strings, as they're 256-bit integers.
+const SCHEMA: &str = "
+    message blob {
+        required int64 slot;
+        required int64 block_number;
+        required int64 block_timestamp;
+        required binary tx_hash (STRING);
+        required binary versioned_hash (STRING);
+        required int32 blob_index;
+        optional binary rollup (STRING);
+        optional binary category (STRING);
+        required int64 size;
+        required int64 usage_size;
+        required binary blob_gas_price (STRING);
+        required binary blob_fee (STRING);
+    }
+";
+
+/// Accumulates the metadata of every blob indexed by the inner sink into
+/// Parquet files partitioned by day, meant for blob market analysis outside of
+/// the Blobscan database. Rows are buffered until a block of another day is
+/// indexed or the sink is flushed, each buffered day being written to a file
+/// named after its slot range, e.g.
+/// `date=2024-03-13/blobs-8624700-8631899.parquet`. Blocks reindexed once
+/// their day was written end up in another file of the day.
+#[derive(Debug)]
+pub struct AnalyticsSink {
+    dir: PathBuf,
+    inner: Box<dyn CommonSink>,
+    /// Rows not written yet, by the day of their block
+    buffered_days: Mutex<BTreeMap<NaiveDate, Vec<BlobRow>>>,
+}
+
+/// Metadata of an indexed blob, stored as a row of the Parquet files.
+#[derive(Debug)]
+struct BlobRow {
+    slot: i64,
+    block_number: i64,
+    block_timestamp: i64,
+    tx_hash: String,
+    versioned_hash: String,
+    blob_index: i32,
+    rollup: Option<String>,
+    category: Option<String>,
+    size: i64,
+    usage_size: i64,
+    blob_gas_price: String,
+    blob_fee: String,
+}
+
+/// Values of a column of the schema, in row order.
+enum ColumnValues {
+    Int32(Vec<i32>),
+    Int64(Vec<i64>),
+    Strings(Vec<String>),
+    OptionalStrings(Vec<Option<String>>),
+}
+
+impl AnalyticsSink {
+    pub fn try_new(dir: PathBuf, inner: Box<dyn CommonSink>) -> anyhow::Result<Self> {
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create analytics directory {}", dir.display()))?;
+
+        Ok(Self {
+            dir,
+            inner,
+            buffered_days: Mutex::new(BTreeMap::new()),
+        })
+    }
+
+    async fn write_days(&self, days: BTreeMap<NaiveDate, Vec<BlobRow>>) -> ClientResult<()> {
+        for (date, rows) in days {
+            let Some(path) = partition_path(&self.dir, date, &rows) else {
+                continue;
+            };
+
+            // A day of rows takes long enough to encode to be done off the
+            // runtime
+            tokio::task::spawn_blocking(move || {
+                write_rows(&path, &rows)
+                    .with_context(|| format!("Failed to write blob metadata to {}", path.display()))
+            })
+            .await
+            .map_err(|error| anyhow!("Analytics task failed: {error}"))??;
+        }
+
+        Ok(())
+    }
+
+    fn lock_buffered_days(&self) -> MutexGuard<'_, BTreeMap<NaiveDate, Vec<BlobRow>>> {
+        match self.buffered_days.lock() {
+            Ok(buffered_days) => buffered_days,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+#[async_trait]
+impl CommonSink for AnalyticsSink {
+    async fn index(
+        &self,
+        block: Block,
+        transactions: Vec<Transaction>,
+        blobs: Vec<Blob>,
+    ) -> ClientResult<()> {
+        let date = DateTime::from_timestamp(block.timestamp as i64, 0)
+            .with_context(|| format!("Invalid timestamp for block {}", block.number))?
+            .date_naive();
+        let rows = blob_rows(&block, &transactions, &blobs);
+
+        self.inner.index(block, transactions, blobs).await?;
+
+        // Every other day is done with once the indexer moves on to this one
+        let finished_days = {
+            let mut buffered_days = self.lock_buffered_days();
+            let mut finished_days = mem::take(&mut *buffered_days);
+            let mut day_rows = finished_days.remove(&date).unwrap_or_default();
+
+            day_rows.extend(rows);
+            buffered_days.insert(date, day_rows);
+
+            finished_days
+        };
+
+        self.write_days(finished_days).await
+    }
+
+    async fn flush(&self) -> ClientResult<()> {
+        let buffered_days = mem::take(&mut *self.lock_buffered_days());
+
+        self.write_days(buffered_days).await?;
+        self.inner.flush().await
+    }
+}
+
+/// Returns the path of the file the rows of a day are written to, named after
+/// their slot range so the files of a day never replace each other.
+fn partition_path(dir: &Path, date: NaiveDate, rows: &[BlobRow]) -> Option<PathBuf> {
+    let first_slot = rows.iter().map(|row| row.slot).min()?;
+    let last_slot = rows.iter().map(|row| row.slot).max()?;
+
+    Some(
+        dir.join(format!("date={date}"))
+            .join(format!("blobs-{first_slot}-{last_slot}.parquet")),
+    )
+}
+
+fn blob_rows(block: &Block, transactions: &[Transaction], blobs: &[Blob]) -> Vec<BlobRow> {
+    let transactions = transactions
+        .iter()
+        .map(|tx| (tx.hash, tx))
+        .collect::<HashMap<_, _>>();
+    let blob_fee = block.blob_gas_price * U256::from(GAS_PER_BLOB);
+
+    // Blobs shared by several transactions get a row for every one of them, as
+    // each pays for it
+    blobs
+        .iter()
+        .flat_map(|blob| {
+            let references = blob
+                .references
+                .iter()
+                .map(|reference| (reference.tx_hash, reference.index));
+
+            [(blob.tx_hash, blob.index)]
+                .into_iter()
+                .chain(references)
+                .map(move |(tx_hash, index)| (blob, tx_hash, index))
+        })
+        .map(|(blob, tx_hash, index)| {
+            let tx = transactions.get(&tx_hash);
+
+            BlobRow {
+                slot: block.slot as i64,
+                block_number: block.number as i64,
+                block_timestamp: block.timestamp as i64,
+                tx_hash: tx_hash.to_string(),
+                versioned_hash: blob.versioned_hash.to_string(),
+                blob_index: index as i32,
+                rollup: tx.and_then(|tx| tx.rollup.clone()),
+                category: tx.and_then(|tx| tx.category.clone()),
+                size: blob.size as i64,
+                usage_size: blob.usage_size as i64,
+                blob_gas_price: block.blob_gas_price.to_string(),
+                blob_fee: blob_fee.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Returns the values of every column, in the order of the schema.
+fn columns(rows: &[BlobRow]) -> Vec<ColumnValues> {
+    let int64 = |value: fn(&BlobRow) -> i64| ColumnValues::Int64(rows.iter().map(value).collect());
+    let strings = |value: fn(&BlobRow) -> &String| {
+        ColumnValues::Strings(rows.iter().map(|row| value(row).clone()).collect())
+    };
+    let optional_strings = |value: fn(&BlobRow) -> &Option<String>| {
+        ColumnValues::OptionalStrings(rows.iter().map(|row| value(row).clone()).collect())
+    };
+
+    vec![
+        int64(|row| row.slot),
+        int64(|row| row.block_number),
+        int64(|row| row.block_timestamp),
+        strings(|row| &row.tx_hash),
+        strings(|row| &row.versioned_hash),
+        ColumnValues::Int32(rows.iter().map(|row| row.blob_index).collect()),
+        optional_strings(|row| &row.rollup),
+        optional_strings(|row| &row.category),
+        int64(|row| row.size),
+        int64(|row| row.usage_size),
+        strings(|row| &row.blob_gas_price),
+        strings(|row| &row.blob_fee),
+    ]
+}
+
+/// Writes the rows to a temporary file first, so readers never see a partial
+/// one.
+fn write_rows(path: &Path, rows: &[BlobRow]) -> anyhow::Result<()> {
+    if let Some(partition_dir) = path.parent() {
+        fs::create_dir_all(partition_dir)?;
+    }
+
+    let tmp_path = path.with_extension("parquet.tmp");
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut writer = SerializedFileWriter::new(
+        File::create(&tmp_path)?,
+        Arc::new(parse_message_type(SCHEMA)?),
+        Arc::new(properties),
+    )?;
+    let mut row_group = writer.next_row_group()?;
+
+    for values in columns(rows) {
+        let Some(mut column) = row_group.next_column()? else {
+            break;
+        };
+
+        match values {
+            ColumnValues::Int32(values) => {
+                column
+                    .typed::<Int32Type>()
+                    .write_batch(&values, None, None)?;
+            }
+            ColumnValues::Int64(values) => {
+                column
+                    .typed::<Int64Type>()
+                    .write_batch(&values, None, None)?;
+            }
+            ColumnValues::Strings(values) => {
+                let values = values
+                    .into_iter()
+                    .map(|value| ByteArray::from(value.into_bytes()))
+                    .collect::<Vec<_>>();
+
+                column
+                    .typed::<ByteArrayType>()
+                    .write_batch(&values, None, None)?;
+            }
+            ColumnValues::OptionalStrings(values) => {
+                let definition_levels = values
+                    .iter()
+                    .map(|value| value.is_some() as i16)
+                    .collect::<Vec<_>>();
+                let values = values
+                    .into_iter()
+                    .flatten()
+                    .map(|value| ByteArray::from(value.into_bytes()))
+                    .collect::<Vec<_>>();
+
+                column.typed::<ByteArrayType>().write_batch(
+                    &values,
+                    Some(&definition_levels),
+                    None,
+                )?;
+            }
+        }
+
+        column.close()?;
+    }
+
+    row_group.close()?;
+    writer.close()?;
+    fs::rename(&tmp_path, path)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    use super::{write_rows, BlobRow};
+
+    fn row(blob_index: i32, rollup: Option<&str>) -> BlobRow {
+        BlobRow {
+            slot: 8_626_178,
+            block_number: 19_426_587,
+            block_timestamp: 1_710_338_135,
+            tx_hash: "0x01".to_string(),
+            versioned_hash: format!("0x0{blob_index}"),
+            blob_index,
+            rollup: rollup.map(str::to_string),
+            category: None,
+            size: 131_072,
+            usage_size: 1_000,
+            blob_gas_price: "1".to_string(),
+            blob_fee: "131072".to_string(),
+        }
+    }
+
+    #[test]
+    fn rows_are_written_to_parquet() {
+        let dir = std::env::temp_dir().join(format!("analytics-{}", std::process::id()));
+        let path = dir
+            .join("date=2024-03-13")
+            .join("blobs-8626178-8626178.parquet");
+
+        write_rows(&path, &[row(0, Some("base")), row(1, None)]).unwrap();
+
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        let rows = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| row.unwrap().to_string())
+            .collect::<Vec<_>>();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+        assert!(rows[0].contains("slot: 8626178"), "{}", rows[0]);
+        assert!(rows[0].contains("rollup: \"base\""), "{}", rows[0]);
+        assert!(rows[1].contains("rollup: null"), "{}", rows[1]);
+        assert!(rows[1].contains("blob_fee: \"131072\""), "{}", rows[1]);
+    }
+}
@@ -0,0 +1,34 @@
+use async_trait::async_trait;
+
+use crate::clients::{
+    blobscan::{
+        types::{Blob, Block, Transaction},
+        BlobscanClient, CommonBlobscanClient,
+    },
+    common::ClientResult,
+};
+
+use super::CommonSink;
+
+#[derive(Debug, Clone)]
+pub struct BlobscanSink {
+    client: BlobscanClient,
+}
+
+impl BlobscanSink {
+    pub fn new(client: BlobscanClient) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl CommonSink for BlobscanSink {
+    async fn index(
+        &self,
+        block: Block,
+        transactions: Vec<Transaction>,
+        blobs: Vec<Blob>,
+    ) -> ClientResult<()> {
+        self.client.index(block, transactions, blobs).await
+    }
+}
@@ -0,0 +1,50 @@
+use anyhow::anyhow;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::clients::{
+    blobscan::types::{Blob, Block, Transaction},
+    common::ClientResult,
+};
+
+use super::CommonSink;
+
+/// A block together with the blob transactions and blobs it contains.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct IndexedBlock {
+    pub block: Block,
+    pub transactions: Vec<Transaction>,
+    pub blobs: Vec<Blob>,
+}
+
+/// Forwards indexed entities to a channel so they can be consumed in-process.
+#[derive(Debug, Clone)]
+pub struct ChannelSink {
+    sender: mpsc::Sender<IndexedBlock>,
+}
+
+impl ChannelSink {
+    pub fn new(sender: mpsc::Sender<IndexedBlock>) -> Self {
+        Self { sender }
+    }
+}
+
+#[async_trait]
+impl CommonSink for ChannelSink {
+    async fn index(
+        &self,
+        block: Block,
+        transactions: Vec<Transaction>,
+        blobs: Vec<Blob>,
+    ) -> ClientResult<()> {
+        self.sender
+            .send(IndexedBlock {
+                block,
+                transactions,
+                blobs,
+            })
+            .await
+            .map_err(|_| anyhow!("Indexed blocks receiver dropped").into())
+    }
+}
@@ -0,0 +1,98 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context as AnyhowContext};
+use async_trait::async_trait;
+
+use crate::clients::{
+    blobscan::types::{Blob, Block, Transaction},
+    common::ClientResult,
+};
+
+use super::{channel::IndexedBlock, CommonSink};
+
+/// Writes every indexed block, along with its transactions and blobs, to a JSON
+/// file per slot in the export directory, before handing it over to the inner
+/// sink if there's one.
+#[derive(Debug)]
+pub struct ExportSink {
+    dir: PathBuf,
+    inner: Option<Box<dyn CommonSink>>,
+}
+
+impl ExportSink {
+    pub fn try_new(dir: PathBuf, inner: Option<Box<dyn CommonSink>>) -> anyhow::Result<Self> {
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create export directory {}", dir.display()))?;
+
+        Ok(Self { dir, inner })
+    }
+
+    /// Path of the file the block of the given slot is exported to.
+    pub fn slot_path(dir: &Path, slot: u32) -> PathBuf {
+        dir.join(format!("{slot}.json"))
+    }
+}
+
+#[async_trait]
+impl CommonSink for ExportSink {
+    async fn index(
+        &self,
+        block: Block,
+        transactions: Vec<Transaction>,
+        blobs: Vec<Blob>,
+    ) -> ClientResult<()> {
+        let path = Self::slot_path(&self.dir, block.slot);
+        let indexed_block = IndexedBlock {
+            block,
+            transactions,
+            blobs,
+        };
+
+        // Blob contents make the files large enough to be written off the
+        // runtime
+        let indexed_block = tokio::task::spawn_blocking(move || {
+            write_atomically(&path, &indexed_block)
+                .with_context(|| format!("Failed to export block to {}", path.display()))
+                .map(|_| indexed_block)
+        })
+        .await
+        .map_err(|error| anyhow!("Export task failed: {error}"))??;
+
+        match &self.inner {
+            Some(inner) => {
+                let IndexedBlock {
+                    block,
+                    transactions,
+                    blobs,
+                } = indexed_block;
+
+                inner.index(block, transactions, blobs).await
+            }
+            None => Ok(()),
+        }
+    }
+
+    async fn flush(&self) -> ClientResult<()> {
+        match &self.inner {
+            Some(inner) => inner.flush().await,
+            None => Ok(()),
+        }
+    }
+}
+
+/// Writes the file next to its final path first, so readers never come across
+/// a partially written one.
+fn write_atomically(path: &Path, indexed_block: &IndexedBlock) -> anyhow::Result<()> {
+    let tmp_path = path.with_extension("json.tmp");
+    let mut writer = BufWriter::new(File::create(&tmp_path)?);
+
+    serde_json::to_writer(&mut writer, indexed_block)?;
+    writer.flush()?;
+    fs::rename(&tmp_path, path)?;
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+use clap::ValueEnum;
+
+#[cfg(test)]
+use mockall::automock;
+
+use crate::clients::{
+    blobscan::types::{Blob, Block, Transaction},
+    common::ClientResult,
+};
+
+pub mod analytics;
+pub mod blobscan;
+pub mod channel;
+pub mod export;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+
+/// Destination where indexed entities are written to.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum SinkType {
+    /// Send entities to the Blobscan API
+    #[default]
+    Blobscan,
+    /// Write entities directly to the Blobscan Postgres database
+    Postgres,
+}
+
+#[async_trait]
+#[cfg_attr(test, automock)]
+pub trait CommonSink: Send + Sync + Debug {
+    async fn index(
+        &self,
+        block: Block,
+        transactions: Vec<Transaction>,
+        blobs: Vec<Blob>,
+    ) -> ClientResult<()>;
+
+    /// Writes out the entities held back by the sink. Called once the indexer
+    /// stops.
+    async fn flush(&self) -> ClientResult<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,629 @@
+use std::{
+    collections::HashSet,
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use deadpool_postgres::{Manager, ManagerConfig, Pool, RecyclingMethod};
+use futures::future::BoxFuture;
+use rustls::ServerName;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_postgres::{
+    config::SslMode,
+    tls::{ChannelBinding, MakeTlsConnect, TlsConnect},
+    types::ToSql,
+    NoTls, Socket,
+};
+use tokio_rustls::{client::TlsStream, TlsConnector};
+use tracing::debug;
+
+use crate::clients::{
+    blobscan::types::{Blob, Block, Transaction},
+    common::ClientResult,
+    tls::TlsSettings,
+};
+
+use super::CommonSink;
+
+type SqlParam = Box<dyn ToSql + Sync + Send>;
+
+const INSERT_BLOCK_QUERY: &str = r#"
+    INSERT INTO "block" (hash, number, timestamp, slot, blob_gas_used, excess_blob_gas, blob_gas_price, transactions_count, blob_transactions_ratio, fork, proposer_index, signed_block_header, inserted_at, updated_at)
+    VALUES ($1, $2::int8, to_timestamp($3), $4::int8, $5::text::numeric, $6::text::numeric, $7::text::numeric, $8::int8, $9::float8, $10, $11::int8, $12::text::jsonb, NOW(), NOW())
+    ON CONFLICT (hash) DO UPDATE SET
+        number = EXCLUDED.number,
+        timestamp = EXCLUDED.timestamp,
+        slot = EXCLUDED.slot,
+        blob_gas_used = EXCLUDED.blob_gas_used,
+        excess_blob_gas = EXCLUDED.excess_blob_gas,
+        blob_gas_price = EXCLUDED.blob_gas_price,
+        transactions_count = EXCLUDED.transactions_count,
+        blob_transactions_ratio = EXCLUDED.blob_transactions_ratio,
+        fork = EXCLUDED.fork,
+        proposer_index = EXCLUDED.proposer_index,
+        signed_block_header = EXCLUDED.signed_block_header,
+        updated_at = NOW()
+"#;
+
+pub struct Config {
+    pub database_url: String,
+    pub max_connections: usize,
+    /// Trust anchors and client certificate of the connections, used when the
+    /// database URL sets `sslmode=require`
+    pub tls: TlsSettings,
+}
+
+/// Writes indexed entities straight into the Blobscan database, bypassing the
+/// API. Every slot is written within a single database transaction.
+#[derive(Debug, Clone)]
+pub struct PostgresSink {
+    pool: Pool,
+}
+
+/// Parameterized query writing some of the entities of a slot.
+struct Statement {
+    query: String,
+    params: Vec<SqlParam>,
+}
+
+/// Connects to the database over TLS with the same settings as the
+/// connections to the nodes and the Blobscan API.
+#[derive(Clone)]
+struct RustlsConnect(TlsConnector);
+
+struct RustlsConnectFuture {
+    connector: TlsConnector,
+    server_name: ServerName,
+}
+
+struct RustlsStream(TlsStream<Socket>);
+
+impl PostgresSink {
+    pub fn try_new(config: Config) -> ClientResult<Self> {
+        let pg_config = config.database_url.parse::<tokio_postgres::Config>()?;
+        let manager_config = ManagerConfig {
+            recycling_method: RecyclingMethod::Fast,
+        };
+        // Other modes would silently fall back to plain connections
+        let manager = if pg_config.get_ssl_mode() == SslMode::Require {
+            let connector = TlsConnector::from(Arc::new(config.tls.client_config()));
+
+            Manager::from_config(pg_config, RustlsConnect(connector), manager_config)
+        } else {
+            Manager::from_config(pg_config, NoTls, manager_config)
+        };
+        let pool = Pool::builder(manager)
+            .max_size(config.max_connections)
+            .build()
+            .map_err(|err| anyhow!("Failed to create postgres connection pool: {err}"))?;
+
+        Ok(Self { pool })
+    }
+}
+
+#[async_trait]
+impl CommonSink for PostgresSink {
+    async fn index(
+        &self,
+        block: Block,
+        transactions: Vec<Transaction>,
+        blobs: Vec<Blob>,
+    ) -> ClientResult<()> {
+        let mut client = self
+            .pool
+            .get()
+            .await
+            .map_err(|err| anyhow!("Failed to get postgres connection: {err}"))?;
+        let db_tx = client.transaction().await?;
+
+        for statement in statements(&block, &transactions, &blobs) {
+            db_tx
+                .execute(&statement.query, &as_sql_params(&statement.params))
+                .await?;
+        }
+
+        db_tx.commit().await?;
+
+        debug!(
+            block_number = block.number,
+            slot = block.slot,
+            "Block entities written to postgres"
+        );
+
+        Ok(())
+    }
+}
+
+/// Returns the statements writing the entities of a slot, in the order the
+/// rows they reference are written.
+fn statements(block: &Block, transactions: &[Transaction], blobs: &[Blob]) -> Vec<Statement> {
+    let mut statements = vec![block_statement(block)];
+
+    statements.extend(addresses_statement(transactions));
+    statements.extend(transactions_statement(block, transactions));
+
+    if !blobs.is_empty() {
+        statements.push(blobs_statement(block, blobs));
+        statements.extend(blob_data_statement(blobs));
+        statements.push(blobs_on_transactions_statement(block, blobs));
+    }
+
+    statements
+}
+
+fn block_statement(block: &Block) -> Statement {
+    let signed_block_header = block
+        .signed_block_header
+        .as_ref()
+        .map(|header| serde_json::to_string(header).expect("headers are serializable"));
+
+    Statement {
+        query: INSERT_BLOCK_QUERY.to_string(),
+        params: vec![
+            Box::new(block.hash.to_string()),
+            Box::new(block.number as i64),
+            Box::new(block.timestamp as f64),
+            Box::new(block.slot as i64),
+            Box::new(block.blob_gas_used.to_string()),
+            Box::new(block.excess_blob_gas.to_string()),
+            Box::new(block.blob_gas_price.to_string()),
+            Box::new(block.transactions_count as i64),
+            Box::new(block.blob_transactions_ratio),
+            Box::new(block.fork.map(|fork| fork.to_string())),
+            Box::new(block.proposer_index.map(i64::from)),
+            Box::new(signed_block_header),
+        ],
+    }
+}
+
+fn addresses_statement(transactions: &[Transaction]) -> Option<Statement> {
+    let addresses = transactions
+        .iter()
+        .flat_map(|tx| [Some(tx.from), tx.to])
+        .flatten()
+        .map(|address| address.to_string())
+        .collect::<HashSet<_>>();
+
+    if addresses.is_empty() {
+        return None;
+    }
+
+    let params = addresses
+        .into_iter()
+        .map(|address| Box::new(address) as SqlParam)
+        .collect::<Vec<_>>();
+    let query = format!(
+        r#"INSERT INTO "address" (address, inserted_at, updated_at) VALUES {} ON CONFLICT (address) DO NOTHING"#,
+        build_values_placeholders(params.len(), &["$", "NOW()", "NOW()"])
+    );
+
+    Some(Statement { query, params })
+}
+
+fn transactions_statement(block: &Block, transactions: &[Transaction]) -> Option<Statement> {
+    if transactions.is_empty() {
+        return None;
+    }
+
+    let params = transactions
+        .iter()
+        .flat_map(|tx| {
+            [
+                Box::new(tx.hash.to_string()) as SqlParam,
+                Box::new(block.hash.to_string()),
+                Box::new(tx.block_number as i64),
+                Box::new(block.timestamp as f64),
+                Box::new(tx.from.to_string()),
+                Box::new(tx.to.map(|to| to.to_string())),
+                Box::new(tx.index as i64),
+                Box::new(tx.gas_price.to_string()),
+                Box::new(tx.max_fee_per_blob_gas.to_string()),
+                Box::new(tx.rollup.clone()),
+                Box::new(tx.category.clone()),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let query = format!(
+        r#"INSERT INTO "transaction" (hash, block_hash, block_number, block_timestamp, from_id, to_id, index, gas_price, max_fee_per_blob_gas, rollup, category, inserted_at, updated_at)
+        VALUES {}
+        ON CONFLICT (hash) DO UPDATE SET
+            block_hash = EXCLUDED.block_hash,
+            block_number = EXCLUDED.block_number,
+            block_timestamp = EXCLUDED.block_timestamp,
+            index = EXCLUDED.index,
+            rollup = EXCLUDED.rollup,
+            category = EXCLUDED.category,
+            updated_at = NOW()"#,
+        build_values_placeholders(
+            transactions.len(),
+            &[
+                "$",
+                "$",
+                "$::int8",
+                "to_timestamp($)",
+                "$",
+                "$",
+                "$::int8",
+                "$::text::numeric",
+                "$::text::numeric",
+                "$",
+                "$",
+                "NOW()",
+                "NOW()"
+            ]
+        )
+    );
+
+    Some(Statement { query, params })
+}
+
+fn blobs_statement(block: &Block, blobs: &[Blob]) -> Statement {
+    let params = blobs
+        .iter()
+        .flat_map(|blob| {
+            [
+                Box::new(blob.versioned_hash.to_string()) as SqlParam,
+                Box::new(blob.commitment.clone()),
+                Box::new(blob.proof.clone()),
+                Box::new(blob.size as i64),
+                Box::new(blob.usage_size as i64),
+                Box::new(blob.zero_bytes_ratio),
+                Box::new(
+                    blob.inclusion_proof
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>(),
+                ),
+                Box::new(block.number as i64),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let query = format!(
+        r#"INSERT INTO "blob" (versioned_hash, commitment, proof, size, usage_size, zero_bytes_ratio, inclusion_proof, first_block_number, inserted_at, updated_at)
+        VALUES {}
+        ON CONFLICT (versioned_hash) DO NOTHING"#,
+        build_values_placeholders(
+            blobs.len(),
+            &[
+                "$",
+                "$",
+                "$",
+                "$::int8",
+                "$::int8",
+                "$::float8",
+                "$::text[]",
+                "$::int8",
+                "NOW()",
+                "NOW()"
+            ]
+        )
+    );
+
+    Statement { query, params }
+}
+
+fn blob_data_statement(blobs: &[Blob]) -> Option<Statement> {
+    let params = blobs
+        .iter()
+        .filter_map(|blob| {
+            blob.data.as_ref().map(|data| {
+                [
+                    Box::new(blob.versioned_hash.to_string()) as SqlParam,
+                    Box::new(data.to_vec()),
+                ]
+            })
+        })
+        .flatten()
+        .collect::<Vec<_>>();
+
+    if params.is_empty() {
+        return None;
+    }
+
+    let query = format!(
+        r#"INSERT INTO "blob_data" (id, data) VALUES {} ON CONFLICT (id) DO NOTHING"#,
+        build_values_placeholders(params.len() / 2, &["$", "$"])
+    );
+
+    Some(Statement { query, params })
+}
+
+fn blobs_on_transactions_statement(block: &Block, blobs: &[Blob]) -> Statement {
+    // Blobs referenced by several transactions are linked to every one of them
+    let blob_references = blobs
+        .iter()
+        .flat_map(|blob| {
+            std::iter::once((blob.tx_hash, blob.index))
+                .chain(
+                    blob.references
+                        .iter()
+                        .map(|reference| (reference.tx_hash, reference.index)),
+                )
+                .map(move |(tx_hash, index)| (blob, tx_hash, index))
+        })
+        .collect::<Vec<_>>();
+    let params = blob_references
+        .iter()
+        .flat_map(|(blob, tx_hash, index)| {
+            [
+                Box::new(blob.versioned_hash.to_string()) as SqlParam,
+                Box::new(tx_hash.to_string()),
+                Box::new(block.hash.to_string()),
+                Box::new(block.number as i64),
+                Box::new(block.timestamp as f64),
+                Box::new(*index as i64),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let query = format!(
+        r#"INSERT INTO "blobs_on_transactions" (blob_hash, tx_hash, block_hash, block_number, block_timestamp, index)
+        VALUES {}
+        ON CONFLICT (tx_hash, index) DO UPDATE SET
+            blob_hash = EXCLUDED.blob_hash,
+            block_hash = EXCLUDED.block_hash,
+            block_number = EXCLUDED.block_number,
+            block_timestamp = EXCLUDED.block_timestamp"#,
+        build_values_placeholders(
+            blob_references.len(),
+            &["$", "$", "$", "$::int8", "to_timestamp($)", "$::int8"]
+        )
+    );
+
+    Statement { query, params }
+}
+
+impl MakeTlsConnect<Socket> for RustlsConnect {
+    type Stream = RustlsStream;
+    type TlsConnect = RustlsConnectFuture;
+    type Error = io::Error;
+
+    fn make_tls_connect(&mut self, domain: &str) -> Result<Self::TlsConnect, Self::Error> {
+        let server_name = ServerName::try_from(domain)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+        Ok(RustlsConnectFuture {
+            connector: self.0.clone(),
+            server_name,
+        })
+    }
+}
+
+impl TlsConnect<Socket> for RustlsConnectFuture {
+    type Stream = RustlsStream;
+    type Error = io::Error;
+    type Future = BoxFuture<'static, io::Result<RustlsStream>>;
+
+    fn connect(self, stream: Socket) -> Self::Future {
+        Box::pin(async move {
+            let stream = self.connector.connect(self.server_name, stream).await?;
+
+            Ok(RustlsStream(stream))
+        })
+    }
+}
+
+impl tokio_postgres::tls::TlsStream for RustlsStream {
+    fn channel_binding(&self) -> ChannelBinding {
+        ChannelBinding::none()
+    }
+}
+
+impl AsyncRead for RustlsStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for RustlsStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
+
+/// Builds the `VALUES` list of a multi-row insert. Each column template must
+/// contain at most one `$` which gets replaced by the next parameter index.
+fn build_values_placeholders(rows: usize, column_templates: &[&str]) -> String {
+    let mut param_index = 0;
+
+    (0..rows)
+        .map(|_| {
+            let columns = column_templates
+                .iter()
+                .map(|template| {
+                    if template.contains('$') {
+                        param_index += 1;
+
+                        template.replacen('$', &format!("${param_index}"), 1)
+                    } else {
+                        template.to_string()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            format!("({columns})")
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn as_sql_params(params: &[SqlParam]) -> Vec<&(dyn ToSql + Sync)> {
+    params
+        .iter()
+        .map(|param| param.as_ref() as &(dyn ToSql + Sync))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{Address, Bytes, B256, U256};
+
+    use crate::{
+        clients::{
+            beacon::types::{BeaconBlockHeader, SignedBeaconBlockHeader},
+            blobscan::types::{Blob, Block, Transaction},
+        },
+        network::Fork,
+    };
+
+    use super::{statements, Statement};
+
+    fn block() -> Block {
+        Block {
+            number: 100,
+            hash: B256::repeat_byte(1),
+            timestamp: 1_700_000_000,
+            slot: 200,
+            blob_gas_used: U256::from(131_072),
+            excess_blob_gas: U256::from(0),
+            blob_gas_price: U256::from(1),
+            transactions_count: 4,
+            blob_transactions_ratio: 0.25,
+            fork: Some(Fork::Electra),
+            proposer_index: Some(7),
+            signed_block_header: Some(SignedBeaconBlockHeader {
+                message: BeaconBlockHeader {
+                    slot: 200,
+                    proposer_index: 7,
+                    parent_root: B256::ZERO,
+                    state_root: B256::ZERO,
+                    body_root: B256::ZERO,
+                },
+                signature: "0x00".to_string(),
+            }),
+        }
+    }
+
+    fn transaction() -> Transaction {
+        Transaction {
+            hash: B256::repeat_byte(2),
+            from: Address::repeat_byte(3),
+            to: Some(Address::repeat_byte(4)),
+            block_number: 100,
+            index: 0,
+            gas_price: U256::from(10),
+            max_fee_per_blob_gas: U256::from(20),
+            rollup: Some("base".to_string()),
+            category: Some("batch".to_string()),
+        }
+    }
+
+    fn blob() -> Blob {
+        Blob {
+            versioned_hash: B256::repeat_byte(5),
+            commitment: "0xc0".to_string(),
+            proof: "0xf0".to_string(),
+            inclusion_proof: vec![B256::repeat_byte(6)],
+            data: Some(Bytes::from_static(&[1, 0])),
+            size: 2,
+            usage_size: 1,
+            zero_bytes_ratio: 0.5,
+            tx_hash: B256::repeat_byte(2),
+            index: 0,
+            references: vec![],
+        }
+    }
+
+    fn params(statement: &Statement) -> Vec<String> {
+        statement
+            .params
+            .iter()
+            .map(|param| format!("{param:?}"))
+            .collect()
+    }
+
+    /// Returns the amount of columns written and the amount of values of the
+    /// first row, along with the highest parameter index used.
+    fn shape(query: &str) -> (usize, usize, usize) {
+        let columns = &query[query.find('(').unwrap() + 1..query.find(')').unwrap()];
+        let values = &query[query.find("VALUES").unwrap()..];
+        let mut depth = 0;
+        let mut row_values = 1;
+
+        for c in values[values.find('(').unwrap() + 1..].chars() {
+            match c {
+                '(' => depth += 1,
+                ')' if depth == 0 => break,
+                ')' => depth -= 1,
+                ',' if depth == 0 => row_values += 1,
+                _ => {}
+            }
+        }
+
+        let highest_param = query
+            .split('$')
+            .skip(1)
+            .filter_map(|part| {
+                let digits = part
+                    .chars()
+                    .take_while(char::is_ascii_digit)
+                    .collect::<String>();
+
+                digits.parse::<usize>().ok()
+            })
+            .max()
+            .unwrap_or(0);
+
+        (columns.split(',').count(), row_values, highest_param)
+    }
+
+    #[test]
+    fn every_entity_field_is_bound_to_its_column() {
+        let statements = statements(&block(), &[transaction()], &[blob()]);
+
+        assert_eq!(statements.len(), 6);
+
+        for statement in &statements {
+            let (columns, row_values, highest_param) = shape(&statement.query);
+
+            assert_eq!(columns, row_values, "{}", statement.query);
+            assert_eq!(highest_param, statement.params.len(), "{}", statement.query);
+        }
+
+        let block_params = params(&statements[0]);
+
+        assert_eq!(
+            block_params[6..11],
+            ["\"1\"", "4", "0.25", "Some(\"electra\")", "Some(7)"]
+        );
+        assert!(block_params[11].contains("proposerIndex"));
+
+        let transaction_params = params(&statements[2]);
+
+        assert_eq!(
+            transaction_params[9..],
+            ["Some(\"base\")", "Some(\"batch\")"]
+        );
+
+        let blob_params = params(&statements[3]);
+
+        assert_eq!(
+            blob_params[4..7],
+            [
+                "1".to_string(),
+                "0.5".to_string(),
+                format!("[\"{}\"]", B256::repeat_byte(6))
+            ]
+        );
+    }
+}
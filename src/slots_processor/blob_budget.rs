@@ -0,0 +1,144 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tracing::debug;
+
+use crate::{clients::priority::RequestPriority, metrics::set_gauge};
+
+/// Size of a blob's contents
+pub const BYTES_PER_BLOB: usize = 131_072;
+
+/// Bytes of blobs held in memory by every worker, from the moment they're
+/// fetched until their slot is indexed. When a cap is set, slots wait before
+/// fetching their blobs until enough bytes are released.
+#[derive(Debug)]
+pub struct BlobBudget {
+    semaphore: Option<Semaphore>,
+    max_bytes: usize,
+    bytes: AtomicUsize,
+}
+
+pub struct BlobBudgetGuard<'a> {
+    budget: &'a BlobBudget,
+    size: usize,
+    _permit: Option<SemaphorePermit<'a>>,
+}
+
+impl BlobBudget {
+    pub fn new(max_bytes: Option<usize>) -> Self {
+        let max_bytes = max_bytes.map(|max_bytes| max_bytes.clamp(1, u32::MAX as usize));
+
+        Self {
+            semaphore: max_bytes.map(Semaphore::new),
+            max_bytes: max_bytes.unwrap_or(usize::MAX),
+            bytes: AtomicUsize::new(0),
+        }
+    }
+
+    /// Whether acquiring the given amount of blobs would have to wait.
+    pub fn is_exhausted(&self, blobs: usize) -> bool {
+        self.semaphore.as_ref().is_some_and(|semaphore| {
+            semaphore.available_permits() < permits(blobs * BYTES_PER_BLOB, self.max_bytes) as usize
+        })
+    }
+
+    /// Reserves the memory of the given amount of blobs, waiting first if it
+    /// would exceed the cap. Slots with more blobs than the cap fits are
+    /// processed alone. High priority slots don't wait.
+    pub async fn acquire(&self, blobs: usize) -> BlobBudgetGuard<'_> {
+        let size = blobs * BYTES_PER_BLOB;
+        let permit = match &self.semaphore {
+            Some(_) if RequestPriority::current().is_high() => None,
+            Some(semaphore) => {
+                if self.is_exhausted(blobs) {
+                    debug!(
+                        blobs,
+                        inflight_bytes = self.bytes.load(Ordering::Relaxed),
+                        "Waiting for in-flight blobs to be indexed…"
+                    );
+                }
+
+                // The semaphore is never closed
+                semaphore
+                    .acquire_many(permits(size, self.max_bytes))
+                    .await
+                    .ok()
+            }
+            None => None,
+        };
+        let bytes = self.bytes.fetch_add(size, Ordering::Relaxed) + size;
+
+        report(bytes);
+
+        BlobBudgetGuard {
+            budget: self,
+            size,
+            _permit: permit,
+        }
+    }
+}
+
+impl Default for BlobBudget {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl Drop for BlobBudgetGuard<'_> {
+    fn drop(&mut self) {
+        let bytes = self.budget.bytes.fetch_sub(self.size, Ordering::Relaxed) - self.size;
+
+        report(bytes);
+    }
+}
+
+fn permits(size: usize, max_bytes: usize) -> u32 {
+    size.clamp(1, max_bytes) as u32
+}
+
+fn report(bytes: usize) {
+    set_gauge("blob_indexer_inflight_blob_bytes", &[], bytes as u64);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{BlobBudget, BYTES_PER_BLOB};
+    use crate::clients::priority::RequestPriority;
+
+    #[tokio::test]
+    async fn blobs_wait_for_the_budget_to_be_released() {
+        let budget = BlobBudget::new(Some(3 * BYTES_PER_BLOB));
+        let guard = budget.acquire(2).await;
+
+        assert!(budget.is_exhausted(2));
+        assert!(!budget.is_exhausted(1));
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), budget.acquire(2))
+                .await
+                .is_err()
+        );
+
+        drop(guard);
+
+        // Slots with more blobs than the budget still get processed
+        let _guard = budget.acquire(6).await;
+
+        assert!(budget.is_exhausted(1));
+    }
+
+    #[tokio::test]
+    async fn high_priority_slots_skip_the_queue() {
+        let budget = BlobBudget::new(Some(2 * BYTES_PER_BLOB));
+        let _guard = budget.acquire(2).await;
+
+        let high_priority = RequestPriority::High.scope(budget.acquire(1));
+
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), high_priority)
+                .await
+                .is_ok()
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex, MutexGuard,
+};
+
+use anyhow::anyhow;
+use tracing::warn;
+
+use crate::{
+    clients::{
+        beacon::{types::BlockId, CommonBeaconClient},
+        common::ClientResult,
+    },
+    network::parse_spec_u64,
+};
+
+/// Epochs beacon nodes keep serving blob sidecars for when their spec doesn't
+/// tell
+const DEFAULT_MIN_EPOCHS_FOR_BLOB_SIDECARS_REQUESTS: u64 = 4096;
+
+#[derive(Debug, Clone, Copy)]
+struct RetentionWindow {
+    /// Slots the beacon node keeps blob sidecars for
+    retention_slots: u32,
+    /// Oldest slot whose blob sidecar was still kept the last time the head
+    /// was checked
+    oldest_available_slot: u32,
+}
+
+/// Window of recent slots beacon nodes keep blob sidecars for, set by
+/// `MIN_EPOCHS_FOR_BLOB_SIDECARS_REQUESTS` in their spec, used to tell the
+/// sidecars pruned by the node apart from the ones missing for other reasons.
+/// It's only fetched once a sidecar is found missing, and shared by every task
+/// through the context.
+#[derive(Debug, Default)]
+pub struct BlobRetention {
+    window: Mutex<Option<RetentionWindow>>,
+    warned: AtomicBool,
+}
+
+impl BlobRetention {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the blob sidecar of the slot is older than the retention window,
+    /// so the beacon node may have pruned it. The head is checked again for
+    /// slots within the window, as it moves forward.
+    pub async fn is_pruned(
+        &self,
+        beacon_client: &dyn CommonBeaconClient,
+        slot: u32,
+    ) -> ClientResult<bool> {
+        let window = *self.lock();
+
+        if window.is_some_and(|window| slot < window.oldest_available_slot) {
+            return Ok(true);
+        }
+
+        let retention_slots = match window {
+            Some(window) => window.retention_slots,
+            None => fetch_retention_slots(beacon_client).await?,
+        };
+        let head_slot = beacon_client
+            .get_block_header(&BlockId::Head)
+            .await?
+            .ok_or_else(|| anyhow!("head block header not found"))?
+            .header
+            .message
+            .slot;
+        let window = RetentionWindow {
+            retention_slots,
+            oldest_available_slot: head_slot.saturating_sub(retention_slots),
+        };
+
+        *self.lock() = Some(window);
+
+        let is_pruned = slot < window.oldest_available_slot;
+
+        if is_pruned && !self.warned.swap(true, Ordering::Relaxed) {
+            warn!(
+                slot,
+                retention_slots,
+                oldest_available_slot = window.oldest_available_slot,
+                "The beacon node prunes blob sidecars older than its retention window, so the blobs of older slots can't be indexed. Point BEACON_NODE_BLOBS_ENDPOINT to a node keeping every blob sidecar to index them"
+            );
+        }
+
+        Ok(is_pruned)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<RetentionWindow>> {
+        match self.window.lock() {
+            Ok(window) => window,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+async fn fetch_retention_slots(beacon_client: &dyn CommonBeaconClient) -> ClientResult<u32> {
+    let spec = beacon_client
+        .get_spec()
+        .await?
+        .ok_or_else(|| anyhow!("beacon node spec not found"))?;
+    let slots_per_epoch = spec
+        .get("SLOTS_PER_EPOCH")
+        .and_then(parse_spec_u64)
+        .ok_or_else(|| anyhow!("SLOTS_PER_EPOCH missing from beacon node spec"))?;
+    let retention_epochs = spec
+        .get("MIN_EPOCHS_FOR_BLOB_SIDECARS_REQUESTS")
+        .and_then(parse_spec_u64)
+        .unwrap_or(DEFAULT_MIN_EPOCHS_FOR_BLOB_SIDECARS_REQUESTS);
+
+    Ok(u32::try_from(retention_epochs.saturating_mul(slots_per_epoch)).unwrap_or(u32::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use alloy::primitives::B256;
+    use serde_json::json;
+
+    use super::BlobRetention;
+    use crate::clients::beacon::{
+        types::{BlockHeader, BlockHeaderMessage, InnerBlockHeader},
+        MockCommonBeaconClient,
+    };
+
+    #[tokio::test]
+    async fn slots_older_than_the_retention_window_are_pruned() {
+        let mut beacon_client = MockCommonBeaconClient::new();
+
+        beacon_client.expect_get_spec().times(1).returning(|| {
+            let spec = HashMap::from([
+                ("SLOTS_PER_EPOCH".to_string(), json!("32")),
+                (
+                    "MIN_EPOCHS_FOR_BLOB_SIDECARS_REQUESTS".to_string(),
+                    json!("10"),
+                ),
+            ]);
+
+            Box::pin(async move { Ok(Some(spec)) })
+        });
+        beacon_client.expect_get_block_header().returning(|_| {
+            let header = BlockHeader {
+                root: B256::ZERO,
+                header: InnerBlockHeader {
+                    message: BlockHeaderMessage {
+                        parent_root: B256::ZERO,
+                        slot: 1000,
+                    },
+                },
+            };
+
+            Box::pin(async move { Ok(Some(header)) })
+        });
+
+        let retention = BlobRetention::new();
+
+        assert!(!retention.is_pruned(&beacon_client, 680).await.unwrap());
+        assert!(retention.is_pruned(&beacon_client, 679).await.unwrap());
+        assert!(retention.is_pruned(&beacon_client, 100).await.unwrap());
+    }
+}
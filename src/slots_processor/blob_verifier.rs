@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use c_kzg::{ethereum_kzg_settings, Blob as KzgBlob, Bytes48, KzgProof};
+use futures::future::try_join_all;
+use tokio::{sync::Semaphore, task};
+
+use crate::clients::{beacon::types::Blob, priority::RequestPriority};
+
+#[derive(Debug, thiserror::Error)]
+pub enum BlobVerificationError {
+    #[error("blob {index} has an invalid format: {error}")]
+    InvalidFormat { index: usize, error: c_kzg::Error },
+    #[error("KZG proof of blob {index} with commitment {commitment} is invalid")]
+    InvalidProof { index: usize, commitment: String },
+    #[error("blob verification task failed")]
+    TaskFailure(#[from] task::JoinError),
+}
+
+/// Verifies the KZG proofs of blob sidecars on the blocking thread pool. The
+/// amount of blobs verified at the same time is bounded across all the slots
+/// being processed so verification can't starve other tasks.
+#[derive(Debug, Clone)]
+pub struct BlobVerifier {
+    permits: Arc<Semaphore>,
+}
+
+impl BlobVerifier {
+    pub fn new(max_parallelism: usize) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(max_parallelism.max(1))),
+        }
+    }
+
+    /// Verifies the given blobs. Those of high priority slots don't wait for
+    /// the others to be verified.
+    pub async fn verify(&self, blobs: &[Blob]) -> Result<(), BlobVerificationError> {
+        let is_high_priority = RequestPriority::current().is_high();
+        let verifications = blobs.iter().enumerate().map(|(index, blob)| {
+            let permits = self.permits.clone();
+            let blob = blob.clone();
+
+            async move {
+                // The semaphore is never closed
+                let _permit = if is_high_priority {
+                    None
+                } else {
+                    permits.acquire_owned().await.ok()
+                };
+
+                task::spawn_blocking(move || verify_blob(index, &blob)).await?
+            }
+        });
+
+        try_join_all(verifications).await?;
+
+        Ok(())
+    }
+}
+
+fn verify_blob(index: usize, blob: &Blob) -> Result<(), BlobVerificationError> {
+    let invalid_format = |error| BlobVerificationError::InvalidFormat { index, error };
+
+    let kzg_blob = KzgBlob::from_bytes(&blob.blob).map_err(invalid_format)?;
+    let commitment = Bytes48::from_hex(&blob.kzg_commitment).map_err(invalid_format)?;
+    let proof = Bytes48::from_hex(&blob.kzg_proof).map_err(invalid_format)?;
+
+    let is_valid =
+        KzgProof::verify_blob_kzg_proof(&kzg_blob, &commitment, &proof, ethereum_kzg_settings())
+            .map_err(invalid_format)?;
+
+    if is_valid {
+        Ok(())
+    } else {
+        Err(BlobVerificationError::InvalidProof {
+            index,
+            commitment: blob.kzg_commitment.clone(),
+        })
+    }
+}
@@ -0,0 +1,104 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use tokio::sync::Notify;
+
+/// Slots being processed right now, shared by every task through the context
+/// so two of them never process the same slot at once, e.g. when the head goes
+/// back into the range being caught up after a reorg.
+#[derive(Debug, Default)]
+pub struct SlotClaims {
+    claimed: Mutex<HashSet<u32>>,
+    released: Notify,
+}
+
+/// Claim over a slot, released when dropped.
+#[derive(Debug)]
+pub struct SlotClaim {
+    claims: Arc<SlotClaims>,
+    slot: u32,
+}
+
+impl SlotClaims {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Claims the slot, waiting for the task holding it to release it first.
+    /// Returns whether it had to wait along with the claim.
+    pub async fn claim(self: Arc<Self>, slot: u32) -> (SlotClaim, bool) {
+        let mut waited = false;
+
+        loop {
+            // Registered before checking so a release in between isn't missed
+            let released = self.released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+
+            if self.lock().insert(slot) {
+                return (
+                    SlotClaim {
+                        claims: self.clone(),
+                        slot,
+                    },
+                    waited,
+                );
+            }
+
+            waited = true;
+            released.await;
+        }
+    }
+
+    pub fn is_claimed(&self, slot: u32) -> bool {
+        self.lock().contains(&slot)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashSet<u32>> {
+        match self.claimed.lock() {
+            Ok(claimed) => claimed,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+impl Drop for SlotClaim {
+    fn drop(&mut self) {
+        self.claims.lock().remove(&self.slot);
+        self.claims.released.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use super::SlotClaims;
+
+    #[tokio::test]
+    async fn claimed_slots_wait_for_their_release() {
+        let claims = Arc::new(SlotClaims::new());
+        let (claim, waited) = claims.clone().claim(10).await;
+
+        assert!(!waited);
+        assert!(claims.is_claimed(10));
+
+        let (_other_claim, waited) = claims.clone().claim(11).await;
+
+        assert!(!waited);
+
+        let waiting_claim = tokio::spawn(claims.clone().claim(10));
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!waiting_claim.is_finished());
+
+        drop(claim);
+
+        let (_claim, waited) = waiting_claim.await.unwrap();
+
+        assert!(waited);
+        assert!(claims.is_claimed(10));
+    }
+}
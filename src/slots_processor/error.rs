@@ -1,3 +1,11 @@
+use std::time::Duration;
+
+use alloy::transports::{RpcError, TransportErrorKind};
+
+use crate::clients::common::{is_recoverable_status, ClientError, NumericOrTextCode};
+
+use super::blob_verifier::BlobVerificationError;
+
 #[derive(Debug, thiserror::Error)]
 pub enum SlotProcessingError {
     #[error(transparent)]
@@ -5,6 +13,10 @@ pub enum SlotProcessingError {
     #[error(transparent)]
     Provider(#[from] alloy::transports::TransportError),
     #[error(transparent)]
+    BlobVerification(#[from] BlobVerificationError),
+    #[error("slot processing timed out after {timeout:?}")]
+    OperationTimeout { timeout: Duration },
+    #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
 
@@ -19,6 +31,69 @@ pub enum SlotsProcessorError {
         failed_slot: u32,
         error: SlotProcessingError,
     },
+    #[error("slots processing worker panicked")]
+    WorkerPanic(#[source] tokio::task::JoinError),
+    #[error("processing slot {slot} panicked: {message}")]
+    SlotPanic { slot: u32, message: String },
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
+
+impl SlotProcessingError {
+    /// Whether the failure was caused by a provider throttling our requests
+    pub fn is_rate_limited(&self) -> bool {
+        match self {
+            SlotProcessingError::ClientError(ClientError::ApiError(error)) => {
+                matches!(&error.code, NumericOrTextCode::Number(429))
+                    || matches!(&error.code, NumericOrTextCode::String(code) if code == "429")
+            }
+            SlotProcessingError::ClientError(ClientError::RateLimited { .. }) => true,
+            SlotProcessingError::ClientError(ClientError::Reqwest(error)) => {
+                error.status().is_some_and(|status| status.as_u16() == 429)
+            }
+            SlotProcessingError::Provider(RpcError::Transport(TransportErrorKind::HttpError(
+                error,
+            ))) => error.is_rate_limit_err(),
+            _ => false,
+        }
+    }
+
+    /// Whether processing the slot may succeed if retried later. Invalid blobs
+    /// and responses that can't be decoded are fatal.
+    pub fn is_recoverable(&self) -> bool {
+        match self {
+            SlotProcessingError::ClientError(error) => error.is_recoverable(),
+            SlotProcessingError::Provider(error) => match error {
+                RpcError::Transport(TransportErrorKind::HttpError(error)) => {
+                    is_recoverable_status(error.status)
+                }
+                RpcError::Transport(_) | RpcError::ErrorResp(_) | RpcError::NullResp => true,
+                _ => false,
+            },
+            SlotProcessingError::BlobVerification(error) => {
+                matches!(error, BlobVerificationError::TaskFailure(_))
+            }
+            // Retried on the next attempt, which may find the nodes healthier
+            SlotProcessingError::OperationTimeout { .. } | SlotProcessingError::Other(_) => true,
+        }
+    }
+}
+
+impl SlotsProcessorError {
+    pub fn is_recoverable(&self) -> bool {
+        match self {
+            SlotsProcessorError::FailedSlotsProcessing { error, .. } => error.is_recoverable(),
+            SlotsProcessorError::WorkerPanic(_)
+            | SlotsProcessorError::SlotPanic { .. }
+            | SlotsProcessorError::Other(_) => true,
+        }
+    }
+
+    pub fn failed_slot(&self) -> Option<u32> {
+        match self {
+            SlotsProcessorError::FailedSlotsProcessing { failed_slot, .. } => Some(*failed_slot),
+            SlotsProcessorError::SlotPanic { slot, .. } => Some(*slot),
+            SlotsProcessorError::WorkerPanic(_) | SlotsProcessorError::Other(_) => None,
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use std::collections::HashSet;
+
+use alloy::primitives::Address;
+
+/// Restricts the indexed blob transactions to the ones sent from or to a set of
+/// addresses. Every transaction passes when no addresses are given.
+#[derive(Debug, Default, Clone)]
+pub struct AddressFilter {
+    from: HashSet<Address>,
+    to: HashSet<Address>,
+}
+
+impl AddressFilter {
+    pub fn new(
+        from: impl IntoIterator<Item = Address>,
+        to: impl IntoIterator<Item = Address>,
+    ) -> Self {
+        Self {
+            from: from.into_iter().collect(),
+            to: to.into_iter().collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.from.is_empty() && self.to.is_empty()
+    }
+
+    /// Whether a transaction with the given sender and recipient is indexed.
+    pub fn matches(&self, from: &Address, to: Option<&Address>) -> bool {
+        self.is_empty() || self.from.contains(from) || to.is_some_and(|to| self.to.contains(to))
+    }
+}
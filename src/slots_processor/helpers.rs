@@ -1,6 +1,12 @@
 use std::collections::HashMap;
 
-use crate::{clients::beacon::types::Blob as BeaconBlob, utils::web3::calculate_versioned_hash};
+use crate::{
+    clients::{
+        beacon::types::Blob as BeaconBlob,
+        blobscan::types::{Blob, BlobReference},
+    },
+    utils::web3::calculate_versioned_hash,
+};
 use alloy::{
     primitives::B256,
     rpc::types::{Block, Transaction},
@@ -12,19 +18,20 @@ pub fn create_tx_hash_versioned_hashes_mapping(
     let mut tx_to_versioned_hashes = HashMap::new();
 
     if let Some(transactions) = block.transactions.as_transactions() {
-        transactions
-            .iter()
-            .for_each(|tx| match &tx.blob_versioned_hashes {
-                Some(versioned_hashes) => {
-                    tx_to_versioned_hashes.insert(tx.hash, versioned_hashes.clone());
-                }
-                None => {}
-            });
+        transactions.iter().for_each(|tx| {
+            if let Some(versioned_hashes) = &tx.blob_versioned_hashes {
+                tx_to_versioned_hashes.insert(tx.hash, versioned_hashes.clone());
+            }
+        });
     }
 
     Ok(tx_to_versioned_hashes)
 }
 
+pub fn create_index_blob_mapping(blobs: &[BeaconBlob]) -> HashMap<u32, &BeaconBlob> {
+    blobs.iter().map(|blob| (blob.index, blob)).collect()
+}
+
 pub fn create_versioned_hash_blob_mapping(
     blobs: &Vec<BeaconBlob>,
 ) -> Result<HashMap<B256, &BeaconBlob>, anyhow::Error> {
@@ -38,3 +45,127 @@ pub fn create_versioned_hash_blob_mapping(
 
     Ok(version_hash_to_blob)
 }
+
+/// Merges the blobs sharing a versioned hash into the first one, which keeps
+/// the position of the blob in every other transaction referencing it, so
+/// their contents are only sent once.
+pub fn dedup_blobs(blobs: Vec<Blob>) -> Vec<Blob> {
+    let mut positions: HashMap<B256, usize> = HashMap::new();
+    let mut unique_blobs: Vec<Blob> = Vec::with_capacity(blobs.len());
+
+    for blob in blobs {
+        match positions.get(&blob.versioned_hash) {
+            Some(&position) => unique_blobs[position].references.push(BlobReference {
+                tx_hash: blob.tx_hash,
+                index: blob.index,
+            }),
+            None => {
+                positions.insert(blob.versioned_hash, unique_blobs.len());
+                unique_blobs.push(blob);
+            }
+        }
+    }
+
+    unique_blobs
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::Bytes;
+
+    use super::*;
+
+    fn blob(versioned_hash: B256, index: usize, tx_hash: B256) -> Blob {
+        let beacon_blob = BeaconBlob {
+            index: 0,
+            kzg_commitment: String::from("0x"),
+            kzg_proof: String::from("0x"),
+            blob: Bytes::from(vec![versioned_hash[0]; 8]),
+            kzg_commitment_inclusion_proof: vec![],
+            signed_block_header: None,
+        };
+
+        Blob::from((&beacon_blob, &versioned_hash, index, &tx_hash))
+    }
+
+    #[test]
+    fn dedup_blobs_keeps_unique_blobs() {
+        let blobs = vec![
+            blob(B256::repeat_byte(1), 0, B256::repeat_byte(0xa)),
+            blob(B256::repeat_byte(2), 1, B256::repeat_byte(0xa)),
+            blob(B256::repeat_byte(3), 0, B256::repeat_byte(0xb)),
+        ];
+
+        let unique_blobs = dedup_blobs(blobs);
+
+        assert_eq!(unique_blobs.len(), 3);
+        assert!(unique_blobs.iter().all(|blob| blob.references.is_empty()));
+    }
+
+    #[test]
+    fn dedup_blobs_references_blobs_shared_across_transactions() {
+        let shared_hash = B256::repeat_byte(1);
+        let blobs = vec![
+            blob(shared_hash, 0, B256::repeat_byte(0xa)),
+            blob(B256::repeat_byte(2), 1, B256::repeat_byte(0xa)),
+            blob(shared_hash, 0, B256::repeat_byte(0xb)),
+            blob(shared_hash, 1, B256::repeat_byte(0xc)),
+        ];
+
+        let unique_blobs = dedup_blobs(blobs);
+
+        assert_eq!(unique_blobs.len(), 2);
+        assert_eq!(unique_blobs[0].versioned_hash, shared_hash);
+        assert_eq!(unique_blobs[0].tx_hash, B256::repeat_byte(0xa));
+        assert_eq!(unique_blobs[0].index, 0);
+        assert_eq!(
+            unique_blobs[0].references,
+            vec![
+                BlobReference {
+                    tx_hash: B256::repeat_byte(0xb),
+                    index: 0,
+                },
+                BlobReference {
+                    tx_hash: B256::repeat_byte(0xc),
+                    index: 1,
+                },
+            ]
+        );
+        assert_eq!(unique_blobs[1].versioned_hash, B256::repeat_byte(2));
+        assert!(unique_blobs[1].references.is_empty());
+    }
+
+    #[test]
+    fn dedup_blobs_references_blobs_repeated_within_a_transaction() {
+        let tx_hash = B256::repeat_byte(0xa);
+        let blobs = vec![
+            blob(B256::repeat_byte(1), 0, tx_hash),
+            blob(B256::repeat_byte(1), 1, tx_hash),
+        ];
+
+        let unique_blobs = dedup_blobs(blobs);
+
+        assert_eq!(unique_blobs.len(), 1);
+        assert_eq!(
+            unique_blobs[0].references,
+            vec![BlobReference { tx_hash, index: 1 }]
+        );
+    }
+
+    #[test]
+    fn deduped_blobs_serialize_their_references() {
+        let blobs = vec![
+            blob(B256::repeat_byte(1), 0, B256::repeat_byte(0xa)),
+            blob(B256::repeat_byte(1), 2, B256::repeat_byte(0xb)),
+        ];
+
+        let unique_blobs = dedup_blobs(blobs);
+        let payload = serde_json::to_value(&unique_blobs).unwrap();
+
+        assert_eq!(payload.as_array().unwrap().len(), 1);
+        assert_eq!(
+            payload[0]["references"],
+            serde_json::json!([{ "txHash": B256::repeat_byte(0xb), "index": 2 }])
+        );
+    }
+}
@@ -0,0 +1,332 @@
+use std::{
+    collections::BTreeMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use clap::ValueEnum;
+use tracing::warn;
+
+use crate::{metrics::increment_counter, utils::roaring::RoaringBitmap};
+
+/// Magic bytes of bitmap files holding the bitmap words as is
+const DENSE_BITMAP_MAGIC: &[u8; 8] = b"BSSLOTS1";
+/// Magic bytes of bitmap files holding a serialized roaring bitmap
+const ROARING_BITMAP_MAGIC: &[u8; 8] = b"BSSLOTS2";
+/// Amount of newly marked slots after which the bitmap is written to disk
+const SLOTS_PER_SAVE: usize = 256;
+
+/// Where a backfill looks up whether a slot was already indexed.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum IndexedSlotsSource {
+    /// Ask Blobscan for the block at the slot
+    Blobscan,
+    /// Look the slot up in a local bitmap of the slots processed so far
+    Bitmap,
+}
+
+/// Tasks processing slots, which may go over the same ones when their ranges
+/// overlap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SlotsTask {
+    Backfill,
+    CatchUp,
+    Head,
+}
+
+/// Slots already indexed, shared by every task so none of them processes a
+/// slot another one already did during this run. Backfills can additionally
+/// skip the slots indexed by previous runs, looking them up in Blobscan or in
+/// the bitmap those runs left on disk.
+#[derive(Debug)]
+pub struct IndexedSlots {
+    source: Option<IndexedSlotsSource>,
+    /// File the processed slots are saved to when using the bitmap source
+    path: Option<PathBuf>,
+    state: Mutex<IndexedSlotsState>,
+}
+
+#[derive(Debug, Default)]
+struct IndexedSlotsState {
+    /// Slots processed by previous runs
+    previous: RoaringBitmap,
+    /// Slots processed during this run
+    current: RoaringBitmap,
+    unsaved: usize,
+    /// Slots each task skipped as another one processed them during this run
+    overlaps: BTreeMap<SlotsTask, u64>,
+}
+
+impl IndexedSlots {
+    /// Tracks the slots processed during this run in memory only.
+    pub fn new() -> Self {
+        Self {
+            source: None,
+            path: None,
+            state: Mutex::new(IndexedSlotsState::default()),
+        }
+    }
+
+    /// Tracks the slots processed during this run and lets backfills skip the
+    /// ones indexed by previous runs according to the given source. The bitmap
+    /// source loads them from the given file, starting an empty one if it
+    /// doesn't exist yet.
+    pub fn open(source: IndexedSlotsSource, bitmap_path: &Path) -> io::Result<Self> {
+        let mut indexed_slots = Self::new();
+
+        indexed_slots.source = Some(source);
+
+        if source == IndexedSlotsSource::Bitmap {
+            indexed_slots.lock().previous = read_bitmap(bitmap_path)?;
+            indexed_slots.path = Some(bitmap_path.to_path_buf());
+        }
+
+        Ok(indexed_slots)
+    }
+
+    /// Whether the task can skip the slot, counting it as an overlap when it
+    /// was processed by this run.
+    pub fn contains(&self, slot: u32, task: SlotsTask) -> bool {
+        let mut state = self.lock();
+
+        if state.current.contains(slot) {
+            *state.overlaps.entry(task).or_default() += 1;
+            increment_counter(
+                "blob_indexer_overlapping_slots_total",
+                &[("task", &task.to_string())],
+            );
+
+            return true;
+        }
+
+        task == SlotsTask::Backfill
+            && self.source == Some(IndexedSlotsSource::Bitmap)
+            && state.previous.contains(slot)
+    }
+
+    /// Whether the task has to ask Blobscan if a block was already indexed.
+    pub fn checks_blobscan(&self, task: SlotsTask) -> bool {
+        task == SlotsTask::Backfill && self.source == Some(IndexedSlotsSource::Blobscan)
+    }
+
+    /// Marks the slot as processed, writing the bitmap to disk every few
+    /// slots when using the bitmap source.
+    pub fn insert(&self, slot: u32) {
+        let mut state = self.lock();
+
+        if state.current.insert(slot) {
+            state.unsaved += 1;
+        }
+
+        if state.unsaved >= SLOTS_PER_SAVE {
+            self.save_state(&mut state);
+        }
+    }
+
+    /// Forgets the given slots so they get processed again, as when they're
+    /// reorged.
+    pub fn remove(&self, slots: impl IntoIterator<Item = u32>) {
+        let mut state = self.lock();
+
+        for slot in slots {
+            let removed_previous = state.previous.remove(slot);
+            let removed_current = state.current.remove(slot);
+
+            if removed_previous || removed_current {
+                state.unsaved += 1;
+            }
+        }
+    }
+
+    /// Writes the slots marked since the last save to disk.
+    pub fn flush(&self) {
+        let mut state = self.lock();
+
+        if state.unsaved > 0 {
+            self.save_state(&mut state);
+        }
+    }
+
+    /// Returns the amount of slots each task skipped because another task had
+    /// already processed them during this run.
+    pub fn overlaps(&self) -> Vec<(SlotsTask, u64)> {
+        self.lock()
+            .overlaps
+            .iter()
+            .map(|(&task, &overlaps)| (task, overlaps))
+            .collect()
+    }
+
+    /// Writes the bitmap to a temporary file first so an interrupted write
+    /// can't leave a truncated bitmap behind. Failures are only logged as the
+    /// bitmap can be rebuilt by indexing the slots again.
+    fn save_state(&self, state: &mut IndexedSlotsState) {
+        let Some(path) = &self.path else {
+            state.unsaved = 0;
+
+            return;
+        };
+        let mut slots = state.previous.clone();
+
+        slots.union_with(&state.current);
+
+        let mut contents = ROARING_BITMAP_MAGIC.to_vec();
+
+        contents.extend(slots.to_bytes());
+
+        let tmp_path = path.with_extension("tmp");
+        let result = fs::write(&tmp_path, contents).and_then(|_| fs::rename(&tmp_path, path));
+
+        match result {
+            Ok(()) => state.unsaved = 0,
+            Err(error) => warn!(
+                ?error,
+                path = %path.display(),
+                "Failed to save indexed slots bitmap"
+            ),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, IndexedSlotsState> {
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+impl Default for IndexedSlots {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for IndexedSlots {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+impl fmt::Display for SlotsTask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SlotsTask::Backfill => "backfill",
+            SlotsTask::CatchUp => "catch_up",
+            SlotsTask::Head => "head",
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// Reads a bitmap file, including the dense ones written by older versions.
+fn read_bitmap(path: &Path) -> io::Result<RoaringBitmap> {
+    let contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(RoaringBitmap::new()),
+        Err(error) => return Err(error),
+    };
+    let bitmap = match contents.strip_prefix(ROARING_BITMAP_MAGIC) {
+        Some(bytes) => RoaringBitmap::from_bytes(bytes),
+        None => contents
+            .strip_prefix(DENSE_BITMAP_MAGIC)
+            .filter(|words| words.len() % 8 == 0)
+            .map(|words| {
+                words
+                    .chunks_exact(8)
+                    .enumerate()
+                    .flat_map(|(index, word)| {
+                        let word = u64::from_le_bytes(word.try_into().unwrap());
+
+                        (0..64)
+                            .filter(move |offset| word & (1 << offset) != 0)
+                            .map(move |offset| (index * 64 + offset) as u32)
+                    })
+                    .collect()
+            }),
+    };
+
+    bitmap.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid slot bitmap file"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf, process};
+
+    use super::{IndexedSlots, IndexedSlotsSource, SlotsTask};
+
+    fn bitmap_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("indexed-slots-{name}-{}.bin", process::id()))
+    }
+
+    #[test]
+    fn bitmap_survives_reopening() {
+        let path = bitmap_path("reopen");
+        // Spread over several roaring containers
+        let slots = [5, 6, 70_000, 1_000_000];
+        let indexed_slots = IndexedSlots::open(IndexedSlotsSource::Bitmap, &path).unwrap();
+
+        for slot in slots {
+            indexed_slots.insert(slot);
+        }
+
+        drop(indexed_slots);
+
+        let indexed_slots = IndexedSlots::open(IndexedSlotsSource::Bitmap, &path).unwrap();
+
+        for slot in slots {
+            assert!(indexed_slots.contains(slot, SlotsTask::Backfill), "{slot}");
+            // Only backfills skip the slots indexed by previous runs
+            assert!(!indexed_slots.contains(slot, SlotsTask::Head), "{slot}");
+        }
+
+        assert!(!indexed_slots.contains(7, SlotsTask::Backfill));
+        assert!(indexed_slots.overlaps().is_empty());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn removed_slots_are_processed_again() {
+        let path = bitmap_path("remove");
+        let indexed_slots = IndexedSlots::open(IndexedSlotsSource::Bitmap, &path).unwrap();
+
+        for slot in 0..10 {
+            indexed_slots.insert(slot);
+        }
+
+        drop(indexed_slots);
+
+        // Slots from a previous run along with the ones of this one
+        let indexed_slots = IndexedSlots::open(IndexedSlotsSource::Bitmap, &path).unwrap();
+
+        for slot in 10..20 {
+            indexed_slots.insert(slot);
+        }
+
+        indexed_slots.remove(5..15);
+
+        for slot in 0..20 {
+            assert_eq!(
+                indexed_slots.contains(slot, SlotsTask::Backfill),
+                !(5..15).contains(&slot),
+                "{slot}"
+            );
+        }
+
+        drop(indexed_slots);
+
+        let indexed_slots = IndexedSlots::open(IndexedSlotsSource::Bitmap, &path).unwrap();
+
+        for slot in 0..20 {
+            assert_eq!(
+                indexed_slots.contains(slot, SlotsTask::Backfill),
+                !(5..15).contains(&slot),
+                "{slot}"
+            );
+        }
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -1,31 +1,169 @@
-use alloy::{rpc::types::BlockTransactionsKind, transports::http::ReqwestTransport};
+use alloy::{
+    primitives::B256,
+    rpc::types::{Block as ExecutionBlock, BlockTransactionsKind},
+    transports::{BoxTransport, RpcError},
+};
 use anyhow::{anyhow, Context as AnyhowContext, Result};
+use std::{
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use tokio::sync::mpsc::UnboundedSender;
 
-use tracing::{debug, info};
+use tracing::{debug, field::display, info, warn, Instrument, Span};
 
 use crate::{
     clients::{
         beacon::types::BlockId,
         blobscan::types::{Blob, Block, Transaction},
+        priority::RequestPriority,
     },
     context::CommonContext,
+    era::{EraBlock, EraSlot},
+    metrics::increment_counter,
+    network::ForkSchedule,
+    synchronizer::{
+        concurrency::AdaptiveConcurrency,
+        watchdog::{SlotPhase, WorkerStatus},
+    },
+    utils::web3::{calculate_blob_gas_price, calculate_blob_gas_used, calculate_versioned_hash},
 };
 
+use self::blob_verifier::BlobVerifier;
 use self::error::{SlotProcessingError, SlotsProcessorError};
-use self::helpers::{create_tx_hash_versioned_hashes_mapping, create_versioned_hash_blob_mapping};
-
+use self::helpers::{
+    create_index_blob_mapping, create_tx_hash_versioned_hashes_mapping,
+    create_versioned_hash_blob_mapping, dedup_blobs,
+};
+use self::indexed_slots::{IndexedSlots, SlotsTask};
+use self::stats::{SkipReason, SlotStats};
+use self::timings::SlotTimings;
+
+pub mod blob_budget;
+pub mod blob_retention;
+pub mod blob_verifier;
+pub mod claims;
 pub mod error;
-mod helpers;
+pub mod filter;
+pub(crate) mod helpers;
+pub mod indexed_slots;
+pub mod stats;
+pub mod timings;
 
 pub struct SlotsProcessor<T> {
     context: Box<dyn CommonContext<T>>,
+    progress_tx: Option<UnboundedSender<u32>>,
+    concurrency: Option<Arc<AdaptiveConcurrency>>,
+    blob_verifier: Option<BlobVerifier>,
+    fork_schedule: Option<ForkSchedule>,
+    status: Option<Arc<WorkerStatus>>,
+    indexed_slots: Option<(Arc<IndexedSlots>, SlotsTask)>,
+    slot_timeout: Option<Duration>,
+    slow_slot_threshold: Option<Duration>,
+    timings: Mutex<SlotTimings>,
+    slot_stats: Option<Arc<SlotStats>>,
+    request_priority: RequestPriority,
+    /// Last slot skipped as its blobs sidecar was pruned, which isn't marked
+    /// as indexed
+    pruned_slot: Option<u32>,
 }
 
-impl SlotsProcessor<ReqwestTransport> {
-    pub fn new(
-        context: Box<dyn CommonContext<ReqwestTransport>>,
-    ) -> SlotsProcessor<ReqwestTransport> {
-        Self { context }
+impl SlotsProcessor<BoxTransport> {
+    pub fn new(context: Box<dyn CommonContext<BoxTransport>>) -> SlotsProcessor<BoxTransport> {
+        Self {
+            context,
+            progress_tx: None,
+            concurrency: None,
+            blob_verifier: None,
+            fork_schedule: None,
+            status: None,
+            indexed_slots: None,
+            slot_timeout: None,
+            slow_slot_threshold: None,
+            timings: Mutex::new(SlotTimings::default()),
+            slot_stats: None,
+            request_priority: RequestPriority::Normal,
+            pruned_slot: None,
+        }
+    }
+
+    /// Verifies the KZG proofs of the fetched blobs before indexing them.
+    pub fn with_blob_verifier(mut self, blob_verifier: BlobVerifier) -> Self {
+        self.blob_verifier = Some(blob_verifier);
+
+        self
+    }
+
+    /// Checks the blobs of every block against the limit of its fork and tags
+    /// the block with it.
+    pub fn with_fork_schedule(mut self, fork_schedule: ForkSchedule) -> Self {
+        self.fork_schedule = Some(fork_schedule);
+
+        self
+    }
+
+    /// Skips the slots already indexed by any task, marking the processed
+    /// ones as indexed.
+    pub fn with_indexed_slots(mut self, indexed_slots: Arc<IndexedSlots>, task: SlotsTask) -> Self {
+        self.indexed_slots = Some((indexed_slots, task));
+
+        self
+    }
+
+    /// Feeds the latency and outcome of every processed slot into the given
+    /// concurrency controller.
+    pub fn with_concurrency_feedback(mut self, concurrency: Arc<AdaptiveConcurrency>) -> Self {
+        self.concurrency = Some(concurrency);
+
+        self
+    }
+
+    /// Reports every successfully processed slot through the given channel.
+    pub fn with_progress_reporting(mut self, progress_tx: UnboundedSender<u32>) -> Self {
+        self.progress_tx = Some(progress_tx);
+
+        self
+    }
+
+    /// Records the slot and phase being processed in the given status so a
+    /// watchdog can detect when processing gets stuck.
+    pub fn with_status(mut self, status: Arc<WorkerStatus>) -> Self {
+        self.status = Some(status);
+
+        self
+    }
+
+    /// Fails the slots taking longer than the given timeout to be processed,
+    /// so a slot whose requests keep being retried doesn't hold its task up
+    /// indefinitely.
+    pub fn with_slot_timeout(mut self, slot_timeout: Duration) -> Self {
+        self.slot_timeout = Some(slot_timeout);
+
+        self
+    }
+
+    /// Warns about the slots taking longer than the given threshold to be
+    /// processed, along with the time spent on each of their phases.
+    pub fn with_slow_slot_threshold(mut self, slow_slot_threshold: Duration) -> Self {
+        self.slow_slot_threshold = Some(slow_slot_threshold);
+
+        self
+    }
+
+    /// Records the processing time and skip reason of every slot in the given
+    /// stats.
+    pub fn with_slot_stats(mut self, slot_stats: Arc<SlotStats>) -> Self {
+        self.slot_stats = Some(slot_stats);
+
+        self
+    }
+
+    /// Processes the slots with the given request priority.
+    pub fn with_request_priority(mut self, request_priority: RequestPriority) -> Self {
+        self.request_priority = request_priority;
+
+        self
     }
 
     pub async fn process_slots(
@@ -40,8 +178,76 @@ impl SlotsProcessor<ReqwestTransport> {
             (initial_slot..final_slot).collect::<Vec<_>>()
         };
 
+        let slot_claims = self.context.slot_claims();
+
         for current_slot in slots {
-            if let Err(error) = self.process_slot(current_slot).await {
+            self.set_phase(current_slot, SlotPhase::WaitingForSlotClaim);
+
+            // Held until the slot is processed, so the indexed slots are checked
+            // once the task that was processing it is done
+            let (_slot_claim, waited) = slot_claims.clone().claim(current_slot).await;
+
+            if waited {
+                debug!(
+                    slot = current_slot,
+                    "Waited for another task to finish processing the slot"
+                );
+                increment_counter("blob_indexer_slot_claim_waits_total", &[]);
+            }
+
+            if let Some((indexed_slots, task)) = &self.indexed_slots {
+                if indexed_slots.contains(current_slot, *task) {
+                    debug!(
+                        slot = current_slot,
+                        "Skipping as the slot was already indexed"
+                    );
+                    increment_counter("blob_indexer_skipped_indexed_slots_total", &[]);
+                    self.record_skip(SkipReason::AlreadyIndexed);
+
+                    if let Some(progress_tx) = &self.progress_tx {
+                        let _ = progress_tx.send(current_slot);
+                    }
+
+                    continue;
+                }
+            }
+
+            let started_at = Instant::now();
+            let slot_span = tracing::info_span!(
+                "slot",
+                slot = current_slot,
+                block_hash = tracing::field::Empty
+            );
+            let request_priority = self.request_priority;
+            let result = match self.slot_timeout {
+                Some(timeout) => tokio::time::timeout(
+                    timeout,
+                    request_priority.scope(self.process_slot(current_slot).instrument(slot_span)),
+                )
+                .await
+                .unwrap_or_else(|_| {
+                    warn!(slot = current_slot, ?timeout, "Slot processing timed out");
+                    increment_counter("blob_indexer_slot_timeouts_total", &[]);
+
+                    Err(SlotProcessingError::OperationTimeout { timeout })
+                }),
+                None => {
+                    request_priority
+                        .scope(self.process_slot(current_slot).instrument(slot_span))
+                        .await
+                }
+            };
+
+            self.record_timings(current_slot, started_at.elapsed());
+
+            if let Some(concurrency) = &self.concurrency {
+                match &result {
+                    Ok(()) => concurrency.record_success(started_at.elapsed()),
+                    Err(error) => concurrency.record_failure(error.is_rate_limited()),
+                }
+            }
+
+            if let Err(error) = result {
                 return Err(SlotsProcessorError::FailedSlotsProcessing {
                     initial_slot,
                     final_slot,
@@ -49,6 +255,22 @@ impl SlotsProcessor<ReqwestTransport> {
                     error,
                 });
             }
+
+            // Pruned slots are left for a node keeping their blobs to index them
+            let is_pruned = self.pruned_slot.take() == Some(current_slot);
+
+            if let Some((indexed_slots, _)) = self.indexed_slots.as_ref().filter(|_| !is_pruned) {
+                indexed_slots.insert(current_slot);
+            }
+
+            if let Some(progress_tx) = &self.progress_tx {
+                // The receiver going away only means nobody is tracking progress anymore
+                let _ = progress_tx.send(current_slot);
+            }
+        }
+
+        if let Some((indexed_slots, _)) = &self.indexed_slots {
+            indexed_slots.flush();
         }
 
         Ok(())
@@ -56,13 +278,37 @@ impl SlotsProcessor<ReqwestTransport> {
 
     pub async fn process_slot(&mut self, slot: u32) -> Result<(), SlotProcessingError> {
         let beacon_client = self.context.beacon_client();
-        let blobscan_client = self.context.blobscan_client();
-        let provider = self.context.provider();
 
-        let beacon_block = match beacon_client.get_block(&BlockId::Slot(slot)).await? {
+        self.set_phase(slot, SlotPhase::FetchingBeaconBlock);
+
+        // Blocks of the slots covered by the era files are read from them instead
+        let era_slot = match self.context.era_archive() {
+            // Reading the block recovers the signer of every transaction, so
+            // it's done off the runtime
+            Some(era_archive) => tokio::task::spawn_blocking(move || era_archive.block(slot))
+                .await
+                .map_err(|error| anyhow!("Era file read task failed: {error}"))?
+                .map_err(anyhow::Error::from)?,
+            None => None,
+        };
+        let (beacon_block, era_execution_block) = match era_slot {
+            Some(EraSlot::Block(era_block)) => {
+                let EraBlock {
+                    beacon_block,
+                    execution_block,
+                } = *era_block;
+
+                (Some(beacon_block), execution_block)
+            }
+            Some(EraSlot::Missed) => (None, None),
+            None => (beacon_client.get_block(&BlockId::Slot(slot)).await?, None),
+        };
+
+        let beacon_block = match beacon_block {
             Some(block) => block,
             None => {
                 debug!(slot = slot, "Skipping as there is no beacon block");
+                self.record_skip(SkipReason::NoBeaconBlock);
 
                 return Ok(());
             }
@@ -75,33 +321,69 @@ impl SlotsProcessor<ReqwestTransport> {
                     slot,
                     "Skipping as beacon block doesn't contain execution payload"
                 );
+                self.record_skip(SkipReason::NoExecutionPayload);
 
                 return Ok(());
             }
         };
 
-        let has_kzg_blob_commitments = match beacon_block.message.body.blob_kzg_commitments {
-            Some(commitments) => !commitments.is_empty(),
-            None => false,
-        };
+        let kzg_blob_commitments = beacon_block
+            .message
+            .body
+            .blob_kzg_commitments
+            .map(|commitments| commitments.len())
+            .unwrap_or_default();
 
-        if !has_kzg_blob_commitments {
+        if kzg_blob_commitments == 0 {
             debug!(
                 slot,
                 "Skipping as beacon block doesn't contain blob kzg commitments"
             );
+            self.record_skip(SkipReason::NoBlobCommitments);
 
             return Ok(());
         }
 
         let execution_block_hash = execution_payload.block_hash;
 
+        Span::current().record("block_hash", display(execution_block_hash));
+
+        if self
+            .indexed_slots
+            .as_ref()
+            .is_some_and(|(indexed_slots, task)| indexed_slots.checks_blobscan(*task))
+        {
+            self.set_phase(slot, SlotPhase::CheckingIndexedBlock);
+
+            match self.context.blobscan_client().get_block(slot).await {
+                Ok(Some(block)) if block.hash == execution_block_hash => {
+                    debug!(slot, "Skipping as the block is already indexed");
+                    increment_counter("blob_indexer_skipped_indexed_slots_total", &[]);
+                    self.record_skip(SkipReason::AlreadyIndexed);
+
+                    return Ok(());
+                }
+                Ok(_) => {}
+                // The block gets indexed anyway, which is harmless
+                Err(error) => warn!(
+                    slot,
+                    ?error,
+                    "Failed to check whether the block is already indexed"
+                ),
+            }
+        }
+
         // Fetch execution block and perform some checks
 
-        let execution_block = provider
-            .get_block(execution_block_hash.into(), BlockTransactionsKind::Full)
-            .await?
-            .with_context(|| format!("Execution block {execution_block_hash} not found"))?;
+        self.set_phase(slot, SlotPhase::FetchingExecutionBlock);
+
+        let execution_block = match era_execution_block {
+            Some(execution_block) => execution_block,
+            None => {
+                self.fetch_execution_block(slot, execution_block_hash)
+                    .await?
+            }
+        };
 
         let tx_hash_to_versioned_hashes =
             create_tx_hash_versioned_hashes_mapping(&execution_block)?;
@@ -112,49 +394,193 @@ impl SlotsProcessor<ReqwestTransport> {
 
         // Fetch blobs and perform some checks
 
+        let blob_budget = self.context.blob_budget();
+
+        if blob_budget.is_exhausted(kzg_blob_commitments) {
+            self.set_phase(slot, SlotPhase::WaitingForBlobBudget);
+        }
+
+        // Held until the slot is indexed, as the blobs are kept in memory until then
+        let _blob_budget_guard = blob_budget.acquire(kzg_blob_commitments).await;
+
+        self.set_phase(slot, SlotPhase::FetchingBlobs);
+
         let blobs = match beacon_client
             .get_blobs(&BlockId::Slot(slot))
             .await
             .map_err(SlotProcessingError::ClientError)?
         {
-            Some(blobs) => {
-                if blobs.is_empty() {
-                    debug!(slot, "Skipping as blobs sidecar is empty");
-
-                    return Ok(());
-                } else {
-                    blobs
+            Some(blobs) if !blobs.is_empty() => blobs,
+            blobs => {
+                let reason = self.missing_blobs_reason(slot, blobs.is_some()).await;
+
+                match reason {
+                    SkipReason::PrunedBlobSidecar => {
+                        // The slot still counts as synced, so it's reported to be
+                        // indexed later from a node keeping every blob sidecar
+                        warn!(
+                            slot,
+                            "Skipping as the blobs sidecar was pruned by the beacon node"
+                        );
+
+                        if let Some(slot_stats) = &self.slot_stats {
+                            slot_stats.record_pruned_slot(slot);
+                        }
+
+                        self.pruned_slot = Some(slot);
+                    }
+                    SkipReason::EmptyBlobSidecar => {
+                        debug!(slot, "Skipping as blobs sidecar is empty")
+                    }
+                    _ => debug!(slot, "Skipping as there is no blobs sidecar"),
                 }
-            }
-            None => {
-                debug!(slot, "Skipping as there is no blobs sidecar");
+
+                self.record_skip(reason);
 
                 return Ok(());
             }
         };
 
+        // Catches inconsistencies between the execution and consensus clients
+        let expected_blob_gas_used = calculate_blob_gas_used(blobs.len());
+
+        if let Some(execution_blob_gas_used) = execution_block.header.blob_gas_used {
+            if execution_blob_gas_used != expected_blob_gas_used {
+                increment_counter("blob_indexer_blob_gas_mismatches_total", &[]);
+
+                warn!(
+                    slot,
+                    blobs = blobs.len(),
+                    execution_blob_gas_used,
+                    expected_blob_gas_used,
+                    "Execution block's blob gas used doesn't match the blobs sidecar"
+                );
+            }
+        }
+
+        if let Some(blob_verifier) = &self.blob_verifier {
+            self.set_phase(slot, SlotPhase::VerifyingBlobs);
+
+            blob_verifier.verify(&blobs).await?;
+
+            debug!(slot, blobs = blobs.len(), "Blobs verified");
+        }
+
         // Create entities to be indexed
 
-        let block_entity = Block::try_from((&execution_block, slot))?;
+        let mut block_entity = Block::try_from((&execution_block, slot))?;
+
+        block_entity.proposer_index = Some(beacon_block.message.proposer_index);
+        block_entity.signed_block_header = blobs
+            .iter()
+            .find_map(|blob| blob.signed_block_header.clone());
+
+        if let Some(fork_schedule) = &self.fork_schedule {
+            block_entity.fork = fork_schedule.fork_at(slot);
+
+            if let Some(update_fraction) = fork_schedule.blob_base_fee_update_fraction(slot) {
+                block_entity.blob_gas_price =
+                    calculate_blob_gas_price(block_entity.excess_blob_gas, update_fraction);
+            }
+
+            if let Some(max_blobs_per_block) = fork_schedule.max_blobs_per_block(slot) {
+                if blobs.len() > max_blobs_per_block as usize {
+                    warn!(
+                        slot,
+                        fork = ?block_entity.fork,
+                        blobs = blobs.len(),
+                        max_blobs_per_block,
+                        "Block carries more blobs than its fork allows"
+                    );
+                }
+            }
+        }
+
         let block_transactions = execution_block
             .transactions
             .as_transactions()
             .ok_or_else(|| anyhow!("Failed to parse transactions"))?;
 
-        let transactions_entities = block_transactions
+        let address_filter = self.context.address_filter();
+        let tx_categories = self.context.tx_categories();
+        let mut transactions_entities = block_transactions
             .iter()
             .filter(|tx| tx_hash_to_versioned_hashes.contains_key(&tx.hash))
-            .map(|tx| Transaction::try_from((tx, &execution_block)))
+            .filter(|tx| address_filter.matches(&tx.from, tx.to.as_ref()))
+            .map(|tx| {
+                let mut tx_entity = Transaction::try_from((tx, &execution_block))?;
+
+                tx_entity.category = tx_categories.category(&tx.input).map(str::to_string);
+
+                Ok(tx_entity)
+            })
             .collect::<Result<Vec<Transaction>>>()?;
+        let rollups = self.context.rollups();
+
+        for tx in transactions_entities.iter_mut() {
+            tx.rollup = rollups.rollup(&tx.from, tx.to.as_ref()).map(str::to_string);
 
+            if let Some(rollup) = &tx.rollup {
+                increment_counter(
+                    "blob_indexer_rollup_transactions_total",
+                    &[("rollup", rollup)],
+                );
+            }
+        }
+
+        let index_to_blob = create_index_blob_mapping(&blobs);
         let versioned_hash_to_blob = create_versioned_hash_blob_mapping(&blobs)?;
         let mut blob_entities: Vec<Blob> = vec![];
+        // Blobs are laid out in the block in the same order as the transactions
+        // referencing them, which is what the sidecar index refers to
+        let mut block_blob_index = 0;
+
+        if transactions_entities.is_empty() {
+            debug!(
+                slot,
+                "Skipping as no blob transaction matches the address filter"
+            );
+            self.record_skip(SkipReason::FilteredOut);
+
+            return Ok(());
+        }
+
+        for tx in block_transactions {
+            let Some(versioned_hashes) = tx_hash_to_versioned_hashes.get(&tx.hash) else {
+                continue;
+            };
+            let tx_hash = &tx.hash;
+
+            if !address_filter.matches(&tx.from, tx.to.as_ref()) {
+                block_blob_index += versioned_hashes.len() as u32;
+
+                continue;
+            }
 
-        for (tx_hash, versioned_hashes) in tx_hash_to_versioned_hashes.iter() {
             for (i, versioned_hash) in versioned_hashes.iter().enumerate() {
-                let blob = *versioned_hash_to_blob.get(versioned_hash).with_context(|| format!("Sidecar not found for blob {i} with versioned hash {versioned_hash} from tx {tx_hash}"))?;
+                let indexed_blob = index_to_blob.get(&block_blob_index).copied();
+                let blob = match indexed_blob {
+                    Some(blob)
+                        if calculate_versioned_hash(&blob.kzg_commitment)? == *versioned_hash =>
+                    {
+                        blob
+                    }
+                    _ => {
+                        warn!(
+                            slot,
+                            block_blob_index,
+                            %versioned_hash,
+                            %tx_hash,
+                            sidecar_found = indexed_blob.is_some(),
+                            "Sidecar index doesn't match the blob's position in the block. Matching it by versioned hash…"
+                        );
+
+                        *versioned_hash_to_blob.get(versioned_hash).with_context(|| format!("Sidecar not found for blob {i} with versioned hash {versioned_hash} from tx {tx_hash}"))?
+                    }
+                };
 
                 blob_entities.push(Blob::from((blob, versioned_hash, i, tx_hash)));
+                block_blob_index += 1;
             }
         }
 
@@ -169,15 +595,241 @@ impl SlotsProcessor<ReqwestTransport> {
             .collect::<Vec<String>>();
          */
 
+        // Transactions may reference the same blob, whose contents are only sent once
+        let blob_entities = dedup_blobs(blob_entities);
         let block_number = block_entity.number;
 
-        blobscan_client
+        self.set_phase(slot, SlotPhase::Indexing);
+
+        self.context
+            .sink()
             .index(block_entity, transactions_entities, blob_entities)
             .await
             .map_err(SlotProcessingError::ClientError)?;
 
+        increment_counter("blob_indexer_indexed_blocks_total", &[]);
+
         info!(slot, block_number, "Block indexed successfully");
 
         Ok(())
     }
+
+    fn set_phase(&self, slot: u32, phase: SlotPhase) {
+        if let Some(status) = &self.status {
+            status.set_phase(slot, phase);
+        }
+
+        // Waiting for another task isn't part of processing the slot
+        if phase != SlotPhase::WaitingForSlotClaim {
+            self.lock_timings().enter(phase);
+        }
+    }
+
+    fn record_timings(&self, slot: u32, elapsed: Duration) {
+        let phase_durations = self.lock_timings().finish();
+
+        phase_durations.record_metrics();
+
+        if let Some(slot_stats) = &self.slot_stats {
+            slot_stats.record_slot(slot, elapsed, &phase_durations);
+        }
+
+        match self.slow_slot_threshold {
+            Some(threshold) if elapsed >= threshold => {
+                increment_counter("blob_indexer_slow_slots_total", &[]);
+
+                warn!(
+                    slot,
+                    ?elapsed,
+                    slowest_phase = phase_durations
+                        .slowest()
+                        .map(|(phase, _)| phase.to_string()),
+                    phases = %phase_durations,
+                    "Slot took longer than expected to be processed"
+                );
+            }
+            _ => debug!(slot, ?elapsed, phases = %phase_durations, "Slot processed"),
+        }
+    }
+
+    /// Why the blobs sidecar of the slot is missing or empty, telling apart
+    /// the ones the beacon node pruned. Failing to tell is treated as it not
+    /// being pruned.
+    async fn missing_blobs_reason(&self, slot: u32, is_empty: bool) -> SkipReason {
+        let is_pruned = match self
+            .context
+            .blob_retention()
+            .is_pruned(self.context.beacon_client(), slot)
+            .await
+        {
+            Ok(is_pruned) => is_pruned,
+            Err(error) => {
+                warn!(
+                    slot,
+                    ?error,
+                    "Failed to check whether the blobs sidecar was pruned"
+                );
+
+                false
+            }
+        };
+
+        if is_pruned {
+            SkipReason::PrunedBlobSidecar
+        } else if is_empty {
+            SkipReason::EmptyBlobSidecar
+        } else {
+            SkipReason::NoBlobSidecar
+        }
+    }
+
+    fn record_skip(&self, reason: SkipReason) {
+        increment_counter(
+            "blob_indexer_skipped_slots_total",
+            &[("reason", reason.metric_label())],
+        );
+
+        if let Some(slot_stats) = &self.slot_stats {
+            slot_stats.record_skip(reason);
+        }
+    }
+
+    fn lock_timings(&self) -> MutexGuard<'_, SlotTimings> {
+        match self.timings.lock() {
+            Ok(timings) => timings,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    async fn fetch_execution_block(
+        &self,
+        slot: u32,
+        execution_block_hash: B256,
+    ) -> Result<ExecutionBlock, SlotProcessingError> {
+        let provider = self.context.provider();
+        let execution_block = backoff::future::retry_notify(
+            self.context
+                .backoff_profiles()
+                .execution_read
+                .exponential_backoff(),
+            || async move {
+                provider
+                    .get_block(execution_block_hash.into(), BlockTransactionsKind::Full)
+                    .await
+                    .map_err(|error| match &error {
+                        RpcError::Transport(kind) if kind.is_retry_err() => {
+                            backoff::Error::transient(error)
+                        }
+                        _ => backoff::Error::permanent(error),
+                    })
+            },
+            |error, duration: Duration| {
+                let duration = duration.as_secs();
+
+                warn!(
+                    slot,
+                    ?error,
+                    "Failed to fetch execution block. Retrying in {duration} seconds…"
+                );
+            },
+        )
+        .await?
+        .with_context(|| format!("Execution block {execution_block_hash} not found"))?;
+
+        Ok(execution_block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use alloy::primitives::B256;
+    use serde_json::json;
+
+    use super::{stats::SkipReason, SlotsProcessor};
+    use crate::{
+        clients::beacon::{
+            types::{BlockHeader, BlockHeaderMessage, InnerBlockHeader},
+            MockCommonBeaconClient,
+        },
+        context::Context,
+    };
+
+    /// Beacon node keeping the blob sidecars of the last 320 slots, with the
+    /// head at slot 1000.
+    fn pruning_beacon_client() -> MockCommonBeaconClient {
+        let mut beacon_client = MockCommonBeaconClient::new();
+
+        beacon_client.expect_get_spec().returning(|| {
+            let spec = HashMap::from([
+                ("SLOTS_PER_EPOCH".to_string(), json!("32")),
+                (
+                    "MIN_EPOCHS_FOR_BLOB_SIDECARS_REQUESTS".to_string(),
+                    json!("10"),
+                ),
+            ]);
+
+            Box::pin(async move { Ok(Some(spec)) })
+        });
+        beacon_client.expect_get_block_header().returning(|_| {
+            let header = BlockHeader {
+                root: B256::ZERO,
+                header: InnerBlockHeader {
+                    message: BlockHeaderMessage {
+                        parent_root: B256::ZERO,
+                        slot: 1000,
+                    },
+                },
+            };
+
+            Box::pin(async move { Ok(Some(header)) })
+        });
+
+        beacon_client
+    }
+
+    #[tokio::test]
+    async fn pruned_sidecars_are_told_apart_from_missing_ones() {
+        let slots_processor = SlotsProcessor::new(Context::with_mocks(
+            Some(pruning_beacon_client()),
+            None,
+            None,
+        ));
+
+        // Not found, or found empty, past the retention window
+        for is_empty in [false, true] {
+            assert_eq!(
+                slots_processor.missing_blobs_reason(100, is_empty).await,
+                SkipReason::PrunedBlobSidecar
+            );
+        }
+
+        // Not found within the retention window
+        assert_eq!(
+            slots_processor.missing_blobs_reason(900, false).await,
+            SkipReason::NoBlobSidecar
+        );
+        assert_eq!(
+            slots_processor.missing_blobs_reason(900, true).await,
+            SkipReason::EmptyBlobSidecar
+        );
+    }
+
+    #[tokio::test]
+    async fn sidecars_are_not_considered_pruned_when_the_window_is_unknown() {
+        let mut beacon_client = MockCommonBeaconClient::new();
+
+        beacon_client
+            .expect_get_spec()
+            .returning(|| Box::pin(async { Ok(None) }));
+
+        let slots_processor =
+            SlotsProcessor::new(Context::with_mocks(Some(beacon_client), None, None));
+
+        assert_eq!(
+            slots_processor.missing_blobs_reason(100, false).await,
+            SkipReason::NoBlobSidecar
+        );
+    }
 }
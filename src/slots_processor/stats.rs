@@ -0,0 +1,266 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BTreeMap, BinaryHeap},
+    fmt,
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+
+use tracing::{info, warn};
+
+use crate::utils::roaring::RoaringBitmap;
+
+use super::timings::PhaseDurations;
+
+/// Amount of slowest slots reported at the end of a ranged sync.
+const REPORTED_SLOW_SLOTS: usize = 10;
+
+/// Why a slot was processed without indexing anything.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SkipReason {
+    NoBeaconBlock,
+    NoExecutionPayload,
+    NoBlobCommitments,
+    NoBlobSidecar,
+    EmptyBlobSidecar,
+    /// Older than the window the beacon node keeps blob sidecars for
+    PrunedBlobSidecar,
+    /// Indexed by another task or a previous run
+    AlreadyIndexed,
+    /// None of the blob transactions matched the address filter
+    FilteredOut,
+}
+
+/// Processing time of a slot, ordered by how long it took.
+#[derive(Clone, Debug)]
+pub struct SlowSlot {
+    pub slot: u32,
+    pub elapsed: Duration,
+    pub phases: PhaseDurations,
+}
+
+#[derive(Debug, Default)]
+struct SlotStatsState {
+    processed_slots: u64,
+    /// Min-heap holding the slowest slots seen so far
+    slowest_slots: BinaryHeap<Reverse<SlowSlot>>,
+    skip_reasons: BTreeMap<SkipReason, u64>,
+    /// Slots skipped as their blobs sidecar was pruned by the beacon node
+    pruned_slots: RoaringBitmap,
+}
+
+/// Collects the slowest slots and the reasons slots got skipped for during a
+/// ranged sync, reported once it's done to spot systemic issues with the
+/// nodes.
+#[derive(Debug, Default)]
+pub struct SlotStats {
+    state: Mutex<SlotStatsState>,
+}
+
+impl SlotStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_slot(&self, slot: u32, elapsed: Duration, phases: &PhaseDurations) {
+        let mut state = self.lock();
+
+        state.processed_slots += 1;
+        state.slowest_slots.push(Reverse(SlowSlot {
+            slot,
+            elapsed,
+            phases: phases.clone(),
+        }));
+
+        if state.slowest_slots.len() > REPORTED_SLOW_SLOTS {
+            state.slowest_slots.pop();
+        }
+    }
+
+    pub fn record_skip(&self, reason: SkipReason) {
+        *self.lock().skip_reasons.entry(reason).or_default() += 1;
+    }
+
+    pub fn record_pruned_slot(&self, slot: u32) {
+        self.lock().pruned_slots.insert(slot);
+    }
+
+    /// Lowest and highest slots skipped as their blobs sidecar was pruned,
+    /// along with the amount of them.
+    pub fn pruned_slots(&self) -> Option<(u32, u32, usize)> {
+        let state = self.lock();
+        let lowest_slot = state.pruned_slots.iter().next()?;
+        let highest_slot = state.pruned_slots.iter().last()?;
+
+        Some((lowest_slot, highest_slot, state.pruned_slots.len()))
+    }
+
+    /// Slowest slots, from the slowest one.
+    pub fn slowest_slots(&self) -> Vec<SlowSlot> {
+        let mut slowest_slots = self
+            .lock()
+            .slowest_slots
+            .iter()
+            .map(|Reverse(slow_slot)| slow_slot.clone())
+            .collect::<Vec<_>>();
+
+        slowest_slots.sort_by(|a, b| b.cmp(a));
+
+        slowest_slots
+    }
+
+    /// Skip reasons along with the amount of slots skipped for them, from the
+    /// most common one.
+    pub fn skip_reasons(&self) -> Vec<(SkipReason, u64)> {
+        let mut skip_reasons = self
+            .lock()
+            .skip_reasons
+            .iter()
+            .map(|(reason, count)| (*reason, *count))
+            .collect::<Vec<_>>();
+
+        skip_reasons.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+        skip_reasons
+    }
+
+    /// Logs the slowest slots and the most common skip reasons.
+    pub fn log_report(&self) {
+        let processed_slots = self.lock().processed_slots;
+
+        info!(processed_slots, "Slot processing report");
+
+        for (rank, slow_slot) in self.slowest_slots().iter().enumerate() {
+            info!(
+                rank = rank + 1,
+                slot = slow_slot.slot,
+                elapsed = ?slow_slot.elapsed,
+                phases = %slow_slot.phases,
+                "Slow slot"
+            );
+        }
+
+        for (reason, slots) in self.skip_reasons() {
+            info!(%reason, slots, "Skipped slots");
+        }
+
+        if let Some((lowest_slot, highest_slot, slots)) = self.pruned_slots() {
+            warn!(
+                slots,
+                lowest_slot,
+                highest_slot,
+                "Blob sidecars were pruned by the beacon node, so their slots weren't indexed. Index them with --ranges {lowest_slot}-{} from a node keeping every blob sidecar",
+                highest_slot + 1
+            );
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SlotStatsState> {
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+impl SkipReason {
+    /// Name the reason is labeled with in the metrics.
+    pub fn metric_label(&self) -> &'static str {
+        match self {
+            SkipReason::NoBeaconBlock => "no_beacon_block",
+            SkipReason::NoExecutionPayload => "no_execution_payload",
+            SkipReason::NoBlobCommitments => "no_blob_commitments",
+            SkipReason::NoBlobSidecar => "no_blob_sidecar",
+            SkipReason::EmptyBlobSidecar => "empty_blob_sidecar",
+            SkipReason::PrunedBlobSidecar => "pruned_blob_sidecar",
+            SkipReason::AlreadyIndexed => "already_indexed",
+            SkipReason::FilteredOut => "filtered_out",
+        }
+    }
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            SkipReason::NoBeaconBlock => "no beacon block",
+            SkipReason::NoExecutionPayload => "no execution payload",
+            SkipReason::NoBlobCommitments => "no blob commitments",
+            SkipReason::NoBlobSidecar => "no blob sidecar",
+            SkipReason::EmptyBlobSidecar => "empty blob sidecar",
+            SkipReason::PrunedBlobSidecar => "pruned blob sidecar",
+            SkipReason::AlreadyIndexed => "already indexed",
+            SkipReason::FilteredOut => "filtered out",
+        };
+
+        f.write_str(reason)
+    }
+}
+
+impl PartialEq for SlowSlot {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SlowSlot {}
+
+impl PartialOrd for SlowSlot {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SlowSlot {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.elapsed, self.slot).cmp(&(other.elapsed, other.slot))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::slots_processor::timings::PhaseDurations;
+
+    use super::{SkipReason, SlotStats, REPORTED_SLOW_SLOTS};
+
+    #[test]
+    fn only_the_slowest_slots_are_kept() {
+        let stats = SlotStats::new();
+
+        for slot in 0..30 {
+            stats.record_slot(
+                slot,
+                Duration::from_millis((slot as u64 * 7) % 30),
+                &PhaseDurations::default(),
+            );
+        }
+
+        stats.record_skip(SkipReason::NoBlobCommitments);
+        stats.record_skip(SkipReason::NoBeaconBlock);
+        stats.record_skip(SkipReason::NoBlobCommitments);
+
+        let slowest_slots = stats.slowest_slots();
+
+        assert_eq!(slowest_slots.len(), REPORTED_SLOW_SLOTS);
+        assert_eq!(slowest_slots[0].elapsed, Duration::from_millis(29));
+        assert_eq!(
+            slowest_slots[REPORTED_SLOW_SLOTS - 1].elapsed,
+            Duration::from_millis(20)
+        );
+        assert_eq!(
+            stats.skip_reasons(),
+            vec![
+                (SkipReason::NoBlobCommitments, 2),
+                (SkipReason::NoBeaconBlock, 1)
+            ]
+        );
+        assert_eq!(stats.pruned_slots(), None);
+
+        for slot in [70_000, 5, 9] {
+            stats.record_pruned_slot(slot);
+        }
+
+        assert_eq!(stats.pruned_slots(), Some((5, 70_000, 3)));
+    }
+}
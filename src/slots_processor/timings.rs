@@ -0,0 +1,107 @@
+use std::{
+    fmt, mem,
+    time::{Duration, Instant},
+};
+
+use crate::{metrics::add_to_counter, synchronizer::watchdog::SlotPhase};
+
+/// Time spent on every phase of a slot's processing, telling whether the
+/// consensus node, the execution node or the Blobscan API is slowing it down.
+#[derive(Debug, Default)]
+pub struct SlotTimings {
+    phases: Vec<(SlotPhase, Duration)>,
+    current_phase: Option<(SlotPhase, Instant)>,
+}
+
+impl SlotTimings {
+    /// Ends the current phase, if any, and starts timing the given one.
+    pub fn enter(&mut self, phase: SlotPhase) {
+        self.end_current_phase();
+        self.current_phase = Some((phase, Instant::now()));
+    }
+
+    /// Ends the current phase and returns the time spent on every phase,
+    /// leaving the timings ready for the next slot.
+    pub fn finish(&mut self) -> PhaseDurations {
+        self.end_current_phase();
+
+        PhaseDurations(mem::take(&mut self.phases))
+    }
+
+    fn end_current_phase(&mut self) {
+        if let Some((phase, started_at)) = self.current_phase.take() {
+            self.phases.push((phase, started_at.elapsed()));
+        }
+    }
+}
+
+/// Durations of the phases a slot went through, in the order they happened.
+#[derive(Clone, Debug, Default)]
+pub struct PhaseDurations(Vec<(SlotPhase, Duration)>);
+
+impl PhaseDurations {
+    pub fn total(&self) -> Duration {
+        self.0.iter().map(|(_, duration)| *duration).sum()
+    }
+
+    /// Phase the slot spent the most time on.
+    pub fn slowest(&self) -> Option<(SlotPhase, Duration)> {
+        self.0.iter().max_by_key(|(_, duration)| *duration).copied()
+    }
+
+    /// Adds the durations to the per-phase totals exported as metrics.
+    pub fn record_metrics(&self) {
+        for (phase, duration) in self.0.iter() {
+            let labels = [("phase", phase.metric_label())];
+
+            add_to_counter(
+                "blob_indexer_slot_phase_duration_milliseconds_total",
+                &labels,
+                duration.as_millis() as u64,
+            );
+            add_to_counter("blob_indexer_slot_phases_total", &labels, 1);
+        }
+    }
+}
+
+impl fmt::Display for PhaseDurations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (phase, duration)) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+
+            write!(f, "{phase}: {}ms", duration.as_millis())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::synchronizer::watchdog::SlotPhase;
+
+    use super::PhaseDurations;
+
+    #[test]
+    fn phase_durations_are_summarized() {
+        let durations = PhaseDurations(vec![
+            (SlotPhase::FetchingBeaconBlock, Duration::from_millis(120)),
+            (SlotPhase::FetchingBlobs, Duration::from_millis(900)),
+            (SlotPhase::Indexing, Duration::from_millis(300)),
+        ]);
+
+        assert_eq!(durations.total(), Duration::from_millis(1320));
+        assert_eq!(
+            durations.slowest(),
+            Some((SlotPhase::FetchingBlobs, Duration::from_millis(900)))
+        );
+        assert_eq!(
+            durations.to_string(),
+            "fetching beacon block: 120ms, fetching blobs: 900ms, indexing: 300ms"
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use std::pin::Pin;
+
+use anyhow::{anyhow, Result as AnyhowResult};
+use futures::{stream, Stream};
+use tokio::{
+    sync::mpsc::{self, Receiver},
+    task::JoinHandle,
+};
+
+use crate::{
+    clients::beacon::types::BlockId,
+    context::{Config as ContextConfig, Context},
+    sinks::channel::{ChannelSink, IndexedBlock},
+    synchronizer::{
+        error::SynchronizerError, CheckpointType, CommonSynchronizer, SynchronizerBuilder,
+    },
+};
+
+pub type IndexedBlockStream =
+    Pin<Box<dyn Stream<Item = Result<IndexedBlock, SynchronizerError>> + Send>>;
+
+const INDEXED_BLOCKS_BUFFER_SIZE: usize = 32;
+
+struct StreamState {
+    receiver: Receiver<IndexedBlock>,
+    sync_handle: Option<JoinHandle<Result<(), SynchronizerError>>>,
+}
+
+/// Indexes the given block range and yields every indexed block instead of
+/// sending it to Blobscan. No sync state is saved.
+///
+/// Blocks are yielded as soon as they're processed, so they're not guaranteed
+/// to be ordered when using more than one thread. The stream ends after the
+/// whole range has been indexed or right after yielding the first error.
+pub fn index_blocks(
+    config: ContextConfig,
+    initial_block_id: BlockId,
+    final_block_id: BlockId,
+    num_threads: u32,
+) -> AnyhowResult<IndexedBlockStream> {
+    let (sender, receiver) = mpsc::channel(INDEXED_BLOCKS_BUFFER_SIZE);
+    let context = Context::try_with_sink(config, Box::new(ChannelSink::new(sender)))?;
+    let synchronizer = SynchronizerBuilder::new()
+        .with_checkpoint_type(CheckpointType::Disabled)
+        .with_num_threads(num_threads)
+        .build(Box::new(context));
+
+    let sync_handle =
+        tokio::spawn(async move { synchronizer.run(&initial_block_id, &final_block_id).await });
+
+    let state = StreamState {
+        receiver,
+        sync_handle: Some(sync_handle),
+    };
+
+    Ok(Box::pin(stream::unfold(state, |mut state| async move {
+        if let Some(indexed_block) = state.receiver.recv().await {
+            return Some((Ok(indexed_block), state));
+        }
+
+        // All senders are dropped once the synchronizer is done
+        let sync_result = match state.sync_handle.take()?.await {
+            Ok(result) => result,
+            Err(error) => Err(anyhow!("Synchronizer task panicked: {:?}", error).into()),
+        };
+
+        sync_result.err().map(|error| (Err(error), state))
+    })))
+}
@@ -0,0 +1,132 @@
+use std::{fs, io, path::PathBuf};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::clients::blobscan::types::BlockchainSyncState;
+
+use super::{checkpoint::CheckpointPolicy, CheckpointType};
+
+/// Order the historical slots are filled in.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BackfillDirection {
+    /// From the lowest synced slot down to the Dencun fork, newest first
+    #[default]
+    Backward,
+    /// From the Dencun fork up to the lowest synced slot, oldest first
+    Forward,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct BackfillProgress {
+    last_synced_slot: u32,
+}
+
+/// Checkpoint of a backfill synced forward. Blobscan's sync state holds a
+/// single synced range, which a forward backfill only joins once it reaches
+/// it, so its progress is kept in a local file until then and the lower bound
+/// is saved once it's done.
+#[derive(Debug)]
+pub struct ForwardBackfillCheckpoint {
+    path: PathBuf,
+    /// First slot of the backfill
+    start_slot: u32,
+    /// Lowest slot already synced, where the backfill stops
+    end_slot: u32,
+    checkpoint_type: CheckpointType,
+}
+
+impl ForwardBackfillCheckpoint {
+    pub fn new(
+        path: PathBuf,
+        start_slot: u32,
+        end_slot: u32,
+        checkpoint_type: CheckpointType,
+    ) -> Self {
+        Self {
+            path,
+            start_slot,
+            end_slot,
+            checkpoint_type,
+        }
+    }
+
+    /// Returns the last slot synced by an interrupted backfill, if any.
+    pub fn load(&self) -> io::Result<Option<u32>> {
+        let content = match fs::read(&self.path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        let progress: BackfillProgress = serde_json::from_slice(&content)?;
+
+        Ok(Some(progress.last_synced_slot))
+    }
+
+    fn save(&self, last_synced_slot: u32) -> io::Result<()> {
+        let content = serde_json::to_vec(&BackfillProgress { last_synced_slot })?;
+
+        fs::write(&self.path, content)
+    }
+}
+
+impl CheckpointPolicy for ForwardBackfillCheckpoint {
+    fn sync_state(&self, _first_slot: u32, last_slot: u32) -> Option<BlockchainSyncState> {
+        if last_slot + 1 < self.end_slot {
+            if let Err(error) = self.save(last_slot) {
+                warn!(
+                    path = %self.path.display(),
+                    ?error,
+                    "Failed to save the forward backfill checkpoint"
+                );
+            }
+
+            return None;
+        }
+
+        if let Err(error) = fs::remove_file(&self.path) {
+            if error.kind() != io::ErrorKind::NotFound {
+                warn!(
+                    path = %self.path.display(),
+                    ?error,
+                    "Failed to remove the forward backfill checkpoint"
+                );
+            }
+        }
+
+        // Saved as if synced backwards, so the lower bound is where the
+        // backfill started from
+        self.checkpoint_type.sync_state(last_slot, self.start_slot)
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.checkpoint_type.is_enabled()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::ForwardBackfillCheckpoint;
+    use crate::synchronizer::{checkpoint::CheckpointPolicy, CheckpointType};
+
+    #[test]
+    fn lower_bound_is_saved_once_the_synced_range_is_reached() {
+        let path = env::temp_dir().join(format!("backfill-{}.json", process::id()));
+        let checkpoint =
+            ForwardBackfillCheckpoint::new(path.clone(), 100, 200, CheckpointType::Lower);
+
+        assert_eq!(checkpoint.sync_state(100, 149), None);
+        assert_eq!(checkpoint.load().unwrap(), Some(149));
+
+        let sync_state = checkpoint.sync_state(150, 199).unwrap();
+
+        assert_eq!(sync_state.last_lower_synced_slot, Some(100));
+        assert_eq!(sync_state.last_upper_synced_slot, None);
+        assert_eq!(checkpoint.load().unwrap(), None);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,48 @@
+use std::fmt::Debug;
+
+#[cfg(test)]
+use mockall::automock;
+
+use crate::clients::blobscan::types::BlockchainSyncState;
+
+use super::CheckpointType;
+
+/// Decides which sync state a synchronizer saves as it makes progress.
+#[cfg_attr(test, automock)]
+pub trait CheckpointPolicy: Send + Sync + Debug {
+    /// Returns the sync state to save once the slots from `first_slot` to
+    /// `last_slot`, both included, are synced. `first_slot` is greater than
+    /// `last_slot` for ranges synced backwards. Nothing is saved when `None`
+    /// is returned.
+    fn sync_state(&self, first_slot: u32, last_slot: u32) -> Option<BlockchainSyncState>;
+
+    /// Whether the policy saves anything at all. Synchronizers skip tracking
+    /// their progress when it doesn't.
+    fn is_enabled(&self) -> bool {
+        true
+    }
+}
+
+impl CheckpointPolicy for CheckpointType {
+    fn sync_state(&self, first_slot: u32, last_slot: u32) -> Option<BlockchainSyncState> {
+        let (last_lower_synced_slot, last_upper_synced_slot) = match self {
+            CheckpointType::Disabled => return None,
+            CheckpointType::Lower => (Some(last_slot), None),
+            CheckpointType::Upper => (None, Some(last_slot)),
+            CheckpointType::Both => (
+                Some(first_slot.min(last_slot)),
+                Some(first_slot.max(last_slot)),
+            ),
+        };
+
+        Some(BlockchainSyncState {
+            last_finalized_block: None,
+            last_lower_synced_slot,
+            last_upper_synced_slot,
+        })
+    }
+
+    fn is_enabled(&self) -> bool {
+        *self != CheckpointType::Disabled
+    }
+}
@@ -0,0 +1,146 @@
+use std::{
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use tracing::{debug, info};
+
+/// Amount of processed slots observed before reconsidering the concurrency
+const WINDOW_SIZE: u32 = 20;
+/// Error rate above which the concurrency is halved
+const MAX_ERROR_RATE: f64 = 0.1;
+/// Latency increase over the best observed one tolerated before scaling down
+const MAX_LATENCY_RATIO: f64 = 2.0;
+/// Latency increase over the best observed one under which it's safe to scale up
+const SCALE_UP_LATENCY_RATIO: f64 = 1.25;
+
+#[derive(Debug, Default)]
+struct Window {
+    samples: u32,
+    errors: u32,
+    rate_limited: u32,
+    total_latency: Duration,
+    best_latency: Option<Duration>,
+}
+
+/// Adjusts the amount of concurrent slot-processing workers based on the
+/// latency and error rate observed while processing slots.
+///
+/// It follows an additive-increase/multiplicative-decrease strategy: workers are
+/// added one at a time while latency stays close to the best one observed and
+/// halved as soon as providers rate limit requests or start failing.
+#[derive(Debug)]
+pub struct AdaptiveConcurrency {
+    min_workers: u32,
+    max_workers: u32,
+    current_workers: AtomicU32,
+    window: Mutex<Window>,
+}
+
+impl AdaptiveConcurrency {
+    pub fn new(min_workers: u32, max_workers: u32) -> Self {
+        let min_workers = min_workers.max(1);
+        let max_workers = max_workers.max(min_workers);
+
+        Self {
+            min_workers,
+            max_workers,
+            current_workers: AtomicU32::new(min_workers),
+            window: Mutex::new(Window::default()),
+        }
+    }
+
+    pub fn current_workers(&self) -> u32 {
+        self.current_workers.load(Ordering::Relaxed)
+    }
+
+    pub fn record_success(&self, latency: Duration) {
+        self.record(|window| window.total_latency += latency);
+    }
+
+    pub fn record_failure(&self, rate_limited: bool) {
+        self.record(|window| {
+            window.errors += 1;
+
+            if rate_limited {
+                window.rate_limited += 1;
+            }
+        });
+    }
+
+    fn record(&self, update: impl FnOnce(&mut Window)) {
+        let mut window = match self.window.lock() {
+            Ok(window) => window,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        window.samples += 1;
+        update(&mut window);
+
+        if window.samples >= WINDOW_SIZE {
+            self.adjust(&mut window);
+        }
+    }
+
+    fn adjust(&self, window: &mut Window) {
+        let current_workers = self.current_workers();
+        let successes = window.samples - window.errors;
+        let error_rate = window.errors as f64 / window.samples as f64;
+        let avg_latency = if successes > 0 {
+            Some(window.total_latency / successes)
+        } else {
+            None
+        };
+
+        if let Some(avg_latency) = avg_latency {
+            window.best_latency = Some(match window.best_latency {
+                Some(best_latency) => best_latency.min(avg_latency),
+                None => avg_latency,
+            });
+        }
+
+        let latency_ratio = match (avg_latency, window.best_latency) {
+            (Some(avg_latency), Some(best_latency)) if !best_latency.is_zero() => {
+                avg_latency.as_secs_f64() / best_latency.as_secs_f64()
+            }
+            _ => 1.0,
+        };
+
+        let new_workers = if window.rate_limited > 0 || error_rate > MAX_ERROR_RATE {
+            current_workers / 2
+        } else if latency_ratio > MAX_LATENCY_RATIO {
+            current_workers - 1
+        } else if latency_ratio <= SCALE_UP_LATENCY_RATIO {
+            current_workers + 1
+        } else {
+            current_workers
+        }
+        .clamp(self.min_workers, self.max_workers);
+
+        if new_workers != current_workers {
+            info!(
+                previous_workers = current_workers,
+                new_workers,
+                error_rate,
+                rate_limited = window.rate_limited,
+                latency_ratio,
+                "Adjusting slot processing concurrency"
+            );
+
+            self.current_workers.store(new_workers, Ordering::Relaxed);
+        } else {
+            debug!(
+                workers = current_workers,
+                error_rate, latency_ratio, "Concurrency unchanged"
+            );
+        }
+
+        *window = Window {
+            best_latency: window.best_latency,
+            ..Default::default()
+        };
+    }
+}
@@ -1,5 +1,7 @@
 use crate::{clients::beacon::types::BlockId, slots_processor::error::SlotsProcessorError};
 
+use super::intent::IntentLogError;
+
 #[derive(Debug, thiserror::Error)]
 pub enum SynchronizerError {
     #[error(
@@ -20,12 +22,52 @@ pub enum SynchronizerError {
         slot: u32,
         error: crate::clients::common::ClientError,
     },
+    #[error("Failed to update intent log: {0}")]
+    FailedIntentLogUpdate(#[from] IntentLogError),
     #[error(transparent)]
     FailedSlotsProcessing(#[from] SlotsProcessorError),
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
 
+impl SynchronizerError {
+    /// Whether the failure is transient and syncing may succeed if retried.
+    /// Failing to update the intent log is fatal as retrying would sync slots
+    /// the log doesn't know about.
+    pub fn is_recoverable(&self) -> bool {
+        match self {
+            SynchronizerError::FailedParallelSlotsProcessing { chunk_errors, .. } => chunk_errors
+                .0
+                .iter()
+                .all(SlotsProcessorError::is_recoverable),
+            SynchronizerError::FailedBlockIdResolution { error, .. } => error.is_recoverable(),
+            SynchronizerError::FailedSlotCheckpointSave { error, .. } => error.is_recoverable(),
+            SynchronizerError::FailedIntentLogUpdate(_) => false,
+            SynchronizerError::FailedSlotsProcessing(error) => error.is_recoverable(),
+            SynchronizerError::Other(_) => true,
+        }
+    }
+
+    /// Returns the slots whose processing failed, in ascending order.
+    pub fn failed_slots(&self) -> Vec<u32> {
+        let mut failed_slots = match self {
+            SynchronizerError::FailedParallelSlotsProcessing { chunk_errors, .. } => chunk_errors
+                .0
+                .iter()
+                .filter_map(SlotsProcessorError::failed_slot)
+                .collect(),
+            SynchronizerError::FailedSlotsProcessing(error) => {
+                error.failed_slot().into_iter().collect()
+            }
+            _ => vec![],
+        };
+
+        failed_slots.sort_unstable();
+
+        failed_slots
+    }
+}
+
 #[derive(Debug)]
 pub struct SlotsChunksErrors(pub Vec<SlotsProcessorError>);
 
@@ -0,0 +1,163 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::job::SlotRange;
+
+const INTENT_LOG_VERSION: u32 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum IntentLogError {
+    #[error("failed to access intent log {path}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        error: io::Error,
+    },
+    #[error("failed to parse intent log {path}")]
+    Serde {
+        path: PathBuf,
+        #[source]
+        error: serde_json::Error,
+    },
+    #[error("unsupported intent log version {0}")]
+    UnsupportedVersion(u32),
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct IntentLogFile {
+    version: u32,
+    pending: Vec<SlotRange>,
+}
+
+/// Write-ahead journal of the slot ranges being synced. Ranges are recorded
+/// before being synced and confirmed once their checkpoint is saved, so the
+/// ones left unconfirmed after a crash can be synced again on startup.
+#[derive(Debug)]
+pub struct IntentLog {
+    path: PathBuf,
+    pending: Mutex<Vec<SlotRange>>,
+}
+
+impl IntentLog {
+    /// Opens the intent log at the given path, loading the ranges left pending
+    /// by a previous run if it exists.
+    pub fn open(path: &Path) -> Result<Self, IntentLogError> {
+        let pending = match fs::read_to_string(path) {
+            Ok(contents) => {
+                let file: IntentLogFile =
+                    serde_json::from_str(&contents).map_err(|error| IntentLogError::Serde {
+                        path: path.to_path_buf(),
+                        error,
+                    })?;
+
+                if file.version != INTENT_LOG_VERSION {
+                    return Err(IntentLogError::UnsupportedVersion(file.version));
+                }
+
+                file.pending
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => vec![],
+            Err(error) => {
+                return Err(IntentLogError::Io {
+                    path: path.to_path_buf(),
+                    error,
+                })
+            }
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            pending: Mutex::new(pending),
+        })
+    }
+
+    /// Returns the ranges recorded but not confirmed yet.
+    pub fn pending(&self) -> Vec<SlotRange> {
+        self.lock().clone()
+    }
+
+    pub fn record(&self, range: SlotRange) -> Result<(), IntentLogError> {
+        let mut pending = self.lock();
+
+        if !pending.contains(&range) {
+            pending.push(range);
+        }
+
+        self.save(&pending)
+    }
+
+    pub fn confirm(&self, range: SlotRange) -> Result<(), IntentLogError> {
+        let mut pending = self.lock();
+
+        pending.retain(|pending_range| *pending_range != range);
+
+        self.save(&pending)
+    }
+
+    /// Writes the log to a temporary file first so an interrupted write can't
+    /// leave a truncated log behind.
+    fn save(&self, pending: &[SlotRange]) -> Result<(), IntentLogError> {
+        let file = IntentLogFile {
+            version: INTENT_LOG_VERSION,
+            pending: pending.to_vec(),
+        };
+        let contents =
+            serde_json::to_string_pretty(&file).map_err(|error| IntentLogError::Serde {
+                path: self.path.clone(),
+                error,
+            })?;
+        let tmp_path = self.path.with_extension("tmp");
+        let io_error = |error| IntentLogError::Io {
+            path: self.path.clone(),
+            error,
+        };
+
+        fs::write(&tmp_path, contents).map_err(io_error)?;
+        fs::rename(&tmp_path, &self.path).map_err(io_error)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<SlotRange>> {
+        match self.pending.lock() {
+            Ok(pending) => pending,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::{IntentLog, SlotRange};
+
+    #[test]
+    fn unconfirmed_intents_are_pending_after_reopening() {
+        let path = env::temp_dir().join(format!("intent-log-{}.json", process::id()));
+        let confirmed = SlotRange::new(100, 200);
+        let interrupted = SlotRange::new(200, 300);
+        let intent_log = IntentLog::open(&path).unwrap();
+
+        intent_log.record(confirmed).unwrap();
+        intent_log.confirm(confirmed).unwrap();
+        intent_log.record(interrupted).unwrap();
+
+        // Crashes before confirming the second range
+        drop(intent_log);
+
+        let intent_log = IntentLog::open(&path).unwrap();
+
+        assert_eq!(intent_log.pending(), vec![interrupted]);
+
+        // Replaying it confirms it once synced again
+        intent_log.confirm(interrupted).unwrap();
+
+        assert!(IntentLog::open(&path).unwrap().pending().is_empty());
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,233 @@
+use std::{fmt, fs, io, path::Path, str::FromStr, sync::Mutex};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+const MANIFEST_VERSION: u32 = 1;
+
+/// Range of slots to be synced, from `from` (inclusive) to `to` (exclusive).
+/// `from` is greater than `to` for ranges synced backwards.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SlotRange {
+    pub from: u32,
+    pub to: u32,
+}
+
+/// Remaining work of a bounded indexing job, persisted on shutdown so the job
+/// can be resumed later with `--resume-job`.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JobManifest {
+    pub version: u32,
+    pub created_at: String,
+    pub ranges: Vec<SlotRange>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum JobManifestError {
+    #[error("failed to access job manifest")]
+    Io(#[from] io::Error),
+    #[error("failed to parse job manifest")]
+    Serde(#[from] serde_json::Error),
+    #[error("unsupported job manifest version {0}")]
+    UnsupportedVersion(u32),
+}
+
+#[derive(Debug, Default)]
+struct Progress {
+    /// Work units of the chunk being synced, shrunk as slots get synced
+    units: Vec<SlotRange>,
+    /// Rest of the range being synced, after the current chunk
+    pending: Option<SlotRange>,
+    /// Ranges to be synced after the current one
+    queued: Vec<SlotRange>,
+}
+
+/// Keeps track of the slots a synchronizer still has to sync, so the remaining
+/// work can be saved when the job is interrupted.
+#[derive(Debug, Default)]
+pub struct JobProgress {
+    progress: Mutex<Progress>,
+}
+
+impl SlotRange {
+    pub fn new(from: u32, to: u32) -> Self {
+        Self { from, to }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.from == self.to
+    }
+
+    pub fn len(&self) -> u32 {
+        self.from.abs_diff(self.to)
+    }
+
+    /// Returns the `len` slots of the range starting `offset` slots after its
+    /// start, in sync order.
+    pub fn slice(&self, offset: u32, len: u32) -> SlotRange {
+        if self.from <= self.to {
+            SlotRange::new(self.from + offset, self.from + offset + len)
+        } else {
+            SlotRange::new(self.from - offset, self.from - offset - len)
+        }
+    }
+
+    /// Whether the slot is part of the range. Reverse ranges go from `from`
+    /// down to `to`, excluding `from` just like forward ranges exclude `to`.
+    pub fn contains(&self, slot: u32) -> bool {
+        if self.from < self.to {
+            (self.from..self.to).contains(&slot)
+        } else {
+            (self.to..self.from).contains(&slot)
+        }
+    }
+
+    /// Whether both ranges have any slot in common.
+    pub fn overlaps(&self, other: &SlotRange) -> bool {
+        !self.is_empty()
+            && !other.is_empty()
+            && self.from.min(self.to) < other.from.max(other.to)
+            && other.from.min(other.to) < self.from.max(self.to)
+    }
+
+    /// Returns the first slot synced, if any.
+    pub fn first(&self) -> Option<u32> {
+        match self.from.cmp(&self.to) {
+            std::cmp::Ordering::Less => Some(self.from),
+            std::cmp::Ordering::Greater => Some(self.from - 1),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+
+    /// Drops the slots of the range up to the given one, if it's part of it.
+    pub(super) fn advance_past(&mut self, slot: u32) {
+        if !self.contains(slot) {
+            return;
+        }
+
+        self.from = if self.from < self.to { slot + 1 } else { slot };
+    }
+}
+
+impl fmt::Display for SlotRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.from, self.to)
+    }
+}
+
+impl FromStr for SlotRange {
+    type Err = String;
+
+    /// Parses a `FROM-TO` range of slots.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((from, to)) = s.split_once('-') else {
+            return Err(format!("Invalid slot range: {s}. Expected FROM-TO."));
+        };
+        let parse_slot = |slot: &str| {
+            slot.trim()
+                .parse::<u32>()
+                .map_err(|_| format!("Invalid slot range: {s}. Expected FROM-TO slot numbers."))
+        };
+        let range = SlotRange::new(parse_slot(from)?, parse_slot(to)?);
+
+        if range.is_empty() {
+            return Err(format!("Empty slot range: {s}"));
+        }
+
+        Ok(range)
+    }
+}
+
+impl JobProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_queued(&self, ranges: Vec<SlotRange>) {
+        self.lock().queued = ranges;
+    }
+
+    pub(super) fn set_pending(&self, pending: SlotRange) {
+        self.lock().pending = Some(pending);
+    }
+
+    pub(super) fn start_chunk(&self, units: Vec<SlotRange>) {
+        self.lock().units = units;
+    }
+
+    pub(super) fn record_synced_slot(&self, unit: usize, slot: u32) {
+        if let Some(range) = self.lock().units.get_mut(unit) {
+            range.advance_past(slot);
+        }
+    }
+
+    /// Returns the ranges still to be synced, in the order they would be synced.
+    /// Ranges following each other, like untouched work units, get merged.
+    pub fn remaining_ranges(&self) -> Vec<SlotRange> {
+        let progress = self.lock();
+        let mut ranges: Vec<SlotRange> = vec![];
+
+        for range in progress
+            .units
+            .iter()
+            .chain(progress.pending.iter())
+            .chain(progress.queued.iter())
+            .filter(|range| !range.is_empty())
+        {
+            match ranges.last_mut() {
+                Some(last)
+                    if last.to == range.from
+                        && (last.from < last.to) == (range.from < range.to) =>
+                {
+                    last.to = range.to;
+                }
+                _ => ranges.push(*range),
+            }
+        }
+
+        ranges
+    }
+
+    pub fn to_manifest(&self) -> JobManifest {
+        JobManifest::new(self.remaining_ranges())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Progress> {
+        match self.progress.lock() {
+            Ok(progress) => progress,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+impl JobManifest {
+    pub fn new(ranges: Vec<SlotRange>) -> Self {
+        Self {
+            version: MANIFEST_VERSION,
+            created_at: Utc::now().to_rfc3339(),
+            ranges,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, JobManifestError> {
+        let manifest: JobManifest = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+        if manifest.version != MANIFEST_VERSION {
+            return Err(JobManifestError::UnsupportedVersion(manifest.version));
+        }
+
+        Ok(manifest)
+    }
+
+    /// Writes the manifest to a temporary file first so an interrupted write
+    /// can't leave a truncated manifest behind.
+    pub fn save(&self, path: &Path) -> Result<(), JobManifestError> {
+        let tmp_path = path.with_extension("tmp");
+
+        fs::write(&tmp_path, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&tmp_path, path)?;
+
+        Ok(())
+    }
+}
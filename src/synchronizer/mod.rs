@@ -1,24 +1,61 @@
-use std::fmt::Debug;
+use std::{
+    any::Any,
+    fmt::Debug,
+    panic::AssertUnwindSafe,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use alloy::transports::http::ReqwestTransport;
+use alloy::transports::BoxTransport;
 use anyhow::anyhow;
 use async_trait::async_trait;
-use futures::future::join_all;
-use tokio::task::JoinHandle;
-use tracing::{debug, info, Instrument};
+use clap::ValueEnum;
+use futures::{future::join_all, FutureExt};
+use tokio::{sync::mpsc, task::JoinHandle, time::Interval};
+use tracing::{debug, error, info, warn, Instrument};
 
 #[cfg(test)]
 use mockall::automock;
 
 use crate::{
-    clients::{beacon::types::BlockId, blobscan::types::BlockchainSyncState, common::ClientError},
+    clients::{beacon::types::BlockId, common::ClientError, priority::RequestPriority},
     context::CommonContext,
-    slots_processor::{error::SlotsProcessorError, SlotsProcessor},
+    metrics::increment_counter,
+    network::ForkSchedule,
+    slots_processor::{
+        blob_verifier::BlobVerifier,
+        error::SlotsProcessorError,
+        indexed_slots::{IndexedSlots, SlotsTask},
+        stats::SlotStats,
+        SlotsProcessor,
+    },
+    tunables::Tunables,
 };
 
-use self::error::{SlotsChunksErrors, SynchronizerError};
+use self::{
+    checkpoint::CheckpointPolicy,
+    concurrency::AdaptiveConcurrency,
+    error::{SlotsChunksErrors, SynchronizerError},
+    intent::IntentLog,
+    job::{JobProgress, SlotRange},
+    pause::PauseSwitch,
+    scheduler::{SlotScheduler, WorkQueue},
+    watchdog::WorkerStatus,
+};
 
+pub mod backfill;
+pub mod checkpoint;
+pub mod concurrency;
 pub mod error;
+pub mod intent;
+pub mod job;
+pub mod pause;
+pub mod scheduler;
+pub mod watchdog;
+
+/// Minimum amount of newly contiguous synced slots required to save a
+/// checkpoint before the whole chunk completes
+const MIN_SLOTS_PER_EARLY_CHECKPOINT: u32 = 50;
 
 #[async_trait]
 #[cfg_attr(test, automock)]
@@ -35,22 +72,69 @@ pub struct SynchronizerBuilder {
     num_threads: u32,
     min_slots_per_thread: u32,
     slots_checkpoint: u32,
-    checkpoint_type: CheckpointType,
+    checkpoint_policy: Arc<dyn CheckpointPolicy>,
+    concurrency: Option<Arc<AdaptiveConcurrency>>,
+    blob_verifier: Option<BlobVerifier>,
+    fork_schedule: Option<ForkSchedule>,
+    job_progress: Option<Arc<JobProgress>>,
+    worker_stall_timeout: Option<Duration>,
+    slot_timeout: Option<Duration>,
+    slow_slot_threshold: Option<Duration>,
+    slot_stats: Option<Arc<SlotStats>>,
+    intent_log: Option<Arc<IntentLog>>,
+    indexed_slots: Option<(Arc<IndexedSlots>, SlotsTask)>,
+    pause_switch: Option<Arc<PauseSwitch>>,
+    tunables: Option<Arc<Tunables>>,
+    request_priority: RequestPriority,
 }
 
 pub struct Synchronizer<T> {
     context: Box<dyn CommonContext<T>>,
     num_threads: u32,
-    min_slots_per_thread: u32,
-    slots_checkpoint: u32,
-    checkpoint_type: CheckpointType,
+    scheduler: SlotScheduler,
+    checkpoint_policy: Arc<dyn CheckpointPolicy>,
+    concurrency: Option<Arc<AdaptiveConcurrency>>,
+    blob_verifier: Option<BlobVerifier>,
+    fork_schedule: Option<ForkSchedule>,
+    job_progress: Option<Arc<JobProgress>>,
+    worker_stall_timeout: Option<Duration>,
+    slot_timeout: Option<Duration>,
+    slow_slot_threshold: Option<Duration>,
+    slot_stats: Option<Arc<SlotStats>>,
+    intent_log: Option<Arc<IntentLog>>,
+    indexed_slots: Option<(Arc<IndexedSlots>, SlotsTask)>,
+    pause_switch: Option<Arc<PauseSwitch>>,
+    tunables: Option<Arc<Tunables>>,
+    request_priority: RequestPriority,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+/// Slot a worker panicked on, reported so it's never considered synced.
+struct SlotPanic {
+    /// Position in the chunk of the work unit the slot belongs to
+    unit: usize,
+    slot: u32,
+    message: String,
+}
+
+struct Worker {
+    handle: JoinHandle<Result<(), SlotsProcessorError>>,
+    status: Arc<WorkerStatus>,
+    /// Position in the chunk of the work unit being processed
+    current_unit: Arc<Mutex<Option<usize>>>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
 pub enum CheckpointType {
+    /// Saves nothing
     Disabled,
+    /// Saves the last synced slot as the lower bound. Meant for ranges synced
+    /// backwards
     Lower,
+    /// Saves the last synced slot as the upper bound
     Upper,
+    /// Saves the whole synced range as both bounds. Meant for when the range
+    /// being synced is the only one, so both bounds converge on it
+    Both,
 }
 
 impl Default for SynchronizerBuilder {
@@ -59,7 +143,20 @@ impl Default for SynchronizerBuilder {
             num_threads: 1,
             min_slots_per_thread: 50,
             slots_checkpoint: 1000,
-            checkpoint_type: CheckpointType::Upper,
+            checkpoint_policy: Arc::new(CheckpointType::Upper),
+            concurrency: None,
+            blob_verifier: None,
+            fork_schedule: None,
+            job_progress: None,
+            worker_stall_timeout: None,
+            slot_timeout: None,
+            slow_slot_threshold: None,
+            slot_stats: None,
+            intent_log: None,
+            indexed_slots: None,
+            pause_switch: None,
+            tunables: None,
+            request_priority: RequestPriority::Normal,
         }
     }
 }
@@ -70,7 +167,18 @@ impl SynchronizerBuilder {
     }
 
     pub fn with_checkpoint_type(&mut self, checkpoint_type: CheckpointType) -> &mut Self {
-        self.checkpoint_type = checkpoint_type;
+        self.checkpoint_policy = Arc::new(checkpoint_type);
+
+        self
+    }
+
+    /// Saves the sync state the given policy returns instead of the one of a
+    /// predefined [`CheckpointType`].
+    pub fn with_checkpoint_policy(
+        &mut self,
+        checkpoint_policy: Arc<dyn CheckpointPolicy>,
+    ) -> &mut Self {
+        self.checkpoint_policy = checkpoint_policy;
 
         self
     }
@@ -81,6 +189,114 @@ impl SynchronizerBuilder {
         self
     }
 
+    /// Lets the given controller decide the number of threads used for every
+    /// chunk instead of using a fixed one.
+    pub fn with_adaptive_concurrency(
+        &mut self,
+        concurrency: Arc<AdaptiveConcurrency>,
+    ) -> &mut Self {
+        self.concurrency = Some(concurrency);
+
+        self
+    }
+
+    pub fn with_blob_verifier(&mut self, blob_verifier: BlobVerifier) -> &mut Self {
+        self.blob_verifier = Some(blob_verifier);
+
+        self
+    }
+
+    /// Checks the blobs of every block against the limit of its fork and tags
+    /// the block with it.
+    pub fn with_fork_schedule(&mut self, fork_schedule: ForkSchedule) -> &mut Self {
+        self.fork_schedule = Some(fork_schedule);
+
+        self
+    }
+
+    /// Keeps track of the slots left to sync in the given progress so they can
+    /// be saved if the job gets interrupted.
+    pub fn with_job_progress(&mut self, job_progress: Arc<JobProgress>) -> &mut Self {
+        self.job_progress = Some(job_progress);
+
+        self
+    }
+
+    /// Restarts the workers that make no progress for longer than the given
+    /// timeout, resuming their remaining slots on fresh tasks.
+    pub fn with_worker_stall_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.worker_stall_timeout = Some(timeout);
+
+        self
+    }
+
+    /// Fails the slots whose processing, retries included, takes longer than
+    /// the given timeout.
+    pub fn with_slot_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.slot_timeout = Some(timeout);
+
+        self
+    }
+
+    /// Warns about the slots taking longer than the given threshold to be
+    /// processed.
+    pub fn with_slow_slot_threshold(&mut self, threshold: Duration) -> &mut Self {
+        self.slow_slot_threshold = Some(threshold);
+
+        self
+    }
+
+    /// Collects the processing time and skip reason of every slot in the given
+    /// stats.
+    pub fn with_slot_stats(&mut self, slot_stats: Arc<SlotStats>) -> &mut Self {
+        self.slot_stats = Some(slot_stats);
+
+        self
+    }
+
+    /// Records every chunk in the given intent log before syncing it, and
+    /// confirms it once its checkpoint is saved.
+    pub fn with_intent_log(&mut self, intent_log: Arc<IntentLog>) -> &mut Self {
+        self.intent_log = Some(intent_log);
+
+        self
+    }
+
+    /// Skips the slots already indexed by any task, reporting the ones this
+    /// synchronizer processes as the given task.
+    pub fn with_indexed_slots(
+        &mut self,
+        indexed_slots: Arc<IndexedSlots>,
+        task: SlotsTask,
+    ) -> &mut Self {
+        self.indexed_slots = Some((indexed_slots, task));
+
+        self
+    }
+
+    /// Holds the workers between work units while the given switch is paused.
+    pub fn with_pause_switch(&mut self, pause_switch: Arc<PauseSwitch>) -> &mut Self {
+        self.pause_switch = Some(pause_switch);
+
+        self
+    }
+
+    /// Follows the worker threads, checkpoint size and slow slot threshold of
+    /// the given tunables as they change, from the next chunk on. Synchronizers
+    /// syncing one slot at a time keep doing so.
+    pub fn with_tunables(&mut self, tunables: Arc<Tunables>) -> &mut Self {
+        self.tunables = Some(tunables);
+
+        self
+    }
+
+    /// Processes the slots with the given request priority.
+    pub fn with_request_priority(&mut self, request_priority: RequestPriority) -> &mut Self {
+        self.request_priority = request_priority;
+
+        self
+    }
+
     pub fn with_slots_checkpoint(&mut self, slots_checkpoint: u32) -> &mut Self {
         self.slots_checkpoint = slots_checkpoint;
         self
@@ -88,75 +304,190 @@ impl SynchronizerBuilder {
 
     pub fn build(
         &self,
-        context: Box<dyn CommonContext<ReqwestTransport>>,
-    ) -> Synchronizer<ReqwestTransport> {
+        context: Box<dyn CommonContext<BoxTransport>>,
+    ) -> Synchronizer<BoxTransport> {
         Synchronizer {
             context,
             num_threads: self.num_threads,
-            min_slots_per_thread: self.min_slots_per_thread,
-            slots_checkpoint: self.slots_checkpoint,
-            checkpoint_type: self.checkpoint_type,
+            scheduler: SlotScheduler::new(self.slots_checkpoint, self.min_slots_per_thread),
+            checkpoint_policy: self.checkpoint_policy.clone(),
+            concurrency: self.concurrency.clone(),
+            blob_verifier: self.blob_verifier.clone(),
+            fork_schedule: self.fork_schedule.clone(),
+            job_progress: self.job_progress.clone(),
+            worker_stall_timeout: self.worker_stall_timeout,
+            slot_timeout: self.slot_timeout,
+            slow_slot_threshold: self.slow_slot_threshold,
+            slot_stats: self.slot_stats.clone(),
+            intent_log: self.intent_log.clone(),
+            indexed_slots: self.indexed_slots.clone(),
+            pause_switch: self.pause_switch.clone(),
+            tunables: self.tunables.clone(),
+            request_priority: self.request_priority,
         }
     }
 }
 
-impl Synchronizer<ReqwestTransport> {
-    async fn sync_slots(&self, from_slot: u32, to_slot: u32) -> Result<(), SynchronizerError> {
+impl Synchronizer<BoxTransport> {
+    /// Syncs the given chunk of slots in parallel. `first_slot` is where the
+    /// whole range being synced starts, used to save early checkpoints.
+    async fn sync_slots(
+        &self,
+        first_slot: u32,
+        from_slot: u32,
+        to_slot: u32,
+    ) -> Result<(), SynchronizerError> {
         let is_reverse_sync = to_slot < from_slot;
         let unprocessed_slots = to_slot.abs_diff(from_slot);
-        let min_slots_per_thread = std::cmp::min(unprocessed_slots, self.min_slots_per_thread);
-        let slots_per_thread =
-            std::cmp::max(min_slots_per_thread, unprocessed_slots / self.num_threads);
-        let num_threads = std::cmp::max(1, unprocessed_slots / slots_per_thread);
-        let remaining_slots = unprocessed_slots % num_threads;
-
-        let mut handles: Vec<JoinHandle<Result<(), SlotsProcessorError>>> = vec![];
-
-        for i in 0..num_threads {
-            let mut slots_processor = SlotsProcessor::new(self.context.clone());
-            let thread_total_slots = slots_per_thread
-                + if i == num_threads - 1 {
-                    remaining_slots
-                } else {
-                    0
-                };
-            let thread_initial_slot = if is_reverse_sync {
-                from_slot - i * slots_per_thread
-            } else {
-                from_slot + i * slots_per_thread
+        let max_threads = match (&self.concurrency, &self.tunables) {
+            (Some(concurrency), _) => concurrency.current_workers(),
+            (None, Some(tunables)) if self.num_threads > 1 => {
+                tunables.num_threads().unwrap_or(self.num_threads)
+            }
+            (None, _) => self.num_threads,
+        };
+        let chunk = SlotRange::new(from_slot, to_slot);
+        let units = self.scheduler.work_units(chunk).collect::<Vec<_>>();
+
+        if units.is_empty() {
+            return Ok(());
+        }
+
+        let units_total_slots = units.iter().map(SlotRange::len).collect::<Vec<_>>();
+        let work_queue = Arc::new(WorkQueue::new(units.clone()));
+        let (progress_tx, mut progress_rx) = mpsc::unbounded_channel();
+        let (panic_tx, mut panic_rx) = mpsc::unbounded_channel();
+        let mut workers = (0..self.scheduler.workers(chunk, max_threads))
+            .map(|thread| {
+                self.spawn_worker(
+                    thread,
+                    work_queue.clone(),
+                    progress_tx.clone(),
+                    panic_tx.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        if let Some(job_progress) = &self.job_progress {
+            job_progress.start_chunk(units.clone());
+        }
+
+        // Only the workers hold senders now, so the channel closes once all of them
+        // finish. Restarted workers get a sender from the weak one
+        let weak_progress_tx = progress_tx.downgrade();
+
+        drop(progress_tx);
+
+        let mut watchdog_interval = self.worker_stall_timeout.map(|timeout| {
+            tokio::time::interval(std::cmp::max(timeout / 4, Duration::from_secs(1)))
+        });
+        let mut units_processed_slots = vec![0; units.len()];
+        // Processed slots of the units up to the first slot that panicked, past
+        // which they're never considered synced
+        let mut units_panicked_at = vec![None; units.len()];
+        let mut panics = vec![];
+        let mut last_saved_frontier = 0;
+
+        loop {
+            let slot = tokio::select! {
+                // Handled before the slots synced after it
+                biased;
+
+                Some(panic) = panic_rx.recv() => {
+                    let processed_slots = units[panic.unit].from.abs_diff(panic.slot)
+                        - if is_reverse_sync { 1 } else { 0 };
+                    let panicked_at = units_panicked_at[panic.unit].get_or_insert(processed_slots);
+
+                    *panicked_at = std::cmp::min(*panicked_at, processed_slots);
+                    units_processed_slots[panic.unit] =
+                        std::cmp::min(units_processed_slots[panic.unit], *panicked_at);
+
+                    panics.push(SlotsProcessorError::SlotPanic {
+                        slot: panic.slot,
+                        message: panic.message,
+                    });
+
+                    continue;
+                }
+                slot = progress_rx.recv() => match slot {
+                    Some(slot) => slot,
+                    None => break,
+                },
+                _ = tick(&mut watchdog_interval) => {
+                    self.restart_stalled_workers(
+                        &mut workers,
+                        &work_queue,
+                        &units,
+                        &units_processed_slots,
+                        &weak_progress_tx,
+                        &panic_tx,
+                    );
+
+                    continue;
+                }
             };
-            let thread_final_slot = if is_reverse_sync {
-                thread_initial_slot - thread_total_slots
-            } else {
-                thread_initial_slot + thread_total_slots
+            let Some(unit) = units.iter().position(|range| range.contains(slot)) else {
+                continue;
             };
+            // Units are processed in order. A restarted worker may report a slot
+            // again, so keep the furthest position instead of counting
+            let unit_processed_slots =
+                units[unit].from.abs_diff(slot) + if is_reverse_sync { 0 } else { 1 };
 
-            let synchronizer_thread_span = tracing::debug_span!(
-                parent:  &tracing::Span::current(),
-                "thread",
-                thread = i,
-                chunk_initial_slot = thread_initial_slot,
-                chunk_final_slot = thread_final_slot
-            );
+            units_processed_slots[unit] =
+                std::cmp::max(units_processed_slots[unit], unit_processed_slots);
 
-            let handle = tokio::spawn(
-                async move {
-                    slots_processor
-                        .process_slots(thread_initial_slot, thread_final_slot)
-                        .await?;
+            if let Some(panicked_at) = units_panicked_at[unit] {
+                units_processed_slots[unit] =
+                    std::cmp::min(units_processed_slots[unit], panicked_at);
 
-                    Ok(())
+                // The panicked slot is kept as the next one to sync, so it gets
+                // saved in the job manifest
+                if unit_processed_slots > panicked_at {
+                    continue;
                 }
-                .instrument(synchronizer_thread_span)
-                .in_current_span(),
-            );
+            }
+
+            if let Some(job_progress) = &self.job_progress {
+                job_progress.record_synced_slot(unit, slot);
+            }
+
+            if !self.checkpoint_policy.is_enabled() {
+                continue;
+            }
+
+            let frontier = contiguous_synced_slots(&units_total_slots, &units_processed_slots);
+
+            if frontier == unprocessed_slots
+                || frontier - last_saved_frontier < MIN_SLOTS_PER_EARLY_CHECKPOINT
+            {
+                continue;
+            }
+
+            let last_slot = if is_reverse_sync {
+                from_slot - frontier + 1
+            } else {
+                from_slot + frontier - 1
+            };
+
+            match self.save_checkpoint(first_slot, last_slot).await {
+                Ok(()) => {
+                    debug!(
+                        last_slot,
+                        "Contiguous slots synced. Early checkpoint saved…"
+                    );
 
-            handles.push(handle);
+                    last_saved_frontier = frontier;
+                }
+                Err(error) => {
+                    warn!(?error, "Failed to save early checkpoint");
+                }
+            }
         }
 
-        let handle_outputs = join_all(handles).await;
+        let handle_outputs = join_all(workers.into_iter().map(|worker| worker.handle)).await;
 
-        let mut errors = vec![];
+        let mut errors = panics;
 
         for handle in handle_outputs {
             match handle {
@@ -164,11 +495,7 @@ impl Synchronizer<ReqwestTransport> {
                     Ok(()) => {}
                     Err(error) => errors.push(error),
                 },
-                Err(error) => {
-                    let err = anyhow!("Synchronizer thread panicked: {:?}", error);
-
-                    errors.push(err.into());
-                }
+                Err(error) => errors.push(SlotsProcessorError::WorkerPanic(error)),
             }
         }
 
@@ -183,14 +510,246 @@ impl Synchronizer<ReqwestTransport> {
         }
     }
 
+    fn spawn_worker(
+        &self,
+        thread: u32,
+        work_queue: Arc<WorkQueue>,
+        progress_tx: mpsc::UnboundedSender<u32>,
+        panic_tx: mpsc::UnboundedSender<SlotPanic>,
+    ) -> Worker {
+        let status = Arc::new(WorkerStatus::new());
+        let new_slots_processor = self.slots_processor_factory(progress_tx, status.clone());
+        let synchronizer_thread_span = tracing::debug_span!(
+            parent:  &tracing::Span::current(),
+            "thread",
+            thread
+        );
+        let current_unit = Arc::new(Mutex::new(None));
+        let worker_current_unit = current_unit.clone();
+        let worker_status = status.clone();
+        let pause_switch = self.pause_switch.clone();
+
+        let handle = tokio::spawn(
+            async move {
+                let mut slots_processor = new_slots_processor();
+
+                loop {
+                    if let Some(pause_switch) = &pause_switch {
+                        if pause_switch.wait_until_resumed().await {
+                            worker_status.touch();
+                        }
+                    }
+
+                    let Some((index, unit)) = work_queue.pop() else {
+                        break;
+                    };
+
+                    *lock(&worker_current_unit) = Some(index);
+
+                    let mut remaining_range = unit;
+
+                    while !remaining_range.is_empty() {
+                        let unit_span = tracing::debug_span!(
+                            "unit",
+                            unit_initial_slot = remaining_range.from,
+                            unit_final_slot = remaining_range.to
+                        );
+                        let result = AssertUnwindSafe(
+                            slots_processor
+                                .process_slots(remaining_range.from, remaining_range.to)
+                                .instrument(unit_span),
+                        )
+                        .catch_unwind()
+                        .await;
+
+                        let panic = match result {
+                            Ok(result) => {
+                                result?;
+
+                                break;
+                            }
+                            Err(panic) => panic,
+                        };
+                        let Some(slot) = worker_status
+                            .activity()
+                            .slot
+                            .filter(|slot| remaining_range.contains(*slot))
+                            .or(remaining_range.first())
+                        else {
+                            break;
+                        };
+                        let message = panic_message(panic.as_ref());
+
+                        error!(
+                            thread,
+                            slot,
+                            panic = message,
+                            "Worker panicked while processing slot. Respawning it…"
+                        );
+                        increment_counter("blob_indexer_worker_panics_total", &[]);
+
+                        let _ = panic_tx.send(SlotPanic {
+                            unit: index,
+                            slot,
+                            message,
+                        });
+
+                        // The panicked processor may have been left in an
+                        // inconsistent state, so the rest of the unit gets a fresh one
+                        slots_processor = new_slots_processor();
+                        remaining_range.advance_past(slot);
+                    }
+                }
+
+                *lock(&worker_current_unit) = None;
+
+                Ok(())
+            }
+            .instrument(synchronizer_thread_span)
+            .in_current_span(),
+        );
+
+        Worker {
+            handle,
+            status,
+            current_unit,
+        }
+    }
+
+    /// Returns a function creating the slots processors of a worker, so it can
+    /// get a fresh one after a panic.
+    fn slots_processor_factory(
+        &self,
+        progress_tx: mpsc::UnboundedSender<u32>,
+        status: Arc<WorkerStatus>,
+    ) -> impl Fn() -> SlotsProcessor<BoxTransport> + Send + 'static {
+        let context = self.context.clone();
+        let concurrency = self.concurrency.clone();
+        let blob_verifier = self.blob_verifier.clone();
+        let fork_schedule = self.fork_schedule.clone();
+        let indexed_slots = self.indexed_slots.clone();
+        let slot_timeout = self.slot_timeout;
+        let slow_slot_threshold = match &self.tunables {
+            Some(tunables) => Some(tunables.slow_slot_threshold()),
+            None => self.slow_slot_threshold,
+        };
+        let slot_stats = self.slot_stats.clone();
+        let request_priority = self.request_priority;
+
+        move || {
+            let mut slots_processor = SlotsProcessor::new(context.clone())
+                .with_progress_reporting(progress_tx.clone())
+                .with_status(status.clone());
+
+            if let Some(concurrency) = &concurrency {
+                slots_processor = slots_processor.with_concurrency_feedback(concurrency.clone());
+            }
+
+            if let Some(blob_verifier) = &blob_verifier {
+                slots_processor = slots_processor.with_blob_verifier(blob_verifier.clone());
+            }
+
+            if let Some(fork_schedule) = &fork_schedule {
+                slots_processor = slots_processor.with_fork_schedule(fork_schedule.clone());
+            }
+
+            if let Some((indexed_slots, task)) = &indexed_slots {
+                slots_processor = slots_processor.with_indexed_slots(indexed_slots.clone(), *task);
+            }
+
+            if let Some(slot_timeout) = slot_timeout {
+                slots_processor = slots_processor.with_slot_timeout(slot_timeout);
+            }
+
+            if let Some(slow_slot_threshold) = slow_slot_threshold {
+                slots_processor = slots_processor.with_slow_slot_threshold(slow_slot_threshold);
+            }
+
+            if let Some(slot_stats) = &slot_stats {
+                slots_processor = slots_processor.with_slot_stats(slot_stats.clone());
+            }
+
+            slots_processor.with_request_priority(request_priority)
+        }
+    }
+
+    /// Aborts the workers that haven't made any progress for longer than the
+    /// stall timeout, putting the remaining slots of their work unit back in
+    /// the queue for a fresh worker to pick up.
+    fn restart_stalled_workers(
+        &self,
+        workers: &mut [Worker],
+        work_queue: &Arc<WorkQueue>,
+        units: &[SlotRange],
+        units_processed_slots: &[u32],
+        weak_progress_tx: &mpsc::WeakUnboundedSender<u32>,
+        panic_tx: &mpsc::UnboundedSender<SlotPanic>,
+    ) {
+        let Some(stall_timeout) = self.worker_stall_timeout else {
+            return;
+        };
+
+        // Paused workers make no progress on purpose
+        if self
+            .pause_switch
+            .as_ref()
+            .is_some_and(|pause_switch| pause_switch.is_paused())
+        {
+            return;
+        }
+
+        for (thread, worker) in workers.iter_mut().enumerate() {
+            let activity = worker.status.activity();
+
+            if worker.handle.is_finished() || activity.idle_for < stall_timeout {
+                continue;
+            }
+
+            // All workers finished in the meantime
+            let Some(progress_tx) = weak_progress_tx.upgrade() else {
+                return;
+            };
+
+            worker.handle.abort();
+
+            let current_unit = *lock(&worker.current_unit);
+            let remaining_range = current_unit.map(|unit| {
+                let processed_slots = units_processed_slots[unit];
+
+                units[unit].slice(processed_slots, units[unit].len() - processed_slots)
+            });
+
+            if let (Some(unit), Some(remaining_range)) = (current_unit, remaining_range) {
+                work_queue.push_front(unit, remaining_range);
+            }
+
+            error!(
+                thread,
+                slot = activity.slot,
+                phase = %activity.phase,
+                stalled_for = ?activity.idle_for,
+                remaining_initial_slot = remaining_range.map(|range| range.from),
+                remaining_final_slot = remaining_range.map(|range| range.to),
+                "Worker stalled. Restarting it…"
+            );
+            increment_counter("blob_indexer_stalled_workers_total", &[]);
+
+            *worker = self.spawn_worker(
+                thread as u32,
+                work_queue.clone(),
+                progress_tx,
+                panic_tx.clone(),
+            );
+        }
+    }
+
     async fn sync_slots_by_checkpoints(
         &self,
         initial_slot: u32,
         final_slot: u32,
     ) -> Result<(), SynchronizerError> {
         let is_reverse_sync = final_slot < initial_slot;
-        let mut current_slot = initial_slot;
-        let mut unprocessed_slots = final_slot.abs_diff(current_slot);
+        let unprocessed_slots = final_slot.abs_diff(initial_slot);
 
         info!(
             initial_slot,
@@ -199,14 +758,31 @@ impl Synchronizer<ReqwestTransport> {
             "Syncing {unprocessed_slots} slots…"
         );
 
-        while unprocessed_slots > 0 {
-            let slots_chunk = std::cmp::min(unprocessed_slots, self.slots_checkpoint);
-            let initial_chunk_slot = current_slot;
-            let final_chunk_slot = if is_reverse_sync {
-                current_slot - slots_chunk
-            } else {
-                current_slot + slots_chunk
+        let mut chunks = self
+            .scheduler
+            .chunks(SlotRange::new(initial_slot, final_slot));
+
+        loop {
+            // The checkpoint size may have been changed since the last chunk
+            if let Some(slots_per_save) = self
+                .tunables
+                .as_ref()
+                .and_then(|tunables| tunables.slots_per_save())
+            {
+                chunks.set_chunk_size(slots_per_save);
+            }
+
+            let Some(chunk_range) = chunks.next() else {
+                break;
             };
+            let SlotRange {
+                from: initial_chunk_slot,
+                to: final_chunk_slot,
+            } = chunk_range;
+
+            if let Some(job_progress) = &self.job_progress {
+                job_progress.set_pending(SlotRange::new(final_chunk_slot, final_slot));
+            }
 
             let sync_slots_chunk_span = tracing::debug_span!(
                 parent: &tracing::Span::current(),
@@ -215,77 +791,61 @@ impl Synchronizer<ReqwestTransport> {
                 checkpoint_final_slot = final_chunk_slot
             );
 
-            self.sync_slots(initial_chunk_slot, final_chunk_slot)
+            if let Some(intent_log) = &self.intent_log {
+                intent_log.record(chunk_range)?;
+            }
+
+            self.sync_slots(initial_slot, initial_chunk_slot, final_chunk_slot)
                 .instrument(sync_slots_chunk_span)
                 .await?;
 
-            let last_slot = Some(if is_reverse_sync {
+            let last_slot = if is_reverse_sync {
                 final_chunk_slot + 1
             } else {
                 final_chunk_slot - 1
-            });
-
-            if self.checkpoint_type != CheckpointType::Disabled {
-                let last_lower_synced_slot = if self.checkpoint_type == CheckpointType::Lower {
-                    last_slot
-                } else {
-                    None
-                };
-                let last_upper_synced_slot = if self.checkpoint_type == CheckpointType::Upper {
-                    last_slot
-                } else {
-                    None
-                };
-
-                if let Err(error) = self
-                    .context
-                    .blobscan_client()
-                    .update_sync_state(BlockchainSyncState {
-                        last_finalized_block: None,
-                        last_lower_synced_slot,
-                        last_upper_synced_slot,
-                    })
-                    .await
-                {
-                    let new_synced_slot = match last_lower_synced_slot {
-                        Some(slot) => slot,
-                        None => match last_upper_synced_slot {
-                            Some(slot) => slot,
-                            None => {
-                                return Err(SynchronizerError::Other(anyhow!(
-                                    "Failed to get new last synced slot: last_lower_synced_slot and last_upper_synced_slot are both None"
-                                )))
-                            }
-                        }
-                    };
+            };
 
-                    return Err(SynchronizerError::FailedSlotCheckpointSave {
-                        slot: new_synced_slot,
-                        error,
-                    });
-                }
+            if self.checkpoint_policy.is_enabled() {
+                self.save_checkpoint(initial_slot, last_slot).await?;
 
-                if unprocessed_slots >= self.slots_checkpoint {
+                if chunk_range.len() == chunks.chunk_size() {
                     debug!(
-                        new_last_lower_synced_slot = last_lower_synced_slot,
-                        new_last_upper_synced_slot = last_upper_synced_slot,
+                        new_last_synced_slot = last_slot,
+                        checkpoint_policy = ?self.checkpoint_policy,
                         "Checkpoint reached. Last synced slot saved…"
                     );
                 }
             }
 
-            current_slot = if is_reverse_sync {
-                current_slot - slots_chunk
-            } else {
-                current_slot + slots_chunk
-            };
-
-            unprocessed_slots -= slots_chunk;
+            if let Some(intent_log) = &self.intent_log {
+                intent_log.confirm(chunk_range)?;
+            }
         }
 
         Ok(())
     }
 
+    /// Saves the sync state of the slots synced so far, from `first_slot` to
+    /// `last_slot`, as the checkpoint policy dictates.
+    async fn save_checkpoint(
+        &self,
+        first_slot: u32,
+        last_slot: u32,
+    ) -> Result<(), SynchronizerError> {
+        let Some(sync_state) = self.checkpoint_policy.sync_state(first_slot, last_slot) else {
+            return Ok(());
+        };
+
+        self.context
+            .blobscan_client()
+            .update_sync_state(sync_state)
+            .await
+            .map_err(|error| SynchronizerError::FailedSlotCheckpointSave {
+                slot: last_slot,
+                error,
+            })
+    }
+
     async fn resolve_to_slot(&self, block_id: &BlockId) -> Result<u32, SynchronizerError> {
         let beacon_client = self.context.beacon_client();
 
@@ -313,7 +873,7 @@ impl Synchronizer<ReqwestTransport> {
 }
 
 #[async_trait]
-impl CommonSynchronizer for Synchronizer<ReqwestTransport> {
+impl CommonSynchronizer for Synchronizer<BoxTransport> {
     async fn run(
         &self,
         initial_block_id: &BlockId,
@@ -326,6 +886,10 @@ impl CommonSynchronizer for Synchronizer<ReqwestTransport> {
             return Ok(());
         }
 
+        if let Some(job_progress) = &self.job_progress {
+            job_progress.set_pending(SlotRange::new(initial_slot, final_slot));
+        }
+
         loop {
             self.sync_slots_by_checkpoints(initial_slot, final_slot)
                 .await?;
@@ -340,3 +904,302 @@ impl CommonSynchronizer for Synchronizer<ReqwestTransport> {
         }
     }
 }
+
+/// Resolves on the next tick of the given interval, or never if there's none.
+async fn tick(interval: &mut Option<Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+/// Returns the message a panic was raised with.
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Returns the amount of slots synced without gaps from the start of the range,
+/// given the slots of each work unit and how many of them were processed. Units
+/// are processed in order, so the prefix ends at the first unit that hasn't
+/// been finished yet.
+fn contiguous_synced_slots(units_total_slots: &[u32], units_processed_slots: &[u32]) -> u32 {
+    let mut synced_slots = 0;
+
+    for (total_slots, processed_slots) in units_total_slots.iter().zip(units_processed_slots) {
+        synced_slots += processed_slots;
+
+        if processed_slots < total_slots {
+            break;
+        }
+    }
+
+    synced_slots
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+
+    use super::{
+        checkpoint::MockCheckpointPolicy, error::SynchronizerError, lock, CommonSynchronizer,
+        SynchronizerBuilder,
+    };
+    use crate::{
+        clients::beacon::{types::BlockId, MockCommonBeaconClient},
+        context::Context,
+        slots_processor::error::SlotsProcessorError,
+    };
+
+    /// How the mocked beacon node answers the block request of a slot. Slots
+    /// without one are answered right away with no block, so they're skipped.
+    #[derive(Clone, Copy)]
+    enum SlotBehavior {
+        Delay(Duration),
+        Panic,
+        /// Never answers the first request
+        StallOnce,
+    }
+
+    /// Times each slot was processed.
+    #[derive(Clone, Default)]
+    struct ProcessedSlots(Arc<Mutex<HashMap<u32, u32>>>);
+
+    impl ProcessedSlots {
+        fn count(&self, slot: u32) -> u32 {
+            lock(&self.0).get(&slot).copied().unwrap_or_default()
+        }
+
+        /// Whether the slots a checkpoint from `first_slot` to `last_slot`
+        /// covers were all processed. Ranges synced backwards don't include
+        /// their first slot.
+        fn covers(&self, first_slot: u32, last_slot: u32) -> bool {
+            if first_slot <= last_slot {
+                (first_slot..=last_slot).all(|slot| self.count(slot) > 0)
+            } else {
+                (last_slot..first_slot).all(|slot| self.count(slot) > 0)
+            }
+        }
+    }
+
+    /// Checkpoint saved, along with whether every slot it covers had been
+    /// processed by then.
+    #[derive(Debug, PartialEq)]
+    struct SavedCheckpoint {
+        last_slot: u32,
+        covers_processed_slots: bool,
+    }
+
+    fn mock_beacon_client(
+        behaviors: HashMap<u32, SlotBehavior>,
+        processed_slots: ProcessedSlots,
+    ) -> MockCommonBeaconClient {
+        let mut beacon_client = MockCommonBeaconClient::new();
+        let stalled = Arc::new(AtomicBool::new(false));
+
+        beacon_client.expect_get_block().returning(move |block_id| {
+            let BlockId::Slot(slot) = *block_id else {
+                panic!("unexpected block id {block_id}");
+            };
+            let behavior = behaviors.get(&slot).copied();
+            let processed_slots = processed_slots.clone();
+            let stalled = stalled.clone();
+
+            // Panics are raised once polled, as the synchronizer would see them
+            Box::pin(async move {
+                match behavior {
+                    Some(SlotBehavior::Delay(delay)) => tokio::time::sleep(delay).await,
+                    Some(SlotBehavior::Panic) => panic!("slot {slot} exploded"),
+                    Some(SlotBehavior::StallOnce) if !stalled.swap(true, Ordering::SeqCst) => {
+                        std::future::pending::<()>().await
+                    }
+                    Some(SlotBehavior::StallOnce) | None => {}
+                }
+
+                *lock(&processed_slots.0).entry(slot).or_default() += 1;
+
+                Ok(None)
+            })
+        });
+
+        beacon_client
+    }
+
+    fn recording_checkpoint_policy(
+        processed_slots: ProcessedSlots,
+        checkpoints: Arc<Mutex<Vec<SavedCheckpoint>>>,
+    ) -> MockCheckpointPolicy {
+        let mut checkpoint_policy = MockCheckpointPolicy::new();
+
+        checkpoint_policy.expect_is_enabled().return_const(true);
+        checkpoint_policy
+            .expect_sync_state()
+            .returning(move |first_slot, last_slot| {
+                lock(&checkpoints).push(SavedCheckpoint {
+                    last_slot,
+                    covers_processed_slots: processed_slots.covers(first_slot, last_slot),
+                });
+
+                None
+            });
+
+        checkpoint_policy
+    }
+
+    /// Syncs the range with a synchronizer whose slots behave as given,
+    /// returning the outcome along with the checkpoints it saved.
+    async fn sync(
+        builder: &mut SynchronizerBuilder,
+        from_slot: u32,
+        to_slot: u32,
+        behaviors: HashMap<u32, SlotBehavior>,
+        processed_slots: &ProcessedSlots,
+    ) -> (Result<(), SynchronizerError>, Vec<SavedCheckpoint>) {
+        let checkpoints = Arc::new(Mutex::new(vec![]));
+        let checkpoint_policy =
+            recording_checkpoint_policy(processed_slots.clone(), checkpoints.clone());
+        let beacon_client = mock_beacon_client(behaviors, processed_slots.clone());
+        let synchronizer = builder
+            .with_checkpoint_policy(Arc::new(checkpoint_policy))
+            .build(Context::with_mocks(Some(beacon_client), None, None));
+        let result = synchronizer
+            .run(&BlockId::Slot(from_slot), &BlockId::Slot(to_slot))
+            .await;
+        let checkpoints = std::mem::take(&mut *lock(&checkpoints));
+
+        (result, checkpoints)
+    }
+
+    #[tokio::test]
+    async fn worker_panic_only_fails_its_slot() {
+        let processed_slots = ProcessedSlots::default();
+        let (result, checkpoints) = sync(
+            SynchronizerBuilder::new().with_num_threads(4),
+            0,
+            200,
+            HashMap::from([(37, SlotBehavior::Panic)]),
+            &processed_slots,
+        )
+        .await;
+        let Err(SynchronizerError::FailedParallelSlotsProcessing { chunk_errors, .. }) = result
+        else {
+            panic!("expected the chunk to fail, got {result:?}");
+        };
+
+        assert!(matches!(
+            chunk_errors.0.as_slice(),
+            [SlotsProcessorError::SlotPanic { slot: 37, message }] if message == "slot 37 exploded"
+        ));
+        assert_eq!(processed_slots.count(37), 0);
+
+        // The rest of the panicked unit is processed by a fresh processor
+        for slot in (0..200).filter(|slot| *slot != 37) {
+            assert_eq!(processed_slots.count(slot), 1, "slot {slot}");
+        }
+
+        assert!(checkpoints
+            .iter()
+            .all(|checkpoint| checkpoint.last_slot < 37));
+    }
+
+    #[tokio::test]
+    async fn early_checkpoints_never_pass_unfinished_slots() {
+        // The slow slots hold their units back while the workers finish the
+        // ones after them
+        for (from_slot, to_slot, slow_slots, expected_checkpoints) in
+            [(0, 200, [5, 120], [119, 199]), (200, 0, [195, 80], [82, 1])]
+        {
+            let processed_slots = ProcessedSlots::default();
+            let behaviors = HashMap::from([
+                (
+                    slow_slots[0],
+                    SlotBehavior::Delay(Duration::from_millis(100)),
+                ),
+                (
+                    slow_slots[1],
+                    SlotBehavior::Delay(Duration::from_millis(300)),
+                ),
+            ]);
+            let (result, checkpoints) = sync(
+                SynchronizerBuilder::new()
+                    .with_num_threads(4)
+                    .with_slots_checkpoint(200),
+                from_slot,
+                to_slot,
+                behaviors,
+                &processed_slots,
+            )
+            .await;
+
+            result.unwrap();
+
+            assert_eq!(
+                checkpoints,
+                expected_checkpoints.map(|last_slot| SavedCheckpoint {
+                    last_slot,
+                    covers_processed_slots: true,
+                }),
+                "{from_slot} to {to_slot}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn every_slot_is_processed_once() {
+        // Neither the chunks nor the work units divide the range evenly, and the
+        // unit with the stalled slot is put back in the queue for another worker
+        for behaviors in [
+            HashMap::new(),
+            HashMap::from([(65, SlotBehavior::StallOnce)]),
+        ] {
+            let processed_slots = ProcessedSlots::default();
+            let (result, checkpoints) = sync(
+                SynchronizerBuilder::new()
+                    .with_num_threads(3)
+                    .with_slots_checkpoint(60)
+                    .with_worker_stall_timeout(Duration::from_millis(200)),
+                0,
+                137,
+                behaviors,
+                &processed_slots,
+            )
+            .await;
+
+            result.unwrap();
+
+            for slot in 0..137 {
+                assert_eq!(processed_slots.count(slot), 1, "slot {slot}");
+            }
+
+            assert_eq!(processed_slots.count(137), 0);
+            assert!(checkpoints
+                .iter()
+                .all(|checkpoint| checkpoint.covers_processed_slots));
+            assert_eq!(
+                checkpoints.last().map(|checkpoint| checkpoint.last_slot),
+                Some(136)
+            );
+        }
+    }
+}
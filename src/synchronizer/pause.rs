@@ -0,0 +1,55 @@
+use tokio::sync::watch;
+
+/// Switch pausing the synchronizers sharing it between work units, so a task
+/// can be held at runtime without losing its progress, e.g. to relieve a shared
+/// RPC provider during peak hours.
+#[derive(Debug)]
+pub struct PauseSwitch {
+    paused: watch::Sender<bool>,
+}
+
+impl PauseSwitch {
+    pub fn new() -> Self {
+        let (paused, _) = watch::channel(false);
+
+        Self { paused }
+    }
+
+    /// Pauses the synchronizers, returning whether they were running.
+    pub fn pause(&self) -> bool {
+        self.set_paused(true)
+    }
+
+    /// Resumes the synchronizers, returning whether they were paused.
+    pub fn resume(&self) -> bool {
+        self.set_paused(false)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    /// Waits until the switch isn't paused, returning whether it had to.
+    pub async fn wait_until_resumed(&self) -> bool {
+        let mut paused = self.paused.subscribe();
+
+        if !*paused.borrow_and_update() {
+            return false;
+        }
+
+        // The sender lives as long as `self`, so this can't fail
+        let _ = paused.wait_for(|paused| !paused).await;
+
+        true
+    }
+
+    fn set_paused(&self, paused: bool) -> bool {
+        self.paused.send_replace(paused) != paused
+    }
+}
+
+impl Default for PauseSwitch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
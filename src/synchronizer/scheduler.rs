@@ -0,0 +1,296 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use super::job::SlotRange;
+
+/// Slots per work unit. Small enough for a few slow slots, like the ones of
+/// blocks full of blobs, to hold back a single worker only briefly
+pub const DEFAULT_WORK_UNIT_SIZE: u32 = 10;
+
+/// Decides how the slots of a range get synced: first split into chunks, each
+/// one followed by a checkpoint, and then every chunk split into small work
+/// units the workers syncing it in parallel pull from a shared queue.
+///
+/// It holds no state besides its settings, so the same range is always
+/// scheduled the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotScheduler {
+    chunk_size: u32,
+    min_slots_per_thread: u32,
+    work_unit_size: u32,
+}
+
+/// Iterator over the chunks, or work units, of a range in sync order.
+#[derive(Debug, Clone)]
+pub struct SlotChunks {
+    remaining: SlotRange,
+    chunk_size: u32,
+}
+
+/// Work units of a chunk waiting for a worker, along with their position in
+/// the chunk. Workers pull the next one as soon as they're done with theirs,
+/// so none sits idle while others still have slots left.
+#[derive(Debug, Default)]
+pub struct WorkQueue {
+    units: Mutex<VecDeque<(usize, SlotRange)>>,
+}
+
+impl SlotScheduler {
+    pub fn new(chunk_size: u32, min_slots_per_thread: u32) -> Self {
+        Self {
+            chunk_size: chunk_size.max(1),
+            min_slots_per_thread,
+            work_unit_size: DEFAULT_WORK_UNIT_SIZE,
+        }
+    }
+
+    pub fn with_work_unit_size(mut self, work_unit_size: u32) -> Self {
+        self.work_unit_size = work_unit_size.max(1);
+
+        self
+    }
+
+    pub fn chunk_size(&self) -> u32 {
+        self.chunk_size
+    }
+
+    /// Returns the chunks of at most the chunk size the range is synced in.
+    pub fn chunks(&self, range: SlotRange) -> SlotChunks {
+        SlotChunks {
+            remaining: range,
+            chunk_size: self.chunk_size,
+        }
+    }
+
+    /// Returns the amount of workers to sync the chunk with, at most
+    /// `max_threads` and few enough for each one to get the minimum slots per
+    /// thread on average.
+    pub fn workers(&self, chunk: SlotRange, max_threads: u32) -> u32 {
+        let slots = chunk.len();
+
+        if slots == 0 {
+            return 0;
+        }
+
+        let min_slots_per_thread = self.min_slots_per_thread.clamp(1, slots);
+
+        (slots / min_slots_per_thread).clamp(1, max_threads.max(1))
+    }
+
+    /// Returns the work units the chunk is split into.
+    pub fn work_units(&self, chunk: SlotRange) -> SlotChunks {
+        SlotChunks {
+            remaining: chunk,
+            chunk_size: self.work_unit_size,
+        }
+    }
+}
+
+impl SlotChunks {
+    pub fn chunk_size(&self) -> u32 {
+        self.chunk_size
+    }
+
+    /// Changes the size of the next chunks.
+    pub fn set_chunk_size(&mut self, chunk_size: u32) {
+        self.chunk_size = chunk_size.max(1);
+    }
+}
+
+impl Iterator for SlotChunks {
+    type Item = SlotRange;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining.is_empty() {
+            return None;
+        }
+
+        let chunk = self
+            .remaining
+            .slice(0, std::cmp::min(self.remaining.len(), self.chunk_size));
+
+        self.remaining.from = chunk.to;
+
+        Some(chunk)
+    }
+}
+
+impl WorkQueue {
+    pub fn new(units: impl IntoIterator<Item = SlotRange>) -> Self {
+        Self {
+            units: Mutex::new(units.into_iter().enumerate().collect()),
+        }
+    }
+
+    pub fn pop(&self) -> Option<(usize, SlotRange)> {
+        self.lock().pop_front()
+    }
+
+    /// Puts back the remaining slots of a unit a worker couldn't finish, so
+    /// they're the next ones to be pulled.
+    pub fn push_front(&self, index: usize, unit: SlotRange) {
+        if !unit.is_empty() {
+            self.lock().push_front((index, unit));
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<(usize, SlotRange)>> {
+        match self.units.lock() {
+            Ok(units) => units,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::{SlotRange, SlotScheduler, WorkQueue};
+
+    /// Slots of the range in the order they get synced.
+    fn range_slots(range: &SlotRange) -> Vec<u32> {
+        if range.from <= range.to {
+            (range.from..range.to).collect()
+        } else {
+            (range.to..range.from).rev().collect()
+        }
+    }
+
+    /// Checks the ranges are non-empty and follow each other, covering the
+    /// whole range in sync order without gaps or overlaps.
+    fn check_partition(range: SlotRange, parts: &[SlotRange]) -> Result<(), TestCaseError> {
+        let slots = parts.iter().flat_map(range_slots).collect::<Vec<_>>();
+
+        prop_assert_eq!(slots, range_slots(&range));
+
+        for (i, part) in parts.iter().enumerate() {
+            prop_assert!(!part.is_empty());
+
+            if let Some(next_part) = parts.get(i + 1) {
+                prop_assert_eq!(part.to, next_part.from);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_workers(
+        range: SlotRange,
+        min_slots_per_thread: u32,
+        max_threads: u32,
+    ) -> Result<(), TestCaseError> {
+        let workers = SlotScheduler::new(1000, min_slots_per_thread).workers(range, max_threads);
+
+        if range.is_empty() {
+            prop_assert_eq!(workers, 0);
+        } else {
+            prop_assert!(workers >= 1);
+            prop_assert!(workers <= max_threads.max(1));
+            prop_assert!(workers == 1 || workers * min_slots_per_thread <= range.len());
+        }
+
+        Ok(())
+    }
+
+    fn check_work_units(range: SlotRange, work_unit_size: u32) -> Result<(), TestCaseError> {
+        let units = SlotScheduler::new(1000, 50)
+            .with_work_unit_size(work_unit_size)
+            .work_units(range)
+            .collect::<Vec<_>>();
+
+        check_partition(range, &units)?;
+
+        for unit in &units {
+            prop_assert!(unit.len() <= work_unit_size.max(1));
+        }
+
+        Ok(())
+    }
+
+    fn check_chunks(range: SlotRange, chunk_size: u32) -> Result<(), TestCaseError> {
+        let chunks = SlotScheduler::new(chunk_size, 50)
+            .chunks(range)
+            .collect::<Vec<_>>();
+
+        check_partition(range, &chunks)?;
+
+        for (i, chunk) in chunks.iter().enumerate() {
+            if i < chunks.len() - 1 {
+                prop_assert_eq!(chunk.len(), chunk_size.max(1));
+            } else {
+                prop_assert!(chunk.len() <= chunk_size.max(1));
+            }
+        }
+
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn forward_work_units_cover_range_exactly(
+            from_slot in 0..1_000_000u32,
+            slots in 0..5_000u32,
+            work_unit_size in 0..100u32,
+        ) {
+            check_work_units(SlotRange::new(from_slot, from_slot + slots), work_unit_size)?;
+        }
+
+        #[test]
+        fn reverse_work_units_cover_range_exactly(
+            to_slot in 0..1_000_000u32,
+            slots in 0..5_000u32,
+            work_unit_size in 0..100u32,
+        ) {
+            check_work_units(SlotRange::new(to_slot + slots, to_slot), work_unit_size)?;
+        }
+
+        #[test]
+        fn workers_get_minimum_slots(
+            from_slot in 0..1_000_000u32,
+            slots in 0..5_000u32,
+            min_slots_per_thread in 0..100u32,
+            max_threads in 0..64u32,
+            reverse in any::<bool>(),
+        ) {
+            let range = if reverse {
+                SlotRange::new(from_slot + slots, from_slot)
+            } else {
+                SlotRange::new(from_slot, from_slot + slots)
+            };
+
+            check_workers(range, min_slots_per_thread, max_threads)?;
+        }
+
+        #[test]
+        fn forward_chunks_cover_range_exactly(
+            from_slot in 0..1_000_000u32,
+            slots in 0..5_000u32,
+            chunk_size in 0..1_500u32,
+        ) {
+            check_chunks(SlotRange::new(from_slot, from_slot + slots), chunk_size)?;
+        }
+
+        #[test]
+        fn reverse_chunks_cover_range_exactly(
+            to_slot in 0..1_000_000u32,
+            slots in 0..5_000u32,
+            chunk_size in 0..1_500u32,
+        ) {
+            check_chunks(SlotRange::new(to_slot + slots, to_slot), chunk_size)?;
+        }
+    }
+
+    #[test]
+    fn work_queue_hands_out_put_back_units_first() {
+        let queue = WorkQueue::new([SlotRange::new(0, 10), SlotRange::new(10, 20)]);
+
+        assert_eq!(queue.pop(), Some((0, SlotRange::new(0, 10))));
+
+        queue.push_front(0, SlotRange::new(4, 10));
+        queue.push_front(0, SlotRange::new(10, 10));
+
+        assert_eq!(queue.pop(), Some((0, SlotRange::new(4, 10))));
+        assert_eq!(queue.pop(), Some((1, SlotRange::new(10, 20))));
+        assert_eq!(queue.pop(), None);
+    }
+}
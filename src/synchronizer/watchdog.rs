@@ -0,0 +1,128 @@
+use std::{
+    fmt,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Step of the slot processing a worker is going through.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlotPhase {
+    Starting,
+    /// Waiting for another task to finish processing the same slot
+    WaitingForSlotClaim,
+    FetchingBeaconBlock,
+    CheckingIndexedBlock,
+    FetchingExecutionBlock,
+    /// Waiting for other workers to release the memory of their blobs
+    WaitingForBlobBudget,
+    FetchingBlobs,
+    VerifyingBlobs,
+    Indexing,
+}
+
+#[derive(Debug)]
+struct WorkerState {
+    slot: Option<u32>,
+    phase: SlotPhase,
+    last_progress_at: Instant,
+}
+
+/// Snapshot of what a worker is doing and for how long it has been doing it.
+#[derive(Debug)]
+pub struct WorkerActivity {
+    pub slot: Option<u32>,
+    pub phase: SlotPhase,
+    pub idle_for: Duration,
+}
+
+/// Progress of a slot-processing worker, shared with the synchronizer so it can
+/// tell when the worker got stuck.
+#[derive(Debug)]
+pub struct WorkerStatus {
+    state: Mutex<WorkerState>,
+}
+
+impl WorkerStatus {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(WorkerState {
+                slot: None,
+                phase: SlotPhase::Starting,
+                last_progress_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Records that the worker moved on to the given phase of a slot.
+    pub fn set_phase(&self, slot: u32, phase: SlotPhase) {
+        let mut state = self.lock();
+
+        state.slot = Some(slot);
+        state.phase = phase;
+        state.last_progress_at = Instant::now();
+    }
+
+    /// Records that the worker is making progress without moving on to
+    /// another phase, as when resuming after being paused.
+    pub fn touch(&self) {
+        self.lock().last_progress_at = Instant::now();
+    }
+
+    pub fn activity(&self) -> WorkerActivity {
+        let state = self.lock();
+
+        WorkerActivity {
+            slot: state.slot,
+            phase: state.phase,
+            idle_for: state.last_progress_at.elapsed(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, WorkerState> {
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+impl SlotPhase {
+    /// Name the phase is labeled with in the metrics.
+    pub fn metric_label(&self) -> &'static str {
+        match self {
+            SlotPhase::Starting => "starting",
+            SlotPhase::WaitingForSlotClaim => "waiting_for_slot_claim",
+            SlotPhase::FetchingBeaconBlock => "fetching_beacon_block",
+            SlotPhase::CheckingIndexedBlock => "checking_indexed_block",
+            SlotPhase::FetchingExecutionBlock => "fetching_execution_block",
+            SlotPhase::WaitingForBlobBudget => "waiting_for_blob_budget",
+            SlotPhase::FetchingBlobs => "fetching_blobs",
+            SlotPhase::VerifyingBlobs => "verifying_blobs",
+            SlotPhase::Indexing => "indexing",
+        }
+    }
+}
+
+impl Default for WorkerStatus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for SlotPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let phase = match self {
+            SlotPhase::Starting => "starting",
+            SlotPhase::WaitingForSlotClaim => "waiting for slot claim",
+            SlotPhase::FetchingBeaconBlock => "fetching beacon block",
+            SlotPhase::CheckingIndexedBlock => "checking indexed block",
+            SlotPhase::FetchingExecutionBlock => "fetching execution block",
+            SlotPhase::WaitingForBlobBudget => "waiting for blob budget",
+            SlotPhase::FetchingBlobs => "fetching blobs",
+            SlotPhase::VerifyingBlobs => "verifying blobs",
+            SlotPhase::Indexing => "indexing",
+        };
+
+        f.write_str(phase)
+    }
+}
@@ -0,0 +1,285 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+
+use serde_json::Value;
+use tracing::{info, warn};
+
+use crate::{
+    args::IndexArgs,
+    config::{Config, ConfigSources},
+    env::Environment,
+    utils::{banner::config_dump, telemetry::LogFilterHandle},
+};
+
+/// How often the config file is checked for changes
+const RELOAD_INTERVAL: Duration = Duration::from_secs(5);
+/// Options of the config dump applied by [`Tunables`], which don't need a
+/// restart
+const TUNABLE_OPTIONS: [&str; 5] = [
+    "environment.logLevel",
+    "environment.maxHeadLagSlots",
+    "indexing.numThreads",
+    "indexing.slotsPerSave",
+    "indexing.slowSlotThreshold",
+];
+
+/// Settings that can be changed while the indexer runs, by editing the config
+/// file. Zero stands for an unset option.
+#[derive(Debug, Default)]
+pub struct Tunables {
+    num_threads: AtomicU32,
+    slots_per_save: AtomicU32,
+    slow_slot_threshold_ms: AtomicU64,
+    max_head_lag_slots: AtomicU32,
+}
+
+impl Tunables {
+    pub fn new(env: &Environment, args: &IndexArgs) -> Self {
+        let tunables = Self::default();
+
+        tunables.update(env, args);
+
+        tunables
+    }
+
+    /// Worker threads of the parallel synchronizers.
+    pub fn num_threads(&self) -> Option<u32> {
+        load(&self.num_threads)
+    }
+
+    /// Slots synced between checkpoints.
+    pub fn slots_per_save(&self) -> Option<u32> {
+        load(&self.slots_per_save)
+    }
+
+    pub fn slow_slot_threshold(&self) -> Duration {
+        Duration::from_millis(self.slow_slot_threshold_ms.load(Ordering::Relaxed))
+    }
+
+    /// Slots behind the head above which an alert is raised.
+    pub fn max_head_lag_slots(&self) -> Option<u32> {
+        load(&self.max_head_lag_slots)
+    }
+
+    /// Applies the given configuration, returning the names of the options
+    /// that changed.
+    pub fn update(&self, env: &Environment, args: &IndexArgs) -> Vec<&'static str> {
+        let slow_slot_threshold = Duration::from_secs(args.slow_slot_threshold).as_millis() as u64;
+        let mut changed = vec![];
+
+        if store(&self.num_threads, args.num_threads) {
+            changed.push("num_threads");
+        }
+
+        if store(&self.slots_per_save, args.slots_per_save) {
+            changed.push("slots_per_save");
+        }
+
+        if self
+            .slow_slot_threshold_ms
+            .swap(slow_slot_threshold, Ordering::Relaxed)
+            != slow_slot_threshold
+        {
+            changed.push("slow_slot_threshold");
+        }
+
+        if store(&self.max_head_lag_slots, env.max_head_lag_slots) {
+            changed.push("max_head_lag_slots");
+        }
+
+        changed
+    }
+}
+
+fn load(value: &AtomicU32) -> Option<u32> {
+    Some(value.load(Ordering::Relaxed)).filter(|value| *value > 0)
+}
+
+/// Stores the value, returning whether it changed.
+fn store(value: &AtomicU32, new_value: Option<u32>) -> bool {
+    let new_value = new_value.unwrap_or_default();
+
+    value.swap(new_value, Ordering::Relaxed) != new_value
+}
+
+/// Reloads the config file whenever it's modified, applying the tunables and
+/// warning about the changes that need a restart, such as the endpoints or
+/// the network.
+pub struct ConfigWatcher {
+    sources: ConfigSources,
+    path: PathBuf,
+    tunables: Arc<Tunables>,
+    log_filter: LogFilterHandle,
+    log_level: Option<String>,
+    config: Value,
+    modified_at: Option<SystemTime>,
+}
+
+impl ConfigWatcher {
+    /// Watches the file the given indexing configuration was loaded from, if
+    /// any.
+    pub fn new(config: &Config, log_filter: LogFilterHandle) -> Option<Self> {
+        let path = config.sources.path()?.to_path_buf();
+        let args = config.args.index_args()?;
+        let modified_at = modified_at(&path);
+
+        Some(Self {
+            sources: config.sources.clone(),
+            path,
+            tunables: Arc::new(Tunables::new(&config.env, args)),
+            log_filter,
+            log_level: config.env.log_level.clone(),
+            config: config_dump(args, &config.env),
+            modified_at,
+        })
+    }
+
+    /// Returns the tunables updated on every reload.
+    pub fn tunables(&self) -> Arc<Tunables> {
+        self.tunables.clone()
+    }
+
+    pub async fn run(mut self) {
+        let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let modified_at = modified_at(&self.path);
+
+            if modified_at != self.modified_at {
+                self.modified_at = modified_at;
+                self.reload();
+            }
+        }
+    }
+
+    fn reload(&mut self) {
+        let config = match self.sources.load() {
+            Ok(config) => config,
+            Err(error) => {
+                warn!(
+                    path = %self.path.display(),
+                    ?error,
+                    "Failed to reload the config file. Keeping the current configuration"
+                );
+
+                return;
+            }
+        };
+        let Some(args) = config.args.index_args() else {
+            return;
+        };
+        let dump = config_dump(args, &config.env);
+        let restart_required = changed_options(&self.config, &dump)
+            .into_iter()
+            .filter(|option| !TUNABLE_OPTIONS.contains(&option.as_str()))
+            .collect::<Vec<_>>();
+
+        if !restart_required.is_empty() {
+            warn!(
+                options = ?restart_required,
+                "Config file changes need a restart to be applied"
+            );
+        }
+
+        let mut changed = self.tunables.update(&config.env, args);
+
+        if config.env.log_level != self.log_level {
+            if self.sources.has_var("RUST_LOG") {
+                warn!("LOG_LEVEL is overridden by RUST_LOG. Keeping the current log level");
+            } else {
+                let log_level = config.env.log_level.as_deref().unwrap_or("info");
+
+                match self.log_filter.reload(log_level) {
+                    Ok(()) => changed.push("log_level"),
+                    Err(error) => warn!(?error, log_level, "Failed to apply the log level"),
+                }
+            }
+
+            self.log_level = config.env.log_level.clone();
+        }
+
+        if !changed.is_empty() {
+            info!(options = ?changed, "Config file reloaded");
+        }
+
+        self.config = dump;
+    }
+}
+
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Returns the dotted paths of the options whose values differ.
+fn changed_options(old: &Value, new: &Value) -> Vec<String> {
+    let mut changed = vec![];
+
+    collect_changed_options("", old, new, &mut changed);
+
+    changed
+}
+
+fn collect_changed_options(prefix: &str, old: &Value, new: &Value, changed: &mut Vec<String>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            for key in old
+                .keys()
+                .chain(new.keys().filter(|key| !old.contains_key(*key)))
+            {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+
+                collect_changed_options(
+                    &path,
+                    old.get(key).unwrap_or(&Value::Null),
+                    new.get(key).unwrap_or(&Value::Null),
+                    changed,
+                );
+            }
+        }
+        (old, new) if old != new => changed.push(prefix.to_string()),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::changed_options;
+
+    #[test]
+    fn changed_options_are_found_by_path() {
+        let old = json!({
+            "environment": { "beaconNodeEndpoint": "http://a", "logLevel": null },
+            "indexing": { "numThreads": 4, "filterToAddresses": ["0x1"] },
+        });
+        let new = json!({
+            "environment": { "beaconNodeEndpoint": "http://b", "logLevel": "debug" },
+            "indexing": { "numThreads": 4, "filterToAddresses": ["0x1", "0x2"] },
+        });
+
+        assert_eq!(
+            changed_options(&old, &new),
+            vec![
+                "environment.beaconNodeEndpoint",
+                "environment.logLevel",
+                "indexing.filterToAddresses"
+            ]
+        );
+    }
+}
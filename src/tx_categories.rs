@@ -0,0 +1,87 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+use alloy::primitives::Bytes;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TxCategoryMappingError {
+    #[error("failed to read transaction category mapping file")]
+    Io(#[from] io::Error),
+    #[error("failed to parse transaction category mapping file")]
+    Serde(#[from] serde_json::Error),
+    #[error("signature {signature} is mapped to both {first} and {second}")]
+    DuplicateSignature {
+        signature: Bytes,
+        first: String,
+        second: String,
+    },
+}
+
+/// Detects the batch format of blob transactions from their calldata.
+///
+/// The mapping file is a JSON object mapping category labels to the calldata
+/// signatures identifying them, usually the selector of the function the batch
+/// submitter calls. A `0x` signature matches transactions without calldata:
+///
+/// ```json
+/// {
+///   "arbitrum-batch": ["0x3e5aa082"],
+///   "zksync-commit-batches": ["0x6edd4f12", "0x98f81962"],
+///   "raw-frames": ["0x"]
+/// }
+/// ```
+///
+/// Transactions matching several signatures get the category of the longest
+/// one.
+#[derive(Debug, Default, Clone)]
+pub struct TxCategoryRegistry {
+    signatures: Vec<(Bytes, String)>,
+}
+
+impl TxCategoryRegistry {
+    pub fn load(path: &Path) -> Result<Self, TxCategoryMappingError> {
+        let categories: HashMap<String, Vec<Bytes>> =
+            serde_json::from_str(&fs::read_to_string(path)?)?;
+        let mut signatures: HashMap<Bytes, String> = HashMap::new();
+
+        for (category, category_signatures) in categories {
+            for signature in category_signatures {
+                match signatures.get(&signature) {
+                    Some(existing) if existing != &category => {
+                        return Err(TxCategoryMappingError::DuplicateSignature {
+                            signature,
+                            first: existing.clone(),
+                            second: category,
+                        })
+                    }
+                    _ => {
+                        signatures.insert(signature, category.clone());
+                    }
+                }
+            }
+        }
+
+        let mut signatures = signatures.into_iter().collect::<Vec<_>>();
+
+        signatures.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+
+        Ok(Self { signatures })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.signatures.is_empty()
+    }
+
+    /// Returns the category of a transaction given its calldata.
+    pub fn category(&self, input: &[u8]) -> Option<&str> {
+        self.signatures
+            .iter()
+            .find(|(signature, _)| {
+                if signature.is_empty() {
+                    input.is_empty()
+                } else {
+                    input.starts_with(signature)
+                }
+            })
+            .map(|(_, category)| category.as_str())
+    }
+}
@@ -1,127 +1,119 @@
-use url::Url;
-
-use crate::{args::Args, env::Environment};
-
-fn mask_quik_node_url(url_string: &str) -> Option<String> {
-    match Url::parse(url_string) {
-        Ok(mut url) => {
-            // Get the path segments as a vector of strings.
-            let mut segments: Vec<&str> = url.path_segments().map_or(vec![], |c| c.collect());
-
-            if !segments.is_empty() {
-                *segments.last_mut().unwrap() = "*******";
-
-                url.set_path(&segments.join("/"));
-            }
-
-            if let Some(host) = url.host_str() {
-                // Split the host by '.' to isolate the subdomain part.
-                let parts: Vec<&str> = host.split('.').collect();
-                if parts.len() > 2 {
-                    // Join the parts back into a string with the subdomain masked.
-                    let masked_host = format!(
-                        "{}.{}.{}{}{}",
-                        "*******",
-                        parts[1],
-                        parts[2],
-                        if parts.len() > 3 { "." } else { "" },
-                        parts[3..].join(".")
-                    );
-                    // Set the new host.
-                    url.set_host(Some(&masked_host)).ok()?;
-                }
-            }
-
-            // Return the modified URL as a string.
-            Some(url.to_string())
-        }
-        // Return None if the URL parsing fails.
-        Err(_) => None,
-    }
-}
-pub fn remove_credentials_from_url(url_string: &str) -> Option<String> {
-    match Url::parse(url_string) {
-        Ok(mut url) => {
-            if let Some(host) = url.host_str() {
-                if host.contains("quiknode.pro") {
-                    return mask_quik_node_url(url_string);
-                }
-            }
-            url.set_username("******").unwrap();
-            url.set_password(None).unwrap();
-            Some(url.into())
-        }
-        Err(_) => None,
-    }
+use alloy::primitives::Address;
+
+use clap::ValueEnum;
+use serde_json::{json, Map, Value};
+use tracing::info;
+
+use crate::{
+    args::IndexArgs, clients::beacon::types::BlockId, commands::support_bundle::redacted_config,
+    env::Environment, network::Network,
+};
+
+/// How the effective configuration is printed on startup.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum ConfigOutput {
+    /// Banner followed by the configuration as indented JSON
+    #[default]
+    Text,
+    /// Single-line JSON `config` event, for deployment tooling to capture
+    Json,
 }
 
-pub fn print_banner(args: &Args, env: &Environment) {
-    println!("____  _       _                         ");
-    println!("| __ )| | ___ | |__  ___  ___ __ _ _ __  ");
-    println!("|  _ \\| |/ _ \\| '_ \\/ __|/ __/ _` | '_ \\ ");
-    println!("| |_) | | (_) | |_) \\__ \\ (_| (_| | | | |");
-    println!("|____/|_|\\___/|_.__/|___/\\___\\__,_|_| |_|\n");
-    println!("Blobscan indexer (EIP-4844 blob indexer) - blobscan.com");
-    println!("=======================================================");
-
-    println!("Network: {:?}", env.network_name);
-    if let Some(dencun_fork_slot) = env.dencun_fork_slot {
-        println!("Dencun fork slot: {dencun_fork_slot}");
-    } else {
-        println!("Dencun fork slot: {}", env.network_name.dencun_fork_slot());
-    }
-
-    if let Some(from_slot) = args.from_slot.clone() {
-        println!("Custom start slot: {}", from_slot.to_detailed_string());
-    }
-
-    if let Some(to_slot) = args.to_slot.clone() {
-        println!("Custom end slot: {}", to_slot.to_detailed_string());
-    }
-
-    if let Some(num_threads) = args.num_threads {
-        println!("Number of threads: {}", num_threads);
-    } else {
-        println!("Number of threads: auto");
-    }
-
-    if let Some(slots_per_save) = args.slots_per_save {
-        println!("Slots checkpoint size: {}", slots_per_save);
-    } else {
-        println!("Slots checkpoint size: 1000");
-    }
-
-    println!(
-        "Disable sync checkpoint saving: {}",
-        if args.disable_sync_checkpoint_save {
-            "yes"
-        } else {
-            "no"
-        }
-    );
-
-    println!(
-        "Disable historical sync: {}",
-        if args.disable_sync_historical {
-            "yes"
-        } else {
-            "no"
+/// Prints the effective configuration of the indexing run and logs it, with
+/// secrets redacted and credentials stripped from the endpoints.
+pub fn print_banner(args: &IndexArgs, env: &Environment) {
+    let config = config_dump(args, env);
+
+    match args.config_output {
+        ConfigOutput::Text => {
+            println!("____  _       _                         ");
+            println!("| __ )| | ___ | |__  ___  ___ __ _ _ __  ");
+            println!("|  _ \\| |/ _ \\| '_ \\/ __|/ __/ _` | '_ \\ ");
+            println!("| |_) | | (_) | |_) \\__ \\ (_| (_| | | | |");
+            println!("|____/|_|\\___/|_.__/|___/\\___\\__,_|_| |_|\n");
+            println!("Blobscan indexer (EIP-4844 blob indexer) - blobscan.com");
+            println!("=======================================================");
+            println!(
+                "{}\n",
+                serde_json::to_string_pretty(&config).unwrap_or_default()
+            );
         }
-    );
-
-    println!("Blobscan API endpoint: {}", env.blobscan_api_endpoint);
-    println!(
-        "CL endpoint: {:?}",
-        remove_credentials_from_url(env.beacon_node_endpoint.as_str())
-    );
-    println!(
-        "EL endpoint: {:?}",
-        remove_credentials_from_url(env.execution_node_endpoint.as_str())
-    );
-
-    if let Some(sentry_dsn) = env.sentry_dsn.clone() {
-        println!("Sentry DSN: {}", sentry_dsn);
+        ConfigOutput::Json => println!("{}", json!({ "event": "config", "config": config })),
     }
 
-    println!("\n");
+    info!(config = %config, "Effective configuration");
+}
+
+/// Returns the effective configuration of an indexing run: the redacted
+/// environment along with the indexing arguments.
+pub fn config_dump(args: &IndexArgs, env: &Environment) -> Value {
+    let dencun_fork_slot = env.dencun_fork_slot.or(env.network.dencun_fork_slot());
+    let fork_slots = match &env.network {
+        Network::Custom(network) => network
+            .fork_slots
+            .iter()
+            .map(|(fork, slot)| (fork.to_string(), json!(slot)))
+            .collect(),
+        _ => Map::new(),
+    };
+    let addresses = |addresses: &[Address]| {
+        addresses
+            .iter()
+            .map(|address| address.to_string())
+            .collect::<Vec<_>>()
+    };
+
+    json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "chainId": env.network.chain_id(),
+        // Discovered from the beacon node when unknown
+        "resolvedDencunForkSlot": dencun_fork_slot,
+        "forkSlots": fork_slots,
+        "environment": redacted_config(env),
+        "indexing": {
+            "fromSlot": args.from_slot.as_ref().map(BlockId::to_detailed_string),
+            "toSlot": args.to_slot.as_ref().map(BlockId::to_detailed_string),
+            "ranges": args.ranges.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            "resumeJob": args.resume_job,
+            "resume": args.resume,
+            "jobManifest": args.job_manifest,
+            "intentLog": args.intent_log,
+            "reorgLog": args.reorg_log,
+            "reorgConfirmationDepth": args.reorg_confirmation_depth,
+            "skipIndexedSlots": args.skip_indexed_slots.map(|source| format!("{source:?}").to_lowercase()),
+            "indexedSlotsBitmap": args.indexed_slots_bitmap,
+            "numThreads": args.num_threads,
+            "adaptiveConcurrency": args.adaptive_concurrency,
+            "slotsPerSave": args.slots_per_save.unwrap_or(1000),
+            "disableSyncCheckpointSave": args.disable_sync_checkpoint_save,
+            "disableSyncHistorical": args.disable_sync_historical,
+            "backfillDirection": format!("{:?}", args.backfill_direction).to_lowercase(),
+            "backfillCheckpoint": args.backfill_checkpoint,
+            "finalizedOnly": args.finalized_only,
+            "gapFillInterval": args.gap_fill_interval,
+            "catchUpThreshold": args.catch_up_threshold,
+            "sseStallSlots": args.sse_stall_slots,
+            "maxTaskRestarts": args.max_task_restarts,
+            "taskRestartDelay": args.task_restart_delay,
+            "workerStallTimeout": args.worker_stall_timeout,
+            "slotTimeout": args.slot_timeout,
+            "slowSlotThreshold": args.slow_slot_threshold,
+            "maxInflightRequestBytes": args.processing.max_inflight_request_bytes,
+            "maxInflightBlobBytes": args.processing.max_inflight_blob_bytes,
+            "dropInvalidEntities": args.processing.drop_invalid_entities,
+            "noBlobData": args.processing.no_blob_data,
+            "headerCacheSize": args.processing.header_cache_size,
+            "rollupMapping": args.processing.rollup_mapping,
+            "txCategoryMapping": args.processing.tx_category_mapping,
+            "filterFromAddresses": addresses(&args.processing.filter_from_addresses),
+            "filterToAddresses": addresses(&args.processing.filter_to_addresses),
+            "verifyBlobs": args.processing.verify_blobs,
+            "blobVerificationThreads": args.processing.blob_verification_threads,
+            "sink": format!("{:?}", args.processing.sink).to_lowercase(),
+            "exportDir": args.processing.export_dir,
+            "exportOnly": args.processing.export_only,
+            "analyticsDir": args.processing.analytics_dir,
+            "eraDir": args.processing.era_dir,
+        },
+    })
 }
@@ -1,3 +1,6 @@
 pub mod banner;
+pub mod otlp;
+pub mod redaction;
+pub mod roaring;
 pub mod telemetry;
 pub mod web3;
@@ -0,0 +1,402 @@
+use std::{
+    collections::hash_map::RandomState,
+    fmt::Debug,
+    hash::{BuildHasher, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::{json, Value};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Level, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+use super::redaction::Redactor;
+
+/// Spans waiting to be exported. New spans are dropped when the queue is full
+/// so a slow collector can't make the indexer run out of memory.
+const MAX_QUEUED_SPANS: usize = 8192;
+const MAX_BATCH_SIZE: usize = 512;
+const EXPORT_INTERVAL: Duration = Duration::from_secs(5);
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+const SPAN_KIND_INTERNAL: u8 = 1;
+const SPAN_KIND_CLIENT: u8 = 3;
+const STATUS_CODE_ERROR: u8 = 2;
+
+static ID_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Span recorded by the [`OtlpLayer`], exported once it gets closed.
+#[derive(Debug)]
+struct SpanData {
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+    parent_span_id: Option<[u8; 8]>,
+    name: &'static str,
+    kind: u8,
+    start_time: SystemTime,
+    end_time: SystemTime,
+    attributes: Fields,
+    events: Vec<SpanEvent>,
+    error: Option<String>,
+}
+
+#[derive(Debug)]
+struct SpanEvent {
+    time: SystemTime,
+    level: Level,
+    attributes: Fields,
+}
+
+#[derive(Debug)]
+enum ExportMessage {
+    Span(SpanData),
+    /// Exports the queued spans right away, notifying once done
+    Flush(oneshot::Sender<()>),
+}
+
+#[derive(Debug, Default)]
+struct Fields(Vec<(&'static str, String)>);
+
+/// Ships the spans recorded by its [`OtlpLayer`] to an OpenTelemetry collector
+/// (Jaeger, Tempo…) using the OTLP/HTTP JSON protocol.
+#[derive(Debug, Clone)]
+pub struct OtlpExporter {
+    sender: mpsc::Sender<ExportMessage>,
+}
+
+/// Records spans and the warnings and errors emitted within them.
+pub struct OtlpLayer {
+    exporter: OtlpExporter,
+    redactor: Arc<Redactor>,
+}
+
+impl OtlpExporter {
+    /// Starts exporting spans to the collector's `v1/traces` endpoint in the
+    /// background. Must be called within a Tokio runtime.
+    pub fn spawn(endpoint: &str, service_name: &str) -> Self {
+        let url = format!("{}/v1/traces", endpoint.trim_end_matches('/'));
+        let (sender, receiver) = mpsc::channel(MAX_QUEUED_SPANS);
+
+        tokio::spawn(export_spans(url, service_name.to_string(), receiver));
+
+        Self { sender }
+    }
+
+    /// Returns the layer recording spans, removing the secrets known to the
+    /// redactor from their attributes.
+    pub fn layer(&self, redactor: Arc<Redactor>) -> OtlpLayer {
+        OtlpLayer {
+            exporter: self.clone(),
+            redactor,
+        }
+    }
+
+    /// Exports the spans closed so far, waiting up to the export timeout. Meant
+    /// to be called before exiting as queued spans would be lost otherwise.
+    pub async fn flush(&self) {
+        let (done_sender, done_receiver) = oneshot::channel();
+
+        if self
+            .sender
+            .send(ExportMessage::Flush(done_sender))
+            .await
+            .is_ok()
+        {
+            let _ = tokio::time::timeout(EXPORT_TIMEOUT, done_receiver).await;
+        }
+    }
+}
+
+async fn export_spans(
+    url: String,
+    service_name: String,
+    mut receiver: mpsc::Receiver<ExportMessage>,
+) {
+    let client = reqwest::Client::new();
+    let mut interval = tokio::time::interval(EXPORT_INTERVAL);
+    let mut batch = Vec::with_capacity(MAX_BATCH_SIZE);
+
+    loop {
+        let mut flushed = None;
+        let is_closed = tokio::select! {
+            message = receiver.recv() => match message {
+                Some(ExportMessage::Span(span)) => {
+                    batch.push(span);
+
+                    if batch.len() < MAX_BATCH_SIZE {
+                        continue;
+                    }
+
+                    false
+                }
+                Some(ExportMessage::Flush(done)) => {
+                    flushed = Some(done);
+
+                    false
+                }
+                None => true,
+            },
+            _ = interval.tick() => false,
+        };
+
+        if !batch.is_empty() {
+            let body = to_export_request(&service_name, &batch);
+
+            batch.clear();
+
+            let result = client
+                .post(&url)
+                .timeout(EXPORT_TIMEOUT)
+                .json(&body)
+                .send()
+                .await
+                .and_then(|resp| resp.error_for_status());
+
+            // Spans emitted here would be exported too, so failures aren't traced
+            if let Err(error) = result {
+                eprintln!("Failed to export spans to {url}: {error}");
+            }
+        }
+
+        if let Some(done) = flushed {
+            let _ = done.send(());
+        }
+
+        if is_closed {
+            break;
+        }
+    }
+}
+
+impl<S> Layer<S> for OtlpLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let parent = span.parent().and_then(|parent| {
+            parent
+                .extensions()
+                .get::<SpanData>()
+                .map(|parent| (parent.trace_id, parent.span_id))
+        });
+        let mut attributes = Fields::default();
+
+        attrs.record(&mut attributes);
+
+        let now = SystemTime::now();
+        let data = SpanData {
+            trace_id: parent.map_or_else(random_id, |(trace_id, _)| trace_id),
+            span_id: random_id(),
+            parent_span_id: parent.map(|(_, span_id)| span_id),
+            name: span.name(),
+            kind: if span.name() == "http_request" {
+                SPAN_KIND_CLIENT
+            } else {
+                SPAN_KIND_INTERNAL
+            },
+            start_time: now,
+            end_time: now,
+            attributes,
+            events: vec![],
+            error: None,
+        };
+
+        span.extensions_mut().insert(data);
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(data) = span.extensions_mut().get_mut::<SpanData>() {
+                values.record(&mut data.attributes);
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let level = *event.metadata().level();
+
+        if level > Level::WARN {
+            return;
+        }
+
+        let Some(span) = ctx.event_span(event) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        let Some(data) = extensions.get_mut::<SpanData>() else {
+            return;
+        };
+        let mut attributes = Fields::default();
+
+        event.record(&mut attributes);
+
+        if level == Level::ERROR {
+            data.error = attributes.get("message").map(str::to_string);
+        }
+
+        data.events.push(SpanEvent {
+            time: SystemTime::now(),
+            level,
+            attributes,
+        });
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+
+        let data = span.extensions_mut().remove::<SpanData>();
+
+        if let Some(mut data) = data {
+            data.end_time = SystemTime::now();
+            data.redact(&self.redactor);
+
+            // Spans are dropped rather than blocking when the collector lags behind
+            let _ = self.exporter.sender.try_send(ExportMessage::Span(data));
+        }
+    }
+}
+
+impl SpanData {
+    fn redact(&mut self, redactor: &Redactor) {
+        self.attributes.redact(redactor);
+        self.events
+            .iter_mut()
+            .for_each(|event| event.attributes.redact(redactor));
+
+        if let Some(error) = &mut self.error {
+            *error = redactor.redact(error).into_owned();
+        }
+    }
+}
+
+impl Fields {
+    fn redact(&mut self, redactor: &Redactor) {
+        for (_, value) in &mut self.0 {
+            *value = redactor.redact(value).into_owned();
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(field, _)| *field == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn set(&mut self, name: &'static str, value: String) {
+        match self.0.iter_mut().find(|(field, _)| *field == name) {
+            Some((_, current)) => *current = value,
+            None => self.0.push((name, value)),
+        }
+    }
+
+    fn to_attributes(&self) -> Value {
+        self.0
+            .iter()
+            .filter(|(name, _)| *name != "message")
+            .map(|(name, value)| json!({ "key": name, "value": { "stringValue": value } }))
+            .collect()
+    }
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.set(field.name(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.set(field.name(), format!("{value:?}"));
+    }
+}
+
+/// Builds an `ExportTraceServiceRequest` in its JSON encoding, where ids are
+/// hex-encoded and timestamps are given in nanoseconds.
+fn to_export_request(service_name: &str, spans: &[SpanData]) -> Value {
+    let spans = spans
+        .iter()
+        .map(|span| {
+            let events = span
+                .events
+                .iter()
+                .map(|event| {
+                    json!({
+                        "timeUnixNano": unix_nanos(event.time),
+                        "name": event.attributes.get("message").unwrap_or(event.level.as_str()),
+                        "attributes": event.attributes.to_attributes(),
+                    })
+                })
+                .collect::<Vec<_>>();
+            let mut span_json = json!({
+                "traceId": hex::encode(span.trace_id),
+                "spanId": hex::encode(span.span_id),
+                "name": span.name,
+                "kind": span.kind,
+                "startTimeUnixNano": unix_nanos(span.start_time),
+                "endTimeUnixNano": unix_nanos(span.end_time),
+                "attributes": span.attributes.to_attributes(),
+                "events": events,
+            });
+
+            if let Some(parent_span_id) = span.parent_span_id {
+                span_json["parentSpanId"] = json!(hex::encode(parent_span_id));
+            }
+
+            if let Some(error) = &span.error {
+                span_json["status"] = json!({ "code": STATUS_CODE_ERROR, "message": error });
+            }
+
+            span_json
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [
+                    { "key": "service.name", "value": { "stringValue": service_name } },
+                    { "key": "service.version", "value": { "stringValue": env!("CARGO_PKG_VERSION") } },
+                ]
+            },
+            "scopeSpans": [{
+                "scope": { "name": env!("CARGO_PKG_NAME") },
+                "spans": spans,
+            }]
+        }]
+    })
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+/// Generates a random, non-zero trace or span id.
+fn random_id<const N: usize>() -> [u8; N] {
+    let mut id = [0u8; N];
+
+    for chunk in id.chunks_mut(8) {
+        let mut hasher = RandomState::new().build_hasher();
+
+        hasher.write_u64(ID_COUNTER.fetch_add(1, Ordering::Relaxed));
+
+        let bytes = (hasher.finish() | 1).to_le_bytes();
+
+        chunk.copy_from_slice(&bytes[..chunk.len()]);
+    }
+
+    id
+}
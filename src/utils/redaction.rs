@@ -0,0 +1,301 @@
+use std::{borrow::Cow, io, sync::Arc};
+
+use tracing::Metadata;
+use tracing_subscriber::fmt::MakeWriter;
+use url::Url;
+
+use crate::env::Environment;
+
+pub const REDACTED: &str = "******";
+
+/// Query parameters whose values are API keys or tokens
+const SECRET_QUERY_PARAMS: [&str; 8] = [
+    "key",
+    "apikey",
+    "api_key",
+    "api-key",
+    "token",
+    "access_token",
+    "auth",
+    "secret",
+];
+/// Shortest path segment taken for an API key, as providers like Infura,
+/// Alchemy or QuickNode embed them in the endpoint path
+const MIN_PATH_API_KEY_LEN: usize = 20;
+/// Shortest configured secret looked for in logs, so short values like a
+/// `user` username don't mangle unrelated text
+const MIN_SECRET_LEN: usize = 6;
+
+/// Strips the credentials, API keys and tokens from the URL, returning `None`
+/// if it can't be parsed.
+pub fn remove_credentials_from_url(url_string: &str) -> Option<String> {
+    let mut url = Url::parse(url_string).ok()?;
+
+    if !url.username().is_empty() || url.password().is_some() {
+        url.set_username(REDACTED).ok()?;
+        url.set_password(None).ok()?;
+    }
+
+    if url
+        .query_pairs()
+        .any(|(name, _)| is_secret_query_param(&name))
+    {
+        let query_pairs = url
+            .query_pairs()
+            .map(|(name, value)| {
+                let value = if is_secret_query_param(&name) {
+                    Cow::Borrowed(REDACTED)
+                } else {
+                    value
+                };
+
+                (name.into_owned(), value.into_owned())
+            })
+            .collect::<Vec<_>>();
+
+        url.query_pairs_mut().clear().extend_pairs(query_pairs);
+    }
+
+    if let Some(segments) = url.path_segments() {
+        let segments = segments
+            .map(|segment| {
+                if looks_like_api_key(segment) {
+                    REDACTED
+                } else {
+                    segment
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+
+        url.set_path(&segments);
+    }
+
+    Some(url.into())
+}
+
+/// Removes secrets from the text written to the logs: the secrets set in the
+/// environment, the credentials and API keys embedded in the configured
+/// endpoints, and any bearer token or JWT.
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    /// Sorted longest first, so secrets containing others are fully redacted
+    secrets: Vec<String>,
+}
+
+impl Redactor {
+    pub fn new(secrets: impl IntoIterator<Item = String>) -> Self {
+        let mut secrets = secrets
+            .into_iter()
+            .filter(|secret| secret.len() >= MIN_SECRET_LEN)
+            .collect::<Vec<_>>();
+
+        secrets.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        secrets.dedup();
+
+        Self { secrets }
+    }
+
+    pub fn from_env(env: &Environment) -> Self {
+        let mut secrets = vec![env.secret_key.clone()];
+
+        if let Some(jwt_secret) = &env.execution_node_jwt_secret {
+            secrets.push(jwt_secret.to_hex());
+        }
+
+        let auth_headers = [
+            &env.execution_node_auth_header,
+            &env.blobscan_api_auth_header,
+        ];
+
+        let auth_headers = auth_headers
+            .into_iter()
+            .flatten()
+            .chain(env.http_extra_headers.iter());
+
+        for auth_header in auth_headers {
+            if let Ok(value) = auth_header.value.to_str() {
+                secrets.push(value.to_string());
+            }
+        }
+
+        let endpoints = [
+            Some(&env.blobscan_api_endpoint),
+            Some(&env.beacon_node_endpoint),
+            env.beacon_node_blobs_endpoint.as_ref(),
+            env.beacon_node_events_endpoint.as_ref(),
+            Some(&env.execution_node_endpoint),
+            env.database_url.as_ref(),
+            env.otel_exporter_otlp_endpoint.as_ref(),
+            env.sentry_dsn.as_ref(),
+        ];
+
+        for endpoint in endpoints.into_iter().flatten() {
+            secrets.extend(url_secrets(endpoint));
+        }
+
+        for url in [&env.head_lag_webhook_url, &env.proxy_url]
+            .into_iter()
+            .flatten()
+        {
+            secrets.extend(url_secrets(url.as_str()));
+        }
+
+        Self::new(secrets)
+    }
+
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+
+        for secret in &self.secrets {
+            if text.contains(secret.as_str()) {
+                text = Cow::Owned(text.replace(secret.as_str(), REDACTED));
+            }
+        }
+
+        match redact_tokens(&text) {
+            Some(redacted) => Cow::Owned(redacted),
+            None => text,
+        }
+    }
+}
+
+/// Wraps a [`MakeWriter`] so every formatted event gets redacted before being
+/// written.
+pub struct RedactingMakeWriter<M> {
+    inner: M,
+    redactor: Arc<Redactor>,
+}
+
+pub struct RedactingWriter<W> {
+    inner: W,
+    redactor: Arc<Redactor>,
+}
+
+impl<M> RedactingMakeWriter<M> {
+    pub fn new(inner: M, redactor: Arc<Redactor>) -> Self {
+        Self { inner, redactor }
+    }
+}
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for RedactingMakeWriter<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter {
+            inner: self.inner.make_writer(),
+            redactor: self.redactor.clone(),
+        }
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        RedactingWriter {
+            inner: self.inner.make_writer_for(meta),
+            redactor: self.redactor.clone(),
+        }
+    }
+}
+
+// Formatted events are written at once, so secrets are never split between
+// writes
+impl<W: io::Write> io::Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+
+        self.inner
+            .write_all(self.redactor.redact(&text).as_bytes())?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Returns the credentials, API keys and tokens embedded in the URL.
+fn url_secrets(url_string: &str) -> Vec<String> {
+    let Ok(url) = Url::parse(url_string) else {
+        return vec![];
+    };
+    let mut secrets = vec![];
+
+    if !url.username().is_empty() {
+        secrets.push(url.username().to_string());
+    }
+
+    if let Some(password) = url.password() {
+        secrets.push(password.to_string());
+    }
+
+    secrets.extend(
+        url.query_pairs()
+            .filter(|(name, _)| is_secret_query_param(name))
+            .map(|(_, value)| value.into_owned()),
+    );
+
+    if let Some(segments) = url.path_segments() {
+        secrets.extend(
+            segments
+                .filter(|segment| looks_like_api_key(segment))
+                .map(str::to_string),
+        );
+    }
+
+    secrets
+}
+
+fn is_secret_query_param(name: &str) -> bool {
+    SECRET_QUERY_PARAMS.contains(&name.to_lowercase().as_str())
+}
+
+fn looks_like_api_key(segment: &str) -> bool {
+    segment.len() >= MIN_PATH_API_KEY_LEN
+        && segment
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        && segment.chars().any(|c| c.is_ascii_digit())
+        && segment.chars().any(|c| c.is_ascii_alphabetic())
+}
+
+/// Replaces bearer tokens and JWTs, returning `None` when there are none.
+fn redact_tokens(text: &str) -> Option<String> {
+    let is_token_char = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '=');
+    let mut redacted = String::with_capacity(text.len());
+    let mut last_end = 0;
+    let mut found = false;
+    let mut rest = text.char_indices().peekable();
+
+    while let Some((start, c)) = rest.next() {
+        if !is_token_char(c) {
+            continue;
+        }
+
+        let mut end = start + c.len_utf8();
+
+        while let Some(&(index, c)) = rest.peek() {
+            if !is_token_char(c) {
+                break;
+            }
+
+            end = index + c.len_utf8();
+            rest.next();
+        }
+
+        let token = &text[start..end];
+        let is_jwt = token.starts_with("eyJ") && token.matches('.').count() == 2;
+        let is_bearer_token = text[..start].ends_with("Bearer ");
+
+        if is_jwt || is_bearer_token {
+            redacted.push_str(&text[last_end..start]);
+            redacted.push_str(REDACTED);
+            last_end = end;
+            found = true;
+        }
+    }
+
+    found.then(|| {
+        redacted.push_str(&text[last_end..]);
+        redacted
+    })
+}
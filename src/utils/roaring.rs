@@ -0,0 +1,311 @@
+use std::collections::BTreeMap;
+
+/// Values above which an array container is turned into a bitmap one
+const ARRAY_CONTAINER_LIMIT: usize = 4096;
+const BITMAP_CONTAINER_WORDS: usize = 1024;
+
+const ARRAY_CONTAINER: u8 = 0;
+const BITMAP_CONTAINER: u8 = 1;
+
+/// Compressed set of `u32` values following the roaring bitmap layout: values
+/// are grouped by their 16 high bits into containers holding the low bits,
+/// either as a sorted array when sparse or as a 8KiB bitmap when dense. Slots
+/// are mostly contiguous, so a whole chain's worth of them takes a few hundred
+/// kilobytes at most.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RoaringBitmap {
+    containers: BTreeMap<u16, Container>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Container {
+    Array(Vec<u16>),
+    Bitmap { words: Box<[u64]>, len: usize },
+}
+
+impl RoaringBitmap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contains(&self, value: u32) -> bool {
+        let (key, low) = split(value);
+
+        self.containers
+            .get(&key)
+            .is_some_and(|container| container.contains(low))
+    }
+
+    /// Adds the value, returning whether it wasn't present.
+    pub fn insert(&mut self, value: u32) -> bool {
+        let (key, low) = split(value);
+
+        self.containers
+            .entry(key)
+            .or_insert_with(|| Container::Array(vec![]))
+            .insert(low)
+    }
+
+    /// Removes the value, returning whether it was present.
+    pub fn remove(&mut self, value: u32) -> bool {
+        let (key, low) = split(value);
+        let Some(container) = self.containers.get_mut(&key) else {
+            return false;
+        };
+        let removed = container.remove(low);
+
+        if container.len() == 0 {
+            self.containers.remove(&key);
+        }
+
+        removed
+    }
+
+    pub fn len(&self) -> usize {
+        self.containers.values().map(Container::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.containers.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        self.containers.iter().flat_map(|(&key, container)| {
+            container
+                .iter()
+                .map(move |low| ((key as u32) << 16) | low as u32)
+        })
+    }
+
+    /// Adds every value of the other bitmap.
+    pub fn union_with(&mut self, other: &RoaringBitmap) {
+        for value in other.iter() {
+            self.insert(value);
+        }
+    }
+
+    /// Serializes the bitmap as the amount of containers followed by each
+    /// container's key, kind, cardinality and values, all in little endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        bytes.extend_from_slice(&(self.containers.len() as u32).to_le_bytes());
+
+        for (key, container) in &self.containers {
+            bytes.extend_from_slice(&key.to_le_bytes());
+
+            match container {
+                Container::Array(values) => {
+                    bytes.push(ARRAY_CONTAINER);
+                    bytes.extend_from_slice(&(values.len() as u32).to_le_bytes());
+
+                    for value in values {
+                        bytes.extend_from_slice(&value.to_le_bytes());
+                    }
+                }
+                Container::Bitmap { words, len } => {
+                    bytes.push(BITMAP_CONTAINER);
+                    bytes.extend_from_slice(&(*len as u32).to_le_bytes());
+
+                    for word in words.iter() {
+                        bytes.extend_from_slice(&word.to_le_bytes());
+                    }
+                }
+            }
+        }
+
+        bytes
+    }
+
+    /// Parses a bitmap serialized with [`RoaringBitmap::to_bytes`], returning
+    /// `None` when malformed.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut reader = Reader(bytes);
+        let mut containers = BTreeMap::new();
+
+        for _ in 0..reader.u32()? {
+            let key = reader.u16()?;
+            let kind = reader.take(1)?[0];
+            let len = reader.u32()? as usize;
+            let container = match kind {
+                ARRAY_CONTAINER if len <= ARRAY_CONTAINER_LIMIT => {
+                    let values = (0..len).map(|_| reader.u16()).collect::<Option<Vec<_>>>()?;
+
+                    if !values.windows(2).all(|pair| pair[0] < pair[1]) {
+                        return None;
+                    }
+
+                    Container::Array(values)
+                }
+                BITMAP_CONTAINER => {
+                    let words = (0..BITMAP_CONTAINER_WORDS)
+                        .map(|_| reader.u64())
+                        .collect::<Option<Box<[_]>>>()?;
+
+                    if words
+                        .iter()
+                        .map(|word| word.count_ones() as usize)
+                        .sum::<usize>()
+                        != len
+                    {
+                        return None;
+                    }
+
+                    Container::Bitmap { words, len }
+                }
+                _ => return None,
+            };
+
+            if len > 0 {
+                containers.insert(key, container);
+            }
+        }
+
+        reader.0.is_empty().then_some(Self { containers })
+    }
+}
+
+impl FromIterator<u32> for RoaringBitmap {
+    fn from_iter<I: IntoIterator<Item = u32>>(iter: I) -> Self {
+        let mut bitmap = Self::new();
+
+        for value in iter {
+            bitmap.insert(value);
+        }
+
+        bitmap
+    }
+}
+
+impl Container {
+    fn contains(&self, low: u16) -> bool {
+        match self {
+            Container::Array(values) => values.binary_search(&low).is_ok(),
+            Container::Bitmap { words, .. } => words[low as usize / 64] & bit(low) != 0,
+        }
+    }
+
+    fn insert(&mut self, low: u16) -> bool {
+        match self {
+            Container::Array(values) => {
+                let Err(position) = values.binary_search(&low) else {
+                    return false;
+                };
+
+                values.insert(position, low);
+
+                if values.len() > ARRAY_CONTAINER_LIMIT {
+                    let mut words = vec![0u64; BITMAP_CONTAINER_WORDS].into_boxed_slice();
+
+                    for &value in values.iter() {
+                        words[value as usize / 64] |= bit(value);
+                    }
+
+                    *self = Container::Bitmap {
+                        words,
+                        len: values.len(),
+                    };
+                }
+
+                true
+            }
+            Container::Bitmap { words, len } => {
+                let word = &mut words[low as usize / 64];
+
+                if *word & bit(low) != 0 {
+                    return false;
+                }
+
+                *word |= bit(low);
+                *len += 1;
+
+                true
+            }
+        }
+    }
+
+    fn remove(&mut self, low: u16) -> bool {
+        match self {
+            Container::Array(values) => match values.binary_search(&low) {
+                Ok(position) => {
+                    values.remove(position);
+
+                    true
+                }
+                Err(_) => false,
+            },
+            Container::Bitmap { words, len } => {
+                let word = &mut words[low as usize / 64];
+
+                if *word & bit(low) == 0 {
+                    return false;
+                }
+
+                *word &= !bit(low);
+                *len -= 1;
+
+                if *len <= ARRAY_CONTAINER_LIMIT {
+                    *self = Container::Array(self.iter().collect());
+                }
+
+                true
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Container::Array(values) => values.len(),
+            Container::Bitmap { len, .. } => *len,
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = u16> + '_> {
+        match self {
+            Container::Array(values) => Box::new(values.iter().copied()),
+            Container::Bitmap { words, .. } => {
+                Box::new(words.iter().enumerate().flat_map(|(index, &word)| {
+                    (0..64)
+                        .filter(move |offset| word & (1 << offset) != 0)
+                        .map(move |offset| (index * 64 + offset) as u16)
+                }))
+            }
+        }
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+
+        let (taken, rest) = self.0.split_at(len);
+
+        self.0 = rest;
+
+        Some(taken)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.take(2)?.try_into().ok()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+}
+
+fn split(value: u32) -> (u16, u16) {
+    ((value >> 16) as u16, value as u16)
+}
+
+fn bit(low: u16) -> u64 {
+    1 << (low % 64)
+}
@@ -1,26 +1,363 @@
-use tracing::{subscriber::set_global_default, Subscriber};
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use sentry::protocol::{Breadcrumb, Context as SentryContext, Event as SentryEvent, Value};
+use sentry_tracing::{
+    breadcrumb_from_event, default_event_filter, event_from_event, exception_from_event,
+    EventFilter, EventMapping,
+};
+use tracing::{
+    field::{Field, Visit},
+    span,
+    subscriber::set_global_default,
+    subscriber::Interest,
+    Event, Level, Metadata, Subscriber,
+};
 use tracing_log::LogTracer;
 use tracing_subscriber::{
+    filter::FilterExt,
+    filter::ParseError,
     fmt::{self, MakeWriter},
+    layer::{Context, Filter},
     prelude::__tracing_subscriber_SubscriberExt,
-    EnvFilter, Registry,
+    registry::LookupSpan,
+    reload, EnvFilter, Layer, Registry,
 };
 
-/// Gets a subscriber that can be used to initialize the logger.
-pub fn get_subscriber<Sink>(env_filter: String, sink: Sink) -> impl Subscriber + Send + Sync
+use super::{
+    otlp::OtlpExporter,
+    redaction::{RedactingMakeWriter, Redactor},
+};
+
+/// Span fields promoted to Sentry tags, so events can be searched by them
+const SENTRY_TAG_FIELDS: [&str; 4] = ["task", "slot", "block_root", "block_hash"];
+
+/// Per-layer filter that only lets through a fraction of the debug and trace
+/// spans and events. Info, warn and error ones are always kept.
+pub struct DebugSampler {
+    // Keep one out of every `sample_every` spans/events. Zero drops them all
+    sample_every: u64,
+    counter: AtomicU64,
+}
+
+impl DebugSampler {
+    pub fn new(sample_rate: f64) -> Self {
+        let sample_rate = sample_rate.clamp(0.0, 1.0);
+        let sample_every = if sample_rate == 0.0 {
+            0
+        } else {
+            (1.0 / sample_rate).round() as u64
+        };
+
+        Self {
+            sample_every,
+            counter: AtomicU64::new(0),
+        }
+    }
+
+    fn is_always_kept(metadata: &Metadata<'_>) -> bool {
+        *metadata.level() <= Level::INFO
+    }
+}
+
+impl<S: Subscriber> Filter<S> for DebugSampler {
+    fn enabled(&self, metadata: &Metadata<'_>, _cx: &Context<'_, S>) -> bool {
+        if Self::is_always_kept(metadata) || self.sample_every == 1 {
+            return true;
+        }
+
+        if self.sample_every == 0 {
+            return false;
+        }
+
+        self.counter
+            .fetch_add(1, Ordering::Relaxed)
+            .is_multiple_of(self.sample_every)
+    }
+
+    fn callsite_enabled(&self, metadata: &'static Metadata<'static>) -> Interest {
+        if Self::is_always_kept(metadata) || self.sample_every == 1 {
+            Interest::always()
+        } else {
+            // Ask for every span/event so the sampling decision is made each time
+            Interest::sometimes()
+        }
+    }
+}
+
+/// Fields recorded on a span so far.
+#[derive(Debug, Default)]
+struct SpanFields(Vec<(&'static str, String)>);
+
+impl SpanFields {
+    fn set(&mut self, name: &'static str, value: String) {
+        match self.0.iter_mut().find(|(field, _)| *field == name) {
+            Some((_, current)) => *current = value,
+            None => self.0.push((name, value)),
+        }
+    }
+}
+
+impl Visit for SpanFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.set(field.name(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.set(field.name(), format!("{value:?}"));
+    }
+}
+
+/// Keeps the fields of every span around so the events reported to Sentry can
+/// be enriched with the context (task, slot, block) they were emitted in.
+pub struct SpanFieldsLayer;
+
+impl<S> Layer<S> for SpanFieldsLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            let mut fields = SpanFields::default();
+
+            attrs.record(&mut fields);
+            span.extensions_mut().insert(fields);
+        }
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(fields) = span.extensions_mut().get_mut::<SpanFields>() {
+                values.record(fields);
+            }
+        }
+    }
+}
+
+/// Maps events like the default Sentry layer does, tagging them with the task,
+/// slot and block of the spans they were emitted in and attaching the span
+/// hierarchy, from the outermost span to the innermost one.
+fn map_sentry_event<S>(event: &Event<'_>, ctx: Context<'_, S>) -> EventMapping
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let filter = default_event_filter(event.metadata());
+
+    if matches!(filter, EventFilter::Ignore) {
+        return EventMapping::Ignore;
+    }
+
+    let mut tags = BTreeMap::new();
+    let mut spans = vec![];
+
+    if let Some(scope) = ctx.event_scope(event) {
+        for span in scope.from_root() {
+            let extensions = span.extensions();
+            let fields = extensions
+                .get::<SpanFields>()
+                .map(|fields| fields.0.as_slice())
+                .unwrap_or_default();
+
+            for (name, value) in fields {
+                if SENTRY_TAG_FIELDS.contains(name) {
+                    // Inner spans override the fields of outer ones
+                    tags.insert(name.to_string(), value.clone());
+                }
+            }
+
+            let fields = fields
+                .iter()
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            spans.push(Value::from(format!("{}{{{fields}}}", span.name())));
+        }
+    }
+
+    match filter {
+        EventFilter::Breadcrumb => {
+            let mut breadcrumb = breadcrumb_from_event(event);
+
+            breadcrumb.data.extend(
+                tags.into_iter()
+                    .map(|(name, value)| (name, Value::from(value))),
+            );
+
+            EventMapping::Breadcrumb(breadcrumb)
+        }
+        _ => {
+            let mut sentry_event = match filter {
+                EventFilter::Exception => exception_from_event(event, ctx),
+                _ => event_from_event(event, ctx),
+            };
+
+            sentry_event.tags.extend(tags);
+
+            if !spans.is_empty() {
+                sentry_event.contexts.insert(
+                    "spans".to_string(),
+                    SentryContext::Other(BTreeMap::from([(
+                        "hierarchy".to_string(),
+                        Value::Array(spans),
+                    )])),
+                );
+            }
+
+            EventMapping::Event(sentry_event)
+        }
+    }
+}
+
+/// Removes secrets from the message, fields and span hierarchy of the events
+/// and breadcrumbs reported to Sentry.
+fn redact_sentry_event(mapping: EventMapping, redactor: &Redactor) -> EventMapping {
+    let redact_value = |value: &mut Value| redact_sentry_value(value, redactor);
+    let redact_string = |string: &mut String| *string = redactor.redact(string).into_owned();
+
+    match mapping {
+        EventMapping::Event(mut event) => {
+            let SentryEvent {
+                message,
+                exception,
+                extra,
+                contexts,
+                ..
+            } = &mut event;
+
+            message.iter_mut().for_each(redact_string);
+            exception
+                .values
+                .iter_mut()
+                .flat_map(|exception| exception.value.as_mut())
+                .for_each(redact_string);
+            extra.values_mut().for_each(redact_value);
+
+            for context in contexts.values_mut() {
+                if let SentryContext::Other(fields) = context {
+                    fields.values_mut().for_each(redact_value);
+                }
+            }
+
+            EventMapping::Event(event)
+        }
+        EventMapping::Breadcrumb(mut breadcrumb) => {
+            let Breadcrumb { message, data, .. } = &mut breadcrumb;
+
+            message.iter_mut().for_each(redact_string);
+            data.values_mut().for_each(redact_value);
+
+            EventMapping::Breadcrumb(breadcrumb)
+        }
+        mapping => mapping,
+    }
+}
+
+fn redact_sentry_value(value: &mut Value, redactor: &Redactor) {
+    match value {
+        Value::String(string) => *string = redactor.redact(string).into_owned(),
+        Value::Array(values) => values
+            .iter_mut()
+            .for_each(|value| redact_sentry_value(value, redactor)),
+        Value::Object(fields) => fields
+            .values_mut()
+            .for_each(|value| redact_sentry_value(value, redactor)),
+        _ => {}
+    }
+}
+
+/// Spans exported through OTLP: every span of the indexer, including the
+/// debug ones of the synchronizer threads and HTTP requests
+const OTLP_SPAN_FILTER: &str = "info,blob_indexer=debug";
+
+type FilterReloader = Box<dyn Fn(EnvFilter) -> Result<(), reload::Error> + Send + Sync>;
+
+/// Replaces the filter of the logs and the events reported to Sentry while the
+/// subscriber is in use.
+#[derive(Clone)]
+pub struct LogFilterHandle {
+    reloaders: Arc<Vec<FilterReloader>>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LogFilterError {
+    #[error("invalid filter directives: {0}")]
+    Parse(#[from] ParseError),
+    #[error("failed to replace the filter: {0}")]
+    Reload(#[from] reload::Error),
+}
+
+impl LogFilterHandle {
+    /// Applies the given filter directives, e.g. `info,blob_indexer=debug`.
+    pub fn reload(&self, directives: &str) -> Result<(), LogFilterError> {
+        // Parsed upfront so an invalid filter leaves every layer untouched
+        EnvFilter::try_new(directives)?;
+
+        for reloader in self.reloaders.iter() {
+            reloader(EnvFilter::try_new(directives)?)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Gets a subscriber that can be used to initialize the logger, along with the
+/// handle to change its filter.
+///
+/// `debug_sample_rate` is the fraction (between 0 and 1) of debug and trace
+/// spans and events that get logged. Spans are also shipped to the given
+/// [`OtlpExporter`], if any, regardless of the logging filter. Secrets known
+/// to the [`Redactor`] are removed from everything logged, reported to Sentry
+/// or exported.
+pub fn get_subscriber<Sink>(
+    env_filter: String,
+    debug_sample_rate: f64,
+    sink: Sink,
+    otlp_exporter: Option<OtlpExporter>,
+    redactor: Redactor,
+) -> (impl Subscriber + Send + Sync, LogFilterHandle)
 where
     Sink: for<'a> MakeWriter<'a> + Send + Sync + 'static,
 {
-    let env_filter =
-        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(env_filter));
+    let redactor = Arc::new(redactor);
+    let sentry_redactor = redactor.clone();
+    let build_env_filter =
+        || EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&env_filter));
+    let (formatting_filter, formatting_filter_handle) = reload::Layer::new(build_env_filter());
+    let (sentry_filter, sentry_filter_handle) = reload::Layer::new(build_env_filter());
     let formatting_layer = fmt::layer()
         .compact() // Use the Pretty formatter.
-        .with_writer(sink);
+        .with_writer(RedactingMakeWriter::new(sink, redactor.clone()))
+        .with_filter(formatting_filter.and(DebugSampler::new(debug_sample_rate)));
+    let sentry_layer = SpanFieldsLayer
+        .and_then(sentry_tracing::layer().event_mapper(move |event, ctx| {
+            redact_sentry_event(map_sentry_event(event, ctx), &sentry_redactor)
+        }))
+        .with_filter(sentry_filter);
+    let otlp_layer = otlp_exporter.map(|exporter| {
+        exporter
+            .layer(redactor)
+            .with_filter(EnvFilter::new(OTLP_SPAN_FILTER))
+    });
 
-    Registry::default()
-        .with(env_filter)
+    let log_filter = LogFilterHandle {
+        reloaders: Arc::new(vec![
+            Box::new(move |filter| formatting_filter_handle.reload(filter)),
+            Box::new(move |filter| sentry_filter_handle.reload(filter)),
+        ]),
+    };
+    let subscriber = Registry::default()
         .with(formatting_layer)
-        .with(sentry_tracing::layer())
+        .with(sentry_layer)
+        .with(otlp_layer);
+
+    (subscriber, log_filter)
 }
 
 /// Inits the logger with the given subscriber.
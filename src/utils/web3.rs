@@ -1,8 +1,15 @@
-use alloy::primitives::B256;
+use alloy::primitives::{B256, U256};
 use anyhow::{Context, Result};
 use sha2::{Digest, Sha256};
 
 const BLOB_COMMITMENT_VERSION_KZG: u8 = 0x01;
+const MIN_BASE_FEE_PER_BLOB_GAS: u64 = 1;
+
+/// Update fraction of the blob base fee set by EIP-4844 for Deneb
+pub const DENEB_BLOB_BASE_FEE_UPDATE_FRACTION: u64 = 3_338_477;
+
+/// Blob gas consumed by every blob, as set by EIP-4844
+pub const GAS_PER_BLOB: u64 = 131_072;
 
 pub fn sha256(value: &str) -> Result<B256> {
     let value_without_prefix = if let Some(value_without_prefix) = value.strip_prefix("0x") {
@@ -32,6 +39,38 @@ pub fn calculate_versioned_hash(commitment: &str) -> Result<B256> {
     Ok(B256::from_slice(hashed_commitment))
 }
 
+/// Computes the blob base fee of a block from its excess blob gas, as
+/// `get_base_fee_per_blob_gas` does in EIP-4844.
+pub fn calculate_blob_gas_price(excess_blob_gas: U256, update_fraction: u64) -> U256 {
+    fake_exponential(
+        U256::from(MIN_BASE_FEE_PER_BLOB_GAS),
+        excess_blob_gas,
+        U256::from(update_fraction),
+    )
+}
+
+/// Computes the blob gas a block carrying the given amount of blobs must
+/// report in its execution header.
+pub fn calculate_blob_gas_used(blobs: usize) -> u64 {
+    blobs as u64 * GAS_PER_BLOB
+}
+
+/// Approximates `factor * e ** (numerator / denominator)` using Taylor
+/// expansion.
+fn fake_exponential(factor: U256, numerator: U256, denominator: U256) -> U256 {
+    let mut i = U256::from(1);
+    let mut output = U256::ZERO;
+    let mut numerator_accum = factor * denominator;
+
+    while numerator_accum > U256::ZERO {
+        output += numerator_accum;
+        numerator_accum = numerator_accum.saturating_mul(numerator) / (denominator * i);
+        i += U256::from(1);
+    }
+
+    output / denominator
+}
+
 pub fn get_full_hash(hash: &B256) -> String {
     format!("0x{:x}", hash)
 }